@@ -0,0 +1,216 @@
+//! The ABIs of `--abi`, decoding the calls, events and errors of the trace.
+
+use alloy_dyn_abi::{EventExt, FunctionExt, JsonAbiExt};
+use alloy_json_abi::JsonAbi;
+use alloy_primitives::Address;
+use cast::{
+    decode::RevertDecoder,
+    traces::{CallTraceArena, DecodedCallData, DecodedCallLog},
+};
+use eyre::{Result, WrapErr};
+use foundry_common::fmt::format_token;
+use std::{fs, path::PathBuf, str::FromStr};
+
+/// An ABI file of `--abi`, in the `[<ADDRESS>:]<PATH>` format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbiSource {
+    /// The only address decoded with the ABI, all of them if `None`.
+    pub address: Option<Address>,
+    pub path: PathBuf,
+}
+
+impl FromStr for AbiSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let scoped = s.split_once(':').and_then(|(address, path)| {
+            Some(Self { address: Some(address.parse().ok()?), path: path.into() })
+        });
+        Ok(scoped.unwrap_or_else(|| Self { address: None, path: s.into() }))
+    }
+}
+
+impl AbiSource {
+    /// Loads the ABI of the file, either a JSON ABI or an artifact with an `abi` field, such as
+    /// the ones of forge.
+    pub fn load(&self) -> Result<JsonAbi> {
+        let load = || -> Result<JsonAbi> {
+            let content = fs::read_to_string(&self.path)?;
+            let json: serde_json::Value = serde_json::from_str(&content)?;
+            // the ABI can only be deserialized from borrowed strings, not from a `Value`
+            Ok(match json.get("abi") {
+                Some(abi) => serde_json::from_str(&abi.to_string())?,
+                None => serde_json::from_str(&content)?,
+            })
+        };
+        load().wrap_err_with(|| format!("failed to load the ABI of {}", self.path.display()))
+    }
+}
+
+/// The loaded ABIs of `--abi`.
+#[derive(Clone, Debug, Default)]
+pub struct TraceAbis {
+    /// The ABIs the whole trace is decoded with.
+    pub global: Vec<JsonAbi>,
+    /// The ABIs only the calls to their address and its events are decoded with.
+    pub scoped: Vec<(Address, JsonAbi)>,
+}
+
+impl TraceAbis {
+    pub fn load(sources: &[AbiSource]) -> Result<Self> {
+        let mut abis = Self::default();
+        for source in sources {
+            let abi = source.load()?;
+            match source.address {
+                Some(address) => abis.scoped.push((address, abi)),
+                None => abis.global.push(abi),
+            }
+        }
+        Ok(abis)
+    }
+
+    /// Decodes the calls to the scoped addresses, their return or revert data and their events,
+    /// with the ABI of their address instead of the ones the trace was decoded with. The call
+    /// data that the ABI has no function for keeps its decoding.
+    pub fn annotate(&self, arena: &mut CallTraceArena) {
+        if self.scoped.is_empty() {
+            return;
+        }
+        for node in arena.nodes_mut() {
+            let Some((_, abi)) =
+                self.scoped.iter().find(|(address, _)| *address == node.trace.address)
+            else {
+                continue;
+            };
+            let trace = &mut node.trace;
+            if !trace.kind.is_any_create() {
+                let function = trace.data.get(..4).and_then(|selector| {
+                    abi.functions().find(|function| function.selector()[..] == *selector)
+                });
+                if let Some(function) = function {
+                    let args = function
+                        .abi_decode_input(&trace.data[4..], false)
+                        .map(|args| args.iter().map(format_token).collect())
+                        .unwrap_or_default();
+                    trace.decoded.call_data =
+                        Some(DecodedCallData { signature: function.signature(), args });
+                    if trace.success {
+                        if let Ok(values) = function.abi_decode_output(&trace.output, false) {
+                            let values = values.iter().map(format_token).collect::<Vec<_>>();
+                            trace.decoded.return_data =
+                                (!values.is_empty()).then(|| values.join(", "));
+                        }
+                    }
+                }
+            }
+            if !trace.success {
+                let reason =
+                    RevertDecoder::new().with_abi(abi).decode(&trace.output, Some(trace.status));
+                trace.decoded.return_data = Some(reason);
+            }
+            for log in &mut node.logs {
+                if let Some(decoded) = decode_event(abi, &log.raw_log) {
+                    log.decoded = decoded;
+                }
+            }
+        }
+    }
+}
+
+/// Decodes a log with the events of the ABI.
+fn decode_event(abi: &JsonAbi, log: &alloy_primitives::LogData) -> Option<DecodedCallLog> {
+    let topic = log.topics().first()?;
+    abi.events().filter(|event| !event.anonymous && event.selector() == *topic).find_map(|event| {
+        let decoded = event.decode_log(log, false).ok()?;
+        let (mut indexed, mut body) = (decoded.indexed.iter(), decoded.body.iter());
+        let params = event
+            .inputs
+            .iter()
+            .map(|input| {
+                let value = if input.indexed { indexed.next() } else { body.next() };
+                Some((input.name.clone(), format_token(value?)))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(DecodedCallLog { name: Some(event.name.clone()), params: Some(params) })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+    use alloy_primitives::{Bytes, LogData, U256};
+    use alloy_sol_types::SolValue;
+    use cast::traces::CallLog;
+
+    #[test]
+    fn parses_abi_sources() {
+        let address = Address::with_last_byte(1);
+        let scoped = format!("{address}:abis/Token.json").parse::<AbiSource>().unwrap();
+        assert_eq!(scoped, AbiSource { address: Some(address), path: "abis/Token.json".into() });
+        let global = "abis/Token.json".parse::<AbiSource>().unwrap();
+        assert_eq!(global, AbiSource { address: None, path: "abis/Token.json".into() });
+    }
+
+    #[test]
+    fn loads_abis_and_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let abi = r#"[{"type":"function","name":"balanceOf","inputs":[{"name":"owner","type":"address"}],"outputs":[{"name":"","type":"uint256"}],"stateMutability":"view"}]"#;
+        fs::write(dir.path().join("abi.json"), abi).unwrap();
+        fs::write(dir.path().join("artifact.json"), format!(r#"{{"abi":{abi},"bytecode":{{}}}}"#))
+            .unwrap();
+        let source = |name: &str| AbiSource { address: None, path: dir.path().join(name) };
+        assert_eq!(source("abi.json").load().unwrap(), source("artifact.json").load().unwrap());
+        assert!(source("missing.json").load().unwrap_err().to_string().contains("missing.json"));
+    }
+
+    #[test]
+    fn decodes_scoped_addresses() {
+        let abi = JsonAbi::parse([
+            "function balanceOf(address owner) returns (uint256)",
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+            "error Paused()",
+        ])
+        .unwrap();
+        let (token, other) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let abis = TraceAbis { global: vec![], scoped: vec![(token, abi.clone())] };
+
+        let mut traces = arena(&[(None, 100_000), (Some(0), 10_000), (Some(0), 10_000)]);
+        let call =
+            [&abi.function("balanceOf").unwrap()[0].selector()[..], &other.abi_encode()].concat();
+        let transfer = LogData::new_unchecked(
+            vec![
+                abi.event("Transfer").unwrap()[0].selector(),
+                other.into_word(),
+                token.into_word(),
+            ],
+            U256::from(5).abi_encode().into(),
+        );
+        let nodes = traces.nodes_mut();
+        for node in &mut nodes[1..] {
+            node.trace.data = call.clone().into();
+            node.trace.success = true;
+        }
+        nodes[1].trace.address = token;
+        nodes[1].trace.output = U256::from(7).abi_encode().into();
+        nodes[1].logs = vec![CallLog { raw_log: transfer, ..Default::default() }];
+        // a revert of the token
+        nodes[0].trace.address = token;
+        nodes[0].trace.output = Bytes::from(abi.error("Paused").unwrap()[0].selector().to_vec());
+        nodes[2].trace.address = other;
+        abis.annotate(&mut traces);
+
+        let nodes = traces.nodes();
+        let decoded = &nodes[1].trace.decoded;
+        let call_data = decoded.call_data.as_ref().unwrap();
+        assert_eq!(call_data.signature, "balanceOf(address)");
+        assert_eq!(call_data.args, [other.to_string()]);
+        assert_eq!(decoded.return_data.as_deref(), Some("7"));
+        let log = &nodes[1].logs[0].decoded;
+        assert_eq!(log.name.as_deref(), Some("Transfer"));
+        assert_eq!(log.params.as_ref().unwrap()[2], ("value".to_string(), "5".to_string()));
+        assert_eq!(nodes[0].trace.decoded.return_data.as_deref(), Some("Paused()"));
+        // other addresses aren't decoded with the ABI
+        assert_eq!(nodes[2].trace.decoded.call_data, None);
+    }
+}
@@ -0,0 +1,199 @@
+//! The EIP-2930 access list of the accounts and slots a replayed transaction accessed.
+
+use alloy_primitives::{Address, B256};
+use alloy_rpc_types::{AccessList, AccessListItem};
+use cast::{
+    executors::TracingExecutor,
+    inspectors::AccessListInspector,
+    revm::{
+        precompile::{PrecompileSpecId, Precompiles},
+        primitives::EnvWithHandlerCfg,
+    },
+};
+use eyre::Result;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// Executes the transaction configured in `env` without committing it and returns the accounts
+/// and slots it accessed, in ascending order.
+///
+/// The `sender`, the called or created address `to` and the precompiles are warm regardless, so
+/// they're only included for the slots accessed in their storage.
+pub fn record(
+    executor: &TracingExecutor,
+    env: &EnvWithHandlerCfg,
+    sender: Address,
+    to: Address,
+) -> Result<AccessList> {
+    let precompiles = Precompiles::new(PrecompileSpecId::from_spec_id(env.handler_cfg.spec_id));
+    let mut inspector = AccessListInspector::new(
+        AccessList::default(),
+        sender,
+        to,
+        precompiles.addresses().copied(),
+    );
+    let mut backend = executor.backend().clone();
+    backend.inspect(&mut env.clone(), &mut inspector)?;
+    Ok(sorted(to_map(&inspector.into_access_list())))
+}
+
+fn to_map(list: &AccessList) -> BTreeMap<Address, BTreeSet<B256>> {
+    let mut map = BTreeMap::<_, BTreeSet<_>>::new();
+    for item in &list.0 {
+        map.entry(item.address).or_default().extend(item.storage_keys.iter().copied());
+    }
+    map
+}
+
+fn sorted(map: BTreeMap<Address, BTreeSet<B256>>) -> AccessList {
+    AccessList(
+        map.into_iter()
+            .map(|(address, slots)| AccessListItem {
+                address,
+                storage_keys: slots.into_iter().collect(),
+            })
+            .collect(),
+    )
+}
+
+/// Returns the accounts and slots of `list` that aren't in `other`.
+fn difference(list: &AccessList, other: &AccessList) -> AccessList {
+    let other = to_map(other);
+    let mut diff = BTreeMap::new();
+    for (address, slots) in to_map(list) {
+        match other.get(&address) {
+            None => {
+                diff.insert(address, slots);
+            }
+            Some(other_slots) => {
+                let slots = slots.difference(other_slots).copied().collect::<BTreeSet<_>>();
+                if !slots.is_empty() {
+                    diff.insert(address, slots);
+                }
+            }
+        }
+    }
+    sorted(diff)
+}
+
+/// The differences between the accessed accounts and slots and the access list of the transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AccessListDiff {
+    /// The accessed accounts and slots missing from the access list of the transaction.
+    pub missing: AccessList,
+    /// The accounts and slots of the access list of the transaction that weren't accessed.
+    pub unused: AccessList,
+}
+
+/// The access list of a replayed transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListReport {
+    pub access_list: AccessList,
+    /// The comparison with the access list of the transaction, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<AccessListDiff>,
+}
+
+impl AccessListReport {
+    pub fn new(access_list: AccessList, declared: Option<&AccessList>) -> Self {
+        let diff = declared.map(|declared| AccessListDiff {
+            missing: difference(&access_list, declared),
+            unused: difference(declared, &access_list),
+        });
+        Self { access_list, diff }
+    }
+}
+
+fn write_items(f: &mut fmt::Formatter<'_>, list: &AccessList, prefix: &str) -> fmt::Result {
+    for item in &list.0 {
+        write!(f, "\n  {prefix}{}", item.address)?;
+        for slot in &item.storage_keys {
+            write!(f, "\n  {prefix}    {slot}")?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for AccessListReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slots = self.access_list.0.iter().map(|item| item.storage_keys.len()).sum::<usize>();
+        write!(f, "Access list ({} address(es), {slots} slot(s)):", self.access_list.0.len())?;
+        write_items(f, &self.access_list, "")?;
+        match &self.diff {
+            None => Ok(()),
+            Some(AccessListDiff { missing, unused })
+                if missing.0.is_empty() && unused.0.is_empty() =>
+            {
+                write!(f, "\nThe access list of the transaction matches")
+            }
+            Some(AccessListDiff { missing, unused }) => {
+                write!(
+                    f,
+                    "\nDifferences from the access list of the transaction (+ missing, - unused):"
+                )?;
+                write_items(f, missing, "+ ")?;
+                write_items(f, unused, "- ")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(address: u8, slots: &[u8]) -> AccessListItem {
+        AccessListItem {
+            address: Address::with_last_byte(address),
+            storage_keys: slots.iter().map(|slot| B256::with_last_byte(*slot)).collect(),
+        }
+    }
+
+    #[test]
+    fn records_accessed_slots_and_accounts() {
+        use cast::revm::primitives::{AccountInfo, Bytecode, Bytes, Env, TxKind, U256};
+
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        // SLOAD(1), BALANCE(0x33)
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x01, 0x54, 0x60, 0x33, 0x31]));
+        let contract = Address::repeat_byte(0x11);
+        let info =
+            AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() };
+        executor.backend_mut().insert_account_info(contract, info);
+        let sender = Address::repeat_byte(0x22);
+
+        let mut env = Env::default();
+        env.block.gas_limit = U256::from(30_000_000);
+        env.tx.caller = sender;
+        env.tx.transact_to = TxKind::Call(contract);
+        env.tx.gas_limit = 100_000;
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+
+        let list = record(&executor, &env, sender, contract).unwrap();
+        let slots =
+            AccessListItem { address: contract, storage_keys: vec![B256::with_last_byte(1)] };
+        assert_eq!(list, AccessList(vec![item(0x33, &[]), slots]));
+    }
+
+    #[test]
+    fn diffs_declared_access_list() {
+        let accessed = AccessList(vec![item(1, &[1, 2]), item(2, &[])]);
+        let declared = AccessList(vec![item(1, &[2, 3]), item(3, &[1])]);
+        let report = AccessListReport::new(accessed.clone(), Some(&declared));
+        assert_eq!(
+            report.diff,
+            Some(AccessListDiff {
+                missing: AccessList(vec![item(1, &[1]), item(2, &[])]),
+                unused: AccessList(vec![item(1, &[3]), item(3, &[1])]),
+            })
+        );
+
+        let report = AccessListReport::new(accessed.clone(), Some(&accessed));
+        assert!(report.to_string().ends_with("The access list of the transaction matches"));
+        assert_eq!(AccessListReport::new(accessed, None).diff, None);
+    }
+}
@@ -0,0 +1,135 @@
+//! Loading of Anvil state dumps to replay transactions on, instead of a remote fork.
+
+use alloy_primitives::{keccak256, Address, Bytes, U256};
+use cast::{
+    backend::Backend,
+    revm::primitives::{AccountInfo, BlockEnv, Bytecode, KECCAK_EMPTY},
+};
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+use std::{collections::BTreeMap, path::Path};
+
+/// The state of an Anvil node, as dumped with `anvil --dump-state` or `anvil_dumpState`.
+///
+/// Only the accounts and the block of the state are used, the other fields are ignored.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AnvilState {
+    /// The block of the state, missing in dumps of older Anvil versions.
+    pub block: Option<BlockEnv>,
+    pub accounts: BTreeMap<Address, AccountRecord>,
+}
+
+/// An account of an Anvil state dump.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccountRecord {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code: Bytes,
+    pub storage: BTreeMap<U256, U256>,
+}
+
+impl AnvilState {
+    /// Loads the state from a file, or from the `state.json` file of a directory as Anvil does.
+    pub fn load(path: &Path) -> Result<Self> {
+        let path = if path.is_dir() { path.join("state.json") } else { path.to_path_buf() };
+        let content = foundry_common::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .wrap_err_with(|| format!("{} is not an Anvil state dump", path.display()))
+    }
+
+    /// Checks that a transaction of block `tx_block_number` can be replayed on the state, which
+    /// must not be of the same or a later block.
+    pub fn validate(&self, tx_block_number: u64) -> Result<()> {
+        if let Some(block) = &self.block {
+            let number = block.number.saturating_to::<u64>();
+            if number >= tx_block_number {
+                eyre::bail!(
+                    "the loaded state is of block {number}, which is not before the block \
+                     {tx_block_number} of the transaction"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts the accounts of the state into the backend.
+    pub fn apply(&self, backend: &mut Backend) -> Result<()> {
+        for (address, account) in &self.accounts {
+            let code_hash =
+                if account.code.is_empty() { KECCAK_EMPTY } else { keccak256(&account.code) };
+            let info = AccountInfo {
+                balance: account.balance,
+                nonce: account.nonce,
+                code_hash,
+                code: Some(Bytecode::new_raw(account.code.clone())),
+            };
+            backend.insert_account_info(*address, info);
+            for (&slot, &value) in &account.storage {
+                backend.insert_account_storage(*address, slot, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the chain id to replay a transaction of `tx_chain_id` on a loaded state with, which is
+/// the `configured` one if set.
+///
+/// Errors if the transaction was signed for another chain than the configured one.
+pub fn chain_id(configured: Option<u64>, tx_chain_id: Option<u64>, default: u64) -> Result<u64> {
+    match (configured, tx_chain_id) {
+        (Some(configured), Some(tx_chain_id)) if configured != tx_chain_id => eyre::bail!(
+            "the transaction was signed for chain {tx_chain_id}, but the state is loaded for the \
+             configured chain {configured}"
+        ),
+        (Some(chain_id), _) | (None, Some(chain_id)) => Ok(chain_id),
+        (None, None) => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cast::revm::DatabaseRef;
+
+    #[test]
+    fn loads_anvil_state_dumps() {
+        let dir = tempfile::tempdir().unwrap();
+        let address = Address::with_last_byte(1);
+        let dump = format!(
+            r#"{{
+                "block": {{"number": "0x10", "coinbase": "{address}", "timestamp": "0x1",
+                    "gas_limit": "0x1c9c380", "basefee": "0x0", "difficulty": "0x0",
+                    "prevrandao": null, "blob_excess_gas_and_price": null}},
+                "accounts": {{"{address}": {{"nonce": 1, "balance": "0x64", "code": "0x6000",
+                    "storage": {{"0x1": "0x2"}}}}}},
+                "best_block_number": "0x10",
+                "blocks": []
+            }}"#
+        );
+        std::fs::write(dir.path().join("state.json"), dump).unwrap();
+
+        let state = AnvilState::load(dir.path()).unwrap();
+        assert!(state.validate(17).is_ok());
+        assert!(state.validate(16).is_err());
+
+        let mut backend = Backend::spawn(None);
+        state.apply(&mut backend).unwrap();
+        let info = backend.basic_ref(address).unwrap().unwrap();
+        assert_eq!((info.nonce, info.balance), (1, U256::from(100)));
+        assert_eq!(info.code_hash, keccak256([0x60, 0x00]));
+        assert_eq!(backend.storage_ref(address, U256::from(1)).unwrap(), U256::from(2));
+
+        std::fs::write(dir.path().join("state.json"), r#"{"block": null}"#).unwrap();
+        let err = AnvilState::load(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("is not an Anvil state dump"));
+    }
+
+    #[test]
+    fn state_chain_must_match_tx() {
+        assert_eq!(chain_id(None, None, 31337).unwrap(), 31337);
+        assert_eq!(chain_id(None, Some(1), 31337).unwrap(), 1);
+        assert_eq!(chain_id(Some(1), None, 31337).unwrap(), 1);
+        assert!(chain_id(Some(10), Some(1), 31337).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! Benchmarking of the RPC provider at several compute-units-per-second settings.
+
+use alloy_rpc_types::{Block, BlockTransactions, Transaction};
+use cast::{
+    replay::{
+        configure_block_env, execute_preceding, progress::ProgressCallback, PrecedingOptions,
+    },
+    revm::primitives::EnvWithHandlerCfg,
+};
+use comfy_table::{presets::ASCII_MARKDOWN, Table};
+use eyre::Result;
+use foundry_compilers::artifacts::EvmVersion;
+use foundry_config::Config;
+use foundry_evm::{
+    executors::{EvmError, TracingExecutor},
+    opts::EvmOpts,
+    utils::configure_tx_env,
+};
+use std::time::{Duration, Instant};
+
+/// The wall-clock time of a replay at a compute-units-per-second setting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CupsTiming {
+    pub compute_units_per_second: u64,
+    pub elapsed: Duration,
+}
+
+/// Replays the transaction once per compute-units-per-second setting, each time with a fresh
+/// backend that fetches all of its state from the provider.
+#[allow(clippy::too_many_arguments)]
+pub async fn benchmark_cups(
+    settings: &[u64],
+    config: &Config,
+    evm_opts: &EvmOpts,
+    evm_version: Option<EvmVersion>,
+    tx: &Transaction,
+    tx_block_number: u64,
+    block: Option<&Block>,
+    replay_preceding: bool,
+) -> Result<Vec<CupsTiming>> {
+    // cached storage would make every replay after the first one faster
+    let mut config = config.clone();
+    config.no_storage_caching = true;
+
+    let mut timings = Vec::with_capacity(settings.len());
+    for &compute_units_per_second in settings {
+        println!("Replaying at {compute_units_per_second} compute units per second");
+        let mut evm_opts = evm_opts.clone();
+        evm_opts.compute_units_per_second = Some(compute_units_per_second);
+        evm_opts.no_rpc_rate_limit = false;
+
+        let start = Instant::now();
+        let (mut env, fork, _) = TracingExecutor::get_fork_material(&config, evm_opts).await?;
+        let mut evm_version = evm_version;
+        configure_block_env(&mut env, tx_block_number, block, &mut evm_version);
+        let mut executor = TracingExecutor::new(env.clone(), fork, evm_version, false);
+        let mut env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+        if let Some(block) = block.filter(|_| replay_preceding) {
+            let BlockTransactions::Full(txs) = &block.transactions else {
+                eyre::bail!("Could not get block txs")
+            };
+            let preceding = txs.iter().take_while(|block_tx| block_tx.hash != tx.hash).count();
+            let options = PrecedingOptions {
+                progress: Some(ProgressCallback::progress_bar(preceding)),
+                ..Default::default()
+            };
+            execute_preceding(&mut executor, &mut env, &txs[..preceding], &options)?;
+        }
+        configure_tx_env(&mut env, tx);
+        if tx.to.is_some() {
+            executor.transact_with_env(env)?;
+        } else {
+            match executor.deploy_with_env(env, None) {
+                Ok(_) | Err(EvmError::Execution(_)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        timings.push(CupsTiming { compute_units_per_second, elapsed: start.elapsed() });
+    }
+    Ok(timings)
+}
+
+/// Formats the timings as a table, marking the fastest setting.
+pub fn table(timings: &[CupsTiming]) -> Table {
+    let fastest = timings.iter().map(|timing| timing.elapsed).min();
+    let mut table = Table::new();
+    table.load_preset(ASCII_MARKDOWN);
+    table.set_header(["Compute units per second", "Time", ""]);
+    for timing in timings {
+        let marker = if Some(timing.elapsed) == fastest { "fastest" } else { "" };
+        table.add_row([
+            timing.compute_units_per_second.to_string(),
+            format!("{:.3}s", timing.elapsed.as_secs_f64()),
+            marker.to_string(),
+        ]);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_fastest_setting() {
+        let timings = [
+            CupsTiming { compute_units_per_second: 100, elapsed: Duration::from_millis(2_500) },
+            CupsTiming { compute_units_per_second: 330, elapsed: Duration::from_millis(900) },
+        ];
+        let table = table(&timings).to_string();
+        assert!(table.contains("| 330                      | 0.900s | fastest |"), "{table}");
+        assert!(table.contains("| 100                      | 2.500s |         |"), "{table}");
+    }
+}
@@ -0,0 +1,111 @@
+//! On-disk cache of the blocks of the replayed transactions, for repeated replays.
+
+use alloy_provider::Provider;
+use alloy_rpc_types::{Block, BlockTransactions};
+use eyre::Result;
+use foundry_common::provider::RetryProvider;
+use foundry_config::Config;
+use std::path::PathBuf;
+
+/// The blocks of a chain cached on disk, with their full transactions, which is all that is needed
+/// to configure the environment of a replay and to execute the preceding transactions.
+#[derive(Clone, Debug)]
+pub struct BlockCache {
+    /// The directory of the blocks, with a directory per block number.
+    dir: PathBuf,
+    /// Whether the cached blocks are ignored and fetched again.
+    refresh: bool,
+}
+
+impl BlockCache {
+    /// Returns the cache of the blocks of the chain in the Foundry cache directory, next to the
+    /// cached fork storage: `~/.foundry/cache/rpc/<chain>/<block>/block.json`.
+    ///
+    /// With `refresh`, the cached blocks are fetched again and overwritten.
+    pub fn new(chain_id: u64, refresh: bool) -> Option<Self> {
+        Some(Self { dir: Config::foundry_chain_cache_dir(chain_id)?, refresh })
+    }
+
+    fn path(&self, number: u64) -> PathBuf {
+        self.dir.join(number.to_string()).join("block.json")
+    }
+
+    /// Returns the cached block `number`, if it was cached and can be read.
+    pub fn get(&self, number: u64) -> Option<Block> {
+        if self.refresh {
+            return None;
+        }
+        let path = self.path(number);
+        if !path.exists() {
+            return None;
+        }
+        match foundry_common::fs::read_json_file(&path) {
+            Ok(block) => Some(block),
+            Err(err) => {
+                trace!(block = number, %err, "ignoring unreadable cached block");
+                None
+            }
+        }
+    }
+
+    /// Caches the block `number`, if it has its full transactions.
+    pub fn insert(&self, number: u64, block: &Block) -> Result<()> {
+        if !matches!(block.transactions, BlockTransactions::Full(_)) {
+            return Ok(());
+        }
+        let path = self.path(number);
+        if let Some(parent) = path.parent() {
+            foundry_common::fs::create_dir_all(parent)?;
+        }
+        foundry_common::fs::write_json_file(&path, block)?;
+        Ok(())
+    }
+
+    /// Returns the block `number` with its full transactions from the cache, fetching and caching
+    /// it if it isn't cached.
+    pub async fn get_or_fetch(
+        &self,
+        provider: &RetryProvider,
+        number: u64,
+    ) -> Result<Option<Block>> {
+        if let Some(block) = self.get(number) {
+            trace!(block = number, "using cached block");
+            return Ok(Some(block));
+        }
+        let block = provider.get_block(number.into(), true.into()).await?;
+        if let Some(block) = &block {
+            self.insert(number, block)?;
+        }
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_rpc_types::Transaction;
+
+    #[test]
+    fn caches_full_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = BlockCache { dir: dir.path().to_path_buf(), refresh: false };
+        assert!(cache.get(10).is_none());
+
+        let mut block = Block::default();
+        block.header.number = Some(10);
+        block.transactions = BlockTransactions::Full(vec![Transaction {
+            block_number: Some(10),
+            ..Default::default()
+        }]);
+        cache.insert(10, &block).unwrap();
+        assert_eq!(cache.get(10), Some(block.clone()));
+
+        // blocks without their transactions can't be replayed
+        let hashes = Block { transactions: BlockTransactions::Hashes(vec![]), ..block };
+        cache.insert(11, &hashes).unwrap();
+        assert!(cache.get(11).is_none());
+
+        let refreshed = BlockCache { refresh: true, ..cache };
+        assert!(refreshed.get(10).is_none());
+    }
+}
@@ -0,0 +1,105 @@
+//! Overrides of the block environment.
+
+use alloy_primitives::{Address, B256, U256};
+use cast::revm::primitives::Env;
+use clap::Parser;
+
+/// The prevrandao of `--deterministic` blocks, whose difficulty is zero too.
+pub const DETERMINISTIC_PREVRANDAO: B256 = B256::ZERO;
+
+/// Overrides of the fields of the block environment that are otherwise taken from the block.
+#[derive(Clone, Debug, Default, Parser)]
+#[command(next_help_heading = "Block environment overrides")]
+pub struct BlockEnvOverrides {
+    /// Overrides the timestamp of the block.
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub block_timestamp: Option<u64>,
+
+    /// Overrides the base fee of the block, in wei.
+    #[arg(long, value_name = "WEI")]
+    pub block_basefee: Option<u64>,
+
+    /// Overrides the coinbase of the block.
+    #[arg(long, value_name = "ADDRESS")]
+    pub block_coinbase: Option<Address>,
+
+    /// Overrides the gas limit of the block.
+    #[arg(long, value_name = "GAS")]
+    pub block_gas_limit: Option<u64>,
+
+    /// Replaces the volatile fields of the block with constants, for traces that are identical
+    /// across machines, e.g. golden files along with `--no-labels`: the prevrandao and the
+    /// difficulty are zero, and so is the base fee if it isn't enforced.
+    ///
+    /// The other overrides take precedence. Like `--quick`, this may result in different results
+    /// than the live execution!
+    #[arg(long)]
+    pub deterministic: bool,
+}
+
+impl BlockEnvOverrides {
+    /// Applies the overrides to the block environment.
+    pub fn apply(&self, env: &mut Env) {
+        if self.deterministic {
+            env.block.prevrandao = Some(DETERMINISTIC_PREVRANDAO);
+            env.block.difficulty = U256::ZERO;
+            if env.cfg.disable_base_fee {
+                env.block.basefee = U256::ZERO;
+            }
+        }
+        if let Some(timestamp) = self.block_timestamp {
+            env.block.timestamp = U256::from(timestamp);
+        }
+        if let Some(basefee) = self.block_basefee {
+            env.block.basefee = U256::from(basefee);
+        }
+        if let Some(coinbase) = self.block_coinbase {
+            env.block.coinbase = coinbase;
+        }
+        if let Some(gas_limit) = self.block_gas_limit {
+            env.block.gas_limit = U256::from(gas_limit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_set_fields() {
+        let overrides = BlockEnvOverrides::parse_from([
+            "foundry-cli",
+            "--block-basefee",
+            "7",
+            "--block-timestamp",
+            "100",
+        ]);
+        let mut env = Env::default();
+        env.block.gas_limit = U256::from(30_000_000);
+        overrides.apply(&mut env);
+        assert_eq!(env.block.basefee, U256::from(7));
+        assert_eq!(env.block.timestamp, U256::from(100));
+        assert_eq!(env.block.gas_limit, U256::from(30_000_000));
+    }
+
+    #[test]
+    fn deterministic_replaces_volatile_fields() {
+        let deterministic = BlockEnvOverrides::parse_from(["foundry-cli", "--deterministic"]);
+        let mut env = Env::default();
+        env.block.prevrandao = Some(B256::repeat_byte(0xaa));
+        env.block.difficulty = U256::from(5);
+        env.block.basefee = U256::from(7);
+        deterministic.apply(&mut env);
+        assert_eq!(env.block.prevrandao, Some(DETERMINISTIC_PREVRANDAO));
+        assert_eq!((env.block.difficulty, env.block.basefee), (U256::ZERO, U256::from(7)));
+
+        env.cfg.disable_base_fee = true;
+        let overrides = BlockEnvOverrides { block_basefee: Some(3), ..deterministic.clone() };
+        deterministic.apply(&mut env);
+        assert_eq!(env.block.basefee, U256::ZERO);
+        // the explicit overrides take precedence
+        overrides.apply(&mut env);
+        assert_eq!(env.block.basefee, U256::from(3));
+    }
+}
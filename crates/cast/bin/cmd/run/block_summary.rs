@@ -0,0 +1,129 @@
+//! Summary of the replayed transactions of a whole block, or of several transactions.
+
+use alloy_primitives::{Address, TxHash};
+use alloy_rpc_types::Transaction;
+use cast::executors::RawCallResult;
+use comfy_table::{presets::ASCII_MARKDOWN, Table};
+use serde::Serialize;
+
+/// The outcome of a replayed transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayedTx {
+    pub hash: TxHash,
+    pub from: Address,
+    /// The called address, `None` for a contract creation.
+    pub to: Option<Address>,
+    pub gas_used: u64,
+    pub success: bool,
+    /// Why the transaction couldn't be replayed, if it failed to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ReplayedTx {
+    pub fn new(tx: &Transaction, raw: &RawCallResult) -> Self {
+        Self {
+            hash: tx.hash,
+            from: tx.from,
+            to: tx.to,
+            gas_used: raw.gas_used,
+            success: !raw.reverted,
+            error: None,
+        }
+    }
+
+    /// The outcome of a transaction that failed to be replayed with `error`.
+    pub fn failed(tx: &Transaction, error: &eyre::Report) -> Self {
+        Self {
+            hash: tx.hash,
+            from: tx.from,
+            to: tx.to,
+            gas_used: 0,
+            success: false,
+            error: Some(format!("{error:#}")),
+        }
+    }
+}
+
+/// Formats the replayed transactions as a table, followed by their totals.
+pub fn table(txs: &[ReplayedTx]) -> Table {
+    let mut table = Table::new();
+    table.load_preset(ASCII_MARKDOWN);
+    table.set_header(["Hash", "From", "To", "Gas used", "Status"]);
+    for tx in txs {
+        table.add_row([
+            tx.hash.to_string(),
+            tx.from.to_string(),
+            tx.to.map_or_else(|| "CREATE".to_string(), |to| to.to_string()),
+            tx.gas_used.to_string(),
+            match (tx.success, &tx.error) {
+                (_, Some(_)) => "failed",
+                (true, None) => "success",
+                (false, None) => "reverted",
+            }
+            .to_string(),
+        ]);
+    }
+    let failed = txs.iter().filter(|tx| tx.error.is_some()).count();
+    let reverted = txs.iter().filter(|tx| !tx.success).count() - failed;
+    let mut status = format!("{reverted} reverted");
+    if failed > 0 {
+        status.push_str(&format!(", {failed} failed"));
+    }
+    table.add_row([
+        format!("{} transaction(s)", txs.len()),
+        String::new(),
+        String::new(),
+        txs.iter().map(|tx| tx.gas_used).sum::<u64>().to_string(),
+        status,
+    ]);
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_replayed_txs() {
+        let txs = [
+            ReplayedTx {
+                hash: TxHash::with_last_byte(1),
+                from: Address::with_last_byte(1),
+                to: Some(Address::with_last_byte(2)),
+                gas_used: 21_000,
+                success: true,
+                error: None,
+            },
+            ReplayedTx {
+                hash: TxHash::with_last_byte(2),
+                from: Address::with_last_byte(1),
+                to: None,
+                gas_used: 100_000,
+                success: false,
+                error: None,
+            },
+        ];
+        let table = table(&txs).to_string();
+        let rows = table.lines().skip(2).collect::<Vec<_>>();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].contains("21000") && rows[0].contains("success"));
+        assert!(rows[1].contains("CREATE") && rows[1].contains("reverted"));
+        assert!(rows[2].contains("2 transaction(s)") && rows[2].contains("121000"));
+        assert!(rows[2].contains("1 reverted"));
+
+        let json = serde_json::to_value(&txs[1]).unwrap();
+        assert_eq!(json["gasUsed"], 100_000);
+        assert_eq!(json["to"], serde_json::Value::Null);
+        assert!(json.get("error").is_none());
+
+        let tx = Transaction { hash: TxHash::with_last_byte(3), ..Default::default() };
+        let failed = ReplayedTx::failed(&tx, &eyre::eyre!("state not found"));
+        assert_eq!(failed.error.as_deref(), Some("state not found"));
+        let table = super::table(&[txs[0].clone(), failed]).to_string();
+        let rows = table.lines().skip(2).collect::<Vec<_>>();
+        assert!(rows[1].contains("failed"), "{table}");
+        assert!(rows[2].contains("0 reverted, 1 failed"), "{table}");
+    }
+}
@@ -0,0 +1,119 @@
+//! Per-contract gas budgets.
+
+use alloy_primitives::Address;
+use cast::traces::CallTraceArena;
+use std::{fmt, str::FromStr};
+
+/// The maximum amount of gas a contract may consume in the transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasBudget {
+    pub address: Address,
+    pub max: u64,
+}
+
+impl FromStr for GasBudget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, max) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected `<address>:<max gas>`, got `{s}`"))?;
+        Ok(Self {
+            address: address.parse().map_err(|e| format!("invalid address `{address}`: {e}"))?,
+            max: max.parse().map_err(|e| format!("invalid gas amount `{max}`: {e}"))?,
+        })
+    }
+}
+
+/// A gas budget that was exceeded by the transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BudgetViolation {
+    pub budget: GasBudget,
+    /// The aggregate gas consumed by the contract.
+    pub used: u64,
+}
+
+impl fmt::Display for BudgetViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} used {} gas, exceeding its budget of {} by {}",
+            self.budget.address,
+            self.used,
+            self.budget.max,
+            self.used - self.budget.max
+        )
+    }
+}
+
+/// Returns the aggregate gas consumed by the calls to `address`, including their subcalls.
+///
+/// Calls made to `address` from within a call to `address` are already accounted for by the outer
+/// call.
+pub fn contract_gas(arena: &CallTraceArena, address: Address) -> u64 {
+    let nodes = arena.nodes();
+    nodes
+        .iter()
+        .filter(|node| node.trace.address == address)
+        .filter(|node| {
+            let mut parent = node.parent;
+            while let Some(idx) = parent {
+                if nodes[idx].trace.address == address {
+                    return false;
+                }
+                parent = nodes[idx].parent;
+            }
+            true
+        })
+        .map(|node| node.trace.gas_used)
+        .sum()
+}
+
+/// Returns the budgets that were exceeded by the transaction.
+pub fn check_budgets(arena: &CallTraceArena, budgets: &[GasBudget]) -> Vec<BudgetViolation> {
+    budgets
+        .iter()
+        .map(|budget| BudgetViolation {
+            budget: *budget,
+            used: contract_gas(arena, budget.address),
+        })
+        .filter(|violation| violation.used > violation.budget.max)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+
+    #[test]
+    fn parses_budget() {
+        let budget: GasBudget = "0x0000000000000000000000000000000000000001:50000".parse().unwrap();
+        assert_eq!(budget, GasBudget { address: Address::with_last_byte(1), max: 50_000 });
+        assert!("0x0000000000000000000000000000000000000001".parse::<GasBudget>().is_err());
+        assert!("0x01:50000".parse::<GasBudget>().is_err());
+    }
+
+    #[test]
+    fn reports_exceeded_budgets() {
+        let mut arena = arena(&[
+            (None, 100_000),
+            (Some(0), 40_000),
+            (Some(1), 10_000),
+            (Some(0), 30_000),
+            (Some(3), 5_000),
+        ]);
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        for (idx, address) in [(1, a), (2, a), (3, b), (4, a)] {
+            arena.nodes_mut()[idx].trace.address = address;
+        }
+        assert_eq!(contract_gas(&arena, a), 45_000);
+
+        let budgets =
+            [GasBudget { address: a, max: 44_000 }, GasBudget { address: b, max: 30_000 }];
+        assert_eq!(
+            check_budgets(&arena, &budgets),
+            vec![BudgetViolation { budget: budgets[0], used: 45_000 }]
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! The transactions of a `--bundle`, executed in order on top of a block.
+
+use alloy_consensus::TxEnvelope;
+use alloy_network::eip2718::Decodable2718;
+use alloy_primitives::{keccak256, Address, Bytes, TxKind};
+use alloy_provider::Provider;
+use alloy_rpc_types::{Block, Transaction, TransactionRequest};
+use alloy_serde::WithOtherFields;
+use eyre::{Result, WrapErr};
+use foundry_common::provider::RetryProvider;
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+
+/// An entry of a `--bundle` file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum BundleEntry {
+    /// A signed EIP-2718 transaction.
+    Raw(Bytes),
+    /// An unsigned transaction, executed as sent by its `from` address.
+    Unsigned(Box<TransactionRequest>),
+}
+
+/// The values of the fields missing from the unsigned entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BundleDefaults {
+    /// The gas limit, the one of the block.
+    pub gas: u128,
+    /// The gas price, the base fee of the block.
+    pub gas_price: u128,
+}
+
+impl BundleDefaults {
+    pub fn new(block: &Block) -> Self {
+        Self {
+            gas: block.header.gas_limit,
+            gas_price: block.header.base_fee_per_gas.unwrap_or_default(),
+        }
+    }
+}
+
+/// Reads the entries of a bundle file: either a JSON array of raw transactions and transaction
+/// objects, or a raw transaction per line.
+pub fn read_entries(path: &Path) -> Result<Vec<BundleEntry>> {
+    let content = foundry_common::fs::read_to_string(path)?;
+    let entries = match serde_json::from_str::<Vec<BundleEntry>>(&content) {
+        Ok(entries) => entries,
+        Err(_) if !content.trim_start().starts_with('[') => content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| line.parse().map(BundleEntry::Raw))
+            .collect::<Result<_, _>>()
+            .wrap_err_with(|| format!("invalid raw transaction in {}", path.display()))?,
+        Err(err) => {
+            return Err(err).wrap_err_with(|| format!("invalid bundle in {}", path.display()))
+        }
+    };
+    if entries.is_empty() {
+        eyre::bail!("the bundle {} has no transactions", path.display());
+    }
+    Ok(entries)
+}
+
+/// Loads the transactions of the bundle file, executed on top of `block`.
+///
+/// The nonces missing from the unsigned entries follow the nonce of their sender at the block and
+/// the earlier entries of the bundle.
+pub async fn load(
+    path: &Path,
+    provider: &RetryProvider,
+    block: &Block,
+) -> Result<Vec<WithOtherFields<Transaction>>> {
+    let entries = read_entries(path)?;
+    let block_number = block.header.number.ok_or_else(|| eyre::eyre!("block has no number"))?;
+    let mut nonces = HashMap::new();
+    for entry in &entries {
+        if let BundleEntry::Unsigned(request) = entry {
+            if let (Some(from), None) = (request.from, request.nonce) {
+                if let std::collections::hash_map::Entry::Vacant(entry) = nonces.entry(from) {
+                    let nonce =
+                        provider.get_transaction_count(from).block_id(block_number.into()).await?;
+                    entry.insert(nonce);
+                }
+            }
+        }
+    }
+    to_transactions(entries, &mut nonces, BundleDefaults::new(block))
+}
+
+/// Converts the entries of a bundle to transactions, starting from the nonces of `nonces` for the
+/// unsigned entries without one.
+///
+/// The transactions have the position of their entry as index, and no block. Unsigned entries are
+/// identified by the hash of their JSON.
+pub fn to_transactions(
+    entries: Vec<BundleEntry>,
+    nonces: &mut HashMap<Address, u64>,
+    defaults: BundleDefaults,
+) -> Result<Vec<WithOtherFields<Transaction>>> {
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let mut tx = match entry {
+                BundleEntry::Raw(raw) => signed_tx(&raw),
+                BundleEntry::Unsigned(request) => unsigned_tx(*request, nonces, defaults),
+            }
+            .wrap_err_with(|| format!("invalid bundle entry {index}"))?;
+            nonces.insert(tx.from, tx.nonce + 1);
+            tx.transaction_index = Some(index as u64);
+            Ok(WithOtherFields::new(tx))
+        })
+        .collect()
+}
+
+/// Decodes a signed transaction and recovers its sender.
+fn signed_tx(raw: &[u8]) -> Result<Transaction> {
+    let envelope = TxEnvelope::decode_2718(&mut &raw[..])?;
+    let mut tx = Transaction {
+        hash: *envelope.tx_hash(),
+        transaction_type: Some(envelope.tx_type() as u8),
+        ..Default::default()
+    };
+    let signature = match &envelope {
+        TxEnvelope::Legacy(signed) => {
+            set_common_fields(&mut tx, signed.tx());
+            signed.signature()
+        }
+        TxEnvelope::Eip2930(signed) => {
+            set_common_fields(&mut tx, signed.tx());
+            tx.access_list = Some(signed.tx().access_list.clone());
+            signed.signature()
+        }
+        TxEnvelope::Eip1559(signed) => {
+            let inner = signed.tx();
+            set_common_fields(&mut tx, inner);
+            tx.max_fee_per_gas = Some(inner.max_fee_per_gas);
+            tx.max_priority_fee_per_gas = Some(inner.max_priority_fee_per_gas);
+            tx.access_list = Some(inner.access_list.clone());
+            signed.signature()
+        }
+        TxEnvelope::Eip4844(signed) => {
+            let inner = signed.tx().tx();
+            set_common_fields(&mut tx, inner);
+            tx.max_fee_per_gas = Some(inner.max_fee_per_gas);
+            tx.max_priority_fee_per_gas = Some(inner.max_priority_fee_per_gas);
+            tx.access_list = Some(inner.access_list.clone());
+            tx.blob_versioned_hashes = Some(inner.blob_versioned_hashes.clone());
+            tx.max_fee_per_blob_gas = Some(inner.max_fee_per_blob_gas);
+            signed.signature()
+        }
+        _ => eyre::bail!("unsupported transaction type {}", envelope.tx_type() as u8),
+    };
+    tx.from = signature.recover_address_from_prehash(&envelope.signature_hash())?;
+    // the gas price of the transactions with a fee market is their maximum fee
+    tx.gas_price = tx.gas_price.or(tx.max_fee_per_gas);
+    Ok(tx)
+}
+
+/// Sets the fields of `tx` shared by all transaction types.
+fn set_common_fields(tx: &mut Transaction, inner: &impl alloy_consensus::Transaction) {
+    tx.chain_id = inner.chain_id();
+    tx.nonce = inner.nonce();
+    tx.gas = inner.gas_limit();
+    tx.gas_price = inner.gas_price();
+    tx.to = inner.to().to().copied();
+    tx.value = inner.value();
+    tx.input = Bytes::copy_from_slice(inner.input());
+}
+
+/// Builds the transaction of an unsigned entry.
+fn unsigned_tx(
+    request: TransactionRequest,
+    nonces: &HashMap<Address, u64>,
+    defaults: BundleDefaults,
+) -> Result<Transaction> {
+    let hash = keccak256(serde_json::to_vec(&request)?);
+    let from = request.from.ok_or_else(|| eyre::eyre!("unsigned transaction without `from`"))?;
+    let nonce = request.nonce.or_else(|| nonces.get(&from).copied()).unwrap_or_default();
+    let max_fee_per_gas = request.max_fee_per_gas;
+    Ok(Transaction {
+        hash,
+        nonce,
+        from,
+        to: match request.to {
+            Some(TxKind::Call(to)) => Some(to),
+            _ => None,
+        },
+        value: request.value.unwrap_or_default(),
+        gas_price: request.gas_price.or(max_fee_per_gas).or(Some(defaults.gas_price)),
+        gas: request.gas.unwrap_or(defaults.gas),
+        max_fee_per_gas,
+        max_priority_fee_per_gas: request.max_priority_fee_per_gas.or(max_fee_per_gas.map(|_| 0)),
+        max_fee_per_blob_gas: request.max_fee_per_blob_gas,
+        input: request.input.into_input().unwrap_or_default(),
+        chain_id: request.chain_id,
+        blob_versioned_hashes: request.blob_versioned_hashes,
+        access_list: request.access_list,
+        transaction_type: request.transaction_type,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::{SignableTransaction, TxEip1559};
+    use alloy_network::eip2718::Encodable2718;
+    use alloy_primitives::U256;
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+
+    #[test]
+    fn converts_bundle_entries() {
+        let signer = PrivateKeySigner::random();
+        let to = Address::with_last_byte(1);
+        let tx = TxEip1559 {
+            chain_id: 1,
+            nonce: 7,
+            gas_limit: 50_000,
+            max_fee_per_gas: 30,
+            max_priority_fee_per_gas: 2,
+            to: TxKind::Call(to),
+            value: U256::from(5),
+            input: Bytes::from_static(&[1, 2]),
+            ..Default::default()
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        let envelope = TxEnvelope::from(tx.into_signed(signature));
+        let raw = Bytes::from(envelope.encoded_2718());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.json");
+        let sender = Address::with_last_byte(2);
+        let json = serde_json::json!([
+            raw,
+            { "from": sender, "to": to, "input": "0x03" },
+            { "from": signer.address(), "data": "0x04", "gas": "0x5208" },
+        ]);
+        std::fs::write(&path, json.to_string()).unwrap();
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(entries[0], BundleEntry::Raw(raw.clone()));
+
+        let mut nonces = HashMap::from([(sender, 3)]);
+        let defaults = BundleDefaults { gas: 30_000_000, gas_price: 10 };
+        let txs = to_transactions(entries, &mut nonces, defaults).unwrap();
+        let signed = &txs[0];
+        assert_eq!(
+            (signed.hash, signed.from, signed.to),
+            (*envelope.tx_hash(), signer.address(), Some(to))
+        );
+        assert_eq!((signed.nonce, signed.gas, signed.gas_price), (7, 50_000, Some(30)));
+        assert_eq!(signed.input, Bytes::from_static(&[1, 2]));
+        assert_eq!(signed.transaction_index, Some(0));
+
+        let unsigned = &txs[1];
+        assert_eq!((unsigned.from, unsigned.nonce, unsigned.gas), (sender, 3, 30_000_000));
+        assert_eq!(
+            (unsigned.gas_price, unsigned.input.clone()),
+            (Some(10), Bytes::from_static(&[3]))
+        );
+        // the nonce follows the signed transaction of the sender
+        let create = &txs[2];
+        assert_eq!((create.to, create.nonce, create.gas), (None, 8, 21_000));
+        assert_eq!(create.input, Bytes::from_static(&[4]));
+        assert_eq!(nonces[&sender], 4);
+
+        // a raw transaction per line
+        std::fs::write(&path, format!("{raw}\n\n")).unwrap();
+        assert_eq!(read_entries(&path).unwrap(), [BundleEntry::Raw(raw)]);
+        std::fs::write(&path, "[]").unwrap();
+        assert!(read_entries(&path).is_err());
+        std::fs::write(&path, format!(r#"[{{"to": "{to}"}}]"#)).unwrap();
+        assert!(to_transactions(read_entries(&path).unwrap(), &mut nonces, defaults).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Summary of the function the transaction calls, decoded before its execution.
+
+use alloy_dyn_abi::{DynSolValue, JsonAbiExt};
+use alloy_json_abi::{Constructor, Function, JsonAbi};
+use alloy_primitives::{hex, Address, Selector};
+use cast::traces::identifier::SingleSignaturesIdentifier;
+use foundry_common::fmt::format_token;
+use serde::Serialize;
+use std::{collections::HashMap, fmt};
+
+/// The decoded target call of a transaction, as included in the JSON output.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallSummary {
+    /// The called address, `None` for a contract creation.
+    pub to: Option<Address>,
+    /// The selector of the called function, if the calldata has one.
+    pub selector: Option<Selector>,
+    /// The signature of the called function or the constructor, if resolved.
+    pub signature: Option<String>,
+    /// The decoded arguments, with the labels of the addresses.
+    pub args: Vec<String>,
+    /// The length of the calldata or the init code.
+    pub input_len: usize,
+    #[serde(skip)]
+    to_label: Option<String>,
+}
+
+impl CallSummary {
+    /// Decodes the call of `to` with `input` with the resolved `function`, if any.
+    pub fn call(
+        to: Address,
+        input: &[u8],
+        function: Option<&Function>,
+        labels: &HashMap<Address, String>,
+    ) -> Self {
+        let selector = input.get(..4).map(Selector::from_slice);
+        let decoded = function.filter(|function| Some(function.selector()) == selector).and_then(
+            |function| {
+                let args = function.abi_decode_input(&input[4..], false).ok()?;
+                Some((function.signature(), format_args(&args, labels)))
+            },
+        );
+        let (signature, args) = decoded.unzip();
+        Self {
+            to: Some(to),
+            selector,
+            signature,
+            args: args.unwrap_or_default(),
+            input_len: input.len(),
+            to_label: labels.get(&to).cloned(),
+        }
+    }
+
+    /// Decodes the constructor `arguments` of a contract creation with the `constructor` of the
+    /// created contract, if known.
+    pub fn create(
+        init_code: &[u8],
+        constructor: Option<(&Constructor, &[u8])>,
+        labels: &HashMap<Address, String>,
+    ) -> Self {
+        let decoded = constructor.and_then(|(constructor, arguments)| {
+            let args = constructor.abi_decode_input(arguments, false).ok()?;
+            let types = constructor.inputs.iter().map(|param| param.selector_type());
+            let signature = format!("constructor({})", types.collect::<Vec<_>>().join(","));
+            Some((signature, format_args(&args, labels)))
+        });
+        let (signature, args) = decoded.unzip();
+        Self {
+            to: None,
+            selector: None,
+            signature,
+            args: args.unwrap_or_default(),
+            input_len: init_code.len(),
+            to_label: None,
+        }
+    }
+}
+
+/// Resolves the function of `selector` with the local `abi` of the called contract, then with
+/// the signatures of OpenChain.
+pub async fn resolve_function(
+    abi: Option<&JsonAbi>,
+    identifier: Option<&SingleSignaturesIdentifier>,
+    selector: Selector,
+) -> Option<Function> {
+    let local = abi
+        .and_then(|abi| abi.functions().find(|function| function.selector() == selector).cloned());
+    match (local, identifier) {
+        (Some(function), _) => Some(function),
+        (None, Some(identifier)) => identifier.write().await.identify_function(&selector[..]).await,
+        (None, None) => None,
+    }
+}
+
+fn format_args(args: &[DynSolValue], labels: &HashMap<Address, String>) -> Vec<String> {
+    args.iter()
+        .map(|arg| match arg {
+            DynSolValue::Address(address) if labels.contains_key(address) => {
+                format!("{}: [{address}]", labels[address])
+            }
+            arg => format_token(arg),
+        })
+        .collect()
+}
+
+impl fmt::Display for CallSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(to) = self.to else {
+            write!(f, "Deploys a contract with {} bytes of init code", self.input_len)?;
+            return match &self.signature {
+                Some(signature) => write!(f, ", {signature} args: {}", self.args.join(", ")),
+                None => Ok(()),
+            };
+        };
+        let to = match &self.to_label {
+            Some(label) => format!("{label}: [{to}]"),
+            None => to.to_string(),
+        };
+        match (self.selector, &self.signature) {
+            (None, _) => write!(f, "Calls {to} with {} bytes of calldata", self.input_len),
+            (Some(selector), None) => write!(
+                f,
+                "Calls the unknown function {} of {to} with {} bytes of calldata",
+                hex::encode_prefixed(selector),
+                self.input_len
+            ),
+            (Some(selector), Some(signature)) => write!(
+                f,
+                "Calls {signature} ({}) of {to}: {}",
+                hex::encode_prefixed(selector),
+                self.args.join(", ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U256;
+    use alloy_sol_types::SolValue;
+
+    #[test]
+    fn summarizes_calls() {
+        let function = Function::parse("transfer(address to, uint256 amount)").unwrap();
+        let (token, alice) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let args = (alice, U256::from(100)).abi_encode_params();
+        let input = [function.selector().as_slice(), &args].concat();
+        let labels = HashMap::from([(token, "Token".to_string()), (alice, "Alice".to_string())]);
+
+        let summary = CallSummary::call(token, &input, Some(&function), &labels);
+        assert_eq!(summary.signature.as_deref(), Some("transfer(address,uint256)"));
+        assert_eq!(
+            summary.to_string(),
+            format!(
+                "Calls transfer(address,uint256) (0xa9059cbb) of Token: [{token}]: Alice: \
+                 [{alice}], 100"
+            )
+        );
+
+        // calldata that doesn't match the function isn't decoded
+        let summary = CallSummary::call(token, &input[..20], Some(&function), &HashMap::new());
+        assert_eq!(summary.signature, None);
+        assert_eq!(
+            summary.to_string(),
+            format!("Calls the unknown function 0xa9059cbb of {token} with 20 bytes of calldata")
+        );
+        let summary = CallSummary::call(token, &[], None, &HashMap::new());
+        assert_eq!(summary.to_string(), format!("Calls {token} with 0 bytes of calldata"));
+    }
+
+    #[test]
+    fn summarizes_creations() {
+        let abi = JsonAbi::parse(["constructor(address owner, uint256 supply)"]).unwrap();
+        let constructor = abi.constructor().unwrap();
+        let args = (Address::with_last_byte(2), U256::from(5)).abi_encode_params();
+        let init_code = [&[0x60, 0x80][..], &args].concat();
+
+        let summary = CallSummary::create(&init_code, Some((constructor, &args)), &HashMap::new());
+        assert_eq!(
+            summary.to_string(),
+            format!(
+                "Deploys a contract with 66 bytes of init code, constructor(address,uint256) \
+                 args: {}, 5",
+                Address::with_last_byte(2)
+            )
+        );
+        let summary = CallSummary::create(&init_code, None, &HashMap::new());
+        assert_eq!(summary.to_string(), "Deploys a contract with 66 bytes of init code");
+    }
+}
@@ -0,0 +1,60 @@
+//! The payments to the coinbase of `--coinbase-balance`, e.g. the builder payments of searchers.
+
+use super::state_diff::Change;
+use alloy_primitives::{utils::format_ether, Address, U256};
+use cast::revm::primitives::EvmState;
+use serde::Serialize;
+use std::fmt;
+
+/// The balance of the coinbase of the block before and after the transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct CoinbaseBalance {
+    pub coinbase: Address,
+    pub balance: Change<U256>,
+}
+
+impl CoinbaseBalance {
+    /// Reads the balance of the coinbase after the transaction from its `changeset`, the balance
+    /// is unchanged if the transaction didn't touch the coinbase.
+    pub fn new(coinbase: Address, before: U256, changeset: &EvmState) -> Self {
+        let after = changeset.get(&coinbase).map_or(before, |account| account.info.balance);
+        Self { coinbase, balance: Change { before, after } }
+    }
+}
+
+impl fmt::Display for CoinbaseBalance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Change { before, after } = self.balance;
+        let (sign, delta) =
+            if after >= before { ('+', after - before) } else { ('-', before - after) };
+        write!(
+            f,
+            "Coinbase {} balance: {before} -> {after} ({sign}{delta} wei, {sign}{} ETH)",
+            self.coinbase,
+            format_ether(delta)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cast::revm::primitives::{Account, AccountInfo};
+
+    #[test]
+    fn reports_coinbase_payment() {
+        let coinbase = Address::with_last_byte(1);
+        let mut changeset = EvmState::default();
+        let balance = CoinbaseBalance::new(coinbase, U256::from(10), &changeset);
+        assert_eq!(balance.balance, Change { before: U256::from(10), after: U256::from(10) });
+
+        changeset.insert(coinbase, Account::from(AccountInfo::from_balance(U256::from(25))));
+        let balance = CoinbaseBalance::new(coinbase, U256::from(10), &changeset);
+        assert_eq!(
+            balance.to_string(),
+            format!("Coinbase {coinbase} balance: 10 -> 25 (+15 wei, +0.000000000000000015 ETH)")
+        );
+        let json = serde_json::to_value(balance).unwrap();
+        assert_eq!(json["balance"]["after"], "0x19");
+    }
+}
@@ -0,0 +1,149 @@
+//! Line coverage of the contracts of tweak projects.
+
+use alloy_primitives::Bytes;
+use cast::traces::CallTraceArena;
+use eyre::{eyre, Result};
+use foundry_common::compile::ContractSources;
+use foundry_compilers::artifacts::sourcemap::SourceElement;
+use foundry_evm::utils::PcIcMap;
+use foundry_tweak::{ClonedProject, TweakData};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    path::PathBuf,
+};
+
+/// The hits of the source lines of the tweaked contracts, per source file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineCoverage {
+    files: BTreeMap<PathBuf, BTreeMap<usize, u64>>,
+}
+
+impl LineCoverage {
+    /// Records the source lines of the main contract of `project`, deployed with the runtime
+    /// `code`, that were executed in `arena`.
+    ///
+    /// All lines the source map of the contract refers to are reported, executed or not.
+    pub fn record(
+        &mut self,
+        project: &ClonedProject,
+        code: &[u8],
+        arena: &CallTraceArena,
+    ) -> Result<()> {
+        let name = &project.metadata.target_contract;
+        let output = project.compile_safe()?;
+        let sources = ContractSources::from_project_output(&output, &project.root, None)?;
+        let (artifact, _) = sources
+            .get_sources(name)
+            .and_then(|mut sources| sources.next())
+            .ok_or_else(|| eyre!("no sources found for contract {name}"))?;
+        let source_map = artifact
+            .bytecode
+            .deployed_bytecode
+            .bytecode
+            .as_ref()
+            .and_then(|bytecode| bytecode.source_map())
+            .ok_or_else(|| eyre!("no source map found for contract {name}"))?
+            .map_err(|e| eyre!("failed to parse the source map of contract {name}: {e}"))?;
+        let files = sources
+            .sources_by_id
+            .get(&artifact.build_id)
+            .ok_or_else(|| eyre!("no sources found for contract {name}"))?;
+
+        let lines = files
+            .iter()
+            .map(|(id, source)| (*id, line_starts(&source.source)))
+            .collect::<HashMap<_, _>>();
+        let line_of = |element: &SourceElement| {
+            let id = element.index()?;
+            let line = line_at(lines.get(&id)?, element.offset() as usize);
+            Some((project.root.join(&files.get(&id)?.name), line))
+        };
+
+        for (path, line) in source_map.iter().filter_map(line_of) {
+            self.files.entry(path).or_default().entry(line).or_default();
+        }
+
+        let pc_ic_map = PcIcMap::new(code);
+        let address = project.tweaked_address();
+        let steps = arena
+            .nodes()
+            .iter()
+            .filter(|node| node.trace.address == address && !node.trace.kind.is_any_create())
+            .flat_map(|node| &node.trace.steps);
+        for step in steps {
+            let element = pc_ic_map.get(step.pc).and_then(|ic| source_map.get(ic));
+            if let Some((path, line)) = element.and_then(line_of) {
+                *self.files.entry(path).or_default().entry(line).or_default() += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the coverage in the LCOV format.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        for (path, lines) in &self.files {
+            writeln!(lcov, "TN:").unwrap();
+            writeln!(lcov, "SF:{}", path.display()).unwrap();
+            for (line, hits) in lines {
+                writeln!(lcov, "DA:{line},{hits}").unwrap();
+            }
+            writeln!(lcov, "LF:{}", lines.len()).unwrap();
+            writeln!(lcov, "LH:{}", lines.values().filter(|hits| **hits > 0).count()).unwrap();
+            writeln!(lcov, "end_of_record").unwrap();
+        }
+        lcov
+    }
+}
+
+/// Returns the tweak projects whose coverage is recorded, with the runtime code they're deployed
+/// with, i.e. the `tweak_map` code of the address they tweak.
+pub fn covered_projects<'a>(
+    projects: &'a [ClonedProject],
+    tweak_map: &'a TweakData,
+) -> Vec<(&'a ClonedProject, &'a Bytes)> {
+    projects
+        .iter()
+        .filter_map(|project| Some((project, tweak_map.get(&project.tweaked_address())?)))
+        .collect()
+}
+
+/// Returns the byte offsets at which the lines of `source` start.
+fn line_starts(source: &str) -> Vec<usize> {
+    std::iter::once(0).chain(source.match_indices('\n').map(|(i, _)| i + 1)).collect()
+}
+
+/// Returns the 1-based line of the byte `offset`.
+fn line_at(line_starts: &[usize], offset: usize) -> usize {
+    line_starts.partition_point(|start| *start <= offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_offsets_to_lines() {
+        let starts = line_starts("contract A {\n    uint x;\n}\n");
+        assert_eq!(line_at(&starts, 0), 1);
+        assert_eq!(line_at(&starts, 12), 1);
+        assert_eq!(line_at(&starts, 13), 2);
+        assert_eq!(line_at(&starts, 26), 3);
+    }
+
+    #[test]
+    fn writes_lcov() {
+        let coverage = LineCoverage {
+            files: BTreeMap::from([(
+                PathBuf::from("/project/src/A.sol"),
+                BTreeMap::from([(3, 2), (4, 0)]),
+            )]),
+        };
+        assert_eq!(
+            coverage.to_lcov(),
+            "TN:\nSF:/project/src/A.sol\nDA:3,2\nDA:4,0\nLF:2\nLH:1\nend_of_record\n"
+        );
+    }
+}
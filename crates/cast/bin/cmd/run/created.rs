@@ -0,0 +1,98 @@
+//! Listing of the contracts created by a transaction.
+
+use alloy_primitives::{keccak256, Address, B256};
+use cast::traces::{CallKind, CallTraceArena};
+use clap::ValueEnum;
+use comfy_table::{presets::ASCII_MARKDOWN, Table};
+use serde::Serialize;
+
+/// The output format of `--created-contracts`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum CreatedContractsFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+/// A contract created by the transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedContract {
+    pub address: Address,
+    pub deployer: Address,
+    /// Either `CREATE` or `CREATE2`.
+    pub kind: &'static str,
+    pub init_code_size: usize,
+    pub runtime_code_size: usize,
+    pub code_hash: B256,
+}
+
+/// Returns the contracts that were successfully created during the transaction, in execution
+/// order.
+pub fn created_contracts(arena: &CallTraceArena) -> Vec<CreatedContract> {
+    arena
+        .nodes()
+        .iter()
+        .map(|node| &node.trace)
+        .filter(|trace| trace.kind.is_any_create() && trace.success)
+        .map(|trace| CreatedContract {
+            address: trace.address,
+            deployer: trace.caller,
+            kind: if trace.kind == CallKind::Create2 { "CREATE2" } else { "CREATE" },
+            init_code_size: trace.data.len(),
+            runtime_code_size: trace.output.len(),
+            code_hash: keccak256(&trace.output),
+        })
+        .collect()
+}
+
+/// Formats the created contracts as a table.
+pub fn table(contracts: &[CreatedContract]) -> Table {
+    let mut table = Table::new();
+    table.load_preset(ASCII_MARKDOWN);
+    table.set_header(["Address", "Deployer", "Kind", "Init code", "Runtime code", "Code hash"]);
+    for contract in contracts {
+        table.add_row([
+            contract.address.to_string(),
+            contract.deployer.to_string(),
+            contract.kind.to_string(),
+            contract.init_code_size.to_string(),
+            contract.runtime_code_size.to_string(),
+            contract.code_hash.to_string(),
+        ]);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+    use alloy_primitives::Bytes;
+
+    #[test]
+    fn lists_successful_creations() {
+        let mut arena = arena(&[(None, 100_000), (Some(0), 60_000), (Some(0), 5_000)]);
+        for (idx, node) in arena.nodes_mut().iter_mut().enumerate() {
+            node.trace.kind = if idx == 0 { CallKind::Call } else { CallKind::Create2 };
+            node.trace.success = true;
+            node.trace.address = Address::with_last_byte(idx as u8);
+            node.trace.caller = Address::with_last_byte(0xff);
+            node.trace.data = Bytes::from_static(&[0x60, 0x00, 0xf3]);
+            node.trace.output = Bytes::from_static(&[0x00]);
+        }
+        arena.nodes_mut()[2].trace.success = false;
+
+        assert_eq!(
+            created_contracts(&arena),
+            vec![CreatedContract {
+                address: Address::with_last_byte(1),
+                deployer: Address::with_last_byte(0xff),
+                kind: "CREATE2",
+                init_code_size: 3,
+                runtime_code_size: 1,
+                code_hash: keccak256([0x00]),
+            }]
+        );
+    }
+}
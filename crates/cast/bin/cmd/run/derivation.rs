@@ -0,0 +1,226 @@
+//! The address derivations of the creations and the beneficiaries of the selfdestructs of
+//! `--trace-creations`.
+
+use alloy_primitives::{keccak256, Address, B256, U256};
+use cast::{
+    revm::interpreter::OpCode,
+    traces::{CallKind, CallTraceArena, TraceMemberOrder},
+};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// How the address of a created contract was derived, or where the balance of a destructed one
+/// went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Derivation {
+    /// A `CREATE`, whose address derives from the nonce of the deployer, which isn't traced.
+    #[serde(rename = "CREATE", rename_all = "camelCase")]
+    Create { deployer: Address, address: Address },
+    /// A `CREATE2`, whose address derives from the deployer, the salt and the init code hash.
+    #[serde(rename = "CREATE2", rename_all = "camelCase")]
+    Create2 {
+        deployer: Address,
+        address: Address,
+        /// The salt, read from the stack of the creating step if it was recorded.
+        salt: Option<B256>,
+        init_code_hash: B256,
+        /// The address derived from the salt, `None` without salt.
+        predicted: Option<Address>,
+    },
+    /// A `SELFDESTRUCT`, transferring the balance of the contract to its beneficiary.
+    #[serde(rename = "SELFDESTRUCT", rename_all = "camelCase")]
+    Selfdestruct { address: Address, beneficiary: Address, value: Option<U256> },
+}
+
+/// Returns the derivations of the creations and selfdestructs of the transaction, with the index
+/// of the node they annotate, in the order of the nodes.
+pub fn derivations(arena: &CallTraceArena) -> Vec<(usize, Derivation)> {
+    let nodes = arena.nodes();
+    // the salts of the `CREATE2` steps, by the index of the node they created
+    let mut salts = HashMap::new();
+    for node in nodes {
+        let mut last_create2 = None;
+        for item in &node.ordering {
+            match *item {
+                TraceMemberOrder::Step(step) if node.trace.steps[step].op == OpCode::CREATE2 => {
+                    last_create2 = Some(step);
+                }
+                TraceMemberOrder::Call(child) => {
+                    let Some(step) = last_create2.take() else { continue };
+                    // CREATE2 pops the value, the offset and size of the init code, and the salt
+                    let salt = node.trace.steps[step]
+                        .stack
+                        .as_ref()
+                        .and_then(|stack| stack.iter().rev().nth(3))
+                        .map(|salt| B256::from(*salt));
+                    if let Some(salt) = salt {
+                        salts.insert(node.children[child], salt);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut derivations = vec![];
+    for (idx, node) in nodes.iter().enumerate() {
+        let trace = &node.trace;
+        match trace.kind {
+            CallKind::Create => derivations
+                .push((idx, Derivation::Create { deployer: trace.caller, address: trace.address })),
+            CallKind::Create2 => {
+                let salt = salts.get(&idx).copied();
+                let init_code_hash = keccak256(&trace.data);
+                derivations.push((
+                    idx,
+                    Derivation::Create2 {
+                        deployer: trace.caller,
+                        address: trace.address,
+                        salt,
+                        init_code_hash,
+                        predicted: salt.map(|salt| trace.caller.create2(salt, init_code_hash)),
+                    },
+                ));
+            }
+            _ => {}
+        }
+        if let Some(beneficiary) = trace.selfdestruct_refund_target {
+            derivations.push((
+                idx,
+                Derivation::Selfdestruct {
+                    address: trace.address,
+                    beneficiary,
+                    value: trace.selfdestruct_transferred_value,
+                },
+            ));
+        }
+    }
+    derivations
+}
+
+/// Appends the derivations to the names of the calls they annotate, e.g.
+/// `Clone [CREATE2 by Factory, salt 0x01, init code hash 0xab]`, the addresses being named after
+/// `labels`.
+pub fn annotate(
+    arena: &mut CallTraceArena,
+    derivations: &[(usize, Derivation)],
+    labels: &HashMap<Address, String>,
+) {
+    let name = |address: &Address| labels.get(address).cloned().unwrap_or(address.to_string());
+    for (idx, derivation) in derivations {
+        let annotation = match derivation {
+            Derivation::Create { deployer, .. } => format!("CREATE by {}", name(deployer)),
+            Derivation::Create2 { deployer, address, salt, init_code_hash, predicted } => {
+                let mut annotation = format!("CREATE2 by {}", name(deployer));
+                if let Some(salt) = salt {
+                    annotation.push_str(&format!(", salt {salt}"));
+                }
+                annotation.push_str(&format!(", init code hash {init_code_hash}"));
+                if let Some(predicted) = predicted.filter(|predicted| predicted != address) {
+                    annotation.push_str(&format!(", predicted {predicted}"));
+                }
+                annotation
+            }
+            Derivation::Selfdestruct { beneficiary, value, .. } => {
+                let mut annotation = format!("SELFDESTRUCT to {}", name(beneficiary));
+                if let Some(value) = value {
+                    annotation.push_str(&format!(", {value} wei"));
+                }
+                annotation
+            }
+        };
+        let trace = &mut arena.nodes_mut()[*idx].trace;
+        let label = trace.decoded.label.clone().unwrap_or_else(|| name(&trace.address));
+        trace.decoded.label = Some(format!("{label} [{annotation}]"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+    use cast::{revm::interpreter::InstructionResult, traces::CallTraceStep};
+
+    #[test]
+    fn derives_creations_and_selfdestructs() {
+        let (factory, beneficiary) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let mut traces = arena(&[(None, 100_000), (Some(0), 50_000), (Some(0), 20_000)]);
+        let salt = B256::with_last_byte(7);
+        let init_code = [0x60, 0x00];
+        let clone = factory.create2_from_code(salt, init_code);
+        let step = CallTraceStep {
+            depth: 0,
+            pc: 0,
+            op: OpCode::CREATE2,
+            contract: factory,
+            // the top of the stack is last
+            stack: Some(vec![salt.into(), U256::from(2), U256::ZERO, U256::ZERO]),
+            push_stack: None,
+            memory: None,
+            returndata: Default::default(),
+            gas_remaining: 0,
+            gas_refund_counter: 0,
+            gas_used: 0,
+            gas_cost: 0,
+            storage_change: None,
+            status: InstructionResult::Continue,
+            decoded: None,
+        };
+        let nodes = traces.nodes_mut();
+        nodes[0].trace.address = factory;
+        nodes[0].trace.steps.push(step);
+        nodes[0].ordering.insert(0, TraceMemberOrder::Step(0));
+        nodes[1].trace.kind = CallKind::Create2;
+        nodes[1].trace.caller = factory;
+        nodes[1].trace.address = clone;
+        nodes[1].trace.data = init_code.into();
+        nodes[2].trace.kind = CallKind::Call;
+        nodes[2].trace.address = clone;
+        nodes[2].trace.selfdestruct_refund_target = Some(beneficiary);
+        nodes[2].trace.selfdestruct_transferred_value = Some(U256::from(5));
+
+        let derived = derivations(&traces);
+        assert_eq!(
+            derived,
+            [
+                (
+                    1,
+                    Derivation::Create2 {
+                        deployer: factory,
+                        address: clone,
+                        salt: Some(salt),
+                        init_code_hash: keccak256(init_code),
+                        predicted: Some(clone),
+                    }
+                ),
+                (
+                    2,
+                    Derivation::Selfdestruct {
+                        address: clone,
+                        beneficiary,
+                        value: Some(U256::from(5))
+                    }
+                ),
+            ]
+        );
+        let json = serde_json::to_value(derived[1].1).unwrap();
+        assert_eq!(json["kind"], "SELFDESTRUCT");
+
+        traces.nodes_mut()[1].trace.decoded.label = Some("Clone".to_string());
+        let labels = HashMap::from([(factory, "Factory".to_string())]);
+        annotate(&mut traces, &derived, &labels);
+        let nodes = traces.nodes();
+        assert_eq!(
+            nodes[1].trace.decoded.label.as_deref().unwrap(),
+            format!(
+                "Clone [CREATE2 by Factory, salt {salt}, init code hash {}]",
+                keccak256(init_code)
+            )
+        );
+        assert_eq!(
+            nodes[2].trace.decoded.label.as_deref().unwrap(),
+            format!("{clone} [SELFDESTRUCT to {beneficiary}, 5 wei]")
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Dumping of the replayed state for use in `forge` tests.
+
+use alloy_genesis::GenesisAccount;
+use alloy_primitives::{Address, B256, U256};
+use cast::{
+    backend::Backend,
+    revm::{
+        db::{AccountState, CacheDB},
+        primitives::KECCAK_EMPTY,
+        DatabaseRef,
+    },
+};
+use eyre::Result;
+use std::{collections::BTreeMap, str::FromStr};
+
+/// A storage slot of `--dump-slot` to include in the dumped account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DumpSlot {
+    pub address: Address,
+    pub slot: U256,
+}
+
+impl FromStr for DumpSlot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, slot) =
+            s.split_once(':').ok_or_else(|| format!("expected `<address>:<slot>`, got `{s}`"))?;
+        Ok(Self {
+            address: address.parse().map_err(|e| format!("invalid address `{address}`: {e}"))?,
+            slot: slot.parse().map_err(|e| format!("invalid slot `{slot}`: {e}"))?,
+        })
+    }
+}
+
+/// Returns the state of all accounts loaded by the replay, after the replay, in the format of
+/// `vm.dumpState` which can be loaded with `vm.loadAllocs`.
+///
+/// Only the loaded storage slots are included, the remaining state is the one of the forked
+/// block.
+pub fn replayed_state(backend: &Backend) -> BTreeMap<Address, GenesisAccount> {
+    match backend.active_fork_db() {
+        Some(db) => allocs(db),
+        None => allocs(backend.mem_db()),
+    }
+}
+
+/// Returns the accounts of the fork that the replay destroyed or created, or that don't exist,
+/// whose storage isn't the one of the forked block. [`replayed_state`] leaves out the ones that
+/// don't exist, e.g. the accounts destroyed by a `SELFDESTRUCT`.
+pub fn cleared_accounts(backend: &Backend) -> Vec<Address> {
+    let Some(db) = backend.active_fork_db() else { return vec![] };
+    db.accounts
+        .iter()
+        .filter(|(_, account)| {
+            matches!(
+                account.account_state,
+                AccountState::NotExisting | AccountState::StorageCleared
+            )
+        })
+        .map(|(address, _)| *address)
+        .collect()
+}
+
+/// Returns the state of the accounts `addresses` after the replay, in the same format as
+/// [`replayed_state`].
+///
+/// The storage holds the slots loaded by the replay and the ones of `slots`, which are read from
+/// the fork if they weren't loaded.
+pub fn account_states(
+    backend: &Backend,
+    addresses: &[Address],
+    slots: &[DumpSlot],
+) -> Result<BTreeMap<Address, GenesisAccount>> {
+    let mut loaded = replayed_state(backend);
+    let mut accounts = BTreeMap::new();
+    for &address in addresses {
+        let mut account = match loaded.remove(&address) {
+            Some(account) => account,
+            None => {
+                let info = backend.basic_ref(address)?.unwrap_or_default();
+                let code = match &info.code {
+                    Some(code) => Some(code.clone()),
+                    None if info.code_hash != KECCAK_EMPTY => {
+                        Some(backend.code_by_hash_ref(info.code_hash)?)
+                    }
+                    None => None,
+                };
+                GenesisAccount {
+                    nonce: Some(info.nonce),
+                    balance: info.balance,
+                    code: code.map(|code| code.original_bytes()).filter(|code| !code.is_empty()),
+                    storage: Some(BTreeMap::new()),
+                    private_key: None,
+                }
+            }
+        };
+        let storage = account.storage.get_or_insert_with(BTreeMap::new);
+        for slot in slots.iter().filter(|slot| slot.address == address) {
+            if let std::collections::btree_map::Entry::Vacant(entry) =
+                storage.entry(B256::from(slot.slot))
+            {
+                entry.insert(B256::from(backend.storage_ref(address, slot.slot)?));
+            }
+        }
+        accounts.insert(address, account);
+    }
+    Ok(accounts)
+}
+
+fn allocs<ExtDB>(db: &CacheDB<ExtDB>) -> BTreeMap<Address, GenesisAccount> {
+    db.accounts
+        .iter()
+        .filter(|(_, account)| {
+            let empty = account.info.is_empty() && account.storage.is_empty();
+            !empty && !matches!(account.account_state, AccountState::NotExisting)
+        })
+        .map(|(address, account)| {
+            let info = &account.info;
+            let code = info
+                .code
+                .as_ref()
+                .or_else(|| db.contracts.get(&info.code_hash))
+                .filter(|_| info.code_hash != KECCAK_EMPTY)
+                .map(|code| code.original_bytes());
+            let storage = account
+                .storage
+                .iter()
+                .map(|(slot, value)| (B256::from(*slot), B256::from(*value)))
+                .collect();
+            let account = GenesisAccount {
+                nonce: Some(info.nonce),
+                balance: info.balance,
+                code,
+                storage: Some(storage),
+                private_key: None,
+            };
+            (*address, account)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Bytes, U256};
+    use cast::revm::{
+        db::EmptyDB,
+        primitives::{AccountInfo, Bytecode},
+    };
+
+    #[test]
+    fn dumps_loaded_accounts() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        let contract = Address::with_last_byte(1);
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x00]));
+        let mut info = AccountInfo {
+            balance: U256::from(5),
+            nonce: 1,
+            code: Some(code),
+            ..Default::default()
+        };
+        db.insert_contract(&mut info);
+        db.insert_account_info(contract, info);
+        db.insert_account_storage(contract, U256::from(1), U256::from(2)).unwrap();
+        // loading a missing account caches it as not existing
+        db.load_account(Address::with_last_byte(2)).unwrap();
+
+        let allocs = allocs(&db);
+        assert_eq!(allocs.keys().collect::<Vec<_>>(), vec![&contract]);
+        let account = &allocs[&contract];
+        assert_eq!((account.nonce, account.balance), (Some(1), U256::from(5)));
+        assert_eq!(account.code, Some(Bytes::from_static(&[0x60, 0x00])));
+        assert_eq!(
+            account.storage,
+            Some([(B256::with_last_byte(1), B256::with_last_byte(2))].into_iter().collect())
+        );
+    }
+
+    #[test]
+    fn dumps_requested_accounts() {
+        let mut backend = Backend::spawn(None);
+        let (contract, missing) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        backend.insert_account_info(contract, AccountInfo::from_balance(U256::from(5)));
+        backend.insert_account_storage(contract, U256::from(1), U256::from(2)).unwrap();
+
+        let slot: DumpSlot = format!("{missing}:0x3").parse().unwrap();
+        assert_eq!(slot, DumpSlot { address: missing, slot: U256::from(3) });
+        assert!("0x3".parse::<DumpSlot>().is_err());
+
+        let accounts = account_states(&backend, &[contract, missing], &[slot]).unwrap();
+        assert_eq!(accounts[&contract].balance, U256::from(5));
+        assert_eq!(accounts[&contract].storage.as_ref().unwrap().len(), 1);
+        let storage = accounts[&missing].storage.as_ref().unwrap();
+        assert_eq!(storage[&B256::with_last_byte(3)], B256::ZERO);
+        assert_eq!(accounts[&missing].code, None);
+    }
+}
@@ -0,0 +1,400 @@
+//! Rendering filters for the traces of a replayed transaction.
+//!
+//! Filters only hide calls from the rendered trace by unlinking them from their parent's
+//! ordering, the recorded nodes themselves are left untouched. The only exception is
+//! [`subtree`], which copies a subtree into an arena of its own for rendering.
+
+use alloy_primitives::Address;
+use cast::{
+    revm::interpreter::{InstructionResult, OpCode},
+    traces::{CallTraceArena, CallTraceStep, DecodedTraceStep, TraceMemberOrder},
+};
+use eyre::Result;
+use serde::{Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+/// Hides all calls, along with their subtrees, for which `hide` returns `true`.
+///
+/// The root call is always kept. Returns the number of hidden calls, excluding the calls in hidden
+/// subtrees.
+pub fn hide_calls(arena: &mut CallTraceArena, hide: impl Fn(usize) -> bool) -> usize {
+    let hidden = (0..arena.nodes().len()).map(|idx| idx != 0 && hide(idx)).collect::<Vec<_>>();
+
+    for node in arena.nodes_mut() {
+        let children = &node.children;
+        node.ordering
+            .retain(|item| !matches!(item, TraceMemberOrder::Call(i) if hidden[children[*i]]));
+    }
+
+    let visible = visible_calls(arena);
+    arena
+        .nodes()
+        .iter()
+        .filter(|node| hidden[node.idx] && node.parent.is_some_and(|parent| visible[parent]))
+        .count()
+}
+
+/// Returns whether each call of the arena is reachable from the root, i.e. is rendered.
+pub fn visible_calls(arena: &CallTraceArena) -> Vec<bool> {
+    let nodes = arena.nodes();
+    let mut visible = vec![false; nodes.len()];
+    let mut stack = vec![0];
+    while let Some(idx) = stack.pop() {
+        visible[idx] = true;
+        stack.extend(nodes[idx].ordering.iter().filter_map(|item| match item {
+            TraceMemberOrder::Call(i) => Some(nodes[idx].children[*i]),
+            _ => None,
+        }));
+    }
+    visible
+}
+
+/// Hides all calls that consumed less than `min_gas`.
+///
+/// Since the gas of a call includes the gas of its subcalls, hiding a call never hides a call
+/// above the threshold.
+pub fn min_call_gas(arena: &mut CallTraceArena, min_gas: u64) -> usize {
+    let gas_used = arena.nodes().iter().map(|node| node.trace.gas_used).collect::<Vec<_>>();
+    hide_calls(arena, |idx| gas_used[idx] < min_gas)
+}
+
+/// Returns the index of the first revert of the arena, i.e. the first call in execution order that
+/// failed without a failed subcall, the point a bubbling revert originated from.
+pub fn first_revert(arena: &CallTraceArena) -> Option<usize> {
+    let nodes = arena.nodes();
+    let reverted = nodes.iter().find(|node| {
+        !node.trace.success && node.children.iter().all(|&child| nodes[child].trace.success)
+    })?;
+    Some(reverted.idx)
+}
+
+/// Hides all calls except the ones on the path from the root to the first revert, see
+/// [`first_revert`]. Returns the number of hidden calls, or `None` if no call failed.
+pub fn revert_path_only(arena: &mut CallTraceArena) -> Option<usize> {
+    let reverted = first_revert(arena)?;
+    let nodes = arena.nodes();
+    let mut on_path = vec![false; nodes.len()];
+    let mut idx = Some(reverted);
+    while let Some(i) = idx {
+        on_path[i] = true;
+        idx = nodes[i].parent;
+    }
+    Some(hide_calls(arena, |idx| !on_path[idx]))
+}
+
+/// Hides all calls deeper than `max_depth`, the top-level call being at depth 0.
+///
+/// The calls at `max_depth` whose subcalls are collapsed end with a marker line.
+pub fn max_depth(arena: &mut CallTraceArena, max_depth: usize) -> usize {
+    let collapsed = arena
+        .nodes()
+        .iter()
+        .map(|node| {
+            let subcalls =
+                node.ordering.iter().filter(|item| matches!(item, TraceMemberOrder::Call(_)));
+            if node.trace.depth == max_depth {
+                subcalls.count()
+            } else {
+                0
+            }
+        })
+        .collect::<Vec<_>>();
+    let depths = arena.nodes().iter().map(|node| node.trace.depth).collect::<Vec<_>>();
+    let hidden = hide_calls(arena, |idx| depths[idx] > max_depth);
+
+    let visible = visible_calls(arena);
+    for node in arena.nodes_mut() {
+        if !visible[node.idx] || collapsed[node.idx] == 0 {
+            continue;
+        }
+        let line = format!("… {} subcall(s) collapsed", collapsed[node.idx]);
+        node.ordering.push(TraceMemberOrder::Step(node.trace.steps.len()));
+        node.trace.steps.push(marker_step(node.trace.address, node.trace.depth, line));
+    }
+    hidden
+}
+
+/// Returns a step that isn't executed but only renders `line` in the trace of the call.
+fn marker_step(contract: Address, depth: usize, line: String) -> CallTraceStep {
+    CallTraceStep {
+        depth: depth as u64,
+        pc: 0,
+        op: OpCode::STOP,
+        contract,
+        stack: None,
+        push_stack: None,
+        memory: None,
+        returndata: Default::default(),
+        gas_remaining: 0,
+        gas_refund_counter: 0,
+        gas_used: 0,
+        gas_cost: 0,
+        storage_change: None,
+        status: InstructionResult::Continue,
+        decoded: Some(DecodedTraceStep::Line(line)),
+    }
+}
+
+/// Hides all calls except the calls to any of `addresses` with their subcalls, and the calls
+/// leading to them so that they're rendered at their depth.
+pub fn trace_addresses(arena: &mut CallTraceArena, addresses: &[Address]) -> usize {
+    let nodes = arena.nodes();
+    // the subcalls of a call are recorded after it
+    let mut in_subtree = vec![false; nodes.len()];
+    for node in nodes {
+        in_subtree[node.idx] = addresses.contains(&node.trace.address) ||
+            node.parent.is_some_and(|parent| in_subtree[parent]);
+    }
+    let mut shown = in_subtree.clone();
+    for node in nodes.iter().filter(|node| in_subtree[node.idx]) {
+        let mut idx = node.parent;
+        while let Some(i) = idx.filter(|&i| !shown[i]) {
+            shown[i] = true;
+            idx = nodes[i].parent;
+        }
+    }
+    hide_calls(arena, |idx| !shown[idx])
+}
+
+/// The path to a call in the trace, e.g. `0.2.1` for the second subcall of the third subcall of
+/// the top-level call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TracePath(pub(super) Vec<usize>);
+
+impl FromStr for TracePath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('.')
+            .map(|position| position.parse().map_err(|_| format!("invalid trace path: {s}")))
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for TracePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, position) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "{position}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for TracePath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl TracePath {
+    /// Returns the path of the call `idx` of the arena.
+    pub fn of(arena: &CallTraceArena, idx: usize) -> Self {
+        let nodes = arena.nodes();
+        let mut positions = vec![];
+        let mut idx = idx;
+        while let Some(parent) = nodes[idx].parent {
+            positions.push(nodes[parent].children.iter().position(|&child| child == idx).unwrap());
+            idx = parent;
+        }
+        positions.push(0);
+        positions.reverse();
+        Self(positions)
+    }
+
+    /// Returns the index of the call the path designates in the arena.
+    pub fn resolve(&self, arena: &CallTraceArena) -> Result<usize> {
+        let nodes = arena.nodes();
+        let Some((&0, positions)) = self.0.split_first() else {
+            eyre::bail!("trace path {self} does not start at the top-level call 0");
+        };
+        let mut idx = 0;
+        for &position in positions {
+            idx = *nodes[idx]
+                .children
+                .get(position)
+                .ok_or_else(|| eyre::eyre!("trace path {self} does not exist in the trace"))?;
+        }
+        Ok(idx)
+    }
+}
+
+/// Returns an arena with the subtree of the call `idx` only, keeping its internal structure.
+pub fn subtree(arena: &CallTraceArena, idx: usize) -> CallTraceArena {
+    let nodes = arena.nodes();
+    let mut order = vec![idx];
+    let mut i = 0;
+    while let Some(&idx) = order.get(i) {
+        order.extend(&nodes[idx].children);
+        i += 1;
+    }
+
+    let mut new_idx = vec![usize::MAX; nodes.len()];
+    for (new, &old) in order.iter().enumerate() {
+        new_idx[old] = new;
+    }
+
+    let mut subtree = CallTraceArena::default();
+    let subtree_nodes = subtree.nodes_mut();
+    subtree_nodes.clear();
+    for &old in &order {
+        let mut node = nodes[old].clone();
+        node.idx = new_idx[old];
+        node.parent = if old == idx { None } else { node.parent.map(|parent| new_idx[parent]) };
+        for child in &mut node.children {
+            *child = new_idx[*child];
+        }
+        subtree_nodes.push(node);
+    }
+    subtree
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use cast::traces::CallTraceNode;
+
+    /// Builds an arena from `(parent, gas_used)` pairs, the first entry being the root.
+    pub fn arena(calls: &[(Option<usize>, u64)]) -> CallTraceArena {
+        let mut arena = CallTraceArena::default();
+        let nodes = arena.nodes_mut();
+        nodes.clear();
+        for (idx, (parent, gas_used)) in calls.iter().enumerate() {
+            let mut node = CallTraceNode { parent: *parent, idx, ..Default::default() };
+            node.trace.gas_used = *gas_used;
+            node.trace.depth = parent.map(|p| nodes[p].trace.depth + 1).unwrap_or_default();
+            if let Some(parent) = parent {
+                let parent = &mut nodes[*parent];
+                parent.ordering.push(TraceMemberOrder::Call(parent.children.len()));
+                parent.children.push(idx);
+            }
+            nodes.push(node);
+        }
+        arena
+    }
+
+    /// Returns the indices of the calls that are rendered, in rendering order.
+    pub fn rendered(arena: &CallTraceArena) -> Vec<usize> {
+        fn walk(arena: &CallTraceArena, idx: usize, out: &mut Vec<usize>) {
+            out.push(idx);
+            let node = &arena.nodes()[idx];
+            for item in &node.ordering {
+                if let TraceMemberOrder::Call(i) = item {
+                    walk(arena, node.children[*i], out);
+                }
+            }
+        }
+        let mut out = vec![];
+        walk(arena, 0, &mut out);
+        out
+    }
+
+    #[test]
+    fn keeps_revert_path() {
+        let mut arena = arena(&[
+            (None, 100_000),
+            (Some(0), 60_000),
+            (Some(0), 30_000),
+            (Some(2), 20_000),
+            (Some(3), 5_000),
+            (Some(3), 2_000),
+            (Some(0), 1_000),
+        ]);
+        for node in arena.nodes_mut() {
+            node.trace.success = true;
+        }
+        assert_eq!(revert_path_only(&mut arena.clone()), None);
+
+        for idx in [0, 2, 3, 4, 6] {
+            arena.nodes_mut()[idx].trace.success = false;
+        }
+        assert_eq!(revert_path_only(&mut arena), Some(3));
+        assert_eq!(rendered(&arena), vec![0, 2, 3, 4]);
+    }
+
+    #[test]
+    fn renders_subtree() {
+        let arena = arena(&[
+            (None, 100_000),
+            (Some(0), 60_000),
+            (Some(0), 30_000),
+            (Some(2), 20_000),
+            (Some(3), 5_000),
+            (Some(2), 2_000),
+        ]);
+        let path = "0.1".parse::<TracePath>().unwrap();
+        assert_eq!(path.resolve(&arena).unwrap(), 2);
+        assert_eq!(TracePath::of(&arena, 2), path);
+
+        let subtree = subtree(&arena, 2);
+        let gas_used = |arena: &CallTraceArena| {
+            rendered(arena).iter().map(|idx| arena.nodes()[*idx].trace.gas_used).collect::<Vec<_>>()
+        };
+        assert_eq!(gas_used(&subtree), vec![30_000, 20_000, 5_000, 2_000]);
+        assert_eq!(subtree.nodes()[0].parent, None);
+
+        assert!("0.2".parse::<TracePath>().unwrap().resolve(&arena).is_err());
+        assert!("1".parse::<TracePath>().unwrap().resolve(&arena).is_err());
+        assert!("0.x".parse::<TracePath>().is_err());
+    }
+
+    #[test]
+    fn keeps_calls_to_addresses() {
+        let mut arena = arena(&[
+            (None, 100_000),
+            (Some(0), 60_000),
+            (Some(1), 30_000),
+            (Some(2), 20_000),
+            (Some(1), 5_000),
+            (Some(0), 2_000),
+            (Some(5), 1_000),
+        ]);
+        let (vault, pool) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        arena.nodes_mut()[2].trace.address = vault;
+        arena.nodes_mut()[6].trace.address = pool;
+        assert_eq!(trace_addresses(&mut arena.clone(), &[vault]), 2);
+
+        assert_eq!(trace_addresses(&mut arena, &[vault, pool]), 1);
+        assert_eq!(rendered(&arena), vec![0, 1, 2, 3, 5, 6]);
+    }
+
+    #[test]
+    fn collapses_deep_calls() {
+        let mut arena = arena(&[
+            (None, 100_000),
+            (Some(0), 60_000),
+            (Some(1), 30_000),
+            (Some(2), 20_000),
+            (Some(1), 5_000),
+            (Some(0), 2_000),
+        ]);
+        let gas_used = arena.nodes().iter().map(|node| node.trace.gas_used).collect::<Vec<_>>();
+        assert_eq!(max_depth(&mut arena.clone(), 0), 2);
+
+        assert_eq!(max_depth(&mut arena, 1), 2);
+        assert_eq!(rendered(&arena), vec![0, 1, 5]);
+        let marker = &arena.nodes()[1];
+        assert_eq!(marker.ordering.last(), Some(&TraceMemberOrder::Step(0)));
+        assert_eq!(
+            marker.trace.steps[0].decoded,
+            Some(DecodedTraceStep::Line("… 2 subcall(s) collapsed".to_string()))
+        );
+        // calls without subcalls aren't marked, and the gas of the calls is left untouched
+        assert!(arena.nodes()[5].trace.steps.is_empty());
+        assert_eq!(
+            arena.nodes().iter().map(|node| node.trace.gas_used).collect::<Vec<_>>(),
+            gas_used
+        );
+    }
+
+    #[test]
+    fn hides_cheap_calls() {
+        let mut arena =
+            arena(&[(None, 100_000), (Some(0), 60_000), (Some(1), 5_000), (Some(0), 2_000)]);
+        assert_eq!(min_call_gas(&mut arena, 10_000), 2);
+        assert_eq!(rendered(&arena), vec![0, 1]);
+    }
+}
@@ -0,0 +1,170 @@
+//! Gas forwarding of calls and creations according to the EIP-150 63/64 rule.
+
+use cast::{
+    revm::interpreter::OpCode,
+    traces::{CallKind, CallTraceArena, DecodedTraceStep, TraceMemberOrder},
+};
+use std::fmt;
+
+/// The gas stipend added to calls that transfer value.
+const CALL_STIPEND: u64 = 2300;
+
+/// The opcodes that create a new call frame.
+pub const FRAME_OPCODES: [OpCode; 6] = [
+    OpCode::CALL,
+    OpCode::CALLCODE,
+    OpCode::DELEGATECALL,
+    OpCode::STATICCALL,
+    OpCode::CREATE,
+    OpCode::CREATE2,
+];
+
+/// The gas a caller forwarded to a sub-context, and the gas it retained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasForwarding {
+    /// The index of the calling node.
+    pub parent: usize,
+    /// The index of the step of the caller that created the sub-context.
+    pub step: usize,
+    /// The gas available to the caller before the call.
+    pub available: u64,
+    /// The cost of the call itself, e.g. for memory expansion and account access.
+    pub call_cost: u64,
+    /// The gas taken from the caller and forwarded to the sub-context.
+    pub forwarded: u64,
+    /// The stipend given to the sub-context on top of the forwarded gas.
+    pub stipend: u64,
+    /// Whether the forwarded gas was capped to all but one 64th of the remaining gas.
+    pub capped: bool,
+}
+
+impl GasForwarding {
+    /// Returns the gas retained by the caller.
+    pub fn retained(&self) -> u64 {
+        self.available.saturating_sub(self.call_cost + self.forwarded)
+    }
+
+    /// Annotates the step of the caller that created the sub-context.
+    pub fn annotate(&self, arena: &mut CallTraceArena) {
+        arena.nodes_mut()[self.parent].trace.steps[self.step].decoded =
+            Some(DecodedTraceStep::Line(self.to_string()));
+    }
+}
+
+impl fmt::Display for GasForwarding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gas: {} available, {} call cost, {} forwarded",
+            self.available, self.call_cost, self.forwarded
+        )?;
+        if self.capped {
+            f.write_str(" (63/64 cap)")?;
+        }
+        if self.stipend > 0 {
+            write!(f, " + {} stipend", self.stipend)?;
+        }
+        write!(f, ", {} retained", self.retained())
+    }
+}
+
+/// Returns the gas forwarding of every sub-context whose creating step was recorded.
+pub fn gas_forwarding(arena: &CallTraceArena) -> Vec<GasForwarding> {
+    let nodes = arena.nodes();
+    let mut forwarding = vec![];
+    for (parent, node) in nodes.iter().enumerate() {
+        let mut last_step = None;
+        for item in &node.ordering {
+            match *item {
+                TraceMemberOrder::Step(step)
+                    if FRAME_OPCODES.contains(&node.trace.steps[step].op) =>
+                {
+                    last_step = Some(step);
+                }
+                TraceMemberOrder::Call(child) => {
+                    let Some(step) = last_step.take() else { continue };
+                    let child = &nodes[node.children[child]].trace;
+                    let step_trace = &node.trace.steps[step];
+                    let stipend = if matches!(child.kind, CallKind::Call | CallKind::CallCode) &&
+                        !child.value.is_zero()
+                    {
+                        CALL_STIPEND
+                    } else {
+                        0
+                    };
+                    let forwarded = child.gas_limit.saturating_sub(stipend);
+                    // the cost of the step includes the forwarded gas
+                    let call_cost = step_trace.gas_cost.saturating_sub(forwarded);
+                    let remaining = step_trace.gas_remaining.saturating_sub(call_cost);
+                    forwarding.push(GasForwarding {
+                        parent,
+                        step,
+                        available: step_trace.gas_remaining,
+                        call_cost,
+                        forwarded,
+                        stipend,
+                        capped: forwarded == remaining - remaining / 64,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+    forwarding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+    use alloy_primitives::{Address, U256};
+    use cast::{revm::interpreter::InstructionResult, traces::CallTraceStep};
+
+    #[test]
+    fn applies_63_64_rule() {
+        let mut arena = arena(&[(None, 100_000), (Some(0), 60_000)]);
+        let step = CallTraceStep {
+            depth: 0,
+            pc: 0,
+            op: OpCode::CALL,
+            contract: Address::ZERO,
+            stack: None,
+            push_stack: None,
+            memory: None,
+            returndata: Default::default(),
+            gas_remaining: 100_000,
+            gas_refund_counter: 0,
+            gas_used: 0,
+            // 2600 for the cold account access, 9000 for the value transfer
+            gas_cost: 11_600 + 87_019,
+            storage_change: None,
+            status: InstructionResult::Continue,
+            decoded: None,
+        };
+        let nodes = arena.nodes_mut();
+        nodes[0].trace.steps.push(step);
+        nodes[0].ordering.insert(0, TraceMemberOrder::Step(0));
+        nodes[1].trace.kind = CallKind::Call;
+        nodes[1].trace.value = U256::from(1);
+        nodes[1].trace.gas_limit = 87_019 + CALL_STIPEND;
+
+        let forwarding = gas_forwarding(&arena);
+        assert_eq!(
+            forwarding,
+            vec![GasForwarding {
+                parent: 0,
+                step: 0,
+                available: 100_000,
+                call_cost: 11_600,
+                forwarded: 87_019,
+                stipend: CALL_STIPEND,
+                capped: true,
+            }]
+        );
+        assert_eq!(
+            forwarding[0].to_string(),
+            "gas: 100000 available, 11600 call cost, 87019 forwarded (63/64 cap) + 2300 stipend, \
+             1381 retained"
+        );
+    }
+}
@@ -0,0 +1,395 @@
+//! Gas accounting of a replayed transaction.
+
+use alloy_primitives::Address;
+use cast::{
+    executors::RawCallResult,
+    revm::{interpreter::OpCode, primitives::SpecId},
+    traces::{CallKind, CallTraceArena, CallTraceNode, TraceMemberOrder},
+};
+use serde::{Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+};
+
+/// The gas charged per byte of deployed code.
+const CODE_DEPOSIT_BYTE_COST: u64 = 200;
+
+/// Gas accounting of the executed transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct GasBreakdown {
+    /// The gas used by the transaction, net of refunds.
+    pub used: u64,
+    /// The intrinsic gas of the transaction.
+    pub intrinsic: u64,
+    /// The base cost of the transaction, part of the intrinsic gas.
+    pub base: u64,
+    /// The cost of the calldata, part of the intrinsic gas.
+    pub calldata: u64,
+    /// The gas refunded at the end of the transaction.
+    pub refunded: u64,
+}
+
+impl GasBreakdown {
+    pub fn from_raw(raw: &RawCallResult) -> Self {
+        let spec_id = raw.env.handler_cfg.spec_id;
+        let is_create = raw.env.tx.transact_to.is_create();
+        let base =
+            if is_create && spec_id.is_enabled_in(SpecId::HOMESTEAD) { 53_000 } else { 21_000 };
+        let non_zero_byte_cost = if spec_id.is_enabled_in(SpecId::ISTANBUL) { 16 } else { 68 };
+        let calldata = raw
+            .env
+            .tx
+            .data
+            .iter()
+            .map(|byte| if *byte == 0 { 4 } else { non_zero_byte_cost })
+            .sum();
+
+        Self {
+            used: raw.gas_used,
+            intrinsic: raw.stipend,
+            base,
+            calldata,
+            refunded: raw.gas_refunded,
+        }
+    }
+
+    /// Returns the gas spent in EVM execution, excluding the intrinsic gas.
+    ///
+    /// Refunds are already deducted from the gas used.
+    pub fn execution(&self) -> u64 {
+        self.used.saturating_sub(self.intrinsic)
+    }
+
+    /// Returns the gas spent in EVM execution before refunds are applied.
+    pub fn gross_execution(&self) -> u64 {
+        self.execution() + self.refunded
+    }
+
+    /// Returns the remaining intrinsic gas, i.e. the cost of the access list and the init code.
+    pub fn other_intrinsic(&self) -> u64 {
+        self.intrinsic.saturating_sub(self.base + self.calldata)
+    }
+
+    /// Returns the `--pure-execution-gas` report of the JSON output.
+    pub fn pure_execution(&self) -> serde_json::Value {
+        serde_json::json!({
+            "execution": self.execution(),
+            "intrinsic": self.intrinsic,
+            "refunded": self.refunded,
+        })
+    }
+
+    /// Explains the gas of the transaction in prose.
+    pub fn explain(&self, opcode_costs: &[OpcodeCost]) -> String {
+        let mut s = format!("Gas: {} base + {} calldata", self.base, self.calldata);
+        if self.other_intrinsic() > 0 {
+            write!(s, " + {} access list / init code", self.other_intrinsic()).unwrap();
+        }
+        write!(s, " + {} execution", self.gross_execution()).unwrap();
+        if let Some(top) = opcode_costs.first() {
+            write!(s, " (top cost: {} in {})", top.op.as_str(), top.address).unwrap();
+        }
+        if self.refunded > 0 {
+            write!(s, " - {} refund", self.refunded).unwrap();
+        }
+        write!(s, " = {} total", self.used).unwrap();
+
+        if !opcode_costs.is_empty() {
+            s.push_str("\nMost expensive opcodes:");
+            for cost in opcode_costs.iter().take(5) {
+                write!(
+                    s,
+                    "\n  {:<12} {:>10} gas ({} times) in {}",
+                    cost.op.as_str(),
+                    cost.gas,
+                    cost.count,
+                    cost.address
+                )
+                .unwrap();
+            }
+        }
+        s
+    }
+}
+
+/// The gas used by a top-level call of the transaction, including its subcalls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallGas {
+    pub address: Address,
+    pub kind: CallKind,
+    pub gas_used: u64,
+}
+
+/// A summary of the gas usage of the transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasReport {
+    #[serde(flatten)]
+    pub gas: GasBreakdown,
+    /// The calls made by the top-level call.
+    pub calls: Vec<CallGas>,
+    /// The gas used by the top-level call itself, excluding its calls.
+    pub own: u64,
+    /// The cost of storing the deployed code, for creations.
+    pub code_deposit: Option<u64>,
+}
+
+impl GasReport {
+    pub fn new(gas: GasBreakdown, arena: &CallTraceArena) -> Self {
+        let nodes = arena.nodes();
+        let root = &nodes[0];
+        let calls = root
+            .children
+            .iter()
+            .map(|&child| {
+                let trace = &nodes[child].trace;
+                CallGas { address: trace.address, kind: trace.kind, gas_used: trace.gas_used }
+            })
+            .collect::<Vec<_>>();
+        let own = root.trace.gas_used.saturating_sub(calls.iter().map(|call| call.gas_used).sum());
+        // the output of a successful creation is the deployed code
+        let code_deposit = root.trace.kind.is_any_create().then(|| {
+            if root.trace.success {
+                root.trace.output.len() as u64 * CODE_DEPOSIT_BYTE_COST
+            } else {
+                0
+            }
+        });
+        Self { gas, calls, own, code_deposit }
+    }
+}
+
+impl fmt::Display for GasReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gas = &self.gas;
+        writeln!(f, "Gas report:")?;
+        writeln!(f, "  {:<24}{:>10}", "Total used", gas.used)?;
+        writeln!(f, "  {:<24}{:>10}", "Intrinsic", gas.intrinsic)?;
+        writeln!(f, "  {:<24}{:>10}", "Refunded", gas.refunded)?;
+        match self.code_deposit {
+            Some(code_deposit) => {
+                writeln!(f, "  {:<24}{:>10}", "Deployment", gas.intrinsic + code_deposit)?;
+                writeln!(f, "    {:<22}{:>10}", "code deposit", code_deposit)?;
+                writeln!(
+                    f,
+                    "  {:<24}{:>10}",
+                    "Constructor execution",
+                    gas.gross_execution().saturating_sub(code_deposit)
+                )?;
+            }
+            None => writeln!(f, "  {:<24}{:>10}", "Execution", gas.gross_execution())?,
+        }
+        if !self.calls.is_empty() {
+            writeln!(f, "  Top-level calls:")?;
+            for call in &self.calls {
+                writeln!(
+                    f,
+                    "    {:<14}{} {:>10}",
+                    call.kind.to_string(),
+                    call.address,
+                    call.gas_used
+                )?;
+            }
+            write!(f, "    {:<57}{:>10}", "(own execution)", self.own)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `--explain-gas` report of the JSON output: the breakdown of the gas of the transaction and
+/// the aggregated costs of the opcodes.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasExplanation<'a> {
+    #[serde(flatten)]
+    pub gas: GasBreakdown,
+    /// The gas spent in EVM execution before refunds are applied.
+    pub execution: u64,
+    pub opcode_costs: &'a [OpcodeCost],
+}
+
+impl<'a> GasExplanation<'a> {
+    pub fn new(gas: GasBreakdown, opcode_costs: &'a [OpcodeCost]) -> Self {
+        Self { gas, execution: gas.gross_execution(), opcode_costs }
+    }
+}
+
+/// The aggregated gas cost of an opcode in a contract.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OpcodeCost {
+    #[serde(serialize_with = "serialize_op")]
+    pub op: OpCode,
+    pub address: Address,
+    pub gas: u64,
+    pub count: u64,
+}
+
+/// Serializes an opcode as its mnemonic, e.g. `SSTORE`.
+fn serialize_op<S: Serializer>(op: &OpCode, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(op.as_str())
+}
+
+/// Aggregates the gas cost of the recorded steps per opcode and contract, most expensive first.
+///
+/// Calls and creations are excluded, since their cost mostly consists of the gas forwarded to the
+/// sub-context, which is accounted for by the steps of that context.
+pub fn opcode_costs(arena: &CallTraceArena) -> Vec<OpcodeCost> {
+    let mut costs = HashMap::<(u8, Address), OpcodeCost>::new();
+    for step in arena.nodes().iter().flat_map(|node| &node.trace.steps) {
+        if matches!(
+            step.op,
+            OpCode::CALL |
+                OpCode::CALLCODE |
+                OpCode::DELEGATECALL |
+                OpCode::STATICCALL |
+                OpCode::CREATE |
+                OpCode::CREATE2
+        ) {
+            continue;
+        }
+        let cost = costs.entry((step.op.get(), step.contract)).or_insert_with(|| OpcodeCost {
+            op: step.op,
+            address: step.contract,
+            gas: 0,
+            count: 0,
+        });
+        cost.gas += step.gas_cost;
+        cost.count += 1;
+    }
+
+    let mut costs = costs.into_values().collect::<Vec<_>>();
+    costs.sort_by(|a, b| b.gas.cmp(&a.gas).then(a.op.get().cmp(&b.op.get())));
+    costs
+}
+
+/// Returns the cumulative gas of `--trace-gas-cumulative` of every node: the gas used by the
+/// frames rendered up to and including the node, each frame counting its own gas without its
+/// subcalls. The cumulative gas of the last rendered frame is the gas used by the root call.
+pub fn cumulative_gas(arena: &CallTraceArena) -> Vec<u64> {
+    fn walk(nodes: &[CallTraceNode], idx: usize, total: &mut u64, cumulative: &mut [u64]) {
+        let node = &nodes[idx];
+        let subcalls = node.children.iter().map(|&child| nodes[child].trace.gas_used).sum();
+        *total += node.trace.gas_used.saturating_sub(subcalls);
+        cumulative[idx] = *total;
+        for item in &node.ordering {
+            if let TraceMemberOrder::Call(child) = item {
+                walk(nodes, node.children[*child], total, cumulative);
+            }
+        }
+    }
+
+    let nodes = arena.nodes();
+    let mut cumulative = vec![0; nodes.len()];
+    if !nodes.is_empty() {
+        walk(nodes, 0, &mut 0, &mut cumulative);
+    }
+    cumulative
+}
+
+/// Prefixes the name of every call in the trace with its cumulative gas, right-aligned to the
+/// width of the largest one.
+pub fn annotate_cumulative(arena: &mut CallTraceArena) {
+    let cumulative = cumulative_gas(arena);
+    let width = cumulative.iter().max().map_or(1, |max| max.to_string().len());
+    for (node, gas) in arena.nodes_mut().iter_mut().zip(cumulative) {
+        let name =
+            node.trace.decoded.label.take().unwrap_or_else(|| node.trace.address.to_string());
+        node.trace.decoded.label = Some(format!("[total {gas:>width$}] {name}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pure_execution_gas_excludes_intrinsic() {
+        let gas =
+            GasBreakdown { used: 46_000, intrinsic: 21_064, refunded: 4_800, ..Default::default() };
+        assert_eq!(gas.execution(), 24_936);
+
+        let gas =
+            GasBreakdown { used: 21_000, intrinsic: 21_000, refunded: 0, ..Default::default() };
+        assert_eq!(gas.execution(), 0);
+    }
+
+    #[test]
+    fn reports_top_level_calls() {
+        let mut arena = crate::cmd::run::filter::tests::arena(&[
+            (None, 50_000),
+            (Some(0), 20_000),
+            (Some(1), 5_000),
+            (Some(0), 10_000),
+        ]);
+        for node in arena.nodes_mut() {
+            node.trace.kind = CallKind::Call;
+            node.trace.success = true;
+        }
+        let gas =
+            GasBreakdown { used: 71_000, intrinsic: 21_000, refunded: 0, ..Default::default() };
+        let report = GasReport::new(gas, &arena);
+        assert_eq!(
+            report.calls.iter().map(|call| call.gas_used).collect::<Vec<_>>(),
+            [20_000, 10_000]
+        );
+        assert_eq!((report.own, report.code_deposit), (20_000, None));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!((&json["used"], &json["own"]), (&71_000.into(), &20_000.into()));
+        assert_eq!(
+            (&json["calls"][1]["kind"], &json["calls"][1]["gasUsed"]),
+            (&"CALL".into(), &10_000.into())
+        );
+
+        let root = &mut arena.nodes_mut()[0].trace;
+        root.kind = CallKind::Create;
+        root.output = vec![0; 100].into();
+        let report = GasReport::new(gas, &arena);
+        assert_eq!(report.code_deposit, Some(20_000));
+        let report = report.to_string();
+        assert!(report.contains("  Deployment                   41000"), "{report}");
+        assert!(report.contains("  Constructor execution        30000"), "{report}");
+    }
+
+    #[test]
+    fn accumulates_gas_in_rendering_order() {
+        let mut arena = crate::cmd::run::filter::tests::arena(&[
+            (None, 50_000),
+            (Some(0), 20_000),
+            (Some(1), 5_000),
+            (Some(0), 10_000),
+        ]);
+        assert_eq!(cumulative_gas(&arena), [20_000, 35_000, 40_000, 50_000]);
+
+        arena.nodes_mut()[1].trace.decoded.label = Some("Token".to_string());
+        annotate_cumulative(&mut arena);
+        let labels = arena.nodes().iter().map(|node| node.trace.decoded.label.clone().unwrap());
+        let labels = labels.collect::<Vec<_>>();
+        assert_eq!(labels[0], format!("[total 20000] {}", Address::ZERO));
+        assert_eq!(labels[1], "[total 35000] Token");
+    }
+
+    #[test]
+    fn explains_gas() {
+        let gas = GasBreakdown {
+            used: 46_000,
+            intrinsic: 21_064,
+            base: 21_000,
+            calldata: 64,
+            refunded: 4_800,
+        };
+        let costs =
+            [OpcodeCost { op: OpCode::SSTORE, address: Address::ZERO, gas: 22_100, count: 1 }];
+        let explanation = gas.explain(&costs);
+        assert!(explanation.starts_with(
+            "Gas: 21000 base + 64 calldata + 29736 execution (top cost: SSTORE in \
+             0x0000000000000000000000000000000000000000) - 4800 refund = 46000 total"
+        ));
+
+        let json = serde_json::to_value(GasExplanation::new(gas, &costs)).unwrap();
+        assert_eq!((&json["used"], &json["execution"]), (&46_000.into(), &29_736.into()));
+        assert_eq!(json["opcodeCosts"][0]["op"], "SSTORE");
+        assert_eq!(gas.pure_execution()["execution"], 24_936);
+    }
+}
@@ -0,0 +1,214 @@
+//! Labels of the addresses in the trace.
+
+use super::names::ResolvedNames;
+use alloy_primitives::Address;
+use cast::traces::CallTraceArena;
+use eyre::{Result, WrapErr};
+use foundry_block_explorers::Client;
+use foundry_common::{
+    ens::{NameOrAddress, ProviderEnsExt},
+    provider::RetryProvider,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    path::Path,
+    str::FromStr,
+};
+
+/// A `--label`: the name of an address, or of the address of an ENS name resolved with `--ens`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlineLabel {
+    pub target: NameOrAddress,
+    pub name: String,
+}
+
+impl InlineLabel {
+    /// Returns the labeled address, `None` for an ENS name that isn't resolved yet.
+    pub fn address(&self) -> Option<Address> {
+        match self.target {
+            NameOrAddress::Address(address) => Some(address),
+            NameOrAddress::Name(_) => None,
+        }
+    }
+
+    /// Returns the labeled address and its name, `None` for an ENS name that isn't resolved yet.
+    pub fn label(&self) -> Option<(Address, String)> {
+        Some((self.address()?, self.name.clone()))
+    }
+
+    /// Resolves the ENS name of the label, if it names one, which requires `ens`.
+    pub async fn resolve(&mut self, provider: &RetryProvider, ens: bool) -> Result<()> {
+        if let NameOrAddress::Name(name) = &self.target {
+            if !ens {
+                eyre::bail!("the label `{self}` names the ENS name `{name}`, which requires --ens");
+            }
+            let address = self.target.resolve(provider).await.wrap_err_with(|| {
+                format!("failed to resolve the ENS name of the label `{self}`")
+            })?;
+            self.target = NameOrAddress::Address(address);
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for InlineLabel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expected = "expected the `<address>:<name>` or `<ENS name>:<name>` format";
+        // the name may contain colons
+        let (target, name) =
+            s.split_once(':').ok_or_else(|| format!("invalid label `{s}`, {expected}"))?;
+        let (target, name) = (target.trim(), name.trim());
+        if name.is_empty() {
+            return Err(format!("invalid label `{s}`, the name is empty, {expected}"));
+        }
+        let target = match target.parse::<Address>() {
+            Ok(address) => NameOrAddress::Address(address),
+            Err(_) if target.contains('.') && !target.starts_with("0x") => {
+                NameOrAddress::Name(target.to_string())
+            }
+            Err(err) => {
+                return Err(format!("invalid label `{s}`, invalid address `{target}`: {err}"))
+            }
+        };
+        Ok(Self { target, name: name.to_string() })
+    }
+}
+
+impl fmt::Display for InlineLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            NameOrAddress::Address(address) => write!(f, "{address}:{}", self.name),
+            NameOrAddress::Name(name) => write!(f, "{name}:{}", self.name),
+        }
+    }
+}
+
+/// The sources of labels in addition to the inline `--label`s.
+#[derive(Debug, Default)]
+pub struct TraceLabels {
+    /// The labels of the labels file.
+    file: Vec<(Address, String)>,
+    /// The ENS names of the addresses.
+    ens: Option<ResolvedNames>,
+    /// The Etherscan client and the names of the verified contracts.
+    etherscan: Option<(Client, ResolvedNames)>,
+}
+
+impl TraceLabels {
+    pub fn new(labels_file: Option<&Path>, ens: bool, etherscan: Option<Client>) -> Result<Self> {
+        let file = labels_file.map(load_labels_file).transpose()?.unwrap_or_default();
+        Ok(Self {
+            file,
+            ens: ens.then(ResolvedNames::default),
+            etherscan: etherscan.map(|client| (client, ResolvedNames::default())),
+        })
+    }
+
+    /// Returns the labels of the trace, in ascending precedence: the Etherscan contract names, the
+    /// ENS names, the labels file and the inline labels.
+    ///
+    /// Only the callers and callees of the calls in the trace are resolved. The inline labels of
+    /// ENS names that aren't resolved yet are skipped.
+    pub async fn labels(
+        &self,
+        provider: &RetryProvider,
+        arena: Option<&CallTraceArena>,
+        inline: &[InlineLabel],
+    ) -> HashMap<Address, String> {
+        let addresses = arena
+            .map(|arena| {
+                arena
+                    .nodes()
+                    .iter()
+                    .flat_map(|node| [node.trace.caller, node.trace.address])
+                    .collect::<BTreeSet<_>>()
+            })
+            .unwrap_or_default();
+        let mut labels = HashMap::new();
+        if let Some((client, names)) = &self.etherscan {
+            labels.extend(names.labels(&addresses, |address| contract_name(client, address)).await);
+        }
+        if let Some(names) = &self.ens {
+            let lookup = |address| async move { provider.lookup_address(&address).await.ok() };
+            labels.extend(names.labels(&addresses, lookup).await);
+        }
+        labels.extend(self.file.iter().cloned());
+        labels.extend(inline.iter().filter_map(InlineLabel::label));
+        labels
+    }
+}
+
+/// Returns the name of the contract at `address` if it is verified on Etherscan.
+async fn contract_name(client: &Client, address: Address) -> Option<String> {
+    let metadata = client.contract_source_code(address).await.ok()?;
+    metadata.items.into_iter().next().map(|item| item.contract_name).filter(|name| !name.is_empty())
+}
+
+/// Loads a TOML file, if it has the `.toml` extension, or a JSON file mapping addresses to names.
+fn load_labels_file(path: &Path) -> Result<Vec<(Address, String)>> {
+    let content = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read labels file {}", path.display()))?;
+    let labels: BTreeMap<String, String> =
+        if path.extension().is_some_and(|extension| extension == "toml") {
+            toml::from_str(&content).map_err(eyre::Report::from)
+        } else {
+            serde_json::from_str(&content).map_err(eyre::Report::from)
+        }
+        .wrap_err_with(|| format!("failed to parse labels file {}", path.display()))?;
+    labels
+        .into_iter()
+        .map(|(address, name)| {
+            let parsed = address.parse::<Address>().wrap_err_with(|| {
+                format!("invalid address `{address}` of label `{name}` in {}", path.display())
+            })?;
+            Ok((parsed, name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_labels_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let address = Address::with_last_byte(1);
+
+        let json = dir.path().join("labels.json");
+        std::fs::write(&json, format!(r#"{{"{address}": "Vault"}}"#)).unwrap();
+        assert_eq!(load_labels_file(&json).unwrap(), [(address, "Vault".to_string())]);
+
+        let toml = dir.path().join("labels.toml");
+        std::fs::write(&toml, format!(r#""{address}" = "Vault""#)).unwrap();
+        assert_eq!(load_labels_file(&toml).unwrap(), [(address, "Vault".to_string())]);
+
+        std::fs::write(&json, r#"{"0x12": "Vault"}"#).unwrap();
+        let err = load_labels_file(&json).unwrap_err().to_string();
+        assert!(err.contains("invalid address `0x12` of label `Vault`"), "{err}");
+    }
+
+    #[test]
+    fn parses_inline_labels() {
+        let address = Address::with_last_byte(1);
+        let label: InlineLabel = format!(" {address} : Pool: WETH/USDC ").parse().unwrap();
+        assert_eq!(label.address(), Some(address));
+        assert_eq!(label.name, "Pool: WETH/USDC");
+        assert_eq!(label.label(), Some((address, "Pool: WETH/USDC".to_string())));
+
+        let label: InlineLabel = "vitalik.eth:Vitalik".parse().unwrap();
+        assert_eq!(label.target, NameOrAddress::Name("vitalik.eth".to_string()));
+        assert_eq!(label.to_string(), "vitalik.eth:Vitalik");
+        assert_eq!(label.label(), None);
+
+        let err = "Vault".parse::<InlineLabel>().unwrap_err();
+        assert!(err.starts_with("invalid label `Vault`, expected the"), "{err}");
+        let err = "0x12:Vault".parse::<InlineLabel>().unwrap_err();
+        assert!(err.contains("invalid address `0x12`"), "{err}");
+        let err = format!("{address}: ").parse::<InlineLabel>().unwrap_err();
+        assert!(err.contains("the name is empty"), "{err}");
+    }
+}
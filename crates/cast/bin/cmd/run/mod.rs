@@ -0,0 +1,3429 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use alloy_primitives::{Address, Bytes, Selector, TxHash, B256, U256};
+use alloy_provider::Provider;
+use alloy_rpc_types::{
+    AccessList, Block, BlockNumberOrTag, BlockTransactions, Transaction, TransactionInfo,
+};
+use alloy_serde::WithOtherFields;
+use budget::GasBudget;
+use cast::{
+    decode::decode_console_logs,
+    errors::ReplayError,
+    replay::{
+        configure_block_env, fork_block_number, fund_impersonated, prefetch,
+        progress::ProgressCallback, system_tx, BlockReplayer, PrecedingOptions, PrecedingResult,
+        ReplayHooks, ReplayOptions, SkippedTx,
+    },
+    revm::{
+        interpreter::OpCode,
+        primitives::{Env, EnvWithHandlerCfg},
+        DatabaseRef,
+    },
+    traces::{
+        identifier::{SignaturesIdentifier, SingleSignaturesIdentifier},
+        OpcodeFilter, StackSnapshotType,
+    },
+};
+use clap::Parser;
+use eyre::{Result, WrapErr};
+use foundry_cli::{
+    opts::RpcOpts,
+    utils::{handle_traces_with, TraceOptions, TraceResult},
+};
+use foundry_common::{cli_warn, provider::RetryProvider};
+use foundry_compilers::artifacts::EvmVersion;
+use foundry_config::{find_project_root_path, Chain, Config};
+use foundry_evm::{
+    executors::{RawCallResult, TracingExecutor},
+    opts::EvmOpts,
+};
+use foundry_tweak::{tweak_backend, TweakPrecedence};
+use gas::GasBreakdown;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use transfer::ValueTransfer;
+
+mod abis;
+mod access_list;
+mod anvil_state;
+mod benchmark;
+mod block_cache;
+mod block_env;
+mod block_summary;
+mod budget;
+mod bundle;
+mod call_summary;
+mod coinbase;
+mod coverage;
+mod created;
+mod derivation;
+mod dump;
+mod filter;
+mod forwarding;
+mod gas;
+mod labels;
+mod names;
+mod oneline;
+mod oracle;
+mod outcome;
+mod output;
+mod overrides;
+mod perturb;
+mod plan;
+mod prestate;
+mod projects;
+mod proxies;
+mod quick;
+mod randomness;
+mod readonly;
+mod receipt;
+mod repro;
+mod retries;
+mod revert;
+mod rpc_stats;
+mod sqlite;
+mod state_cache;
+mod state_diff;
+mod steps;
+mod storage;
+mod storage_trace;
+mod trace_format;
+mod transfer;
+mod tweak_diff;
+mod tweaks;
+mod tx_fields;
+mod verify;
+mod webhook;
+
+/// The exit code of `cast run` if a replayed transaction reverted, distinct from the exit code 1 of
+/// a failed replay.
+const REVERTED_EXIT_CODE: i32 = 2;
+
+/// The verbosity from which the storage accesses are reported.
+const STORAGE_ACCESS_VERBOSITY: u8 = 5;
+
+/// The default depth up to which the calls are traced, far deeper than legitimate transactions go.
+const DEFAULT_MAX_TRACE_DEPTH: usize = 256;
+
+/// CLI arguments for `cast run`.
+#[derive(Clone, Debug, Parser)]
+pub struct RunArgs {
+    /// The transaction hashes.
+    ///
+    /// Transactions of the same block are replayed on top of each other, in block order, and
+    /// blocks are replayed in ascending order, each on a fresh fork.
+    #[arg(required_unless_present_any = ["tx_json", "tx_file", "block", "bundle"])]
+    tx_hash: Vec<String>,
+
+    /// Replays every transaction of the block, except the system transactions, and prints a
+    /// summary of their outcomes instead of their traces.
+    ///
+    /// The summary is a JSON array with `--json`. With `--index`, only one transaction is
+    /// replayed instead.
+    #[arg(
+        long,
+        value_name = "NUMBER",
+        conflicts_with_all = [
+            "tx_hash",
+            "tx_json",
+            "pending",
+            "future_block",
+            "future_timestamp",
+            "load_state",
+            "benchmark_cups",
+            "stop_after",
+        ]
+    )]
+    pub block: Option<u64>,
+
+    /// Replays only the transaction at this index of `--block`, as if it was given by its hash.
+    #[arg(long, value_name = "INDEX", requires = "block", conflicts_with = "full")]
+    pub index: Option<usize>,
+
+    /// Prints the traces and reports of every transaction of `--block` before the summary.
+    #[arg(long, requires = "block")]
+    pub full: bool,
+
+    /// Opens the transaction in the debugger.
+    #[arg(long, short)]
+    debug: bool,
+
+    /// Print out opcode traces.
+    #[arg(long, short)]
+    trace_printer: bool,
+
+    /// Executes the transaction only with the state from the previous block.
+    /// Note that this also include transactions that are used for tweaking code.
+    ///
+    /// May result in different results than the live execution! The accounts and slots read by
+    /// the transaction that the skipped preceding transactions of the block wrote are listed in a
+    /// warning, if the node supports `debug_traceBlockByNumber`.
+    #[arg(long, short)]
+    quick: bool,
+
+    /// Executes the transaction on the state of the previous block like `--quick`, seeded with
+    /// the state of the accounts and slots it accessed right before it, as traced by the
+    /// `prestateTracer` of the node.
+    ///
+    /// The accounts and slots only the replay accesses, e.g. with tweaked code, have the state of
+    /// the previous block. Falls back to `--quick` with a warning if the node doesn't support
+    /// `debug_traceTransaction`.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "quick",
+            "stop_after",
+            "load_state",
+            "pending",
+            "future_block",
+            "future_timestamp",
+            "bundle",
+        ]
+    )]
+    pub only_target: bool,
+
+    /// Executes only the first N transactions of the block before the transaction, e.g. to
+    /// bisect which preceding transaction influences it.
+    ///
+    /// Like `--quick`, this may result in different results than the live execution!
+    #[arg(long, value_name = "N", conflicts_with = "quick")]
+    pub stop_after: Option<usize>,
+
+    /// Replays the transactions and the preceding ones with this chain ID instead of the one of
+    /// the RPC, e.g. to test the EIP-155 or `CHAINID` dependent logic of contracts deployed on
+    /// several chains.
+    ///
+    /// Like `--quick`, this may result in different results than the live execution!
+    #[arg(long, value_name = "ID")]
+    pub chain_id: Option<u64>,
+
+    /// Sends the transaction from this address instead of its sender, e.g. to test access
+    /// control.
+    ///
+    /// The nonce of the address isn't checked and it is funded with the balance it lacks to pay
+    /// for the gas and value of the transaction. The preceding transactions are sent by their
+    /// original senders.
+    #[arg(long, value_name = "ADDRESS")]
+    pub from: Option<Address>,
+
+    /// Replaces the input of the transaction, i.e. the calldata of a call or the init code of a
+    /// contract creation, keeping its block, sender and value.
+    ///
+    /// The preceding transactions are executed with their original input.
+    #[arg(long, value_name = "HEX", value_parser = |s: &str| s.parse::<Bytes>())]
+    pub calldata: Option<Bytes>,
+
+    /// Replaces the value of the transaction, in wei or with a unit, e.g. `1ether`.
+    ///
+    /// The sender is funded with the balance it lacks to pay for the new value. The preceding
+    /// transactions are executed with their original value.
+    #[arg(long, value_name = "WEI", value_parser = foundry_cli::utils::parse_ether_value)]
+    pub value: Option<U256>,
+
+    /// Replaces the gas limit of the transaction, which can't exceed the gas limit of the block.
+    ///
+    /// The sender is funded with the balance it lacks to pay for the new gas limit. The preceding
+    /// transactions are executed with their original gas limit.
+    #[arg(long, value_name = "GAS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub gas_limit: Option<u64>,
+
+    /// Caches the fetched blocks of the transactions, with their transactions, on disk and reads
+    /// them from the cache when replaying transactions of the same blocks again.
+    ///
+    /// The blocks are cached in `~/.foundry/cache/rpc/<chain>/<block>/block.json`.
+    #[arg(long)]
+    pub block_cache: bool,
+
+    /// Fetches the blocks again instead of reading them from the block cache, refreshing it.
+    #[arg(long, requires = "block_cache")]
+    pub no_cache: bool,
+
+    /// Increases the verbosity of the trace, as for `forge test`, and defaults to the `verbosity`
+    /// of the config.
+    ///
+    /// `-vvv` prints the raw logs after the trace, `-vvvv` includes the traces of the setup calls
+    /// and `-vvvvv` reports the storage accesses like `--storage-access`.
+    #[arg(long, short, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Label addresses in the trace.
+    ///
+    /// Example: 0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045:vitalik.eth
+    ///
+    /// The address can be an ENS name resolved with `--ens`, e.g. `vitalik.eth:Vitalik`. The
+    /// name is everything after the first colon, without surrounding whitespace.
+    ///
+    /// The labels of an address take precedence in this order, from lowest to highest: the
+    /// `labels` of the config, the Etherscan contract names, the ENS names, the labels file and
+    /// the inline labels, the later of which win over the earlier ones.
+    #[arg(long, short)]
+    label: Vec<labels::InlineLabel>,
+
+    /// Renders the raw addresses in the trace, without the labels of any source, including the
+    /// config and the contracts identified on Etherscan, e.g. for golden-file tests of traces.
+    ///
+    /// This only changes the rendering, the execution is the same.
+    #[arg(long, conflicts_with_all = ["label", "labels_file", "ens", "etherscan_labels"])]
+    pub no_labels: bool,
+
+    /// Label the addresses in the trace with their ENS names, resolved with the RPC provider.
+    ///
+    /// Explicit `--label`s take precedence over the resolved names.
+    #[arg(long)]
+    pub ens: bool,
+
+    /// Label addresses in the trace with the names of a JSON or TOML file mapping addresses to
+    /// names, e.g. `{"0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045": "vitalik.eth"}`.
+    ///
+    /// The file is parsed as TOML if it has the `.toml` extension. Inline `--label`s take
+    /// precedence over the labels of the file.
+    #[arg(long, value_name = "PATH")]
+    pub labels_file: Option<PathBuf>,
+
+    /// Label the contracts in the trace that are verified on Etherscan with their names, using
+    /// the Etherscan API key of the config.
+    ///
+    /// Ignored without an API key. Explicit `--label`s take precedence over the fetched names.
+    #[arg(long)]
+    pub etherscan_labels: bool,
+
+    /// Decode the calls, events and errors of the trace with the ABI of a JSON file, either an
+    /// ABI or an artifact with an `abi` field.
+    ///
+    /// The format is `[<ADDRESS>:]<PATH>`: with an address, only the calls to it and its events
+    /// are decoded with the ABI. This option can be used multiple times.
+    #[arg(long, value_name = "[ADDRESS:]PATH")]
+    pub abi: Vec<abis::AbiSource>,
+
+    #[command(flatten)]
+    rpc: RpcOpts,
+
+    /// The EVM version to use.
+    ///
+    /// Overrides the version specified in the config.
+    #[arg(long, short)]
+    evm_version: Option<EvmVersion>,
+
+    /// Sets the number of assumed available compute units per second for this provider
+    ///
+    /// default value: 330
+    ///
+    /// See also, https://docs.alchemy.com/reference/compute-units#what-are-cups-compute-units-per-second
+    #[arg(long, alias = "cups", value_name = "CUPS")]
+    pub compute_units_per_second: Option<u64>,
+
+    /// Disables rate limiting for this node's provider.
+    ///
+    /// default value: false
+    ///
+    /// See also, https://docs.alchemy.com/reference/compute-units#what-are-cups-compute-units-per-second
+    #[arg(long, value_name = "NO_RATE_LIMITS", visible_alias = "no-rpc-rate-limit")]
+    pub no_rate_limit: bool,
+
+    /// The timeout of every RPC request in seconds, including the state reads of the fork, after
+    /// which the replay fails instead of retrying the request.
+    ///
+    /// Defaults to the `fork_timeout` of the configuration, if any.
+    #[arg(long, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    /// The number of times an RPC request is retried after a transient failure, e.g. a rate limit
+    /// or an unavailable endpoint, including the state reads of the fork.
+    ///
+    /// Permanent failures, e.g. an unknown transaction or method, aren't retried. Defaults to the
+    /// `fork_retries` of the configuration, or 8.
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// The backoff before the first retry of an RPC request in milliseconds, doubled on each
+    /// following retry.
+    ///
+    /// Defaults to the `fork_retry_backoff` of the configuration, or 800.
+    #[arg(long, value_name = "MS")]
+    pub retry_backoff: Option<u64>,
+
+    /// One `forge clone`d project that will be used to tweak the code of the corresponding
+    /// on-chain contract.
+    ///
+    /// This option can be used multiple times to tweak multiple contracts. The contracts are
+    /// named after the projects in the trace, and their calls, events and the constructor
+    /// arguments of their creations are decoded with the ABI of the projects.
+    #[arg(long, value_name = "CLONED_PROJECT")]
+    pub tweak: Vec<PathBuf>,
+
+    /// Applies the code of a `--tweak` project to another address than the on-chain contract it
+    /// was cloned from, e.g. to test a fix of one contract against another instance.
+    ///
+    /// The format is `<CLONED_PROJECT>:<ADDRESS>`. This option can be used multiple times per
+    /// project to apply its code to several addresses, e.g. multiple deployments of the same
+    /// contract, which must have code on-chain.
+    #[arg(long, value_name = "CLONED_PROJECT:ADDRESS", requires = "tweak")]
+    pub tweak_at: Vec<foundry_tweak::TweakTarget>,
+
+    /// Tweaks the runtime code of an address with raw bytecode, e.g. hand-patched code or the
+    /// output of another compiler, without a `forge clone`d project.
+    ///
+    /// The format is `<ADDRESS>:<CODE>`. This option can be used multiple times to tweak multiple
+    /// addresses. An address also tweaked by a `--tweak` project or an earlier `--tweak-code` is
+    /// resolved by `--tweak-precedence`, the code tweaks are specified after the projects.
+    #[arg(long, value_name = "ADDRESS:CODE")]
+    pub tweak_code: Vec<foundry_tweak::CodeTweak>,
+
+    /// How multiple tweaks of the same address, by `--tweak` projects or `--tweak-code`, are
+    /// resolved.
+    #[arg(long, value_name = "PRECEDENCE", default_value = "error")]
+    pub tweak_precedence: TweakPrecedence,
+
+    /// Reports whether the tweaked code of every address matches its on-chain code, ignoring the
+    /// metadata hash, before the transaction is executed.
+    ///
+    /// Catches tweaks by the wrong project, mismatches are warned about.
+    #[arg(long, requires = "tweak")]
+    pub verify_tweak: bool,
+
+    /// Same as `--verify-tweak`, but fails if the tweaked code of an address differs from its
+    /// on-chain code.
+    #[arg(long, requires = "tweak")]
+    pub strict_tweak: bool,
+
+    /// Replays the transaction both without and with the tweaks and prints the differences of
+    /// their status, gas used, output and logs.
+    ///
+    /// Both replays execute on the same state: the preceding transactions of the block are
+    /// executed without the tweaks, which only apply to the replayed transaction.
+    #[arg(long)]
+    pub diff_tweak: bool,
+
+    /// Reports the gas spent purely in EVM execution by the transaction, i.e. the gas used
+    /// excluding the intrinsic cost (base, calldata, access list) and net of refunds.
+    ///
+    /// Useful for comparing two implementations of the same logic.
+    ///
+    /// With `--json`, the gas is the `pureExecutionGas` field of the JSON object.
+    #[arg(long)]
+    pub pure_execution_gas: bool,
+
+    /// Print a summary of the gas usage after the trace: the total, intrinsic and refunded gas,
+    /// and the gas of each top-level call.
+    ///
+    /// For creations, the deployment cost is shown separately from the constructor execution.
+    ///
+    /// With `--json`, the summary is the `gasReport` field of the JSON object.
+    #[arg(long)]
+    pub gas_report: bool,
+
+    /// Explains where the gas of the transaction went, e.g. `21000 base + 1204 calldata + 43211
+    /// execution - 4800 refund = 60615 total`, along with the most expensive opcodes.
+    ///
+    /// With `--json`, the explanation is the `gasExplanation` field of the JSON object.
+    #[arg(long)]
+    pub explain_gas: bool,
+
+    /// Hides the calls that consumed less than the given amount of gas, along with their subcalls,
+    /// from the rendered trace.
+    #[arg(long, value_name = "GAS")]
+    pub min_call_gas: Option<u64>,
+
+    /// Replays transactions that are still pending on top of the latest block, without the
+    /// preceding transactions of a block.
+    ///
+    /// The nonce and base fee checks are disabled since a pending transaction may not be
+    /// includable yet.
+    #[arg(long)]
+    pub pending: bool,
+
+    /// Replays system and deposit transactions of L2s, e.g. Optimism, which are rejected and
+    /// skipped among the preceding transactions of the block by default.
+    ///
+    /// They are executed without gas pricing and nonce checks, and the ETH minted by a replayed
+    /// deposit is credited to its sender. Preceding system transactions that fail to execute are
+    /// skipped with a warning.
+    #[arg(long)]
+    pub allow_system_tx: bool,
+
+    /// Doesn't print the number of system transactions skipped among the preceding transactions.
+    ///
+    /// With `--json`, the number skipped before each replayed transaction is still its
+    /// `skippedSystemTransactions` field.
+    #[arg(long, conflicts_with = "allow_system_tx")]
+    pub silent_system: bool,
+
+    /// Replays the transaction without the nonce, balance and base fee checks, to see what the
+    /// EVM does with transactions that are invalid on the replayed state, e.g. very old or
+    /// malformed ones.
+    ///
+    /// The results may diverge from the ones of the chain. The preceding transactions are still
+    /// checked unless `--disable-checks-all` is set.
+    #[arg(long)]
+    pub disable_checks: bool,
+
+    /// Same as `--disable-checks`, and disables the checks of the preceding transactions too.
+    #[arg(long)]
+    pub disable_checks_all: bool,
+
+    /// Skips the preceding transactions of the block that fail to execute, with a warning,
+    /// instead of aborting the replay.
+    ///
+    /// The skipped transactions are listed at the end of the replay. The replayed transactions
+    /// still fail the replay if they can't be executed.
+    #[arg(long)]
+    pub skip_errors: bool,
+
+    /// Warms the fork with the state the preceding transactions of the block are likely to read
+    /// before executing them.
+    ///
+    /// The accounts and slots of their senders, recipients and access lists are fetched
+    /// concurrently, then every preceding transaction is executed concurrently on the state of the
+    /// parent block to fetch the rest. This mostly speeds up the replay of transactions deep in a
+    /// block over a high latency RPC, at the cost of more requests.
+    ///
+    /// The speedup hasn't been benchmarked on a congested mainnet block yet, it depends on the
+    /// latency of the RPC and on how much of the state the prepass predicts.
+    #[arg(long, conflicts_with_all = ["quick", "load_state"])]
+    pub prefetch: bool,
+
+    /// Caches the state after the preceding transactions of the block on disk, and starts from
+    /// the cached state instead of executing them again when a transaction of the same block is
+    /// replayed again.
+    ///
+    /// The states are cached in `~/.foundry/cache/rpc/<chain>/<block>/states/`, keyed by the
+    /// forked block, the number of preceding transactions and the settings they're executed
+    /// with, such as the tweaks and the state overrides. Changing any of them replays the
+    /// preceding transactions again. Their `console.log` messages are cached with the state, for
+    /// `--trace-preceding-logs`.
+    #[arg(long, conflicts_with_all = ["quick", "load_state"])]
+    pub state_cache: bool,
+
+    /// Prints the console logs of the preceding transactions of the block, after the hash of each
+    /// transaction that logged any.
+    ///
+    /// The logs aren't printed with `--json`.
+    #[arg(long)]
+    pub trace_preceding_logs: bool,
+
+    /// Suppresses the progress bar and the status messages, printing only the traces, console
+    /// logs and requested reports.
+    ///
+    /// With `--json`, stdout only contains the JSON document. The progress bar is also suppressed
+    /// if stdout is not a terminal.
+    #[arg(long)]
+    pub quiet: bool,
+
+    /// Prints the number of RPC requests sent by the replay and the time spent waiting for them,
+    /// broken down by phase: the transaction fetch, the block fetch and the reads of the fork
+    /// backend during the execution. The requests answered by each RPC endpoint are reported too,
+    /// for the comma-separated endpoints of `--rpc-url` that requests fail over between.
+    ///
+    /// The statistics are printed to stderr, unless `--quiet` is set.
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Exits successfully even if a replayed transaction reverted.
+    ///
+    /// Otherwise, the exit code is 0 if all replayed transactions succeeded, 2 if any of them
+    /// reverted and 1 if the replay itself failed, e.g. because a transaction couldn't be
+    /// fetched.
+    #[arg(long)]
+    pub allow_failure: bool,
+
+    /// Stops at the first transaction that reverts or fails to be replayed, when several
+    /// transactions are replayed.
+    ///
+    /// By default, the remaining transactions are still replayed, and the ones that failed to be
+    /// replayed are reported after the summary table with an exit code of 1.
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Replays the transaction in a hypothetical future block with the given number, on top of
+    /// the latest state of the chain.
+    ///
+    /// Defaults to the block following the latest one if only `--future-timestamp` is set.
+    #[arg(long, value_name = "NUMBER")]
+    pub future_block: Option<u64>,
+
+    /// Replays the transaction in a hypothetical future block with the given timestamp, on top of
+    /// the latest state of the chain.
+    ///
+    /// Defaults to 12 seconds after the latest block if only `--future-block` is set.
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub future_timestamp: Option<u64>,
+
+    /// Lists the external calls that were repeated identically by the same contract, where some
+    /// attempts reverted and others succeeded, e.g. retry loops and fallback logic.
+    ///
+    /// With `--json`, the calls are the `retriedCalls` field of the JSON object.
+    #[arg(long)]
+    pub show_retries: bool,
+
+    /// Decodes the price reads of known oracle interfaces, e.g. Chainlink's `latestRoundData`,
+    /// and displays the returned price and timestamp inline in the trace.
+    #[arg(long)]
+    pub defi_decode: bool,
+
+    /// Reads the transaction, and optionally the block it was mined in, from a JSON file instead
+    /// of fetching them from the RPC.
+    ///
+    /// The file holds either a transaction object or an object with a `transaction` and an
+    /// optional `block` field. Fields missing in the file are fetched from the RPC, and the state
+    /// is still forked from the configured RPC.
+    #[arg(long, value_name = "FILE")]
+    pub tx_json: Option<PathBuf>,
+
+    /// Reads the transaction from a JSON file instead of fetching it from the RPC, e.g. to replay
+    /// a transaction the RPC doesn't serve anymore.
+    ///
+    /// Unlike with `--tx-json`, the file must hold a complete transaction object and nothing is
+    /// fetched. The state is still forked from the configured RPC or state.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tx_json", "block", "bundle"])]
+    pub tx_file: Option<PathBuf>,
+
+    /// Reads the block of the replayed transactions from a JSON file instead of fetching it from
+    /// the RPC.
+    ///
+    /// The file must hold a complete block object. If it includes the full transactions, the
+    /// preceding transactions and the transactions given by hash are read from it too.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tx_json", "bundle"])]
+    pub block_file: Option<PathBuf>,
+
+    /// Executes a bundle of transactions in order on top of the state of a block, in a
+    /// hypothetical block following it, and prints a summary of their outcomes after their traces.
+    ///
+    /// The file holds either a JSON array of raw signed transactions and unsigned transaction
+    /// objects with a `from` address, or a raw signed transaction per line. The missing nonces of
+    /// the unsigned transactions follow the ones of their senders, their gas limit defaults to the
+    /// block gas limit and their gas price to the base fee of the block.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["tx_hash", "tx_json", "block", "pending", "load_state"]
+    )]
+    pub bundle: Option<PathBuf>,
+
+    /// The block the bundle is executed on top of. Defaults to the latest block.
+    #[arg(long, value_name = "NUMBER", requires = "bundle")]
+    pub bundle_block: Option<u64>,
+
+    /// Forks the state of the given block instead of the parent block of the transactions, e.g. an
+    /// earlier block that an archive node still serves.
+    ///
+    /// The block must not be after the block of the transactions. Only the preceding transactions
+    /// of their block are executed on top of the forked state.
+    #[arg(long, value_name = "NUMBER", conflicts_with_all = ["pending", "future_block", "future_timestamp", "load_state"])]
+    pub fork_block: Option<u64>,
+
+    /// Replays the transactions on the state of an Anvil state dump, e.g. of `anvil --dump-state`,
+    /// instead of forking the state of the parent block from the RPC.
+    ///
+    /// The state is the one right before the transactions, the preceding transactions of their
+    /// block aren't executed. The transactions and their block are still fetched from the RPC,
+    /// unless they're read with `--tx-json`.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["pending", "future_block", "future_timestamp", "benchmark_cups"])]
+    pub load_state: Option<PathBuf>,
+
+    /// Prints a `cast run` command that reproduces this exact replay after it succeeded, with the
+    /// resolved transaction hash, RPC URL, EVM version and block.
+    ///
+    /// An RPC URL with credentials, a path or a query, which may embed an API key, is printed as
+    /// the `$ETH_RPC_URL` placeholder, and the values of the `--rpc-header`s are redacted. A
+    /// bundle is reproduced as a whole, on top of the block it was executed on.
+    ///
+    /// With `--json`, the command is the `repro` field of the JSON object.
+    #[arg(long)]
+    pub print_repro: bool,
+
+    /// Fails the run if the aggregate gas consumed by a contract in the transaction, including its
+    /// subcalls, exceeds the given budget.
+    ///
+    /// This option can be used multiple times to set budgets for multiple contracts.
+    #[arg(long, value_name = "ADDRESS:GAS")]
+    pub gas_budget: Vec<GasBudget>,
+
+    /// Writes an LCOV file with the source lines of the tweaked contracts that were executed by
+    /// the transaction.
+    #[arg(long, value_name = "FILE", requires = "tweak")]
+    pub coverage: Option<PathBuf>,
+
+    /// Lists the storage slots loaded and stored by the transaction.
+    ///
+    /// Slots of tweaked contracts are named after the variables of their storage layout where
+    /// derivable, e.g. `balances[0x…]`.
+    ///
+    /// With `--json`, the accesses are the `storageAccesses` field of the JSON object.
+    #[arg(long)]
+    pub storage_access: bool,
+
+    /// Renders the storage and memory accesses of every call frame in the trace: the slot and
+    /// value of each `SLOAD` and `SSTORE`, and the range and value of each `MLOAD` and `MSTORE`.
+    ///
+    /// Every opcode of the transaction is recorded, which slows down the replay of large
+    /// transactions.
+    #[arg(long)]
+    pub trace_storage: bool,
+
+    /// Prints the changes of the storage slots, balances and nonces of the accounts by the
+    /// transaction, grouped by address and named after the labels.
+    ///
+    /// With `--json`, the changes are the `stateDiff` field of the JSON object.
+    #[arg(long)]
+    pub state_diff: bool,
+
+    /// Prints the balance change of the coinbase of the block across the transaction, i.e. the
+    /// priority fee and the payments to the builder.
+    ///
+    /// With `--json`, the balances are the `coinbaseBalance` field of the JSON object.
+    #[arg(long)]
+    pub coinbase_balance: bool,
+
+    /// Prints the EIP-2930 access list of the accounts and storage slots the transaction
+    /// accessed, and its differences from the access list of the transaction if it has one.
+    ///
+    /// With `--json`, the access list is the `accessList` field of the JSON object.
+    #[arg(long)]
+    pub access_list: bool,
+
+    /// Performs all the setup of the replay, i.e. fetches the transaction and its block, builds
+    /// the fork and applies the tweaks, without executing any transaction.
+    ///
+    /// Any problem with the setup is reported as an error.
+    #[arg(long)]
+    pub validate_only: bool,
+
+    /// Resolves the transaction, its block, the fork block, the EVM version and the number of
+    /// preceding transactions, and parses the tweak and label inputs, then prints the plan of the
+    /// replay without forking or executing anything.
+    ///
+    /// Exits with an error if any of them is invalid.
+    #[arg(long, conflicts_with_all = ["validate_only", "benchmark_cups", "debug"])]
+    pub dry_run: bool,
+
+    /// Adjusts the gas available to every call frame by the given delta, e.g. `-5000`.
+    ///
+    /// This is experimental and makes the execution diverge from the real one. The outcome is
+    /// compared against an unperturbed execution of the transaction.
+    ///
+    /// With `--json`, the comparison is the `gasPerturbation` field of the JSON object.
+    #[arg(long, value_name = "DELTA", allow_hyphen_values = true)]
+    pub gas_perturbation: Option<i64>,
+
+    /// Fetches the on-chain receipt of the transaction and prints it alongside the replay,
+    /// highlighting the fields where they disagree.
+    ///
+    /// With `--json`, the comparison is the `receipt` field of the JSON object.
+    #[arg(long)]
+    pub show_receipt: bool,
+
+    /// Clears the persistent caches of resolved selectors and of the Etherscan ABIs of the chain
+    /// before anything is decoded, so that they are fetched again.
+    ///
+    /// These are the existing caches of `~/.foundry/cache`: no other cache is consulted before the
+    /// selectors and ABIs are fetched.
+    #[arg(long)]
+    pub refresh_abi_cache: bool,
+
+    /// Executes the transaction as a static call, rejecting any state mutation.
+    ///
+    /// Fails with the first mutating opcode if the transaction attempts to modify state.
+    #[arg(long = "readonly")]
+    pub read_only: bool,
+
+    /// Lists the contracts created by the transaction with their deployer, code sizes and code
+    /// hash.
+    ///
+    /// With `--json`, the contracts are the `createdContracts` field of the JSON object, whatever
+    /// the format.
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "table")]
+    pub created_contracts: Option<created::CreatedContractsFormat>,
+
+    /// Benchmarks the RPC provider by replaying the transaction at each of the given
+    /// compute-units-per-second settings, and reports the wall-clock time of every replay.
+    ///
+    /// Every replay uses a fresh backend without storage caching. Nothing else is reported.
+    #[arg(
+        long,
+        value_name = "CUPS",
+        value_delimiter = ',',
+        conflicts_with_all = ["tweak", "debug", "validate_only", "no_rate_limit"]
+    )]
+    pub benchmark_cups: Vec<u64>,
+
+    /// Annotates every call and creation in the trace with the gas available to the caller, and
+    /// how much of it was forwarded and retained according to the EIP-150 63/64 rule.
+    #[arg(long)]
+    pub show_gas_forwarding: bool,
+
+    /// Annotates the creations in the trace with the derivation of their address: the deployer,
+    /// and for `CREATE2` the salt and the init code hash. The selfdestructs are annotated with
+    /// their beneficiary and the transferred value.
+    ///
+    /// With `--json`, the derivations are the `derivations` field of the JSON object.
+    #[arg(long)]
+    pub trace_creations: bool,
+
+    /// Prefixes every call in the trace with the cumulative gas used up to it, the sum of the own
+    /// gas of the calls rendered before it and of its own, to see where the gas is spent over
+    /// the course of the execution.
+    #[arg(long)]
+    pub trace_gas_cumulative: bool,
+
+    /// Names the calls to the proxies after their implementation, e.g. `USDC [transparent proxy
+    /// to FiatTokenV2_2]`.
+    ///
+    /// The accounts that delegate call another contract are resolved from the standard slots of
+    /// EIP-1967 and of the former OpenZeppelin proxies, which may require extra storage reads.
+    /// With `--json`, the resolved proxies are the `proxies` field of the JSON object.
+    #[arg(long)]
+    pub resolve_proxies: bool,
+
+    /// Decodes `bytes` arguments that start with the selector of a known function as nested
+    /// calls, e.g. the calldata passed to executors and multicalls.
+    ///
+    /// This is a heuristic, a decoded interpretation is only shown if the bytes are valid inputs
+    /// to the function.
+    #[arg(long)]
+    pub decode_nested_bytes: bool,
+
+    /// Decodes the custom errors of reverted calls with the signatures of OpenChain, which are
+    /// cached in the Foundry cache directory.
+    ///
+    /// Only the cached signatures are used in offline mode.
+    #[arg(long)]
+    pub decode_errors: bool,
+
+    /// Prints the function the transaction calls and its decoded arguments above the trace, or
+    /// the constructor arguments of the creation of a tweaked contract.
+    ///
+    /// The function is resolved with the ABI of the called tweaked project, then with the
+    /// signatures of OpenChain, unless offline. The address arguments are named after the labels.
+    #[arg(long)]
+    pub call_summary: bool,
+
+    /// Inserts the results of the replay, e.g. status, gas, touched contracts, value transfers
+    /// and state growth, into the SQLite database at the given path.
+    ///
+    /// The database and its tables are created if absent.
+    #[arg(long, value_name = "FILE")]
+    pub sqlite: Option<PathBuf>,
+
+    /// Credits the base fee paid by the replayed transactions to the given address instead of
+    /// burning it, for chains that redirect the base fee.
+    #[arg(long, value_name = "ADDRESS")]
+    pub basefee_recipient: Option<Address>,
+
+    /// Credits the base fee paid by the replayed transactions to the block's coinbase instead of
+    /// burning it.
+    #[arg(long, conflicts_with = "basefee_recipient")]
+    pub no_basefee_burn: bool,
+
+    /// Renders only the subtree of the call at the given path of the trace, e.g. `0.2.1` for the
+    /// second subcall of the third subcall of the top-level call.
+    ///
+    /// Positions count all subcalls in execution order, starting at 0.
+    #[arg(long, value_name = "PATH")]
+    pub trace_only: Option<filter::TracePath>,
+
+    /// Only print the calls on the path from the top-level call to the first revert, hiding all
+    /// unrelated branches.
+    ///
+    /// The first revert is where the earliest failure originated, before bubbling up.
+    #[arg(long, conflicts_with = "trace_only")]
+    pub revert_path_only: bool,
+
+    /// Only prints whether the transaction succeeded, or the call its revert originated from with
+    /// the decoded revert reason, instead of the trace.
+    ///
+    /// Custom errors are decoded with `--decode-errors`.
+    #[arg(long, conflicts_with_all = ["json", "debug", "trace_only", "revert_path_only"])]
+    pub revert_only: bool,
+
+    /// Prints a single line per replayed transaction instead of the trace and reports: its hash,
+    /// block, status, gas used and decoded revert reason, e.g. to grep the results of many
+    /// replays.
+    ///
+    /// With `--json`, each line is a JSON object. Every transaction of a `--block` is printed,
+    /// without the summary table.
+    #[arg(long, conflicts_with_all = ["debug", "revert_only"])]
+    pub oneline: bool,
+
+    /// Only print the calls to this address, with their subcalls and the calls leading to them.
+    ///
+    /// Can be passed multiple times to print the calls to any of the addresses. The console logs
+    /// are printed in full.
+    #[arg(long, value_name = "ADDRESS")]
+    pub trace_address: Vec<Address>,
+
+    /// Only print the calls up to this depth, the top-level call being at depth 0, collapsing
+    /// the subcalls of the deepest printed calls.
+    ///
+    /// Only the rendering is affected, the gas of the printed calls includes their collapsed
+    /// subcalls.
+    #[arg(long, value_name = "N")]
+    pub trace_depth: Option<usize>,
+
+    /// Only record the calls up to this depth in the trace during the execution, the top-level
+    /// call being at depth 0.
+    ///
+    /// Deeper calls are still executed, but neither they nor their steps and logs are traced,
+    /// which bounds the time and memory taken by the traces of pathologically deep transactions.
+    /// The number of calls that weren't traced is reported after the trace.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_TRACE_DEPTH)]
+    pub max_trace_depth: usize,
+
+    /// Overrides the prevrandao, and the difficulty before the merge, of blocks with the values of
+    /// a JSON file mapping block numbers to values, e.g. `{"19000000": "0x…"}`.
+    ///
+    /// The fetched values are used for blocks not in the file.
+    #[arg(long, value_name = "FILE", conflicts_with = "deterministic")]
+    pub randomness_file: Option<PathBuf>,
+
+    /// Print the type of the transaction and all of its typed fields, e.g. fees, access list, blob
+    /// hashes and authorization list, before the trace.
+    ///
+    /// With `--json`, the transaction is the `transaction` field of the JSON object.
+    #[arg(long)]
+    pub show_tx_fields: bool,
+
+    /// Fetches the `callTracer` trace of the transaction from the node with
+    /// `debug_traceTransaction` and reports where the targets, values or outcomes of the calls
+    /// diverge from the replay.
+    ///
+    /// Skipped with a warning if the node doesn't support the method.
+    ///
+    /// With `--json`, the divergences are the `nodeDivergences` field of the JSON object.
+    #[arg(long)]
+    pub verify_against_node: bool,
+
+    /// POSTs the structured results of the replay as JSON to the URL after completion, e.g. to
+    /// feed alerting or dashboards.
+    ///
+    /// Failed requests are retried, the results are printed regardless.
+    #[arg(long, value_name = "URL")]
+    pub webhook: Option<reqwest::Url>,
+
+    /// Writes the state of all accounts loaded by the replay, after the replay, to a JSON file in
+    /// the format of `vm.dumpState`.
+    ///
+    /// A `forge` test forking the block before the transaction can continue from the replayed
+    /// state by loading the file with `vm.loadAllocs`.
+    #[arg(long, value_name = "PATH")]
+    pub dump_state: Option<PathBuf>,
+
+    /// Prints the state of the account after the transaction as JSON: its balance, nonce, code
+    /// and the storage slots loaded by the replay, in the format of `vm.dumpState`. This option
+    /// can be used multiple times.
+    ///
+    /// With `--json`, the accounts are the `accounts` field of the JSON object.
+    #[arg(long, value_name = "ADDRESS")]
+    pub dump_account: Vec<Address>,
+
+    /// A storage slot to include in the state of a `--dump-account` even if the replay didn't
+    /// load it. This option can be used multiple times.
+    #[arg(long, value_name = "ADDRESS:SLOT", requires = "dump_account")]
+    pub dump_slot: Vec<dump::DumpSlot>,
+
+    /// Writes the opcode-level execution trace of the transaction to a file, one JSON object per
+    /// executed step with its program counter, opcode, remaining gas, gas cost, call depth and
+    /// stack, in the format of the struct logs of geth's `debug_traceTransaction`.
+    ///
+    /// The file has a line per executed opcode, including the full stack, so it can take hundreds
+    /// of megabytes for large transactions, and all the steps are held in memory until the
+    /// transaction is executed. The call trace is printed regardless.
+    #[arg(long, value_name = "PATH", conflicts_with = "block")]
+    pub steps_file: Option<PathBuf>,
+
+    /// Print the decoded trace, gas used, console logs and status of the transaction as a single
+    /// JSON object instead of the rendered trace.
+    #[arg(long, conflicts_with = "debug")]
+    pub json: bool,
+
+    /// The format of the printed call trace: `foundry` for the decoded trace, `geth` for the JSON
+    /// call frame of the `callTracer` of `debug_traceTransaction`, or `parity` for the JSON array
+    /// of `trace_transaction`.
+    ///
+    /// The `geth` and `parity` formats can be diffed against the traces of a node. They are
+    /// printed on a single line and aren't decoded.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = trace_format::TraceFormat::Foundry,
+        conflicts_with_all = ["json", "debug", "revert_only"]
+    )]
+    pub trace_format: trace_format::TraceFormat,
+
+    /// Overrides the state of an account before the preceding transactions of the block are
+    /// executed, e.g. `<ADDRESS>,balance=<WEI>,nonce=<NONCE>,code=<HEX>,storage=<SLOT>:<VALUE>`.
+    ///
+    /// All fields are optional and `storage` can be given multiple times. This option can be used
+    /// multiple times to override multiple accounts.
+    #[arg(long, value_name = "OVERRIDE")]
+    pub state_override: Vec<overrides::StateOverride>,
+
+    /// Writes the traces, console logs and reports to a file instead of stdout, creating its
+    /// parent directories.
+    ///
+    /// The traces of multiple transactions are written one after the other, as JSON lines with
+    /// `--json`. Colors are stripped from the file.
+    #[arg(long, value_name = "PATH", conflicts_with = "debug")]
+    pub output_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub block_env: block_env::BlockEnvOverrides,
+}
+
+impl RunArgs {
+    /// Executes the transactions by replaying them
+    ///
+    /// This replays the entire block each transaction was mined in unless `quick` is set to true.
+    /// Transactions of the same block are replayed on top of each other, in block order.
+    ///
+    /// Note: This executes the transaction(s) as is: Cheatcodes are disabled
+    ///
+    /// Exits with [`REVERTED_EXIT_CODE`] if any transaction reverted, unless `allow_failure` is
+    /// set.
+    pub async fn run(mut self) -> Result<()> {
+        let rpc_stats = foundry_common::provider::stats::snapshot();
+        let rpc_endpoints = foundry_common::provider::stats::endpoints();
+        let rpc_retries = foundry_common::provider::stats::retries();
+        let figment = Config::figment_with_root(find_project_root_path(None).unwrap())
+            .merge(self.rpc.clone());
+        let mut evm_opts = figment.extract::<EvmOpts>()?;
+        let config = Config::try_from(figment)?.sanitized();
+        evm_opts.fork_timeout = self.timeout.or(evm_opts.fork_timeout);
+        evm_opts.fork_retries = self.retries.or(evm_opts.fork_retries);
+        evm_opts.fork_retry_backoff = self.retry_backoff.or(evm_opts.fork_retry_backoff);
+        self.verbose = self.verbose.max(config.verbosity);
+        if self.verbose >= STORAGE_ACCESS_VERBOSITY {
+            self.storage_access = true;
+        }
+        if self.disable_checks || self.disable_checks_all {
+            let scope = if self.disable_checks_all {
+                "all transactions"
+            } else {
+                "the replayed transaction"
+            };
+            cli_warn!(
+                "The nonce, balance and base fee checks are disabled for {scope}, the results may \
+                 diverge from consensus"
+            );
+        }
+
+        let compute_units_per_second =
+            if self.no_rate_limit { Some(u64::MAX) } else { self.compute_units_per_second };
+
+        let rpc_url = config.get_rpc_url_or_localhost_http()?.into_owned();
+        let provider = foundry_common::provider::ProviderBuilder::new(&rpc_url)
+            .compute_units_per_second_opt(compute_units_per_second)
+            .maybe_max_retry(evm_opts.fork_retries)
+            .maybe_initial_backoff(evm_opts.fork_retry_backoff)
+            .maybe_timeout(evm_opts.get_fork_timeout())
+            .headers(evm_opts.get_fork_headers())
+            .build()?;
+
+        for label in &mut self.label {
+            label.resolve(&provider, self.ens).await?;
+        }
+
+        // the caches are cleared before any identifier loads them
+        if self.refresh_abi_cache && !self.dry_run {
+            let chain = match config.chain {
+                Some(chain) => chain,
+                None => Chain::from(provider.get_chain_id().await?),
+            };
+            Config::clean_foundry_etherscan_chain_cache(chain)?;
+        }
+        let signatures = self.signatures(Config::foundry_cache_dir(), config.offline)?;
+
+        if let Some(path) = &self.output_file {
+            if let Some(parent) = path.parent() {
+                foundry_common::fs::create_dir_all(parent)?;
+            }
+            // the traces of the transactions are appended to the emptied file
+            foundry_common::fs::create_file(path)?;
+        }
+
+        let tx_hashes = self
+            .tx_hash
+            .iter()
+            .map(|tx_hash| tx_hash.parse::<TxHash>().wrap_err("invalid tx hash"))
+            .collect::<Result<Vec<_>>>()?;
+        let mut txs = vec![];
+        let mut file_block = match &self.block_file {
+            Some(path) => Some(read_block_file(path)?),
+            None => None,
+        };
+        if let Some(number) = self.block {
+            let block = match (file_block.take(), &self.block_file) {
+                (Some(block), Some(path)) if block.header.number != Some(number) => eyre::bail!(
+                    "block {number} is replayed, but {} holds block {}",
+                    path.display(),
+                    block.header.number.unwrap_or_default()
+                ),
+                (Some(block), _) => block,
+                (None, _) => fetch_full_block(&provider, None, number)
+                    .await?
+                    .ok_or(ReplayError::BlockNotFound(number))?,
+            };
+            let BlockTransactions::Full(block_txs) = &block.transactions else {
+                eyre::bail!("Could not get block txs")
+            };
+            if let Some(index) = self.index {
+                txs.push(WithOtherFields::new(tx_at_index(block_txs, number, index)?.clone()));
+            } else {
+                txs.extend(
+                    block_txs
+                        .iter()
+                        .filter(|tx| self.allow_system_tx || !system_tx::is_system_tx(tx))
+                        .cloned()
+                        .map(WithOtherFields::new),
+                );
+            }
+            if txs.is_empty() {
+                eyre::bail!("block {number} has no transactions to replay");
+            }
+            file_block = Some(block);
+        } else if let Some(path) = &self.bundle {
+            let block = provider
+                .get_block(self.base_block().into(), false.into())
+                .await?
+                .ok_or_else(|| eyre::eyre!("block {} not found", self.base_block()))?;
+            txs = bundle::load(path, &provider, &block).await?;
+        } else if let Some(path) = &self.tx_json {
+            if tx_hashes.len() > 1 {
+                eyre::bail!("only a single transaction can be replayed with --tx-json");
+            }
+            let (tx, block) = load_tx_json(path, &provider, tx_hashes.first().copied()).await?;
+            txs.push(tx);
+            file_block = block;
+        } else if let Some(path) = &self.tx_file {
+            txs.push(read_tx_file(path, &tx_hashes)?);
+        } else {
+            for tx_hash in tx_hashes {
+                // the transactions of a block file aren't fetched
+                let block_tx = file_block.as_ref().and_then(|block| match &block.transactions {
+                    BlockTransactions::Full(block_txs) => {
+                        block_txs.iter().find(|tx| tx.hash == tx_hash)
+                    }
+                    _ => None,
+                });
+                let tx = match block_tx {
+                    Some(tx) => WithOtherFields::new(tx.clone()),
+                    None => provider
+                        .get_transaction_by_hash(tx_hash)
+                        .await
+                        .wrap_err_with(|| format!("tx not found: {tx_hash:?}"))?
+                        .ok_or(ReplayError::TxNotFound(tx_hash))?,
+                };
+                txs.push(tx);
+            }
+        }
+        if let (Some(path), Some(block)) = (&self.block_file, &file_block) {
+            // the block is only used for the replay of its own transactions
+            if let Some(tx) = txs.iter().find(|tx| tx.block_number != block.header.number) {
+                eyre::bail!(
+                    "{} holds block {}, but {:?} wasn't mined in it",
+                    path.display(),
+                    block.header.number.unwrap_or_default(),
+                    tx.hash
+                );
+            }
+        }
+
+        for tx in &txs {
+            // check if the tx is a system transaction
+            if !self.allow_system_tx && system_tx::is_system_tx(tx) {
+                return Err(eyre::eyre!(
+                    "{:?} is a system transaction.\nReplaying system transactions is not supported without --allow-system-tx.",
+                    tx.hash
+                ));
+            }
+        }
+        if self.steps_file.is_some() && txs.len() > 1 {
+            eyre::bail!("only the steps of a single transaction can be written with --steps-file");
+        }
+        if !self.benchmark_cups.is_empty() && txs.len() > 1 {
+            eyre::bail!("only a single transaction can be benchmarked");
+        }
+        if self.diff_tweak {
+            if self.tweak.is_empty() && self.tweak_code.is_empty() {
+                eyre::bail!("--diff-tweak requires --tweak or --tweak-code");
+            }
+            if txs.len() > 1 {
+                eyre::bail!("only a single transaction can be diffed with --diff-tweak");
+            }
+        }
+
+        let input_checks = if self.dry_run {
+            plan::check_inputs(
+                &self.tweak,
+                &self.tweak_at,
+                &self.tweak_code,
+                &self.label,
+                &self.abi,
+            )
+        } else {
+            vec![]
+        };
+        for check in &input_checks {
+            println!("{check}");
+        }
+
+        let randomness =
+            self.randomness_file.as_deref().map(randomness::Randomness::load).transpose()?;
+
+        // the transactions of a hypothetical block are replayed in the given order, the ones of
+        // mined blocks block by block
+        let future =
+            self.future_block.is_some() || self.future_timestamp.is_some() || self.bundle.is_some();
+        let blocks = if future { vec![txs] } else { group_by_block(txs, self.pending)? };
+
+        let etherscan = if self.etherscan_labels && !config.offline {
+            let chain = Chain::from(provider.get_chain_id().await?);
+            config
+                .get_etherscan_config_with_chain(Some(chain))
+                .ok()
+                .flatten()
+                .and_then(|etherscan| etherscan.into_client().ok())
+        } else {
+            None
+        };
+        let labels = labels::TraceLabels::new(self.labels_file.as_deref(), self.ens, etherscan)?;
+        // the ABIs are checked by the plan of a dry run instead
+        let trace_abis =
+            if self.dry_run { Default::default() } else { abis::TraceAbis::load(&self.abi)? };
+        let block_cache = if self.block_cache {
+            let chain_id = provider.get_chain_id().await?;
+            block_cache::BlockCache::new(chain_id, self.no_cache)
+        } else {
+            None
+        };
+
+        let context = RunContext {
+            config: &config,
+            evm_opts: &evm_opts,
+            provider: &provider,
+            rpc_url: &rpc_url,
+            randomness: randomness.as_ref(),
+            labels: &labels,
+            abis: &trace_abis,
+            signatures: signatures.as_ref(),
+            block_cache: block_cache.as_ref(),
+        };
+        let mut replayed = vec![];
+        let mut skipped = vec![];
+        let mut skipped_system = 0;
+        for txs in blocks {
+            let block_txs = if self.aggregates_failures() { txs.clone() } else { vec![] };
+            let result = self.replay_block(&context, txs, file_block.take()).await;
+            match result {
+                Ok(outcome) => {
+                    replayed.extend(outcome.replayed);
+                    skipped.extend(outcome.skipped);
+                    skipped_system += outcome.skipped_system;
+                }
+                // the whole block failed to be replayed, e.g. its state couldn't be forked
+                Err(err) if self.aggregates_failures() => {
+                    cli_warn!("failed to replay {} transaction(s): {err:#}", block_txs.len());
+                    replayed.extend(
+                        block_txs.iter().map(|tx| block_summary::ReplayedTx::failed(tx, &err)),
+                    );
+                }
+                Err(err) => return Err(err),
+            }
+            if self.fail_fast && replayed.iter().any(|tx| !tx.success) {
+                break;
+            }
+        }
+        if input_checks.iter().any(|check| !check.is_valid()) {
+            eyre::bail!("invalid inputs, see the plan above");
+        }
+        let success = replayed.iter().all(|tx| tx.success);
+
+        if self.stats && !self.quiet {
+            let after = foundry_common::provider::stats::snapshot();
+            eprintln!("{}", rpc_stats::table(&rpc_stats::since(&rpc_stats, &after)));
+            let after = foundry_common::provider::stats::endpoints();
+            let served = rpc_stats::served_since(&rpc_endpoints, &after);
+            eprintln!("{}", rpc_stats::endpoint_table(&served));
+            let retries = foundry_common::provider::stats::retries() - rpc_retries;
+            eprintln!("Retried RPC requests: {retries}");
+        }
+
+        if self.bundle.is_some() {
+            if !self.json && !self.oneline {
+                println!("{}", block_summary::table(&replayed));
+            }
+            for (index, tx) in replayed.iter().enumerate().filter(|(_, tx)| !tx.success) {
+                eprintln!("Bundle entry {index} ({:?}) reverted", tx.hash);
+            }
+        }
+
+        if self.replays_block() && !self.oneline {
+            if self.json {
+                println!("{}", serde_json::to_string_pretty(&replayed)?);
+            } else {
+                println!("{}", block_summary::table(&replayed));
+            }
+        } else if self.tx_hash.len() > 1 && !self.json && !self.oneline {
+            println!("{}", block_summary::table(&replayed));
+        }
+        let failed = replayed.iter().filter(|tx| tx.error.is_some()).collect::<Vec<_>>();
+        for tx in &failed {
+            eprintln!(
+                "Failed to replay {:?}: {}",
+                tx.hash,
+                tx.error.as_deref().unwrap_or_default()
+            );
+        }
+
+        if skipped_system > 0 && !self.silent_system && !self.quiet {
+            eprintln!(
+                "Skipped {skipped_system} system transaction(s) among the preceding transactions"
+            );
+        }
+        if !skipped.is_empty() {
+            eprintln!("Skipped {} failed preceding transaction(s):", skipped.len());
+            for tx in &skipped {
+                eprintln!("  {tx}");
+            }
+        }
+
+        if !failed.is_empty() {
+            eyre::bail!(
+                "{} of {} transaction(s) failed to be replayed",
+                failed.len(),
+                replayed.len()
+            );
+        }
+        if !success && !self.allow_failure {
+            // flush the signature cache, which isn't dropped on exit
+            drop(signatures);
+            io::stdout().flush()?;
+            std::process::exit(REVERTED_EXIT_CODE);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the writer of the traces, logs and reports: the `--output-file`, appended to without
+    /// colors, or stdout.
+    fn output(&self) -> Result<Box<dyn io::Write + Send>> {
+        Ok(match &self.output_file {
+            Some(path) => Box::new(output::Uncolored::new(
+                std::fs::OpenOptions::new()
+                    .append(true)
+                    .open(path)
+                    .wrap_err_with(|| format!("failed to open output file {}", path.display()))?,
+            )),
+            None => Box::new(io::stdout()),
+        })
+    }
+
+    /// Prints the tweaks applied to the backend, and warns about the tweaked addresses that had no
+    /// code, which may not be the ones the projects were meant for.
+    fn report_tweaks(&self, tweaks: &[tweaks::TweakSummary]) {
+        if tweaks.is_empty() {
+            return;
+        }
+        if self.prints_status() {
+            println!("Applied {} tweak(s):\n{}\n", tweaks.len(), tweaks::table(tweaks));
+        }
+        for tweak in tweaks.iter().filter(|tweak| tweak.old_code_size == 0) {
+            cli_warn!("the tweaked address {} had no code before the tweak", tweak.address);
+        }
+    }
+
+    /// The block that a future block or a bundle is replayed on top of.
+    fn base_block(&self) -> BlockNumberOrTag {
+        self.bundle_block.map_or(BlockNumberOrTag::Latest, BlockNumberOrTag::Number)
+    }
+
+    /// The hash of the settings the preceding transactions are executed with, that their cached
+    /// state depends on.
+    fn state_cache_settings(
+        &self,
+        env: &EnvWithHandlerCfg,
+        tweaks: &foundry_tweak::TweakData,
+        basefee_recipient: Option<Address>,
+    ) -> B256 {
+        state_cache::settings_hash((
+            env.handler_cfg.spec_id,
+            &env.block,
+            env.cfg.chain_id,
+            tweaks,
+            self.diff_tweak,
+            &self.state_override,
+            basefee_recipient,
+            self.allow_system_tx,
+            self.skip_errors,
+            self.disable_checks_all,
+        ))
+    }
+
+    /// The identifier of the signatures of OpenChain if custom errors or the called functions are
+    /// decoded, with the signatures cached in `cache_dir`. With `--refresh-abi-cache`, the cached
+    /// signatures are cleared first, so that the identifier neither serves nor saves them again.
+    fn signatures(
+        &self,
+        cache_dir: Option<PathBuf>,
+        offline: bool,
+    ) -> Result<Option<SingleSignaturesIdentifier>> {
+        if self.refresh_abi_cache && !self.dry_run {
+            let dir =
+                cache_dir.as_ref().ok_or_else(|| eyre::eyre!("failed to get the cache dir"))?;
+            foundry_common::fs::remove_file(dir.join("signatures")).ok();
+        }
+        if !self.decode_errors && !self.call_summary {
+            return Ok(None);
+        }
+        SignaturesIdentifier::new(cache_dir, offline).map(Some)
+    }
+
+    /// Whether the status messages of the replay are printed.
+    fn prints_status(&self) -> bool {
+        !self.quiet &&
+            !self.json &&
+            !self.oneline &&
+            self.trace_format == trace_format::TraceFormat::Foundry
+    }
+
+    /// Whether every transaction of a `--block` is replayed, rather than the one of `--index`.
+    fn replays_block(&self) -> bool {
+        self.block.is_some() && self.index.is_none()
+    }
+
+    /// Whether several transactions are replayed, e.g. of a `--block` or a bundle.
+    fn replays_many(&self) -> bool {
+        self.tx_hash.len() > 1 || self.replays_block() || self.bundle.is_some()
+    }
+
+    /// Whether the transactions that fail to be replayed are reported at the end instead of
+    /// aborting the run, see `--fail-fast`.
+    fn aggregates_failures(&self) -> bool {
+        self.replays_many() && !self.fail_fast
+    }
+
+    /// Whether the traces and reports of each replayed transaction are printed, which they're
+    /// not for the summary of a `--block`.
+    fn prints_txs(&self) -> bool {
+        !self.replays_block() || self.full
+    }
+
+    /// Returns the number of transactions of `block_txs` executed before `tx`, up to
+    /// `--stop-after`.
+    fn preceding_count(&self, block_txs: &[Transaction], tx: &Transaction) -> usize {
+        let preceding = block_txs.iter().take_while(|block_tx| block_tx.hash != tx.hash).count();
+        self.stop_after.map_or(preceding, |stop_after| preceding.min(stop_after))
+    }
+
+    /// Replays the transactions `txs` of the same block on a fresh fork of its parent block.
+    ///
+    /// `file_block` is the block read with `--block-file`.
+    async fn replay_block(
+        &self,
+        context: &RunContext<'_>,
+        txs: Vec<WithOtherFields<Transaction>>,
+        file_block: Option<Block>,
+    ) -> Result<BlockOutcome> {
+        let RunContext {
+            provider, rpc_url, randomness, labels, abis, signatures, block_cache, ..
+        } = *context;
+        let mut config = context.config.clone();
+        let evm_opts = context.evm_opts.clone();
+        let future =
+            self.future_block.is_some() || self.future_timestamp.is_some() || self.bundle.is_some();
+        let pending = !future && txs[0].block_number.is_none();
+        let replay_preceding =
+            !self.quick && !self.only_target && !future && !pending && self.load_state.is_none();
+        let mut future_block = None;
+        let (tx_block_number, block) = if pending {
+            let block = provider
+                .get_block(BlockNumberOrTag::Latest.into(), false.into())
+                .await?
+                .ok_or_else(|| eyre::eyre!("latest block not found"))?;
+            let latest_number =
+                block.header.number.ok_or_else(|| eyre::eyre!("latest block has no number"))?;
+            cli_warn!(
+                "Replaying pending transaction(s) on top of the latest block {latest_number}, \
+                 without nonce and base fee checks"
+            );
+            config.fork_block_number = Some(latest_number);
+            (latest_number, Some(block))
+        } else if future {
+            // the hypothetical block is built on top of the latest one, or the one of the bundle
+            let mut block = provider
+                .get_block(self.base_block().into(), false.into())
+                .await?
+                .ok_or_else(|| eyre::eyre!("block {} not found", self.base_block()))?;
+            let latest_number =
+                block.header.number.ok_or_else(|| eyre::eyre!("block has no number"))?;
+            let (number, timestamp) = future_block_env(
+                latest_number,
+                block.header.timestamp,
+                self.future_block,
+                self.future_timestamp,
+            )?;
+            let base = if self.bundle_block.is_some() { "block" } else { "latest block" };
+            cli_warn!(
+                "Replaying in hypothetical block {number} (timestamp {timestamp}) on top of the \
+                 {base} {latest_number}. Results do not reflect any on-chain execution!"
+            );
+            block.header.number = Some(number);
+            block.header.timestamp = timestamp;
+            future_block = Some((number, timestamp));
+            config.fork_block_number = Some(latest_number);
+            (number, Some(block))
+        } else {
+            let tx_block_number =
+                txs[0].block_number.ok_or(ReplayError::PendingTransaction(txs[0].hash))?;
+
+            // fetch the block the transactions were mined in, unless it was provided
+            let block = match file_block {
+                Some(block)
+                    if self.quick ||
+                        self.only_target ||
+                        matches!(block.transactions, BlockTransactions::Full(_)) =>
+                {
+                    Some(block)
+                }
+                Some(mut block) => {
+                    // the preceding transactions are needed to replay the block
+                    let fetched = fetch_full_block(provider, block_cache, tx_block_number)
+                        .await?
+                        .ok_or(ReplayError::BlockNotFound(tx_block_number))?;
+                    block.transactions = fetched.transactions;
+                    Some(block)
+                }
+                None => fetch_full_block(provider, block_cache, tx_block_number).await?,
+            };
+
+            // we need to fork off the parent block, unless another one is set
+            config.fork_block_number = Some(fork_block_number(tx_block_number, self.fork_block)?);
+            (tx_block_number, block)
+        };
+
+        // the transactions of the block that are executed before the replayed ones
+        let block_txs = if !replay_preceding {
+            vec![]
+        } else {
+            let block = block.as_ref().ok_or(ReplayError::BlockNotFound(tx_block_number))?;
+            let BlockTransactions::Full(txs) = &block.transactions else {
+                eyre::bail!("Could not get block txs")
+            };
+            txs.clone()
+        };
+
+        if self.dry_run {
+            if block.is_none() {
+                return Err(ReplayError::BlockNotFound(tx_block_number).into());
+            }
+            let chain_id = match self.chain_id.or(evm_opts.env.chain_id) {
+                Some(chain_id) => chain_id,
+                None => provider.get_chain_id().await?,
+            };
+            let mut env = Env::default();
+            env.cfg.chain_id = chain_id;
+            let mut evm_version = self.evm_version;
+            configure_block_env(&mut env, tx_block_number, block.as_ref(), &mut evm_version);
+            let plan = plan::BlockPlan {
+                chain_id,
+                block: tx_block_number,
+                fork_block: config.fork_block_number.unwrap_or_default(),
+                evm_version: evm_version.unwrap_or_default(),
+                txs: txs
+                    .iter()
+                    .map(|tx| plan::TxPlan {
+                        hash: tx.hash,
+                        preceding: self.preceding_count(&block_txs, tx),
+                    })
+                    .collect(),
+            };
+            print!("{plan}");
+            return Ok(BlockOutcome::default());
+        }
+
+        if !self.benchmark_cups.is_empty() {
+            let timings = benchmark::benchmark_cups(
+                &self.benchmark_cups,
+                &config,
+                &evm_opts,
+                self.evm_version,
+                &txs[0],
+                tx_block_number,
+                block.as_ref(),
+                replay_preceding,
+            )
+            .await?;
+            println!("{}", benchmark::table(&timings));
+            return Ok(BlockOutcome::default());
+        }
+
+        let state = self.load_state.as_deref().map(anvil_state::AnvilState::load).transpose()?;
+        let (mut env, fork, chain) = match &state {
+            Some(state) => {
+                state.validate(tx_block_number)?;
+                let mut env = evm_opts.local_evm_env();
+                env.cfg.chain_id = anvil_state::chain_id(
+                    evm_opts.env.chain_id,
+                    txs[0].chain_id,
+                    env.cfg.chain_id,
+                )?;
+                let chain = Some(Chain::from(env.cfg.chain_id));
+                (env, None, chain)
+            }
+            None => TracingExecutor::get_fork_material(&config, evm_opts)
+                .await
+                .map_err(ReplayError::ForkSetupFailed)?,
+        };
+        if let Some(chain_id) = self.chain_id {
+            cli_warn!(
+                "Replaying with chain ID {chain_id} instead of {}. Results may differ from the \
+                 live execution!",
+                env.cfg.chain_id
+            );
+            env.cfg.chain_id = chain_id;
+        }
+
+        let mut options = ReplayOptions {
+            debug: self.debug,
+            evm_version: self.evm_version,
+            allow_system_tx: self.allow_system_tx,
+            from: self.from,
+            calldata: self.calldata.clone(),
+            skip_errors: self.skip_errors,
+            disable_checks: self.disable_checks || self.disable_checks_all,
+            ..Default::default()
+        };
+        let mut hooks = BlockHooks { args: self, randomness, number: tx_block_number };
+        let all_txs = block_txs.iter().chain(txs.iter().map(|tx| &tx.inner));
+        let mut replayer = BlockReplayer::new(
+            env,
+            fork,
+            tx_block_number,
+            block.as_ref(),
+            all_txs,
+            &options,
+            &mut hooks,
+        );
+        let executor = &mut replayer.executor;
+        // the depth of the top-level frame is 1 for the inspectors
+        executor.inspector_mut().set_max_trace_depth(Some(self.max_trace_depth + 1));
+        if let Some(state) = &state {
+            state.apply(executor.backend_mut())?;
+        }
+        let mut cloned_projects: Vec<foundry_tweak::ClonedProject> = vec![];
+        let mut tweak_map = foundry_tweak::TweakData::default();
+        let mut project_contracts = vec![];
+        if !self.tweak.is_empty() {
+            // If user specified tweak projects, we need to tweak the code of the contracts
+            for path in self.tweak.iter() {
+                let path = dunce::canonicalize(path)
+                    .map_err(|e| eyre::eyre!("failed to load tweak project: {:?}", e))?;
+                let project =
+                    foundry_tweak::ClonedProject::load_with_root(&path).wrap_err_with(|| {
+                        format!("failed to load tweak project from path: {:?}", &path)
+                    })?;
+                cloned_projects.push(project);
+            }
+            let targets = self
+                .tweak_at
+                .iter()
+                .map(|target| {
+                    let project = dunce::canonicalize(&target.project).wrap_err_with(|| {
+                        format!("failed to load tweak project: {:?}", target.project)
+                    })?;
+                    Ok(foundry_tweak::TweakTarget { project, address: target.address })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // the overridden projects are dropped, only the effective ones are applied
+            cloned_projects =
+                tweaks::resolve_projects(cloned_projects, &targets, self.tweak_precedence)?;
+            foundry_tweak::check_tweak_targets(replayer.executor.backend_mut(), &cloned_projects)?;
+            project_contracts = cloned_projects
+                .iter()
+                .map(projects::ProjectContract::new)
+                .collect::<Result<Vec<_>>>()?;
+            tweak_map = foundry_tweak::build_tweak_data(
+                &cloned_projects,
+                &self.rpc,
+                self.quick,
+                self.tweak_precedence,
+            )
+            .await?;
+            if self.verify_tweak || self.strict_tweak {
+                self.verify_tweaks(&mut replayer.executor, &tweak_map)?;
+            }
+        }
+        foundry_tweak::add_code_tweaks(&mut tweak_map, &self.tweak_code, self.tweak_precedence)?;
+        // with --diff-tweak, the tweaks are only applied to the replayed transaction
+        if !self.diff_tweak {
+            options.tweaks = tweak_map.clone();
+        }
+        let applied = replayer.prepare_state(&options, &mut hooks)?;
+        let applied_tweaks = tweaks::summarize(&applied, &cloned_projects);
+        self.report_tweaks(&applied_tweaks);
+
+        if self.validate_only {
+            if block.is_none() {
+                return Err(ReplayError::BlockNotFound(tx_block_number).into());
+            }
+            for tx in &txs {
+                let preceding = self.preceding_count(&block_txs, tx);
+                println!(
+                    "Replay of {:?} in block {tx_block_number} is valid: {preceding} preceding \
+                     transaction(s), {} tweak(s). The transaction was not executed.",
+                    tx.hash,
+                    tweak_map.len()
+                );
+            }
+            return Ok(BlockOutcome::default());
+        }
+
+        // the base fee is burned by default, as on mainnet
+        let basefee_recipient =
+            self.basefee_recipient.or(self.no_basefee_burn.then_some(replayer.env.block.coinbase));
+        options.basefee_recipient = basefee_recipient;
+
+        let replay = BlockReplay {
+            config: &config,
+            chain,
+            provider,
+            rpc_url,
+            cloned_projects: &cloned_projects,
+            project_contracts: &project_contracts,
+            tweak_map: &tweak_map,
+            evm_version: replayer.evm_version,
+            future_block,
+            tx_block_number,
+            options: &options,
+            pending,
+            labels,
+            abis,
+            signatures,
+            tweaks: &applied_tweaks,
+        };
+        if self.prefetch && replay_preceding {
+            let last = txs
+                .iter()
+                .filter_map(|tx| block_txs.iter().position(|block_tx| block_tx.hash == tx.hash))
+                .max()
+                .unwrap_or_default();
+            let end = self.stop_after.map_or(last, |stop_after| last.min(stop_after));
+            let start = Instant::now();
+            let stats =
+                prefetch::prefetch(replayer.executor.backend(), &replayer.env, &block_txs[..end]);
+            if self.prints_status() {
+                println!(
+                    "Prefetched {} account(s) and {} slot(s), and prepassed {} transaction(s) in \
+                     {:.3}s",
+                    stats.accounts,
+                    stats.slots,
+                    stats.prepassed,
+                    start.elapsed().as_secs_f64()
+                );
+            }
+        }
+
+        let state_cache = if self.state_cache && replay_preceding {
+            state_cache::StateCache::new(
+                chain.map_or(replayer.env.cfg.chain_id, |chain| chain.id()),
+            )
+        } else {
+            None
+        };
+        let state_cache = state_cache.map(|cache| {
+            (cache, self.state_cache_settings(&replayer.env, &tweak_map, basefee_recipient))
+        });
+
+        let mut next = 0;
+        let mut outcome =
+            BlockOutcome { replayed: Vec::with_capacity(txs.len()), ..Default::default() };
+        // the system transactions skipped before the replayed transaction, if the preceding
+        // transactions are executed
+        let mut block_skipped_system = replay_preceding.then_some(0);
+        for tx in &txs {
+            // Set the state to the moment right before the transaction, a future block and the
+            // latest block of pending transactions have no preceding transactions
+            if replay_preceding {
+                let preceding = block_txs[next..]
+                    .iter()
+                    .position(|block_tx| block_tx.hash == tx.hash)
+                    .ok_or_else(|| {
+                        eyre::eyre!("{:?} not found in block {tx_block_number}", tx.hash)
+                    })?;
+                let end = match self.stop_after {
+                    Some(stop_after) => (next + preceding).min(stop_after.max(next)),
+                    None => next + preceding,
+                };
+                let preceding_txs = &block_txs[next..end];
+                // only the state after the preceding transactions from the start of the block is
+                // cached, not the one after replayed transactions
+                let cached = state_cache.as_ref().filter(|_| next == 0).map(|(cache, settings)| {
+                    let key = state_cache::SnapshotKey {
+                        chain_id: replayer.env.cfg.chain_id,
+                        fork_block: config.fork_block_number.unwrap_or_default(),
+                        block: tx_block_number,
+                        preceding: end,
+                        settings: *settings,
+                    };
+                    (cache, key)
+                });
+                let print_logs = self.trace_preceding_logs && !self.json;
+                let options = PrecedingOptions {
+                    basefee_recipient,
+                    allow_system_tx: self.allow_system_tx,
+                    skip_errors: self.skip_errors,
+                    progress: (!self.quiet && io::stdout().is_terminal())
+                        .then(|| ProgressCallback::progress_bar(preceding_txs.len())),
+                    // the cached logs are printed by the runs that load the state too
+                    console_logs: print_logs || cached.is_some(),
+                    cancel: None,
+                    disable_checks: self.disable_checks_all,
+                };
+                let snapshot = cached.as_ref().and_then(|(cache, key)| cache.get(key));
+                let preceding_result = match snapshot {
+                    Some(snapshot) => {
+                        snapshot.apply(replayer.executor.backend_mut())?;
+                        if self.prints_status() && self.prints_txs() {
+                            println!(
+                                "Loaded the state after {end} previous transaction(s) from the \
+                                 state cache."
+                            );
+                        }
+                        let system = preceding_txs
+                            .iter()
+                            .filter(|tx| !self.allow_system_tx && system_tx::is_system_tx(tx))
+                            .count();
+                        PrecedingResult {
+                            skipped: snapshot.skipped,
+                            skipped_system: system,
+                            console_logs: snapshot.console_logs,
+                        }
+                    }
+                    None => {
+                        if self.prints_status() && self.prints_txs() {
+                            println!("Executing previous transactions from the block.");
+                        }
+                        let result = replayer.execute_preceding(preceding_txs, &options)?;
+                        if let Some((cache, key)) = cached {
+                            let backend = replayer.executor.backend();
+                            cache.insert(&state_cache::Snapshot {
+                                key,
+                                skipped: result.skipped.clone(),
+                                accounts: dump::replayed_state(backend),
+                                cleared: dump::cleared_accounts(backend),
+                                console_logs: result.console_logs.clone(),
+                            })?;
+                        }
+                        result
+                    }
+                };
+                let PrecedingResult { skipped: skipped_txs, skipped_system: system, console_logs } =
+                    preceding_result;
+                outcome.skipped.extend(skipped_txs);
+                outcome.skipped_system += system;
+                block_skipped_system = block_skipped_system.map(|count| count + system);
+                if !console_logs.is_empty() && print_logs {
+                    let mut out = self.output()?;
+                    for (hash, logs) in console_logs {
+                        writeln!(out, "Logs of {hash:?}:")?;
+                        for log in logs {
+                            writeln!(out, "  {log}")?;
+                        }
+                        writeln!(out)?;
+                    }
+                }
+                next += preceding + 1;
+            } else if self.only_target {
+                if let Some(prestate) = prestate::fetch(provider, tx.hash).await {
+                    let (accounts, slots) =
+                        prestate::apply(replayer.executor.backend_mut(), &prestate, |address| {
+                            tweak_map.contains_key(address)
+                        })?;
+                    // the overrides take precedence over the pre-state
+                    for state_override in &self.state_override {
+                        state_override.apply(replayer.executor.backend_mut())?;
+                    }
+                    if self.prints_status() && self.prints_txs() {
+                        println!(
+                            "Seeded the state of {accounts} account(s) and {slots} slot(s) \
+                             before {:?} from the node.",
+                            tx.hash
+                        );
+                    }
+                }
+            }
+
+            let result = self.replay_tx(&mut replayer, tx, &replay, block_skipped_system).await;
+            let result = match (&self.bundle, tx.transaction_index) {
+                (Some(_), Some(index)) => {
+                    result.wrap_err_with(|| format!("bundle entry {index} ({:?}) failed", tx.hash))
+                }
+                _ => result,
+            };
+            let replayed_tx = match result {
+                Ok(replayed_tx) => replayed_tx,
+                Err(err) if self.aggregates_failures() => {
+                    cli_warn!("failed to replay {:?}: {err:#}", tx.hash);
+                    block_summary::ReplayedTx::failed(tx, &err)
+                }
+                Err(err) => return Err(err),
+            };
+            let stop = self.fail_fast && !replayed_tx.success;
+            outcome.replayed.push(replayed_tx);
+            if stop {
+                break;
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    /// Decodes the call of the transaction, or of its `--calldata`, with the ABIs of the tweaked
+    /// projects and the signatures of OpenChain.
+    async fn summarize_call(
+        &self,
+        tx: &WithOtherFields<Transaction>,
+        replay: &BlockReplay<'_>,
+    ) -> Result<call_summary::CallSummary> {
+        let input = self.calldata.as_ref().unwrap_or(&tx.input);
+        let mut labels = HashMap::new();
+        if !self.no_labels {
+            labels.extend(replay.config.labels.clone());
+            labels.extend(self.label.iter().filter_map(labels::InlineLabel::label));
+        }
+        match tx.to {
+            Some(to) => {
+                let project =
+                    replay.cloned_projects.iter().find(|project| project.tweaked_address() == to);
+                let abi = project
+                    .map(|project| project.main_artifact())
+                    .transpose()?
+                    .and_then(|artifact| artifact.abi);
+                let function = match input.get(..4) {
+                    Some(selector) => {
+                        call_summary::resolve_function(
+                            abi.as_ref(),
+                            replay.signatures,
+                            Selector::from_slice(selector),
+                        )
+                        .await
+                    }
+                    None => None,
+                };
+                Ok(call_summary::CallSummary::call(to, input, function.as_ref(), &labels))
+            }
+            None => {
+                let project = replay
+                    .cloned_projects
+                    .iter()
+                    .find(|project| project.metadata.creation_transaction == tx.hash);
+                let artifact = project.map(|project| project.main_artifact()).transpose()?;
+                let constructor =
+                    artifact.as_ref().and_then(|artifact| artifact.abi.as_ref()?.constructor());
+                let arguments = project.map(|project| &project.metadata.constructor_arguments[..]);
+                Ok(call_summary::CallSummary::create(input, constructor.zip(arguments), &labels))
+            }
+        }
+    }
+
+    /// Reports whether the tweaked code matches the on-chain code of the not yet tweaked executor.
+    fn verify_tweaks(
+        &self,
+        executor: &mut TracingExecutor,
+        tweak_map: &foundry_tweak::TweakData,
+    ) -> Result<()> {
+        let verifications =
+            foundry_tweak::verify::verify_tweak_data(executor.backend_mut(), tweak_map)?;
+        // the mismatches are still warned about with --json
+        if !self.json {
+            for verification in &verifications {
+                println!("{verification}");
+            }
+        }
+        let mismatches = verifications.iter().filter(|verification| !verification.matches).count();
+        if mismatches > 0 {
+            if self.strict_tweak {
+                eyre::bail!("the tweaked code of {mismatches} address(es) differs from on-chain");
+            }
+            cli_warn!(
+                "The tweaked code of {mismatches} address(es) differs from on-chain, check that \
+                 the tweaks use the right projects"
+            );
+        }
+        Ok(())
+    }
+
+    /// Replays the transaction on top of the current state of the executor, then prints its
+    /// trace and the requested reports.
+    ///
+    /// Returns the outcome of the transaction. Nothing is printed for the summary of a `--block`.
+    ///
+    /// `skipped_system` is the number of system transactions of the block skipped before it, if
+    /// the preceding transactions were executed.
+    async fn replay_tx(
+        &self,
+        replayer: &mut BlockReplayer,
+        tx: &WithOtherFields<Transaction>,
+        replay: &BlockReplay<'_>,
+        skipped_system: Option<usize>,
+    ) -> Result<block_summary::ReplayedTx> {
+        let executor = &mut replayer.executor;
+        let tx_hash = tx.hash;
+        let tx_block_number = replay.tx_block_number;
+        let mut out = self.output()?;
+
+        if self.read_only {
+            if tx.to.is_none() {
+                eyre::bail!("a contract creation cannot be replayed as read-only");
+            }
+            if !tx.value.is_zero() {
+                eyre::bail!("the transaction transfers {} wei, which mutates state", tx.value);
+            }
+        }
+
+        if self.show_tx_fields && !self.json {
+            writeln!(out, "{}\n", tx_fields::format_tx_fields(tx))?;
+        }
+        // the fields of the original transaction, before the value and gas limit overrides
+        let tx_json = (self.show_tx_fields && self.json).then(|| serde_json::to_value(tx));
+        let tx_json = tx_json.transpose()?;
+
+        if self.prints_status() && self.prints_txs() {
+            writeln!(out, "Executing transaction: {:?}", tx.hash)?;
+        }
+
+        let call_summary = if self.call_summary && self.prints_txs() {
+            Some(self.summarize_call(tx, replay).await?)
+        } else {
+            None
+        };
+        if let (Some(summary), false) = (&call_summary, self.json) {
+            writeln!(out, "{summary}")?;
+        }
+
+        self.configure_tracer(executor);
+
+        // the value and gas limit overrides only apply to the replayed transaction
+        let overridden;
+        let tx = if self.value.is_some() || self.gas_limit.is_some() {
+            let mut target = tx.clone();
+            target.value = self.value.unwrap_or(tx.value);
+            target.gas = self.gas_limit.map_or(tx.gas, u128::from);
+            let gas_limit = replayer.env.block.gas_limit;
+            if U256::from(target.gas) > gas_limit {
+                eyre::bail!(
+                    "gas limit {} exceeds the gas limit of block {}: {}",
+                    target.gas,
+                    tx_block_number,
+                    gas_limit
+                );
+            }
+            overridden = target;
+            &overridden
+        } else {
+            tx
+        };
+
+        let sender = self.from.unwrap_or(tx.from);
+        if self.from.is_some() || self.value.is_some() || self.gas_limit.is_some() {
+            fund_impersonated(executor, tx, sender)?;
+        }
+
+        let input = self.calldata.as_ref().unwrap_or(&tx.input);
+        let mut transfer = match tx.to {
+            Some(to) if input.is_empty() => {
+                Some(ValueTransfer::before(executor, sender, to, tx.value)?)
+            }
+            _ => None,
+        };
+
+        // the state before the transaction, the changeset only holds the state after it
+        let state_before = self.state_diff.then(|| executor.backend().clone());
+        let coinbase_before = if self.coinbase_balance {
+            Some(executor.get_balance(replayer.env.block.coinbase)?)
+        } else {
+            None
+        };
+
+        executor.inspector_mut().set_read_only(self.read_only);
+
+        // Execute our transaction
+        let mut hooks = TargetHooks {
+            args: self,
+            replay,
+            sender,
+            original: None,
+            tweaks: None,
+            accessed: None,
+            perturbation: None,
+        };
+        let result = replayer.replay_target(tx, replay.pending, replay.options, &mut hooks);
+        let executor = &mut replayer.executor;
+        // the following transactions of the block are executed as is
+        executor.inspector_mut().set_read_only(false);
+        executor.inspector_mut().set_gas_perturbation(None);
+        let (mut raw, trace_kind, created) = result?;
+        let TargetHooks { original, tweaks: diff_tweaks, accessed, perturbation, .. } = hooks;
+        if let Some(transfer) = &mut transfer {
+            transfer.after(executor)?;
+        }
+        let replayed = block_summary::ReplayedTx::new(tx, &raw);
+        if self.oneline {
+            let line = oneline::OneLine::new(tx, &raw);
+            if self.json {
+                writeln!(out, "{}", serde_json::to_string(&line)?)?;
+            } else {
+                writeln!(out, "{line}")?;
+            }
+            return Ok(replayed);
+        }
+        if !self.prints_txs() {
+            return Ok(replayed);
+        }
+        let position = tx.transaction_index.unwrap_or_default() as usize;
+        if self.quick && position > 0 && !replay.pending && replay.future_block.is_none() {
+            let diffs = quick::preceding_diffs(replay.provider, tx_block_number, position).await;
+            // every transaction pays the beneficiary of the block
+            let ignored = [raw.env.block.coinbase];
+            let writes = diffs
+                .map(|diffs| quick::skipped_writes(&raw.state_changeset, &diffs, &ignored))
+                .unwrap_or_default();
+            if !writes.is_empty() {
+                let writes = writes.iter().map(|write| format!("\n  {write}")).collect::<String>();
+                cli_warn!(
+                    "--quick skipped preceding transactions of the block that wrote state the \
+                     transaction read, the results may differ from the live execution:{writes}"
+                );
+            }
+        }
+        let created_contracts = if self.created_contracts.is_some() {
+            raw.traces.as_ref().map(created::created_contracts).unwrap_or_default()
+        } else {
+            vec![]
+        };
+        let gas_forwarding = if self.show_gas_forwarding {
+            raw.traces.as_ref().map(forwarding::gas_forwarding).unwrap_or_default()
+        } else {
+            vec![]
+        };
+        let mut resolved_proxies = vec![];
+        if let (true, Some(arena)) = (self.resolve_proxies, raw.traces.as_ref()) {
+            for (proxy, target) in proxies::delegators(arena) {
+                // the slots are read after the transaction, e.g. after an upgrade
+                let read = |address, slot| match raw
+                    .state_changeset
+                    .get(&address)
+                    .and_then(|account| account.storage.get(&slot))
+                {
+                    Some(slot) => Ok(slot.present_value),
+                    None => Ok(executor.backend().storage_ref(address, slot)?),
+                };
+                resolved_proxies.extend(proxies::resolve(proxy, target, read)?);
+            }
+        }
+        let state_mutation = if self.read_only {
+            raw.traces.as_ref().and_then(readonly::find_state_mutation)
+        } else {
+            None
+        };
+        let perturbation = perturbation
+            .map(|(delta, baseline)| perturb::PerturbationReport::new(delta, &baseline, &raw));
+        let tweak_diff = original.map(|original| tweak_diff::TweakDiff::new(&original, &raw));
+        let access_list =
+            accessed.map(|list| access_list::AccessListReport::new(list, tx.access_list.as_ref()));
+        let receipt = if self.show_receipt {
+            let receipt = replay
+                .provider
+                .get_transaction_receipt(tx_hash)
+                .await?
+                .ok_or_else(|| eyre::eyre!("receipt not found: {tx_hash:?}"))?;
+            Some(receipt::ReceiptComparison {
+                receipt: receipt::ReceiptFields::from_receipt(&receipt),
+                replay: receipt::ReceiptFields::from_replay(&raw, created),
+            })
+        } else {
+            None
+        };
+        let divergences = match (&raw.traces, self.verify_against_node) {
+            (Some(arena), true) => verify::node_call_frame(replay.provider, tx_hash)
+                .await
+                .map(|frame| verify::compare(arena, &frame)),
+            _ => None,
+        };
+        let console_logs = decode_console_logs(&raw.logs);
+        let gas = GasBreakdown::from_raw(&raw);
+        let gas_report = match (&raw.traces, self.gas_report) {
+            (Some(arena), true) => Some(gas::GasReport::new(gas, arena)),
+            _ => None,
+        };
+        let opcode_costs = if self.explain_gas {
+            raw.traces.as_ref().map(gas::opcode_costs).unwrap_or_default()
+        } else {
+            vec![]
+        };
+        let retried_calls = if self.show_retries {
+            raw.traces.as_ref().map(retries::find_retries).unwrap_or_default()
+        } else {
+            vec![]
+        };
+
+        let budget_violations = raw
+            .traces
+            .as_ref()
+            .map(|arena| budget::check_budgets(arena, &self.gas_budget))
+            .unwrap_or_default();
+        let mut line_coverage = coverage::LineCoverage::default();
+        if self.coverage.is_some() {
+            if let Some(arena) = raw.traces.as_ref() {
+                for (project, code) in
+                    coverage::covered_projects(replay.cloned_projects, replay.tweak_map)
+                {
+                    line_coverage.record(project, code, arena)?;
+                }
+            }
+        }
+        let written_steps = match (&self.steps_file, &raw.traces) {
+            (Some(path), Some(arena)) => {
+                let file = foundry_common::fs::create_file(path)?;
+                Some(steps::write_steps(arena, io::BufWriter::new(file))?)
+            }
+            _ => None,
+        };
+        let storage_accesses = if self.storage_access {
+            let mut layouts = HashMap::new();
+            for project in replay.cloned_projects {
+                if let Some(layout) = project.main_artifact()?.storage_layout {
+                    layouts.insert(project.tweaked_address(), storage::SlotNames::new(layout));
+                }
+            }
+            raw.traces
+                .as_ref()
+                .map(|arena| storage::storage_accesses(arena, &layouts))
+                .unwrap_or_default()
+        } else {
+            vec![]
+        };
+        let mut oracle_reads = if self.defi_decode {
+            raw.traces.as_ref().map(oracle::find_oracle_reads).unwrap_or_default()
+        } else {
+            vec![]
+        };
+        for read in &mut oracle_reads {
+            read.decimals = oracle::decimals(executor, read.address);
+        }
+
+        let trace_only = match (&self.trace_only, raw.traces.as_ref()) {
+            (Some(path), Some(arena)) => Some(path.resolve(arena)?),
+            _ => None,
+        };
+
+        let mut hidden_calls = 0;
+        if let Some(arena) = raw.traces.as_mut() {
+            if let Some(min_gas) = self.min_call_gas {
+                hidden_calls += filter::min_call_gas(arena, min_gas);
+            }
+            if !self.trace_address.is_empty() {
+                hidden_calls += filter::trace_addresses(arena, &self.trace_address);
+            }
+            if let Some(depth) = self.trace_depth {
+                hidden_calls += filter::max_depth(arena, depth);
+            }
+            if self.revert_path_only {
+                match filter::revert_path_only(arena) {
+                    Some(hidden) => hidden_calls += hidden,
+                    None if self.prints_status() => {
+                        writeln!(out, "No call reverted, printing the full trace")?;
+                    }
+                    None => {}
+                }
+            }
+        }
+        let record = (self.sqlite.is_some() || self.webhook.is_some())
+            .then(|| sqlite::ReplayRecord::new(tx, Some(tx_block_number), &raw));
+        let labels = if self.no_labels {
+            None
+        } else {
+            // the names of the project contracts have the lowest precedence
+            let mut labels = replay
+                .project_contracts
+                .iter()
+                .map(projects::ProjectContract::label)
+                .collect::<HashMap<_, _>>();
+            labels.extend(
+                replay.labels.labels(replay.provider, raw.traces.as_ref(), &self.label).await,
+            );
+            Some(labels)
+        };
+        let state_diff = match &state_before {
+            Some(before) => {
+                let label_map = match &labels {
+                    Some(labels) => {
+                        let mut label_map =
+                            replay.config.labels.clone().into_iter().collect::<HashMap<_, _>>();
+                        label_map.extend(labels.clone());
+                        label_map
+                    }
+                    None => HashMap::new(),
+                };
+                state_diff::state_diff(before, &raw.state_changeset, &label_map)?
+            }
+            None => vec![],
+        };
+        let coinbase_balance = coinbase_before.map(|before| {
+            coinbase::CoinbaseBalance::new(raw.env.block.coinbase, before, &raw.state_changeset)
+        });
+        let custom_errors =
+            match (replay.signatures.filter(|_| self.decode_errors), raw.traces.as_ref()) {
+                (Some(identifier), Some(arena)) => {
+                    revert::identify_custom_errors(identifier, arena).await
+                }
+                _ => vec![],
+            };
+        let custom_error = raw
+            .traces
+            .as_ref()
+            .and_then(revert::revert_data)
+            .and_then(|output| revert::decode_custom_error(&custom_errors, output));
+        let revert_origin = match (self.revert_only, raw.traces.as_ref()) {
+            (true, Some(arena)) => {
+                let label_map = labels.clone().unwrap_or_default();
+                revert::RevertOrigin::find(arena, &custom_errors, &label_map)
+            }
+            _ => None,
+        };
+        let derivations = match (self.trace_creations, raw.traces.as_ref()) {
+            (true, Some(arena)) => derivation::derivations(arena),
+            _ => vec![],
+        };
+        let annotation_labels =
+            match (&labels, self.trace_storage || self.resolve_proxies || self.trace_creations) {
+                (Some(labels), true) => labels.clone(),
+                _ => HashMap::new(),
+            };
+        let tx_outcome = raw.traces.as_ref().and_then(|arena| {
+            let scoped = replay.abis.scoped.iter().filter(|(address, _)| tx.to == Some(*address));
+            let abis = scoped.map(|(_, abi)| abi);
+            let abis = abis.chain(replay.project_contracts.iter().map(|contract| &contract.abi));
+            outcome::TxOutcome::new(arena, abis.chain(&replay.abis.global))
+        });
+        let elided_trace_frames = raw.elided_trace_frames;
+        let mut result = TraceResult::from_raw(raw, trace_kind);
+        let applied_tweaks = diff_tweaks.as_deref().unwrap_or(replay.tweaks);
+        if !applied_tweaks.is_empty() && self.json {
+            result.reports.insert("tweaks".to_string(), serde_json::to_value(applied_tweaks)?);
+        }
+        if let (Some(outcome), true) = (&tx_outcome, self.json) {
+            for (name, value) in outcome.reports() {
+                result.reports.insert(name.to_string(), value);
+            }
+        }
+        if elided_trace_frames > 0 && self.json {
+            result.reports.insert("elidedTraceFrames".to_string(), elided_trace_frames.into());
+        }
+        if let (Some(error), true) = (&custom_error, self.json) {
+            result.reports.insert("customError".to_string(), serde_json::to_value(error)?);
+        }
+        if let (Some(summary), true) = (&call_summary, self.json) {
+            result.reports.insert("call".to_string(), serde_json::to_value(summary)?);
+        }
+        if let (Some(count), true) = (skipped_system, self.json) {
+            result.reports.insert("skippedSystemTransactions".to_string(), count.into());
+        }
+        if self.resolve_proxies && self.json {
+            result.reports.insert("proxies".to_string(), serde_json::to_value(&resolved_proxies)?);
+        }
+        if self.trace_creations && self.json {
+            let derivations = derivations.iter().map(|(_, derivation)| derivation);
+            let derivations = derivations.collect::<Vec<_>>();
+            result.reports.insert("derivations".to_string(), serde_json::to_value(derivations)?);
+        }
+        let dumped_accounts = if self.dump_account.is_empty() {
+            None
+        } else {
+            Some(dump::account_states(executor.backend(), &self.dump_account, &self.dump_slot)?)
+        };
+        if let (Some(accounts), true) = (&dumped_accounts, self.json) {
+            result.reports.insert("accounts".to_string(), serde_json::to_value(accounts)?);
+        }
+        if let Some(tx_json) = tx_json {
+            result.reports.insert("transaction".to_string(), tx_json);
+        }
+        let succeeded = result.success && state_mutation.is_none() && budget_violations.is_empty();
+        let repro = if self.print_repro && succeeded {
+            let resolved = repro::ResolvedReplay {
+                tx_hash,
+                rpc_url: replay.rpc_url,
+                evm_version: replay.evm_version.unwrap_or_default(),
+                future_block: replay.future_block,
+                bundle_block: self.bundle.as_ref().and(replay.config.fork_block_number),
+            };
+            Some(repro::repro_command(self, &resolved)?)
+        } else {
+            None
+        };
+        let reports = TxReports {
+            gas,
+            gas_report,
+            opcode_costs,
+            transfer,
+            perturbation,
+            tweak_diff,
+            receipt,
+            divergences,
+            created_contracts,
+            storage_accesses,
+            access_list,
+            state_diff,
+            coinbase_balance,
+            retried_calls,
+            repro,
+        };
+        if self.json {
+            self.insert_reports(&reports, &mut result.reports)?;
+        }
+
+        // the calls and events of the project contracts are decoded with their local ABIs first
+        let abis = replay.project_contracts.iter().map(|contract| contract.abi.clone());
+        let abis = abis.chain(replay.abis.global.iter().cloned());
+        let node_trace = result
+            .traces
+            .as_ref()
+            .and_then(|traces| traces.first())
+            .filter(|_| self.trace_format != trace_format::TraceFormat::Foundry);
+        if let Some((_, arena)) = node_trace {
+            let info = TransactionInfo {
+                hash: Some(tx_hash),
+                index: tx.transaction_index,
+                block_hash: tx.block_hash,
+                block_number: tx.block_number,
+                base_fee: None,
+            };
+            trace_format::write_trace(self.trace_format, arena, result.gas_used, info, &mut out)?;
+        } else if self.revert_only {
+            match &revert_origin {
+                Some(origin) => writeln!(out, "{origin}")?,
+                None if result.success => writeln!(out, "Transaction {tx_hash:?} succeeded")?,
+                None => writeln!(out, "Transaction {tx_hash:?} reverted")?,
+            }
+        } else {
+            let abis = abis.collect::<Vec<_>>();
+            let options = TraceOptions {
+                labels,
+                errors: custom_errors,
+                abis: &abis,
+                debug: self.debug,
+                json: self.json,
+                decode_nested_bytes: self.decode_nested_bytes,
+                verbosity: self.verbose,
+            };
+            handle_traces_with(result, replay.config, replay.chain, options, &mut out, |arena| {
+                replay.abis.annotate(arena);
+                projects::annotate_creations(arena, replay.project_contracts);
+                for read in &oracle_reads {
+                    read.annotate(arena);
+                }
+                for forwarding in &gas_forwarding {
+                    forwarding.annotate(arena);
+                }
+                if self.trace_storage {
+                    storage_trace::annotate(arena, &annotation_labels);
+                }
+                proxies::annotate(arena, &resolved_proxies, &annotation_labels);
+                derivation::annotate(arena, &derivations, &annotation_labels);
+                if self.trace_gas_cumulative && !self.json {
+                    gas::annotate_cumulative(arena);
+                }
+                if let Some(idx) = trace_only {
+                    *arena = filter::subtree(arena, idx);
+                }
+            })
+            .await?;
+        }
+
+        let rendered = !self.json && self.trace_format == trace_format::TraceFormat::Foundry;
+        if let (Some(error), true, false) = (&custom_error, rendered, self.revert_only) {
+            writeln!(out, "Reverted with custom error {error}")?;
+        }
+        if let (Some(outcome), true, false) = (&tx_outcome, rendered, self.revert_only) {
+            writeln!(out, "{outcome}")?;
+        }
+
+        if hidden_calls > 0 && self.prints_status() {
+            writeln!(out, "{hidden_calls} calls hidden from the trace")?;
+        }
+
+        if elided_trace_frames > 0 && self.prints_status() {
+            writeln!(
+                out,
+                "{elided_trace_frames} calls deeper than depth {} were executed but not traced, \
+                 raise --max-trace-depth to trace them",
+                self.max_trace_depth
+            )?;
+        }
+
+        self.print_reports(&reports, &mut out)?;
+
+        if let (Some(path), Some(steps)) = (&self.steps_file, written_steps) {
+            if self.prints_status() {
+                writeln!(out, "Wrote {steps} execution steps to {}", path.display())?;
+            }
+        }
+
+        if let Some(path) = &self.coverage {
+            foundry_common::fs::write(path, line_coverage.to_lcov())?;
+            if self.prints_status() {
+                writeln!(
+                    out,
+                    "Wrote LCOV coverage of the tweaked contracts to {}",
+                    path.display()
+                )?;
+            }
+        }
+
+        // print logs if any, they're part of the JSON output otherwise
+        if !console_logs.is_empty() && !self.json {
+            writeln!(out, "Logs:")?;
+            for log in console_logs {
+                writeln!(out, "  {log}")?;
+            }
+            writeln!(out)?;
+        }
+
+        if let (Some(path), Some(record)) = (&self.sqlite, &record) {
+            sqlite::write_record(path, record)?;
+            if self.prints_status() {
+                writeln!(out, "Inserted the replay of {tx_hash:?} into {}", path.display())?;
+            }
+        }
+
+        if let Some(path) = &self.dump_state {
+            let state = dump::replayed_state(executor.backend());
+            foundry_common::fs::write_json_file(path, &state)?;
+            if self.prints_status() {
+                writeln!(
+                    out,
+                    "Dumped the state of {} accounts to {}, load it with `vm.loadAllocs` in a \
+                     test forking block {}",
+                    state.len(),
+                    path.display(),
+                    replay.config.fork_block_number.unwrap_or_default()
+                )?;
+            }
+        }
+
+        if let (Some(accounts), false) = (&dumped_accounts, self.json) {
+            writeln!(out, "{}", serde_json::to_string_pretty(accounts)?)?;
+        }
+
+        if let (Some(url), Some(record)) = (&self.webhook, &record) {
+            webhook::post_record(url, record).await?;
+        }
+
+        if let Some(mutation) = state_mutation {
+            eyre::bail!("the transaction mutates state: {mutation}");
+        }
+
+        if !budget_violations.is_empty() {
+            for violation in &budget_violations {
+                eprintln!("Gas budget exceeded: {violation}");
+            }
+            eyre::bail!("{} gas budget(s) exceeded", budget_violations.len());
+        }
+
+        Ok(replayed)
+    }
+
+    /// Configures the tracer for the reports of the replayed transaction.
+    fn configure_tracer(&self, executor: &mut TracingExecutor) {
+        if let Some(tracer) = executor.inspector_mut().tracer.as_mut() {
+            let config = tracer.config_mut();
+            if self.explain_gas ||
+                self.coverage.is_some() ||
+                self.steps_file.is_some() ||
+                self.trace_storage
+            {
+                // opcode costs, coverage, the steps file and the loaded values of the storage
+                // trace are derived from the recorded steps of the transaction
+                config.record_steps = true;
+            } else if (self.storage_access ||
+                self.read_only ||
+                self.show_gas_forwarding ||
+                self.trace_creations) &&
+                !config.record_steps
+            {
+                // only the steps of storage accesses and slot derivations, of state mutations and
+                // of created call frames are needed
+                let mut filter = OpcodeFilter::new();
+                if self.storage_access {
+                    filter = filter
+                        .enabled(OpCode::SLOAD)
+                        .enabled(OpCode::SSTORE)
+                        .enabled(OpCode::KECCAK256);
+                }
+                if self.read_only {
+                    for op in readonly::MUTATING_OPCODES {
+                        filter = filter.enabled(op);
+                    }
+                }
+                if self.show_gas_forwarding {
+                    for op in forwarding::FRAME_OPCODES {
+                        filter = filter.enabled(op);
+                    }
+                }
+                if self.trace_creations {
+                    filter = filter.enabled(OpCode::CREATE2);
+                }
+                config.record_opcodes_filter = Some(filter);
+                config.record_steps = true;
+            }
+            if self.steps_file.is_some() || self.trace_storage || self.trace_creations {
+                // the salts of the creations are read from the stack
+                config.record_stack_snapshots = StackSnapshotType::Full;
+            }
+            if self.trace_storage {
+                config.record_state_diff = true;
+            }
+            if self.storage_access {
+                config.record_steps = true;
+                config.record_stack_snapshots = StackSnapshotType::Full;
+                config.record_memory_snapshots = true;
+                config.record_state_diff = true;
+            }
+        }
+    }
+
+    /// Inserts the reports of the replayed transaction into the JSON object.
+    fn insert_reports(
+        &self,
+        reports: &TxReports,
+        json: &mut BTreeMap<String, Value>,
+    ) -> Result<()> {
+        if let Some(report) = &reports.gas_report {
+            json.insert("gasReport".to_string(), serde_json::to_value(report)?);
+        }
+        if let Some(diff) = &reports.tweak_diff {
+            json.insert("tweakDiff".to_string(), serde_json::to_value(diff)?);
+        }
+        if self.state_diff {
+            json.insert("stateDiff".to_string(), serde_json::to_value(&reports.state_diff)?);
+        }
+        if let Some(balance) = &reports.coinbase_balance {
+            json.insert("coinbaseBalance".to_string(), serde_json::to_value(balance)?);
+        }
+        if let Some(report) = &reports.access_list {
+            json.insert("accessList".to_string(), serde_json::to_value(report)?);
+        }
+        if self.pure_execution_gas {
+            json.insert("pureExecutionGas".to_string(), reports.gas.pure_execution());
+        }
+        if self.explain_gas {
+            let explanation = gas::GasExplanation::new(reports.gas, &reports.opcode_costs);
+            json.insert("gasExplanation".to_string(), serde_json::to_value(explanation)?);
+        }
+        if let Some(transfer) = &reports.transfer {
+            json.insert("transfer".to_string(), serde_json::to_value(transfer)?);
+        }
+        if let Some(perturbation) = &reports.perturbation {
+            json.insert("gasPerturbation".to_string(), serde_json::to_value(perturbation)?);
+        }
+        if let Some(receipt) = &reports.receipt {
+            json.insert("receipt".to_string(), receipt.report()?);
+        }
+        if let Some(divergences) = &reports.divergences {
+            json.insert("nodeDivergences".to_string(), serde_json::to_value(divergences)?);
+        }
+        if self.created_contracts.is_some() {
+            let created = serde_json::to_value(&reports.created_contracts)?;
+            json.insert("createdContracts".to_string(), created);
+        }
+        if self.storage_access {
+            let accesses = serde_json::to_value(&reports.storage_accesses)?;
+            json.insert("storageAccesses".to_string(), accesses);
+        }
+        if self.show_retries {
+            let retried = serde_json::to_value(&reports.retried_calls)?;
+            json.insert("retriedCalls".to_string(), retried);
+        }
+        if let Some(repro) = &reports.repro {
+            json.insert("repro".to_string(), repro.clone().into());
+        }
+        Ok(())
+    }
+
+    /// Writes the text reports of the replayed transaction to `out`.
+    fn print_reports(&self, reports: &TxReports, out: &mut dyn io::Write) -> Result<()> {
+        if let (Some(report), false) = (&reports.gas_report, self.json) {
+            writeln!(out, "{report}")?;
+        }
+
+        if self.pure_execution_gas && !self.json {
+            writeln!(
+                out,
+                "Pure execution gas: {} (intrinsic: {}, refunded: {})",
+                reports.gas.execution(),
+                reports.gas.intrinsic,
+                reports.gas.refunded
+            )?;
+        }
+
+        if self.explain_gas && !self.json {
+            writeln!(out, "{}", reports.gas.explain(&reports.opcode_costs))?;
+        }
+
+        if let (Some(transfer), false) = (&reports.transfer, self.json) {
+            writeln!(out, "{transfer}")?;
+        }
+
+        if let (Some(perturbation), false) = (&reports.perturbation, self.json) {
+            writeln!(out, "{perturbation}")?;
+        }
+
+        if let (Some(diff), false) = (&reports.tweak_diff, self.json) {
+            writeln!(out, "{diff}")?;
+        }
+
+        if let (Some(receipt), false) = (&reports.receipt, self.json) {
+            writeln!(out, "{receipt}")?;
+            let mismatches = receipt.mismatches();
+            if !mismatches.is_empty() {
+                writeln!(out, "Replay disagrees with the receipt in: {}", mismatches.join(", "))?;
+            }
+        }
+
+        match reports.divergences.as_ref().filter(|_| !self.json) {
+            Some(divergences) if divergences.is_empty() => {
+                writeln!(out, "Replay matches the call trace of the node")?;
+            }
+            Some(divergences) => {
+                writeln!(out, "Replay diverges from the call trace of the node:")?;
+                for divergence in divergences {
+                    writeln!(out, "  {divergence}")?;
+                }
+            }
+            None => {}
+        }
+
+        match self.created_contracts.filter(|_| !self.json) {
+            Some(created::CreatedContractsFormat::Table) => {
+                writeln!(
+                    out,
+                    "Created contracts:\n{}",
+                    created::table(&reports.created_contracts)
+                )?;
+            }
+            Some(created::CreatedContractsFormat::Json) => {
+                writeln!(out, "{}", serde_json::to_string_pretty(&reports.created_contracts)?)?;
+            }
+            None => {}
+        }
+
+        if self.storage_access && !self.json {
+            writeln!(out, "Storage accesses:")?;
+            for access in &reports.storage_accesses {
+                writeln!(out, "  {access}")?;
+            }
+        }
+
+        if let (Some(report), false) = (&reports.access_list, self.json) {
+            writeln!(out, "{report}")?;
+        }
+
+        if self.state_diff && !self.json {
+            if reports.state_diff.is_empty() {
+                writeln!(out, "No state changes")?;
+            } else {
+                writeln!(out, "State diff:")?;
+                for diff in &reports.state_diff {
+                    write!(out, "  {diff}")?;
+                }
+            }
+        }
+
+        if let (Some(balance), false) = (&reports.coinbase_balance, self.json) {
+            writeln!(out, "{balance}")?;
+        }
+
+        if self.show_retries && !self.json {
+            if reports.retried_calls.is_empty() {
+                writeln!(out, "No retried calls")?;
+            } else {
+                writeln!(out, "Retried calls:")?;
+                for call in &reports.retried_calls {
+                    writeln!(out, "  {call}")?;
+                }
+            }
+        }
+
+        if let (Some(repro), false) = (&reports.repro, self.json) {
+            writeln!(out, "Reproduce with:\n  {repro}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The inputs shared by the replays of every block of a run.
+#[derive(Clone, Copy)]
+struct RunContext<'a> {
+    /// The config the fork of each block is created from, with the fork block overridden.
+    config: &'a Config,
+    evm_opts: &'a EvmOpts,
+    provider: &'a RetryProvider,
+    rpc_url: &'a str,
+    randomness: Option<&'a randomness::Randomness>,
+    labels: &'a labels::TraceLabels,
+    abis: &'a abis::TraceAbis,
+    /// The identifier of the signatures of OpenChain, if custom errors or the called function are
+    /// decoded.
+    signatures: Option<&'a SingleSignaturesIdentifier>,
+    block_cache: Option<&'a block_cache::BlockCache>,
+}
+
+/// The outcome of the replay of a block.
+#[derive(Debug, Default)]
+struct BlockOutcome {
+    /// The outcomes of the replayed transactions.
+    replayed: Vec<block_summary::ReplayedTx>,
+    /// The preceding transactions skipped with `--skip-errors`.
+    skipped: Vec<SkippedTx>,
+    /// The number of system transactions skipped among the preceding transactions.
+    skipped_system: usize,
+}
+
+/// The reports of a replayed transaction that are printed after its trace, or inserted into the
+/// JSON object with `--json`.
+struct TxReports {
+    gas: GasBreakdown,
+    gas_report: Option<gas::GasReport>,
+    opcode_costs: Vec<gas::OpcodeCost>,
+    transfer: Option<ValueTransfer>,
+    perturbation: Option<perturb::PerturbationReport>,
+    tweak_diff: Option<tweak_diff::TweakDiff>,
+    receipt: Option<receipt::ReceiptComparison>,
+    divergences: Option<Vec<verify::Divergence>>,
+    created_contracts: Vec<created::CreatedContract>,
+    storage_accesses: Vec<storage::StorageAccess>,
+    access_list: Option<access_list::AccessListReport>,
+    state_diff: Vec<state_diff::AccountDiff>,
+    coinbase_balance: Option<coinbase::CoinbaseBalance>,
+    retried_calls: Vec<retries::RetriedCall>,
+    repro: Option<String>,
+}
+
+/// The state shared by the replays of the transactions of a block.
+struct BlockReplay<'a> {
+    config: &'a Config,
+    chain: Option<Chain>,
+    provider: &'a RetryProvider,
+    rpc_url: &'a str,
+    cloned_projects: &'a [foundry_tweak::ClonedProject],
+    /// The contracts of the tweak projects, named and decoded in the trace.
+    project_contracts: &'a [projects::ProjectContract],
+    tweak_map: &'a foundry_tweak::TweakData,
+    evm_version: Option<EvmVersion>,
+    /// The number and timestamp of the hypothetical block the transactions are replayed in.
+    future_block: Option<(u64, u64)>,
+    tx_block_number: u64,
+    /// The options of the replay of the transactions.
+    options: &'a ReplayOptions,
+    /// Whether the transactions are pending and replayed on top of the latest block.
+    pending: bool,
+    labels: &'a labels::TraceLabels,
+    abis: &'a abis::TraceAbis,
+    /// The tweaks applied before the preceding transactions.
+    tweaks: &'a [tweaks::TweakSummary],
+    /// The identifier of the signatures of OpenChain, if custom errors or the called function are
+    /// decoded.
+    signatures: Option<&'a SingleSignaturesIdentifier>,
+}
+
+/// The changes of the run to the environment of the block and to the replayed state.
+struct BlockHooks<'a> {
+    args: &'a RunArgs,
+    randomness: Option<&'a randomness::Randomness>,
+    /// The number of the block the transactions are replayed in.
+    number: u64,
+}
+
+impl ReplayHooks for BlockHooks<'_> {
+    fn configure_block(&mut self, env: &mut Env) {
+        if let Some(randomness) = self.randomness {
+            if !randomness.apply(env, self.number) {
+                trace!(block = self.number, "no randomness override, using fetched values");
+            }
+        }
+        // applies to the preceding transactions and the replayed ones alike
+        self.args.block_env.apply(env);
+        if self.args.block_env.deterministic {
+            cli_warn!(
+                "Replaying with a zero prevrandao and difficulty{}. Results may differ from the \
+                 live execution!",
+                if env.block.basefee.is_zero() { ", and a zero base fee" } else { "" }
+            );
+        }
+    }
+
+    fn prepare_state(&mut self, executor: &mut TracingExecutor) -> Result<()> {
+        // the overrides take precedence over the tweaks
+        for state_override in &self.args.state_override {
+            state_override.apply(executor.backend_mut())?;
+        }
+        Ok(())
+    }
+
+    fn warn(&mut self, warning: &str) {
+        cli_warn!("{warning}");
+    }
+}
+
+/// The executions of the run right before the replayed transaction, that its replay is compared
+/// with.
+struct TargetHooks<'a> {
+    args: &'a RunArgs,
+    replay: &'a BlockReplay<'a>,
+    sender: Address,
+    /// The result of the transaction before the tweaks were applied, with `--diff-tweak`.
+    original: Option<RawCallResult>,
+    /// The tweaks applied right before the transaction, with `--diff-tweak`.
+    tweaks: Option<Vec<tweaks::TweakSummary>>,
+    /// The accounts and slots accessed by the transaction, with `--access-list`.
+    accessed: Option<AccessList>,
+    /// The gas perturbation and the unperturbed result of the transaction.
+    perturbation: Option<(i64, RawCallResult)>,
+}
+
+impl ReplayHooks for TargetHooks<'_> {
+    fn before_target(
+        &mut self,
+        executor: &mut TracingExecutor,
+        env: &EnvWithHandlerCfg,
+        tx: &WithOtherFields<Transaction>,
+    ) -> Result<()> {
+        let args = self.args;
+        if args.diff_tweak {
+            self.original = Some(executor.call_with_env(env.clone())?);
+            let applied = tweak_backend(executor.backend_mut(), self.replay.tweak_map)?;
+            let applied = tweaks::summarize(&applied, self.replay.cloned_projects);
+            args.report_tweaks(&applied);
+            self.tweaks = Some(applied);
+        }
+
+        if args.access_list {
+            let sender = self.sender;
+            let to = match tx.to {
+                Some(to) => to,
+                None => sender.create(executor.get_nonce(sender)?),
+            };
+            self.accessed = Some(access_list::record(executor, env, sender, to)?);
+        }
+
+        if let Some(delta) = args.gas_perturbation {
+            cli_warn!(
+                "Gas perturbation of {delta:+} gas per call frame is enabled, the execution \
+                 diverges from the real transaction"
+            );
+            self.perturbation = Some((delta, executor.call_with_env(env.clone())?));
+            executor.inspector_mut().set_gas_perturbation(Some(delta));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the transaction at `index` of the transactions of block `number`.
+fn tx_at_index(block_txs: &[Transaction], number: u64, index: usize) -> Result<&Transaction> {
+    block_txs.get(index).ok_or_else(|| {
+        eyre::eyre!(
+            "block {number} has {} transaction(s), there is no transaction at index {index}",
+            block_txs.len()
+        )
+    })
+}
+
+/// Groups the transactions by the block they were mined in, in ascending block order and in block
+/// order within a block.
+///
+/// With `pending`, the pending transactions are grouped last, in the given order, since they're
+/// replayed on top of the latest block.
+fn group_by_block(
+    txs: Vec<WithOtherFields<Transaction>>,
+    pending: bool,
+) -> Result<Vec<Vec<WithOtherFields<Transaction>>>> {
+    let mut blocks = BTreeMap::<u64, Vec<_>>::new();
+    let mut pending_txs = vec![];
+    for tx in txs {
+        match tx.block_number {
+            Some(tx_block_number) => blocks.entry(tx_block_number).or_default().push(tx),
+            None if pending => pending_txs.push(tx),
+            None => eyre::bail!(
+                "tx may still be pending: {:?}, replay it on top of the latest block with \
+                 --pending",
+                tx.hash
+            ),
+        }
+    }
+    let mut blocks = blocks
+        .into_values()
+        .map(|mut txs| {
+            txs.sort_by_key(|tx| tx.transaction_index);
+            txs
+        })
+        .collect::<Vec<_>>();
+    if !pending_txs.is_empty() {
+        blocks.push(pending_txs);
+    }
+    Ok(blocks)
+}
+
+/// Fetches the block `number` with its full transactions, from the block cache if it is enabled.
+async fn fetch_full_block(
+    provider: &RetryProvider,
+    block_cache: Option<&block_cache::BlockCache>,
+    number: u64,
+) -> Result<Option<Block>> {
+    match block_cache {
+        Some(cache) => cache.get_or_fetch(provider, number).await,
+        None => Ok(provider.get_block(number.into(), true.into()).await?),
+    }
+}
+
+/// Returns the number and timestamp of a hypothetical block following the latest block.
+fn future_block_env(
+    latest_number: u64,
+    latest_timestamp: u64,
+    number: Option<u64>,
+    timestamp: Option<u64>,
+) -> Result<(u64, u64)> {
+    let number = number.unwrap_or(latest_number + 1);
+    let timestamp = timestamp.unwrap_or(latest_timestamp + 12);
+    if number <= latest_number {
+        eyre::bail!("future block {number} is not after the latest block {latest_number}");
+    }
+    if timestamp <= latest_timestamp {
+        eyre::bail!(
+            "future timestamp {timestamp} is not after the latest block's timestamp {latest_timestamp}"
+        );
+    }
+    Ok((number, timestamp))
+}
+
+/// The content of a `--tx-json` file.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TxJsonFile {
+    /// A transaction and the block it was mined in.
+    WithBlock { transaction: Map<String, Value>, block: Option<Map<String, Value>> },
+    /// A bare transaction.
+    Transaction(Map<String, Value>),
+}
+
+/// Loads the transaction, and optionally its block, from a `--tx-json` file.
+///
+/// Fields that are missing in the file are filled in with the values fetched from the RPC.
+async fn load_tx_json(
+    path: &Path,
+    provider: &RetryProvider,
+    tx_hash: Option<TxHash>,
+) -> Result<(WithOtherFields<Transaction>, Option<Block>)> {
+    let (tx, block) = match foundry_common::fs::read_json_file::<TxJsonFile>(path)
+        .wrap_err_with(|| format!("failed to read transaction from {}", path.display()))?
+    {
+        TxJsonFile::WithBlock { transaction, block } => (transaction, block),
+        TxJsonFile::Transaction(transaction) => (transaction, None),
+    };
+
+    let file_hash = tx
+        .get("hash")
+        .map(|hash| serde_json::from_value::<TxHash>(hash.clone()))
+        .transpose()
+        .wrap_err_with(|| format!("invalid transaction hash in {}", path.display()))?;
+    let tx_hash = match (file_hash, tx_hash) {
+        (Some(file_hash), Some(tx_hash)) if file_hash != tx_hash => {
+            eyre::bail!(
+                "transaction hash mismatch: {tx_hash:?} was given, but {} holds {file_hash:?}",
+                path.display()
+            )
+        }
+        (file_hash, tx_hash) => file_hash.or(tx_hash),
+    };
+
+    let tx: WithOtherFields<Transaction> = match serde_json::from_value(Value::Object(tx.clone())) {
+        Ok(tx) => tx,
+        Err(err) => {
+            let Some(tx_hash) = tx_hash else {
+                return Err(err).wrap_err_with(|| {
+                    format!("incomplete transaction without hash in {}", path.display())
+                });
+            };
+            trace!(tx=?tx_hash, %err, "fetching missing transaction fields");
+            let fetched = provider
+                .get_transaction_by_hash(tx_hash)
+                .await
+                .wrap_err_with(|| format!("tx not found: {tx_hash:?}"))?
+                .ok_or(ReplayError::TxNotFound(tx_hash))?;
+            merge_json(&fetched, tx)
+                .wrap_err_with(|| format!("invalid transaction in {}", path.display()))?
+        }
+    };
+
+    let block = match block {
+        Some(block) => Some(match serde_json::from_value(Value::Object(block.clone())) {
+            Ok(block) => block,
+            Err(err) => {
+                let block_number =
+                    tx.block_number.ok_or(ReplayError::PendingTransaction(tx.hash))?;
+                trace!(block=block_number, %err, "fetching missing block fields");
+                let fetched = provider
+                    .get_block(block_number.into(), true.into())
+                    .await?
+                    .ok_or(ReplayError::BlockNotFound(block_number))?;
+                merge_json(&fetched, block)
+                    .wrap_err_with(|| format!("invalid block in {}", path.display()))?
+            }
+        }),
+        None => None,
+    };
+
+    Ok((tx, block))
+}
+
+/// Reads the complete transaction of a `--tx-file`, which must be the one of `tx_hashes` if any is
+/// given.
+fn read_tx_file(path: &Path, tx_hashes: &[TxHash]) -> Result<WithOtherFields<Transaction>> {
+    let tx: WithOtherFields<Transaction> = read_json_object(path, "transaction")?;
+    match tx_hashes {
+        [] => {}
+        [tx_hash] if *tx_hash == tx.hash => {}
+        [tx_hash] => eyre::bail!(
+            "transaction hash mismatch: {tx_hash:?} was given, but {} holds {:?}",
+            path.display(),
+            tx.hash
+        ),
+        _ => eyre::bail!("only a single transaction can be replayed with --tx-file"),
+    }
+    Ok(tx)
+}
+
+/// Reads the complete block of a `--block-file`.
+fn read_block_file(path: &Path) -> Result<Block> {
+    let block: Block = read_json_object(path, "block")?;
+    if block.header.number.is_none() {
+        eyre::bail!("the block in {} has no number", path.display());
+    }
+    Ok(block)
+}
+
+/// Reads a JSON object of the given `kind` from `path`, erroring on missing or mistyped fields.
+fn read_json_object<T: serde::de::DeserializeOwned>(path: &Path, kind: &str) -> Result<T> {
+    let json = foundry_common::fs::read_to_string(path)?;
+    serde_json::from_str(&json)
+        .wrap_err_with(|| format!("{} doesn't hold a valid {kind} object", path.display()))
+}
+
+/// Overrides the fields of `base` with the given `fields` and deserializes the result.
+fn merge_json<T: serde::Serialize + serde::de::DeserializeOwned>(
+    base: &T,
+    fields: Map<String, Value>,
+) -> Result<T> {
+    let Value::Object(mut merged) = serde_json::to_value(base)? else {
+        eyre::bail!("expected a JSON object")
+    };
+    merged.extend(fields);
+    Ok(serde_json::from_value(Value::Object(merged))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_tx_json_file() {
+        let file: TxJsonFile = serde_json::from_str(r#"{"hash":"0x01","nonce":"0x1"}"#).unwrap();
+        assert!(matches!(file, TxJsonFile::Transaction(tx) if tx.len() == 2));
+
+        let file: TxJsonFile =
+            serde_json::from_str(r#"{"transaction":{"hash":"0x01"},"block":{"number":"0x1"}}"#)
+                .unwrap();
+        assert!(matches!(file, TxJsonFile::WithBlock { block: Some(_), .. }));
+    }
+
+    #[test]
+    fn future_block_must_follow_latest() {
+        assert_eq!(future_block_env(100, 1_000, None, None).unwrap(), (101, 1_012));
+        assert_eq!(future_block_env(100, 1_000, Some(200), None).unwrap(), (200, 1_012));
+        assert_eq!(future_block_env(100, 1_000, None, Some(5_000)).unwrap(), (101, 5_000));
+        assert!(future_block_env(100, 1_000, Some(100), None).is_err());
+        assert!(future_block_env(100, 1_000, None, Some(1_000)).is_err());
+    }
+
+    #[test]
+    fn reads_complete_tx_and_block_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let tx = Transaction { hash: TxHash::with_last_byte(1), ..Default::default() };
+        let path = dir.path().join("tx.json");
+        foundry_common::fs::write_json_file(&path, &WithOtherFields::new(tx.clone())).unwrap();
+        assert_eq!(read_tx_file(&path, &[]).unwrap().hash, tx.hash);
+        assert_eq!(read_tx_file(&path, &[tx.hash]).unwrap().hash, tx.hash);
+        let err = read_tx_file(&path, &[TxHash::with_last_byte(2)]).unwrap_err();
+        assert!(err.to_string().contains("transaction hash mismatch"), "{err}");
+
+        std::fs::write(&path, format!(r#"{{"hash":"{}"}}"#, tx.hash)).unwrap();
+        let err = format!("{:#}", read_tx_file(&path, &[]).unwrap_err());
+        assert!(err.contains("doesn't hold a valid transaction object"), "{err}");
+        assert!(err.contains("missing field"), "{err}");
+
+        let mut block: Block = Block::default();
+        let path = dir.path().join("block.json");
+        foundry_common::fs::write_json_file(&path, &block).unwrap();
+        assert!(read_block_file(&path).is_err());
+        block.header.number = Some(1);
+        foundry_common::fs::write_json_file(&path, &block).unwrap();
+        assert_eq!(read_block_file(&path).unwrap().header.number, Some(1));
+    }
+
+    #[test]
+    fn tx_json_requires_tx_or_hash() {
+        assert!(RunArgs::try_parse_from(["foundry-cli"]).is_err());
+        assert!(RunArgs::try_parse_from(["foundry-cli", "--tx-json", "tx.json"]).is_ok());
+    }
+
+    #[test]
+    fn parses_target_overrides() {
+        let args = RunArgs::try_parse_from([
+            "foundry-cli",
+            "0x01",
+            "--value",
+            "1ether",
+            "--gas-limit",
+            "50000",
+        ])
+        .unwrap();
+        assert_eq!(args.value, Some(U256::from(10).pow(U256::from(18))));
+        assert_eq!(args.gas_limit, Some(50_000));
+        assert!(RunArgs::try_parse_from(["foundry-cli", "0x01", "--value", "1x"]).is_err());
+        assert!(RunArgs::try_parse_from(["foundry-cli", "0x01", "--gas-limit", "0"]).is_err());
+    }
+
+    #[test]
+    fn groups_txs_by_block() {
+        let tx = |hash: u8, block_number: Option<u64>, index: u64| {
+            WithOtherFields::new(Transaction {
+                hash: TxHash::with_last_byte(hash),
+                block_number,
+                transaction_index: Some(index),
+                ..Default::default()
+            })
+        };
+        let hashes = |blocks: Vec<Vec<WithOtherFields<Transaction>>>| {
+            blocks
+                .iter()
+                .map(|txs| txs.iter().map(|tx| tx.hash[31]).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        let txs = vec![tx(1, Some(20), 3), tx(2, Some(10), 0), tx(3, Some(20), 1)];
+        assert_eq!(hashes(group_by_block(txs, false).unwrap()), vec![vec![2], vec![3, 1]]);
+
+        assert!(group_by_block(vec![tx(1, None, 0)], false).is_err());
+        let txs = vec![tx(1, None, 0), tx(2, Some(10), 0)];
+        assert_eq!(hashes(group_by_block(txs, true).unwrap()), vec![vec![2], vec![1]]);
+
+        let args = RunArgs::try_parse_from(["foundry-cli", "0x01", "0x02"]).unwrap();
+        assert_eq!(args.tx_hash, vec!["0x01", "0x02"]);
+    }
+
+    #[test]
+    fn json_conflicts_with_debug() {
+        let hash = "0x1234";
+        assert!(RunArgs::try_parse_from(["foundry-cli", hash, "--json"]).unwrap().json);
+        assert!(RunArgs::try_parse_from(["foundry-cli", hash, "--json", "--debug"]).is_err());
+    }
+
+    #[test]
+    fn bundle_replaces_tx_hashes() {
+        let args = RunArgs::try_parse_from(["foundry-cli", "--bundle", "bundle.json"]).unwrap();
+        assert_eq!(args.base_block(), BlockNumberOrTag::Latest);
+        let args = ["foundry-cli", "--bundle", "bundle.json", "--bundle-block", "100"];
+        assert_eq!(
+            RunArgs::try_parse_from(args).unwrap().base_block(),
+            BlockNumberOrTag::Number(100)
+        );
+        assert!(
+            RunArgs::try_parse_from(["foundry-cli", "0x1234", "--bundle", "bundle.json"]).is_err()
+        );
+    }
+
+    #[test]
+    fn parses_trace_format() {
+        let hash = "0x1234";
+        let args = RunArgs::try_parse_from(["foundry-cli", hash]).unwrap();
+        assert_eq!(args.trace_format, trace_format::TraceFormat::Foundry);
+        let args =
+            RunArgs::try_parse_from(["foundry-cli", hash, "--trace-format", "geth"]).unwrap();
+        assert_eq!(args.trace_format, trace_format::TraceFormat::Geth);
+        assert!(
+            RunArgs::try_parse_from(["foundry-cli", hash, "--trace-format", "otterscan"]).is_err()
+        );
+        let json = ["foundry-cli", hash, "--trace-format", "parity", "--json"];
+        assert!(RunArgs::try_parse_from(json).is_err());
+    }
+
+    #[test]
+    fn no_labels_conflicts_with_label_sources() {
+        let hash = "0x1234";
+        assert!(RunArgs::try_parse_from(["foundry-cli", hash, "--no-labels"]).unwrap().no_labels);
+        for source in [
+            "--label=0x0000000000000000000000000000000000000001:A",
+            "--labels-file=labels.json",
+            "--ens",
+            "--etherscan-labels",
+        ] {
+            assert!(RunArgs::try_parse_from(["foundry-cli", hash, "--no-labels", source]).is_err());
+        }
+    }
+
+    #[test]
+    fn traces_calls_up_to_max_depth() {
+        use cast::revm::primitives::{AccountInfo, Bytecode, Env, TxKind, U256};
+
+        // calls itself with all of its gas until it runs out of gas
+        let code = Bytecode::new_raw(Bytes::from_static(&[
+            0x5f, 0x80, 0x80, 0x80, 0x80, 0x30, 0x5a, 0xf1, 0x00,
+        ]));
+        let contract = Address::repeat_byte(0x11);
+        let trace = |max_depth| {
+            let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+            executor.inspector_mut().set_max_trace_depth(max_depth);
+            let info = AccountInfo {
+                code_hash: code.hash_slow(),
+                code: Some(code.clone()),
+                ..Default::default()
+            };
+            executor.backend_mut().insert_account_info(contract, info);
+            let mut env = Env::default();
+            env.block.gas_limit = U256::from(30_000_000);
+            env.tx.transact_to = TxKind::Call(contract);
+            env.tx.gas_limit = 1_000_000;
+            let env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+            executor.transact_with_env(env).unwrap()
+        };
+
+        let full = trace(None);
+        let frames = full.traces.as_ref().unwrap().nodes().len();
+        assert!(frames > 10);
+        assert_eq!(full.elided_trace_frames, 0);
+
+        let capped = trace(Some(3));
+        let nodes = capped.traces.as_ref().unwrap().nodes();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes.iter().map(|node| node.trace.depth).max(), Some(2));
+        assert_eq!(capped.elided_trace_frames, frames - 3);
+        // the execution isn't affected
+        assert_eq!((capped.gas_used, capped.reverted), (full.gas_used, full.reverted));
+    }
+
+    #[test]
+    fn state_cache_depends_on_preceding_checks() {
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::default(), Default::default());
+        let tweaks = foundry_tweak::TweakData::default();
+        let settings = |args: &[&str]| {
+            let args = RunArgs::parse_from([&["foundry-cli", "0x1234"], args].concat());
+            args.state_cache_settings(&env, &tweaks, None)
+        };
+        assert_eq!(settings(&[]), settings(&["--disable-checks"]));
+        assert_ne!(settings(&[]), settings(&["--disable-checks-all"]));
+    }
+
+    #[tokio::test]
+    async fn refreshes_cached_signatures() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("signatures");
+        let stale = serde_json::json!({ "events": {}, "functions": { "0x12345678": "stale()" } });
+        foundry_common::fs::write_json_file(&cache, &stale).unwrap();
+        let identify = |args: &[&str]| {
+            let args = RunArgs::parse_from([&["foundry-cli", "0x1234"], args].concat());
+            let signatures = args.signatures(Some(dir.path().to_path_buf()), true).unwrap();
+            signatures.unwrap()
+        };
+
+        let signatures = identify(&["--decode-errors"]);
+        let function = signatures.write().await.identify_function(&[0x12, 0x34, 0x56, 0x78]).await;
+        assert_eq!(function.unwrap().signature(), "stale()");
+        drop(signatures);
+
+        let signatures = identify(&["--decode-errors", "--refresh-abi-cache"]);
+        let function = signatures.write().await.identify_function(&[0x12, 0x34, 0x56, 0x78]).await;
+        assert!(function.is_none());
+        drop(signatures);
+        // the stale signature isn't saved again either
+        let cached: serde_json::Value = foundry_common::fs::read_json_file(&cache).unwrap();
+        assert_eq!(cached["functions"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn counts_verbosity() {
+        assert_eq!(RunArgs::parse_from(["foundry-cli", "0x1234"]).verbose, 0);
+        assert_eq!(RunArgs::parse_from(["foundry-cli", "0x1234", "-vvv"]).verbose, 3);
+        assert_eq!(RunArgs::parse_from(["foundry-cli", "0x1234", "-v", "--verbose"]).verbose, 2);
+    }
+
+    #[test]
+    fn block_replaces_tx_hashes() {
+        let args = RunArgs::try_parse_from(["foundry-cli", "--block", "100", "--full"]).unwrap();
+        assert_eq!((args.block, args.full), (Some(100), true));
+        assert!(RunArgs::try_parse_from(["foundry-cli", "0x1234", "--block", "100"]).is_err());
+        assert!(RunArgs::try_parse_from(["foundry-cli"]).is_err());
+    }
+
+    #[test]
+    fn aggregates_failures_of_many_txs() {
+        let args = RunArgs::try_parse_from(["foundry-cli", "0x01", "0x02"]).unwrap();
+        assert!(args.aggregates_failures());
+        let args = RunArgs::try_parse_from(["foundry-cli", "0x01", "0x02", "--fail-fast"]);
+        assert!(!args.unwrap().aggregates_failures());
+        let args = RunArgs::try_parse_from(["foundry-cli", "0x01"]).unwrap();
+        assert!(!args.aggregates_failures());
+        let args = RunArgs::try_parse_from(["foundry-cli", "--block", "100"]).unwrap();
+        assert!(args.aggregates_failures());
+    }
+
+    #[test]
+    fn index_selects_block_tx() {
+        let args = RunArgs::try_parse_from(["foundry-cli", "--block", "100", "--index", "1"]);
+        let args = args.unwrap();
+        assert_eq!(args.index, Some(1));
+        assert!(!args.replays_block() && args.prints_txs());
+        assert!(RunArgs::try_parse_from(["foundry-cli", "--block", "1", "--index", "1", "--full"])
+            .is_err());
+
+        let txs = [TxHash::with_last_byte(1), TxHash::with_last_byte(2)]
+            .map(|hash| Transaction { hash, ..Default::default() });
+        assert_eq!(tx_at_index(&txs, 100, 1).unwrap().hash, TxHash::with_last_byte(2));
+        let err = tx_at_index(&txs, 100, 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "block 100 has 2 transaction(s), there is no transaction at index 2"
+        );
+    }
+
+    #[test]
+    fn no_cache_requires_block_cache() {
+        let hash = "0x1234";
+        assert!(RunArgs::try_parse_from(["foundry-cli", hash, "--no-cache"]).is_err());
+        let args =
+            RunArgs::try_parse_from(["foundry-cli", hash, "--block-cache", "--no-cache"]).unwrap();
+        assert!(args.block_cache && args.no_cache);
+    }
+
+    #[test]
+    fn calldata_must_be_hex() {
+        let hash = "0x1234";
+        let args = RunArgs::try_parse_from(["foundry-cli", hash, "--calldata", "0xa9059cbb"]);
+        assert_eq!(args.unwrap().calldata, Some(Bytes::from_static(&[0xa9, 0x05, 0x9c, 0xbb])));
+        assert!(RunArgs::try_parse_from(["foundry-cli", hash, "--calldata", "0xzz"]).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! Names of addresses resolved from external sources, e.g. ENS or Etherscan.
+
+use alloy_primitives::Address;
+use std::{
+    collections::{BTreeSet, HashMap},
+    future::Future,
+    sync::Mutex,
+};
+
+/// The names of addresses resolved by a lookup, cached for the whole run so that an address isn't
+/// resolved twice.
+#[derive(Debug, Default)]
+pub struct ResolvedNames {
+    /// The resolved names, `None` if the address has no name or the lookup failed.
+    names: Mutex<HashMap<Address, Option<String>>>,
+}
+
+impl ResolvedNames {
+    /// Returns the labels of the addresses that have a name, resolving the addresses that weren't
+    /// looked up yet with `lookup`.
+    ///
+    /// The lookups are sequential to stay within the rate limits of the sources.
+    pub async fn labels<F, Fut>(
+        &self,
+        addresses: &BTreeSet<Address>,
+        lookup: F,
+    ) -> Vec<(Address, String)>
+    where
+        F: Fn(Address) -> Fut,
+        Fut: Future<Output = Option<String>>,
+    {
+        let missing = {
+            let names = self.names.lock().unwrap();
+            addresses
+                .iter()
+                .filter(|address| !names.contains_key(*address))
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        let mut resolved = Vec::with_capacity(missing.len());
+        for &address in &missing {
+            resolved.push(lookup(address).await);
+        }
+
+        let mut names = self.names.lock().unwrap();
+        names.extend(missing.into_iter().zip(resolved));
+        addresses.iter().filter_map(|address| Some((*address, names[address].clone()?))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn caches_lookups() {
+        let named = Address::with_last_byte(1);
+        let unnamed = Address::with_last_byte(2);
+        let lookups = AtomicUsize::new(0);
+        let lookup = |address| {
+            lookups.fetch_add(1, Ordering::Relaxed);
+            async move { (address == named).then(|| "vitalik.eth".to_string()) }
+        };
+
+        let names = ResolvedNames::default();
+        let addresses = [named, unnamed].into();
+        let labels = names.labels(&addresses, lookup).await;
+        assert_eq!(labels, [(named, "vitalik.eth".to_string())]);
+        let labels = names.labels(&addresses, lookup).await;
+        assert_eq!(labels, [(named, "vitalik.eth".to_string())]);
+        assert_eq!(lookups.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,87 @@
+//! The single line summary of `--oneline`, for log aggregation.
+
+use alloy_primitives::TxHash;
+use alloy_rpc_types::Transaction;
+use cast::{decode::RevertDecoder, executors::RawCallResult};
+use serde::Serialize;
+use std::fmt;
+
+/// The outcome of a replayed transaction, printed on a single line.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OneLine {
+    pub hash: TxHash,
+    /// The block of the transaction, `None` for a pending one.
+    pub block: Option<u64>,
+    pub success: bool,
+    pub gas_used: u64,
+    /// The decoded revert reason of a reverted transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+}
+
+impl OneLine {
+    pub fn new(tx: &Transaction, raw: &RawCallResult) -> Self {
+        Self {
+            hash: tx.hash,
+            block: tx.block_number,
+            success: !raw.reverted,
+            gas_used: raw.gas_used,
+            revert_reason: raw
+                .reverted
+                .then(|| RevertDecoder::new().decode(&raw.result, Some(raw.exit_reason))),
+        }
+    }
+}
+
+/// Formats the outcome as `key=value` pairs, the revert reason being quoted.
+impl fmt::Display for OneLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hash={:?} block=", self.hash)?;
+        match self.block {
+            Some(block) => write!(f, "{block}")?,
+            None => f.write_str("pending")?,
+        }
+        let status = if self.success { "success" } else { "reverted" };
+        write!(f, " status={status} gas_used={}", self.gas_used)?;
+        if let Some(reason) = &self.revert_reason {
+            write!(f, " revert_reason={reason:?}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_single_line() {
+        let mut line = OneLine {
+            hash: TxHash::with_last_byte(1),
+            block: Some(100),
+            success: true,
+            gas_used: 21_000,
+            revert_reason: None,
+        };
+        assert_eq!(
+            line.to_string(),
+            format!("hash={:?} block=100 status=success gas_used=21000", line.hash)
+        );
+
+        line.block = None;
+        line.success = false;
+        line.revert_reason = Some("not \"owner\"".to_string());
+        assert_eq!(
+            line.to_string(),
+            format!(
+                "hash={:?} block=pending status=reverted gas_used=21000 revert_reason=\"not \
+                 \\\"owner\\\"\"",
+                line.hash
+            )
+        );
+        let json = serde_json::to_string(&line).unwrap();
+        assert!(!json.contains('\n'));
+        assert!(json.contains("\"revertReason\":\"not \\\"owner\\\"\""), "{json}");
+    }
+}
@@ -0,0 +1,195 @@
+//! Decoding of the price reads of common oracle interfaces.
+//!
+//! Support for more interfaces is added by extending [`ORACLE_FUNCTIONS`].
+
+use alloy_primitives::{utils::format_units, Address, Selector, I256, U256};
+use alloy_sol_types::{sol, SolCall};
+use cast::traces::{CallTraceArena, DecodedCallData};
+use chrono::DateTime;
+use foundry_evm::executors::Executor;
+use std::fmt;
+
+sol! {
+    interface IAggregator {
+        function decimals() external view returns (uint8);
+        function latestAnswer() external view returns (int256);
+        function latestTimestamp() external view returns (uint256);
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+        function getRoundData(uint80 _roundId) external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    }
+}
+
+/// The answer and update timestamp returned by an oracle function.
+type OracleOutput = (Option<I256>, Option<U256>);
+
+/// A known oracle function.
+struct OracleFunction {
+    selector: Selector,
+    signature: &'static str,
+    /// Decodes the answer and update timestamp from the output of the function.
+    decode: fn(&[u8]) -> Option<OracleOutput>,
+}
+
+/// The known oracle functions whose outputs are decoded.
+const ORACLE_FUNCTIONS: &[OracleFunction] = &[
+    OracleFunction {
+        selector: Selector::new(IAggregator::latestRoundDataCall::SELECTOR),
+        signature: IAggregator::latestRoundDataCall::SIGNATURE,
+        decode: |output| {
+            let ret = IAggregator::latestRoundDataCall::abi_decode_returns(output, false).ok()?;
+            Some((Some(ret.answer), Some(ret.updatedAt)))
+        },
+    },
+    OracleFunction {
+        selector: Selector::new(IAggregator::getRoundDataCall::SELECTOR),
+        signature: IAggregator::getRoundDataCall::SIGNATURE,
+        decode: |output| {
+            let ret = IAggregator::getRoundDataCall::abi_decode_returns(output, false).ok()?;
+            Some((Some(ret.answer), Some(ret.updatedAt)))
+        },
+    },
+    OracleFunction {
+        selector: Selector::new(IAggregator::latestAnswerCall::SELECTOR),
+        signature: IAggregator::latestAnswerCall::SIGNATURE,
+        decode: |output| {
+            let ret = IAggregator::latestAnswerCall::abi_decode_returns(output, false).ok()?;
+            Some((Some(ret._0), None))
+        },
+    },
+    OracleFunction {
+        selector: Selector::new(IAggregator::latestTimestampCall::SELECTOR),
+        signature: IAggregator::latestTimestampCall::SIGNATURE,
+        decode: |output| {
+            let ret = IAggregator::latestTimestampCall::abi_decode_returns(output, false).ok()?;
+            Some((None, Some(ret._0)))
+        },
+    },
+];
+
+/// A price read from an oracle by the transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OracleRead {
+    /// The index of the call in the trace arena.
+    pub idx: usize,
+    /// The address of the oracle.
+    pub address: Address,
+    /// The signature of the called function.
+    pub signature: &'static str,
+    /// The returned answer, i.e. the price.
+    pub answer: Option<I256>,
+    /// The returned timestamp of the last update of the answer.
+    pub updated_at: Option<U256>,
+    /// The decimals of the answer, if the oracle reports them.
+    pub decimals: Option<u8>,
+}
+
+impl fmt::Display for OracleRead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+        if let Some(answer) = self.answer {
+            match self.decimals.and_then(|decimals| format_units(answer, decimals).ok()) {
+                Some(price) => write!(f, "answer: {price} ({answer})")?,
+                None => write!(f, "answer: {answer}")?,
+            }
+            sep = ", ";
+        }
+        if let Some(updated_at) = self.updated_at {
+            write!(f, "{sep}updatedAt: ")?;
+            match i64::try_from(updated_at).ok().and_then(|ts| DateTime::from_timestamp(ts, 0)) {
+                Some(date) => write!(f, "{} ({updated_at})", date.format("%Y-%m-%d %H:%M:%S UTC"))?,
+                None => write!(f, "{updated_at}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OracleRead {
+    /// Annotates the call of the read in the decoded arena with the decoded price and timestamp.
+    pub fn annotate(&self, arena: &mut CallTraceArena) {
+        let decoded = &mut arena.nodes_mut()[self.idx].trace.decoded;
+        if decoded.call_data.is_none() {
+            decoded.call_data =
+                Some(DecodedCallData { signature: self.signature.to_string(), args: vec![] });
+        }
+        decoded.return_data = Some(self.to_string());
+    }
+}
+
+/// Returns the successful calls of the arena to known oracle functions, with their decoded
+/// outputs.
+pub fn find_oracle_reads(arena: &CallTraceArena) -> Vec<OracleRead> {
+    arena
+        .nodes()
+        .iter()
+        .filter(|node| node.trace.success && node.trace.data.len() >= 4)
+        .filter_map(|node| {
+            let function = ORACLE_FUNCTIONS
+                .iter()
+                .find(|function| node.trace.data[..4] == function.selector[..])?;
+            let (answer, updated_at) = (function.decode)(&node.trace.output)?;
+            Some(OracleRead {
+                idx: node.idx,
+                address: node.trace.address,
+                signature: function.signature,
+                answer,
+                updated_at,
+                decimals: None,
+            })
+        })
+        .collect()
+}
+
+/// Returns the decimals reported by the oracle at `address`, if any.
+pub fn decimals(executor: &Executor, address: Address) -> Option<u8> {
+    executor
+        .call_sol(Address::ZERO, address, &IAggregator::decimalsCall {}, U256::ZERO, None)
+        .ok()
+        .map(|res| res.decoded_result._0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+    use alloy_primitives::Bytes;
+    use alloy_sol_types::SolValue;
+
+    #[test]
+    fn decodes_round_data() {
+        let mut arena = arena(&[(None, 0), (Some(0), 0), (Some(0), 0)]);
+        let output = (
+            U256::from(1),
+            I256::try_from(183_412_000_000i64).unwrap(),
+            U256::from(1_718_020_800),
+            U256::from(1_718_020_800),
+            U256::from(1),
+        )
+            .abi_encode_params();
+        for idx in [1, 2] {
+            let trace = &mut arena.nodes_mut()[idx].trace;
+            trace.success = true;
+            trace.data = IAggregator::latestRoundDataCall {}.abi_encode().into();
+            trace.output = output.clone().into();
+        }
+        // not an oracle call
+        arena.nodes_mut()[2].trace.data = Bytes::from_static(&[0x12, 0x34, 0x56, 0x78]);
+
+        let mut reads = find_oracle_reads(&arena);
+        assert_eq!(reads.len(), 1);
+        assert_eq!(reads[0].idx, 1);
+        assert_eq!(
+            reads[0].to_string(),
+            "answer: 183412000000, updatedAt: 2024-06-10 12:00:00 UTC (1718020800)"
+        );
+
+        reads[0].decimals = Some(8);
+        reads[0].annotate(&mut arena);
+        let decoded = &arena.nodes()[1].trace.decoded;
+        assert_eq!(decoded.call_data.as_ref().unwrap().signature, "latestRoundData()");
+        assert_eq!(
+            decoded.return_data.as_deref(),
+            Some("answer: 1834.12000000 (183412000000), updatedAt: 2024-06-10 12:00:00 UTC (1718020800)")
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! The outcome of a successful transaction: the data returned by its call, or the address of the
+//! contract it deployed.
+
+use alloy_dyn_abi::FunctionExt;
+use alloy_json_abi::JsonAbi;
+use alloy_primitives::{Address, Bytes};
+use cast::traces::CallTraceArena;
+use foundry_common::fmt::format_token;
+use std::fmt;
+
+/// The outcome of a successful transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TxOutcome {
+    /// The data returned by the called address, decoded if an ABI has the called function.
+    Returned { return_data: Bytes, decoded: Option<Vec<String>> },
+    /// The address of the contract deployed by the transaction.
+    Deployed { deployed_address: Address },
+}
+
+impl TxOutcome {
+    /// Returns the outcome of the top-level call of the arena, `None` if it failed.
+    ///
+    /// The return data is decoded with the first ABI that has a function with the selector of the
+    /// calldata.
+    pub fn new<'a>(
+        arena: &CallTraceArena,
+        abis: impl IntoIterator<Item = &'a JsonAbi>,
+    ) -> Option<Self> {
+        let trace = &arena.nodes().first()?.trace;
+        if !trace.success {
+            return None;
+        }
+        if trace.kind.is_any_create() {
+            return Some(Self::Deployed { deployed_address: trace.address });
+        }
+        let decoded = trace.data.get(..4).and_then(|selector| {
+            let function = abis.into_iter().find_map(|abi| {
+                abi.functions().find(|function| function.selector()[..] == *selector)
+            })?;
+            let values = function.abi_decode_output(&trace.output, false).ok()?;
+            Some(values.iter().map(format_token).collect())
+        });
+        Some(Self::Returned { return_data: trace.output.clone(), decoded })
+    }
+
+    /// Returns the fields of the outcome in the JSON output, `returnData` and `decodedReturnData`,
+    /// or `deployedAddress`.
+    pub fn reports(&self) -> Vec<(&'static str, serde_json::Value)> {
+        match self {
+            Self::Returned { return_data, decoded } => {
+                let mut reports = vec![("returnData", return_data.to_string().into())];
+                if let Some(decoded) = decoded {
+                    reports.push(("decodedReturnData", decoded.clone().into()));
+                }
+                reports
+            }
+            Self::Deployed { deployed_address } => {
+                vec![("deployedAddress", deployed_address.to_string().into())]
+            }
+        }
+    }
+}
+
+impl fmt::Display for TxOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Returned { return_data, decoded } => {
+                write!(f, "Return data: {return_data}")?;
+                if let Some(decoded) = decoded {
+                    write!(f, "\nDecoded return data: ({})", decoded.join(", "))?;
+                }
+                Ok(())
+            }
+            Self::Deployed { deployed_address } => write!(f, "Deployed to: {deployed_address}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+    use alloy_primitives::U256;
+    use alloy_sol_types::SolValue;
+    use cast::traces::CallKind;
+
+    #[test]
+    fn finds_outcome_of_top_level_call() {
+        let abi = JsonAbi::parse(["function balanceOf(address owner) returns (uint256)"]).unwrap();
+        let mut traces = arena(&[(None, 100_000)]);
+        let trace = &mut traces.nodes_mut()[0].trace;
+        trace.data = abi.function("balanceOf").unwrap()[0].selector().to_vec().into();
+        trace.output = U256::from(7).abi_encode().into();
+        assert_eq!(TxOutcome::new(&traces, [&abi]), None);
+
+        traces.nodes_mut()[0].trace.success = true;
+        let outcome = TxOutcome::new(&traces, [&abi]).unwrap();
+        assert_eq!(
+            outcome.to_string(),
+            format!("Return data: 0x{}\nDecoded return data: (7)", "0".repeat(63) + "7")
+        );
+        assert_eq!(outcome.reports()[1], ("decodedReturnData", serde_json::json!(["7"])));
+        // without a matching ABI, the data is only printed raw
+        let outcome = TxOutcome::new(&traces, []).unwrap();
+        assert_eq!(outcome.reports().len(), 1);
+
+        let trace = &mut traces.nodes_mut()[0].trace;
+        trace.kind = CallKind::Create;
+        trace.address = Address::with_last_byte(1);
+        let outcome = TxOutcome::new(&traces, [&abi]).unwrap();
+        assert_eq!(outcome, TxOutcome::Deployed { deployed_address: Address::with_last_byte(1) });
+        assert_eq!(outcome.to_string(), format!("Deployed to: {}", Address::with_last_byte(1)));
+    }
+}
@@ -0,0 +1,68 @@
+//! The `--output-file` the traces and reports are written to instead of stdout.
+
+use std::io::{self, Write};
+
+/// A writer that strips the ANSI escape sequences of the colored output, so that the file holds
+/// plain text while the terminal output stays colored.
+#[derive(Debug)]
+pub struct Uncolored<W> {
+    inner: W,
+    state: Escape,
+}
+
+/// The position in an escape sequence, which may be split across writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Escape {
+    None,
+    /// After the `ESC` byte.
+    Start,
+    /// Within a control sequence, e.g. `ESC [ 1 ; 31 m`, until its final byte.
+    Csi,
+}
+
+impl<W> Uncolored<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, state: Escape::None }
+    }
+}
+
+impl<W: Write> Write for Uncolored<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut plain = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            self.state = match (self.state, byte) {
+                (Escape::None, 0x1b) => Escape::Start,
+                (Escape::None, _) => {
+                    plain.push(byte);
+                    Escape::None
+                }
+                (Escape::Start, b'[') => Escape::Csi,
+                // a two-byte sequence
+                (Escape::Start, _) => Escape::None,
+                (Escape::Csi, 0x40..=0x7e) => Escape::None,
+                (Escape::Csi, _) => Escape::Csi,
+            };
+        }
+        self.inner.write_all(&plain)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_colors() {
+        let mut out = Uncolored::new(Vec::new());
+        out.write_all(b"\x1b[1;31mreverted\x1b[0m gas").unwrap();
+        // a sequence split across writes
+        out.write_all(b"\x1b[3").unwrap();
+        out.write_all(b"2mok\x1b[0m\n").unwrap();
+        assert_eq!(String::from_utf8(out.inner).unwrap(), "reverted gasok\n");
+    }
+}
@@ -0,0 +1,161 @@
+//! Raw state overrides applied before the replay.
+
+use alloy_primitives::{keccak256, Address, Bytes, U256};
+use cast::{
+    backend::Backend,
+    revm::{
+        primitives::{Bytecode, KECCAK_EMPTY},
+        DatabaseRef,
+    },
+};
+use eyre::Result;
+use std::{fmt, str::FromStr};
+
+/// An override of the state of an account, e.g.
+/// `0xd8dA…6045,balance=1000000000000000000,storage=0x0:0x1`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateOverride {
+    pub address: Address,
+    pub balance: Option<U256>,
+    pub nonce: Option<u64>,
+    pub code: Option<Bytes>,
+    /// The overridden storage slots, the other slots are left untouched.
+    pub storage: Vec<(U256, U256)>,
+}
+
+impl FromStr for StateOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split(',');
+        let address = fields.next().unwrap_or_default();
+        let mut state_override = Self {
+            address: address.parse().map_err(|e| format!("invalid address `{address}`: {e}"))?,
+            ..Default::default()
+        };
+        for field in fields {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("expected `<field>=<value>`, got `{field}`"))?;
+            match key {
+                "balance" => {
+                    state_override.balance =
+                        Some(value.parse().map_err(|e| format!("invalid balance `{value}`: {e}"))?);
+                }
+                "nonce" => {
+                    state_override.nonce =
+                        Some(value.parse().map_err(|e| format!("invalid nonce `{value}`: {e}"))?);
+                }
+                "code" => {
+                    state_override.code =
+                        Some(value.parse().map_err(|e| format!("invalid code `{value}`: {e}"))?);
+                }
+                "storage" => {
+                    let (slot, slot_value) = value.split_once(':').ok_or_else(|| {
+                        format!("expected `storage=<slot>:<value>`, got `{value}`")
+                    })?;
+                    let parse = |word: &str| {
+                        word.parse::<U256>()
+                            .map_err(|e| format!("invalid storage word `{word}`: {e}"))
+                    };
+                    state_override.storage.push((parse(slot)?, parse(slot_value)?));
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown field `{key}`, expected one of `balance`, `nonce`, `code` and \
+                         `storage`"
+                    ))
+                }
+            }
+        }
+        Ok(state_override)
+    }
+}
+
+impl fmt::Display for StateOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.address)?;
+        if let Some(balance) = self.balance {
+            write!(f, ",balance={balance}")?;
+        }
+        if let Some(nonce) = self.nonce {
+            write!(f, ",nonce={nonce}")?;
+        }
+        if let Some(code) = &self.code {
+            write!(f, ",code={code}")?;
+        }
+        for (slot, value) in &self.storage {
+            write!(f, ",storage={slot:#x}:{value:#x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl StateOverride {
+    /// Applies the override to the backend.
+    pub fn apply(&self, backend: &mut Backend) -> Result<()> {
+        let mut info = backend.basic_ref(self.address)?.unwrap_or_default();
+        if let Some(balance) = self.balance {
+            info.balance = balance;
+        }
+        if let Some(nonce) = self.nonce {
+            info.nonce = nonce;
+        }
+        if let Some(code) = &self.code {
+            info.code_hash = if code.is_empty() { KECCAK_EMPTY } else { keccak256(code) };
+            info.code = Some(Bytecode::new_raw(code.clone()));
+        }
+        backend.insert_account_info(self.address, info);
+        for &(slot, value) in &self.storage {
+            backend.insert_account_storage(self.address, slot, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_state_override() {
+        let address = Address::with_last_byte(1);
+        let state_override: StateOverride =
+            format!("{address},balance=100,nonce=2,code=0x6000,storage=0x1:0x2,storage=3:4")
+                .parse()
+                .unwrap();
+        assert_eq!(
+            state_override,
+            StateOverride {
+                address,
+                balance: Some(U256::from(100)),
+                nonce: Some(2),
+                code: Some(Bytes::from_static(&[0x60, 0x00])),
+                storage: vec![(U256::from(1), U256::from(2)), (U256::from(3), U256::from(4))],
+            }
+        );
+
+        assert_eq!(state_override.to_string().parse::<StateOverride>().unwrap(), state_override);
+
+        assert!(format!("{address}").parse::<StateOverride>().is_ok());
+        assert!("0x12,balance=1".parse::<StateOverride>().is_err());
+        assert!(format!("{address},balance=x").parse::<StateOverride>().is_err());
+        assert!(format!("{address},code=0xzz").parse::<StateOverride>().is_err());
+        assert!(format!("{address},storage=0x1").parse::<StateOverride>().is_err());
+        assert!(format!("{address},gas=1").parse::<StateOverride>().is_err());
+    }
+
+    #[test]
+    fn applies_state_override() {
+        let mut backend = Backend::spawn(None);
+        let address = Address::with_last_byte(1);
+        let state_override: StateOverride =
+            format!("{address},balance=100,code=0x6000,storage=0x1:0x2").parse().unwrap();
+        state_override.apply(&mut backend).unwrap();
+
+        let info = backend.basic_ref(address).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(100));
+        assert_eq!(info.code_hash, keccak256([0x60, 0x00]));
+        assert_eq!(backend.storage_ref(address, U256::from(1)).unwrap(), U256::from(2));
+    }
+}
@@ -0,0 +1,162 @@
+//! Comparison of a gas-perturbed execution against the unperturbed baseline.
+
+use alloy_primitives::{Address, Bytes};
+use cast::{executors::RawCallResult, traces::CallTraceArena};
+use serde::Serialize;
+use std::fmt;
+
+/// The outcome of an execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outcome {
+    pub success: bool,
+    pub gas_used: u64,
+}
+
+impl Outcome {
+    pub fn from_raw(raw: &RawCallResult) -> Self {
+        Self { success: !raw.reverted, gas_used: raw.gas_used }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.success { "succeeded" } else { "reverted" };
+        write!(f, "{status}, {} gas used", self.gas_used)
+    }
+}
+
+/// A call whose outcome changed between the baseline and the perturbed execution.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CallChange {
+    /// The index of the call in the trace.
+    pub idx: usize,
+    pub address: Address,
+    pub data: Bytes,
+    /// Whether the call succeeded in the baseline execution.
+    pub baseline: bool,
+}
+
+impl fmt::Display for CallChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (before, after) =
+            if self.baseline { ("succeeded", "reverted") } else { ("reverted", "succeeded") };
+        write!(f, "call #{} to {} ", self.idx, self.address)?;
+        match self.data.get(..4) {
+            Some(selector) => write!(f, "{}", Bytes::copy_from_slice(selector))?,
+            None => write!(f, "{}", self.data)?,
+        }
+        write!(f, ": {before} -> {after}")
+    }
+}
+
+/// The differences between the baseline and the perturbed execution.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerturbationReport {
+    pub delta: i64,
+    pub baseline: Outcome,
+    pub perturbed: Outcome,
+    /// The calls present in both executions whose outcome changed.
+    pub changed_calls: Vec<CallChange>,
+    /// The index of the first call that differs in target or calldata, after which the call trees
+    /// are not compared anymore.
+    pub diverged_at: Option<usize>,
+}
+
+impl PerturbationReport {
+    pub fn new(delta: i64, baseline: &RawCallResult, perturbed: &RawCallResult) -> Self {
+        let (changed_calls, diverged_at) = match (&baseline.traces, &perturbed.traces) {
+            (Some(baseline), Some(perturbed)) => compare_calls(baseline, perturbed),
+            _ => (vec![], None),
+        };
+        Self {
+            delta,
+            baseline: Outcome::from_raw(baseline),
+            perturbed: Outcome::from_raw(perturbed),
+            changed_calls,
+            diverged_at,
+        }
+    }
+
+    /// Returns whether the perturbation changed the outcome of the transaction or of any call.
+    pub fn changed(&self) -> bool {
+        self.baseline.success != self.perturbed.success ||
+            !self.changed_calls.is_empty() ||
+            self.diverged_at.is_some()
+    }
+}
+
+impl fmt::Display for PerturbationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Gas perturbation of {:+} gas per call frame:", self.delta)?;
+        writeln!(f, "  baseline:  {}", self.baseline)?;
+        write!(f, "  perturbed: {}", self.perturbed)?;
+        if !self.changed() {
+            return write!(f, "\n  no outcome changes");
+        }
+        for change in &self.changed_calls {
+            write!(f, "\n  {change}")?;
+        }
+        if let Some(idx) = self.diverged_at {
+            write!(f, "\n  call trees diverge at call #{idx}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the calls of both executions in execution order until they diverge, i.e. until a call
+/// differs in target or calldata.
+fn compare_calls(
+    baseline: &CallTraceArena,
+    perturbed: &CallTraceArena,
+) -> (Vec<CallChange>, Option<usize>) {
+    let (baseline, perturbed) = (baseline.nodes(), perturbed.nodes());
+    let mut changes = vec![];
+    for idx in 0..baseline.len().max(perturbed.len()) {
+        let (Some(before), Some(after)) = (baseline.get(idx), perturbed.get(idx)) else {
+            return (changes, Some(idx))
+        };
+        let (before, after) = (&before.trace, &after.trace);
+        if before.address != after.address || before.data != after.data {
+            return (changes, Some(idx))
+        }
+        if before.success != after.success {
+            changes.push(CallChange {
+                idx,
+                address: before.address,
+                data: before.data.clone(),
+                baseline: before.success,
+            });
+        }
+    }
+    (changes, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+
+    #[test]
+    fn reports_changed_calls() {
+        let baseline = arena(&[(None, 100_000), (Some(0), 60_000), (Some(0), 5_000)]);
+        let mut perturbed = baseline.clone();
+        perturbed.nodes_mut()[1].trace.success = true;
+        assert_eq!(
+            compare_calls(&baseline, &perturbed),
+            (
+                vec![CallChange {
+                    idx: 1,
+                    address: Address::ZERO,
+                    data: Bytes::new(),
+                    baseline: false
+                }],
+                None
+            )
+        );
+
+        perturbed.nodes_mut().pop();
+        assert_eq!(compare_calls(&baseline, &perturbed).1, Some(2));
+    }
+}
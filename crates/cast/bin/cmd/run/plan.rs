@@ -0,0 +1,184 @@
+//! The execution plan of a `--dry-run`, resolved without forking or executing anything.
+
+use super::{abis::AbiSource, labels::InlineLabel};
+use alloy_primitives::TxHash;
+use foundry_compilers::artifacts::EvmVersion;
+use foundry_tweak::{ClonedProject, CodeTweak, TweakTarget};
+use std::{fmt, path::PathBuf};
+
+/// How the transactions of a block would be replayed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockPlan {
+    pub chain_id: u64,
+    /// The block the transactions are replayed in, which is hypothetical for `--future-block`.
+    pub block: u64,
+    /// The block the fork is created at.
+    pub fork_block: u64,
+    pub evm_version: EvmVersion,
+    pub txs: Vec<TxPlan>,
+}
+
+/// How a transaction would be replayed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxPlan {
+    pub hash: TxHash,
+    /// The number of preceding transactions of the block that would be executed before it.
+    pub preceding: usize,
+}
+
+impl fmt::Display for BlockPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Block {} of chain {}, forked at block {}, EVM version {}:",
+            self.block, self.chain_id, self.fork_block, self.evm_version
+        )?;
+        for tx in &self.txs {
+            writeln!(f, "  {:?} after {} preceding transaction(s)", tx.hash, tx.preceding)?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of the parsing of an input of the replay.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputCheck {
+    /// The option of the input, e.g. `--tweak`.
+    pub option: &'static str,
+    pub input: String,
+    /// What the input resolves to, or why it's invalid.
+    pub outcome: Result<String, String>,
+}
+
+impl InputCheck {
+    pub fn is_valid(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+impl fmt::Display for InputCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            Ok(resolved) => write!(f, "{} {}: {resolved}", self.option, self.input),
+            Err(err) => write!(f, "{} {}: invalid, {err}", self.option, self.input),
+        }
+    }
+}
+
+/// Parses the `--tweak`, `--tweak-at`, `--tweak-code`, `--label` and `--abi` inputs.
+///
+/// The metadata of the tweak projects is loaded, but they aren't compiled, so that the errors
+/// of their compilation are only reported by the replay.
+pub fn check_inputs(
+    tweaks: &[PathBuf],
+    tweak_targets: &[TweakTarget],
+    code_tweaks: &[CodeTweak],
+    labels: &[InlineLabel],
+    abis: &[AbiSource],
+) -> Vec<InputCheck> {
+    let mut checks = vec![];
+    let mut projects = vec![];
+    for path in tweaks {
+        let project = dunce::canonicalize(path)
+            .map_err(|err| err.to_string())
+            .and_then(|root| ClonedProject::load_with_root(root).map_err(|err| err.to_string()));
+        let outcome = project.as_ref().map(describe_project).map_err(Clone::clone);
+        checks.push(InputCheck { option: "--tweak", input: path.display().to_string(), outcome });
+        projects.extend(project);
+    }
+    for target in tweak_targets {
+        let root = dunce::canonicalize(&target.project).ok();
+        let outcome = match projects.iter().find(|project| Some(&project.root) == root.as_ref()) {
+            Some(project) => Ok(format!(
+                "{} of {} applied to {}",
+                project.metadata.target_contract, project.metadata.address, target.address
+            )),
+            None => Err(format!("{} is not one of the tweak projects", target.project.display())),
+        };
+        let input = format!("{}:{}", target.project.display(), target.address);
+        checks.push(InputCheck { option: "--tweak-at", input, outcome });
+    }
+    for tweak in code_tweaks {
+        let outcome = Ok(format!("{} bytes of code", tweak.code.len()));
+        checks.push(InputCheck {
+            option: "--tweak-code",
+            input: tweak.address.to_string(),
+            outcome,
+        });
+    }
+    for label in labels {
+        checks.push(InputCheck {
+            option: "--label",
+            input: label.to_string(),
+            outcome: check_label(label),
+        });
+    }
+    for source in abis {
+        let outcome = source.load().map_err(|err| format!("{err:#}")).map(|abi| {
+            let scope = source.address.map(|address| format!(" for {address}")).unwrap_or_default();
+            format!(
+                "{} function(s), {} event(s), {} error(s){scope}",
+                abi.functions().count(),
+                abi.events().count(),
+                abi.errors().count()
+            )
+        });
+        let input = match source.address {
+            Some(address) => format!("{address}:{}", source.path.display()),
+            None => source.path.display().to_string(),
+        };
+        checks.push(InputCheck { option: "--abi", input, outcome });
+    }
+    checks
+}
+
+fn describe_project(project: &ClonedProject) -> String {
+    format!("{} at {}", project.metadata.target_contract, project.metadata.address)
+}
+
+/// Checks that the address of a `--label` is resolved, the format being validated when parsing
+/// the arguments.
+fn check_label(label: &InlineLabel) -> Result<String, String> {
+    match label.address() {
+        Some(address) => Ok(format!("{} for {address}", label.name)),
+        None => Err("the ENS name isn't resolved".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Address;
+
+    #[test]
+    fn checks_inputs() {
+        let address = Address::with_last_byte(1);
+        let code_tweak = CodeTweak { address, code: vec![0x60, 0x00].into() };
+        let target = TweakTarget { project: PathBuf::from("/nonexistent/project"), address };
+        let labels =
+            [format!("{address}:Token").parse().unwrap(), "token.eth:Token".parse().unwrap()];
+        let checks = check_inputs(
+            &[PathBuf::from("/nonexistent/project")],
+            &[target],
+            &[code_tweak],
+            &labels,
+            &[AbiSource { address: Some(address), path: PathBuf::from("/nonexistent/abi.json") }],
+        );
+
+        let valid = checks.iter().map(InputCheck::is_valid).collect::<Vec<_>>();
+        assert_eq!(valid, [false, false, true, true, false, false]);
+        assert!(checks[5].to_string().starts_with(&format!(
+            "--abi {address}:/nonexistent/abi.json: invalid, failed to load the ABI"
+        )));
+        assert_eq!(checks[2].to_string(), format!("--tweak-code {address}: 2 bytes of code"));
+        assert_eq!(checks[3].to_string(), format!("--label {address}:Token: Token for {address}"));
+        assert_eq!(
+            checks[4].to_string(),
+            "--label token.eth:Token: invalid, the ENS name isn't resolved"
+        );
+        assert_eq!(
+            checks[1].outcome,
+            Err("/nonexistent/project is not one of the tweak projects".to_string())
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! The pre-state of a transaction traced by the node, seeding the replay of `--only-target`.
+
+use alloy_primitives::{keccak256, Address, TxHash, U256};
+use alloy_provider::Provider;
+use alloy_rpc_types::trace::geth::{
+    AccountState, GethDebugBuiltInTracerType, GethDebugTracingOptions,
+};
+use cast::{
+    backend::Backend,
+    revm::{
+        primitives::{Bytecode, KECCAK_EMPTY},
+        DatabaseRef,
+    },
+};
+use eyre::Result;
+use foundry_common::{cli_warn, provider::RetryProvider};
+use std::collections::BTreeMap;
+
+/// The accounts and slots a transaction accessed, with their state right before it.
+pub type PreState = BTreeMap<Address, AccountState>;
+
+/// Fetches the pre-state of the transaction with the `prestateTracer` of
+/// `debug_traceTransaction`.
+///
+/// Returns `None` with a warning if the node doesn't support the method, the replay then starts
+/// from the state of the parent block as with `--quick`.
+pub async fn fetch(provider: &RetryProvider, tx_hash: TxHash) -> Option<PreState> {
+    let options = GethDebugTracingOptions::default()
+        .with_tracer(GethDebugBuiltInTracerType::PreStateTracer.into());
+    // the frame is deserialized as a pre-state directly, the untagged variants being ambiguous
+    match provider.raw_request("debug_traceTransaction".into(), (tx_hash, options)).await {
+        Ok(prestate) => Some(prestate),
+        Err(err) => {
+            cli_warn!(
+                "could not fetch the pre-state of {tx_hash:?}, replaying it on the state of the \
+                 parent block as with --quick: {err}"
+            );
+            None
+        }
+    }
+}
+
+/// Writes the pre-state to the backend and returns the number of written accounts and slots.
+///
+/// The code of the accounts of `keep_code` isn't written, e.g. the tweaked contracts.
+pub fn apply(
+    backend: &mut Backend,
+    prestate: &PreState,
+    keep_code: impl Fn(&Address) -> bool,
+) -> Result<(usize, usize)> {
+    let mut slots = 0;
+    for (&address, account) in prestate {
+        let mut info = backend.basic_ref(address)?.unwrap_or_default();
+        if let Some(balance) = account.balance {
+            info.balance = balance;
+        }
+        if let Some(nonce) = account.nonce {
+            info.nonce = nonce;
+        }
+        match &account.code {
+            Some(code) if !keep_code(&address) => {
+                info.code_hash = if code.is_empty() { KECCAK_EMPTY } else { keccak256(code) };
+                info.code = Some(Bytecode::new_raw(code.clone()));
+            }
+            _ => {}
+        }
+        backend.insert_account_info(address, info);
+        for (slot, value) in &account.storage {
+            backend.insert_account_storage(
+                address,
+                (*slot).into(),
+                U256::from_be_bytes(value.0),
+            )?;
+            slots += 1;
+        }
+    }
+    Ok((prestate.len(), slots))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Bytes, B256};
+
+    #[test]
+    fn applies_prestate() {
+        let mut backend = Backend::spawn(None);
+        let (token, tweaked) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let code = Bytes::from_static(&[0x60, 0x00]);
+        let prestate: PreState = serde_json::from_value(serde_json::json!({
+            token.to_string(): {
+                "balance": "0x64",
+                "nonce": 1,
+                "code": code,
+                "storage": { B256::with_last_byte(1).to_string(): B256::with_last_byte(7) }
+            },
+            tweaked.to_string(): { "balance": "0x1", "code": "0x6001" }
+        }))
+        .unwrap();
+        assert_eq!(apply(&mut backend, &prestate, |address| *address == tweaked).unwrap(), (2, 1));
+
+        let info = backend.basic_ref(token).unwrap().unwrap();
+        assert_eq!((info.balance, info.nonce), (U256::from(100), 1));
+        assert_eq!(info.code.unwrap().original_bytes(), code);
+        assert_eq!(backend.storage_ref(token, U256::from(1)).unwrap(), U256::from(7));
+        let info = backend.basic_ref(tweaked).unwrap().unwrap();
+        assert_eq!((info.balance, info.code_hash), (U256::from(1), KECCAK_EMPTY));
+    }
+}
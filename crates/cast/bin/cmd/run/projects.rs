@@ -0,0 +1,162 @@
+//! Decoding of the contracts of the tweak projects in the trace, with their compiled artifacts.
+
+use alloy_dyn_abi::JsonAbiExt;
+use alloy_json_abi::JsonAbi;
+use alloy_primitives::{Address, Bytes};
+use cast::traces::CallTraceArena;
+use eyre::Result;
+use foundry_common::fmt::format_token;
+use foundry_tweak::ClonedProject;
+
+/// A contract of a tweak project.
+#[derive(Clone, Debug)]
+pub struct ProjectContract {
+    pub name: String,
+    /// The address the tweaked code is applied to.
+    pub address: Address,
+    /// The address the contract was created at, by its creation transaction.
+    pub created_at: Address,
+    pub abi: JsonAbi,
+    /// The constructor arguments of its on-chain creation.
+    pub constructor_arguments: Bytes,
+}
+
+impl ProjectContract {
+    pub fn new(project: &ClonedProject) -> Result<Self> {
+        let artifact = project.main_artifact()?;
+        Ok(Self {
+            name: project.metadata.target_contract.clone(),
+            address: project.tweaked_address(),
+            created_at: project.metadata.address,
+            abi: artifact.abi.unwrap_or_default(),
+            constructor_arguments: project.metadata.constructor_arguments.clone(),
+        })
+    }
+
+    /// Returns the label of the address of the contract, as accepted by `--label`.
+    pub fn label(&self) -> (Address, String) {
+        (self.address, self.name.clone())
+    }
+
+    /// Returns the name of the contract followed by its decoded constructor arguments if
+    /// `init_code` ends with the ones of its on-chain creation, or only its name.
+    fn creation_label(&self, init_code: &[u8]) -> String {
+        let args = self
+            .abi
+            .constructor()
+            .filter(|_| init_code.ends_with(&self.constructor_arguments))
+            .and_then(|constructor| {
+                constructor.abi_decode_input(&self.constructor_arguments, false).ok()
+            });
+        match args {
+            Some(args) => {
+                let args = args.iter().map(format_token).collect::<Vec<_>>();
+                format!("{}({})", self.name, args.join(", "))
+            }
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Names the creations of the contracts in the trace after them, with their decoded constructor
+/// arguments. The other creations keep being rendered as is.
+pub fn annotate_creations(arena: &mut CallTraceArena, contracts: &[ProjectContract]) {
+    for node in arena.nodes_mut() {
+        if !node.trace.kind.is_any_create() {
+            continue;
+        }
+        let address = node.trace.address;
+        let Some(contract) = contracts
+            .iter()
+            .find(|contract| contract.created_at == address || contract.address == address)
+        else {
+            continue;
+        };
+        node.trace.decoded.label = Some(contract.creation_label(&node.trace.data));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+    use alloy_primitives::U256;
+    use alloy_sol_types::SolValue;
+    use cast::traces::CallKind;
+
+    #[test]
+    fn names_project_creations() {
+        let owner = Address::with_last_byte(2);
+        let arguments = Bytes::from((owner, U256::from(5)).abi_encode_params());
+        let contract = ProjectContract {
+            name: "Token".to_string(),
+            address: Address::with_last_byte(1),
+            created_at: Address::with_last_byte(1),
+            abi: JsonAbi::parse(["constructor(address owner, uint256 supply)"]).unwrap(),
+            constructor_arguments: arguments.clone(),
+        };
+        assert_eq!(contract.label(), (Address::with_last_byte(1), "Token".to_string()));
+
+        let mut traces = arena(&[(None, 100_000), (Some(0), 60_000), (Some(0), 5_000)]);
+        let nodes = traces.nodes_mut();
+        nodes[1].trace.kind = CallKind::Create;
+        nodes[1].trace.address = Address::with_last_byte(1);
+        nodes[1].trace.data = [&[0x60, 0x80][..], &arguments].concat().into();
+        // a call of the contract
+        nodes[2].trace.address = Address::with_last_byte(1);
+
+        annotate_creations(&mut traces, std::slice::from_ref(&contract));
+        let labels = traces.nodes().iter().map(|node| node.trace.decoded.label.clone());
+        assert_eq!(labels.collect::<Vec<_>>(), [None, Some(format!("Token({owner}, 5)")), None]);
+
+        // init code with other constructor arguments isn't decoded
+        let mut other = arena(&[(None, 100_000)]);
+        other.nodes_mut()[0].trace.kind = CallKind::Create2;
+        other.nodes_mut()[0].trace.address = Address::with_last_byte(1);
+        annotate_creations(&mut other, &[contract]);
+        assert_eq!(other.nodes()[0].trace.decoded.label.as_deref(), Some("Token"));
+    }
+
+    #[tokio::test]
+    async fn decodes_project_events() {
+        use alloy_primitives::{LogData, B256};
+        use cast::traces::{CallLog, TraceKind, TraceMemberOrder};
+        use foundry_cli::utils::{handle_traces_with, TraceOptions, TraceResult};
+        use foundry_config::Config;
+
+        let abi = JsonAbi::parse([
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+        ])
+        .unwrap();
+        let (alice, bob) = (Address::with_last_byte(2), Address::with_last_byte(3));
+        let transfer = LogData::new_unchecked(
+            vec![abi.events().next().unwrap().selector(), alice.into_word(), bob.into_word()],
+            U256::from(5).abi_encode().into(),
+        );
+        let unknown = LogData::new_unchecked(vec![B256::repeat_byte(0xee)], Bytes::new());
+        let mut traces = arena(&[(None, 100_000)]);
+        traces.nodes_mut()[0].logs = [transfer, unknown]
+            .into_iter()
+            .map(|raw_log| CallLog { raw_log, ..Default::default() })
+            .collect();
+        traces.nodes_mut()[0].ordering = vec![TraceMemberOrder::Log(0), TraceMemberOrder::Log(1)];
+        let result = TraceResult {
+            success: true,
+            traces: Some(vec![(TraceKind::Execution, traces)]),
+            gas_used: 100_000,
+            logs: vec![],
+            reports: Default::default(),
+        };
+
+        let config = Config { offline: true, ..Default::default() };
+        let labels = [(alice, "Alice".to_string())].into();
+        let mut out = vec![];
+        let abis = [abi];
+        let options = TraceOptions { labels: Some(labels), abis: &abis, ..Default::default() };
+        handle_traces_with(result, &config, None, options, &mut out, |_| {}).await.unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(&format!("from: Alice: [{alice}], to: {bob}, value: 5")), "{out}");
+        // unknown events are rendered raw
+        assert!(out.contains("emit topic 0"), "{out}");
+    }
+}
@@ -0,0 +1,183 @@
+//! The proxies of `--resolve-proxies`: the contracts that delegate their calls to an
+//! implementation whose address is in a standard storage slot.
+
+use alloy_primitives::{b256, Address, B256, U256};
+use cast::traces::{CallKind, CallTraceArena};
+use eyre::Result;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+/// The EIP-1967 slot of the implementation, `keccak256("eip1967.proxy.implementation") - 1`.
+const IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+/// The EIP-1967 slot of the admin of transparent proxies, `keccak256("eip1967.proxy.admin") - 1`.
+const ADMIN_SLOT: B256 = b256!("b53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103");
+
+/// The EIP-1967 slot of the beacon, `keccak256("eip1967.proxy.beacon") - 1`.
+const BEACON_SLOT: B256 = b256!("a3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50");
+
+/// The slot of the implementation of the proxies of OpenZeppelin before EIP-1967,
+/// `keccak256("org.zeppelinos.proxy.implementation")`.
+const LEGACY_IMPLEMENTATION_SLOT: B256 =
+    b256!("7050c9e0f4ca769c69bd3a8ef740bc37934f8e2c036e5a723fd8ee048ed3f8c3");
+
+/// The proxy standard, told apart by the slots that are set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProxyKind {
+    /// An EIP-1967 proxy with an admin, upgraded by it.
+    Transparent,
+    /// An EIP-1967 proxy without an admin, upgraded by its implementation.
+    Uups,
+    /// An EIP-1967 proxy reading its implementation from a beacon.
+    Beacon { beacon: Address },
+    /// A proxy of OpenZeppelin before EIP-1967.
+    Legacy,
+}
+
+impl fmt::Display for ProxyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transparent => f.write_str("transparent"),
+            Self::Uups => f.write_str("UUPS"),
+            Self::Beacon { .. } => f.write_str("beacon"),
+            Self::Legacy => f.write_str("legacy"),
+        }
+    }
+}
+
+/// A proxy that delegated calls to its implementation during the transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedProxy {
+    pub proxy: Address,
+    pub implementation: Address,
+    pub kind: ProxyKind,
+}
+
+/// Returns the accounts that delegate called another contract in the transaction, with the first
+/// contract they delegated to.
+pub fn delegators(arena: &CallTraceArena) -> BTreeMap<Address, Address> {
+    let mut delegators = BTreeMap::new();
+    for node in arena.nodes() {
+        if node.trace.kind == CallKind::DelegateCall {
+            // the caller of a delegate call is the account whose storage is used
+            delegators.entry(node.trace.caller).or_insert(node.trace.address);
+        }
+    }
+    delegators
+}
+
+/// Resolves the implementation of `proxy`, which delegated to `target`, from its storage slots
+/// read with `read`. Returns `None` if none of the standard slots is set.
+///
+/// The implementation of a beacon proxy is held by the beacon, `target` is used instead.
+pub fn resolve(
+    proxy: Address,
+    target: Address,
+    mut read: impl FnMut(Address, U256) -> Result<U256>,
+) -> Result<Option<ResolvedProxy>> {
+    let mut read_address = |slot: B256| -> Result<Option<Address>> {
+        let value = read(proxy, slot.into())?;
+        Ok((!value.is_zero()).then(|| Address::from_word(value.into())))
+    };
+    let resolved = |implementation, kind| Some(ResolvedProxy { proxy, implementation, kind });
+    if let Some(implementation) = read_address(IMPLEMENTATION_SLOT)? {
+        let kind = if read_address(ADMIN_SLOT)?.is_some() {
+            ProxyKind::Transparent
+        } else {
+            ProxyKind::Uups
+        };
+        return Ok(resolved(implementation, kind));
+    }
+    if let Some(beacon) = read_address(BEACON_SLOT)? {
+        return Ok(resolved(target, ProxyKind::Beacon { beacon }));
+    }
+    Ok(read_address(LEGACY_IMPLEMENTATION_SLOT)?
+        .and_then(|implementation| resolved(implementation, ProxyKind::Legacy)))
+}
+
+/// Names the calls to the proxies after their implementation, e.g.
+/// `USDC [transparent proxy to FiatTokenV2_2]`, the implementation being named after `labels`.
+pub fn annotate(
+    arena: &mut CallTraceArena,
+    proxies: &[ResolvedProxy],
+    labels: &HashMap<Address, String>,
+) {
+    for node in arena.nodes_mut() {
+        if node.trace.kind == CallKind::DelegateCall {
+            continue;
+        }
+        let Some(proxy) = proxies.iter().find(|proxy| proxy.proxy == node.trace.address) else {
+            continue;
+        };
+        let name = |address: &Address| labels.get(address).cloned();
+        let proxy_name = node
+            .trace
+            .decoded
+            .label
+            .clone()
+            .or_else(|| name(&proxy.proxy))
+            .unwrap_or_else(|| proxy.proxy.to_string());
+        let implementation =
+            name(&proxy.implementation).unwrap_or_else(|| proxy.implementation.to_string());
+        node.trace.decoded.label =
+            Some(format!("{proxy_name} [{} proxy to {implementation}]", proxy.kind));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+
+    #[test]
+    fn resolves_and_names_proxies() {
+        let (proxy, implementation, beacon) =
+            (Address::with_last_byte(1), Address::with_last_byte(2), Address::with_last_byte(3));
+        let mut traces = arena(&[(None, 100_000), (Some(0), 50_000)]);
+        let nodes = traces.nodes_mut();
+        nodes[0].trace.address = proxy;
+        nodes[1].trace.kind = CallKind::DelegateCall;
+        nodes[1].trace.caller = proxy;
+        nodes[1].trace.address = implementation;
+        assert_eq!(delegators(&traces), BTreeMap::from([(proxy, implementation)]));
+
+        let storage = |slots: Vec<(B256, Address)>| {
+            move |address: Address, slot: U256| {
+                assert_eq!(address, proxy);
+                let value = slots.iter().find(|(s, _)| U256::from_be_bytes(s.0) == slot);
+                Ok(value.map_or(U256::ZERO, |(_, value)| value.into_word().into()))
+            }
+        };
+        let uups = resolve(proxy, implementation, storage(vec![(IMPLEMENTATION_SLOT, beacon)]));
+        assert_eq!(
+            uups.unwrap(),
+            Some(ResolvedProxy { proxy, implementation: beacon, kind: ProxyKind::Uups })
+        );
+        let slots = vec![(IMPLEMENTATION_SLOT, implementation), (ADMIN_SLOT, beacon)];
+        let transparent = resolve(proxy, implementation, storage(slots)).unwrap().unwrap();
+        assert_eq!(transparent.kind, ProxyKind::Transparent);
+        let slots = vec![(BEACON_SLOT, beacon)];
+        let beacon_proxy = resolve(proxy, implementation, storage(slots)).unwrap().unwrap();
+        assert_eq!(
+            (beacon_proxy.implementation, beacon_proxy.kind),
+            (implementation, ProxyKind::Beacon { beacon })
+        );
+        assert_eq!(resolve(proxy, implementation, storage(vec![])).unwrap(), None);
+
+        traces.nodes_mut()[0].trace.decoded.label = Some("USDC".to_string());
+        let labels = HashMap::from([(implementation, "FiatToken".to_string())]);
+        annotate(&mut traces, &[transparent], &labels);
+        let nodes = traces.nodes();
+        assert_eq!(
+            nodes[0].trace.decoded.label.as_deref(),
+            Some("USDC [transparent proxy to FiatToken]")
+        );
+        assert_eq!(nodes[1].trace.decoded.label, None);
+    }
+}
@@ -0,0 +1,169 @@
+//! The state written by the preceding transactions of the block that `--quick` doesn't execute
+//! and that the replayed transaction read.
+
+use alloy_primitives::{Address, TxHash, B256, U256};
+use alloy_provider::Provider;
+use alloy_rpc_types::{
+    trace::{
+        common::TraceResult,
+        geth::{DiffMode, GethDebugBuiltInTracerType, GethDebugTracingOptions, PreStateConfig},
+    },
+    BlockNumberOrTag,
+};
+use cast::revm::primitives::EvmState;
+use foundry_common::{cli_warn, provider::RetryProvider};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// An account or a slot read by the replayed transaction that a preceding transaction wrote.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SkippedWrite {
+    pub address: Address,
+    /// The written slot, `None` if the balance, nonce or code of the account was written.
+    pub slot: Option<B256>,
+    /// The preceding transactions that wrote it, in block order.
+    pub writers: Vec<TxHash>,
+}
+
+impl fmt::Display for SkippedWrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.slot {
+            Some(slot) => write!(f, "{} slot {slot}", self.address)?,
+            None => write!(f, "{} (balance, nonce or code)", self.address)?,
+        }
+        let writers = self.writers.iter().map(|hash| format!("{hash:?}")).collect::<Vec<_>>();
+        write!(f, " written by {}", writers.join(", "))
+    }
+}
+
+/// Fetches the state changed by each of the first `count` transactions of the block with the
+/// `prestateTracer` of `debug_traceBlockByNumber`, in block order.
+///
+/// Returns `None` with a warning if the node doesn't support the method.
+pub async fn preceding_diffs(
+    provider: &RetryProvider,
+    block_number: u64,
+    count: usize,
+) -> Option<Vec<(TxHash, DiffMode)>> {
+    let options = GethDebugTracingOptions::default()
+        .with_tracer(GethDebugBuiltInTracerType::PreStateTracer.into())
+        .with_prestate_config(PreStateConfig { diff_mode: Some(true) });
+    // the frames are deserialized as diffs directly, their untagged variants being ambiguous
+    let params = (BlockNumberOrTag::Number(block_number), options);
+    let results: Vec<TraceResult<DiffMode, String>> =
+        match provider.raw_request("debug_traceBlockByNumber".into(), params).await {
+            Ok(results) => results,
+            Err(err) => {
+                cli_warn!(
+                    "could not fetch the state changed by the preceding transactions, skipping \
+                     the --quick check: {err}"
+                );
+                return None;
+            }
+        };
+    let mut diffs = vec![];
+    for result in results.into_iter().take(count) {
+        match result {
+            TraceResult::Success { result, tx_hash } => {
+                diffs.push((tx_hash.unwrap_or_default(), result))
+            }
+            TraceResult::Error { error, tx_hash } => {
+                cli_warn!("could not trace the preceding transaction {tx_hash:?}: {error}");
+            }
+        }
+    }
+    Some(diffs)
+}
+
+/// Returns the accounts and slots loaded by the transaction, as of its state changeset `state`,
+/// that the preceding transactions changed, in ascending order.
+///
+/// The accounts of `ignored` are skipped, e.g. the beneficiary of the block that every
+/// transaction pays.
+pub fn skipped_writes(
+    state: &EvmState,
+    diffs: &[(TxHash, DiffMode)],
+    ignored: &[Address],
+) -> Vec<SkippedWrite> {
+    let mut writes = BTreeMap::<(Address, Option<B256>), Vec<TxHash>>::new();
+    for (tx_hash, diff) in diffs {
+        let accounts = diff.pre.keys().chain(diff.post.keys()).collect::<BTreeSet<_>>();
+        for address in accounts.into_iter().filter(|address| !ignored.contains(address)) {
+            let Some(account) = state.get(address) else { continue };
+            let (pre, post) = (diff.pre.get(address), diff.post.get(address));
+            // the post state only has the changed fields, and no entry for deleted accounts
+            let info_changed = post.map_or(true, |post| {
+                post.balance.is_some() || post.nonce.is_some() || post.code.is_some()
+            });
+            let mut record = |slot| {
+                let writers = writes.entry((*address, slot)).or_default();
+                if writers.last() != Some(tx_hash) {
+                    writers.push(*tx_hash);
+                }
+            };
+            if info_changed {
+                record(None);
+            }
+            for slot in pre.into_iter().chain(post).flat_map(|state| state.storage.keys()) {
+                if account.storage.contains_key(&U256::from_be_bytes(slot.0)) {
+                    record(Some(*slot));
+                }
+            }
+        }
+    }
+    writes
+        .into_iter()
+        .map(|((address, slot), writers)| SkippedWrite { address, slot, writers })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_rpc_types::trace::geth::AccountState;
+    use cast::revm::primitives::{Account, AccountInfo, EvmStorageSlot};
+
+    #[test]
+    fn finds_skipped_writes() {
+        let (token, user, coinbase) =
+            (Address::with_last_byte(1), Address::with_last_byte(2), Address::with_last_byte(3));
+        let (read_slot, other_slot) = (B256::with_last_byte(1), B256::with_last_byte(2));
+        let mut state = EvmState::default();
+        let mut account = Account::from(AccountInfo::default());
+        account.storage.insert(U256::from(1), EvmStorageSlot::new(U256::ZERO));
+        state.insert(token, account);
+        state.insert(user, AccountInfo::default().into());
+        state.insert(coinbase, AccountInfo::default().into());
+
+        let storage =
+            |slot| AccountState { storage: [(slot, B256::ZERO)].into(), ..Default::default() };
+        let balance = AccountState { balance: Some(U256::from(1)), ..Default::default() };
+        let first = DiffMode {
+            pre: [(token, storage(read_slot)), (coinbase, balance.clone())].into(),
+            post: [(token, AccountState::default()), (coinbase, balance.clone())].into(),
+        };
+        let second = DiffMode {
+            // an unread slot of a read account, and an unread account
+            pre: [(token, storage(other_slot)), (Address::with_last_byte(4), balance.clone())]
+                .into(),
+            post: [(token, storage(other_slot)), (user, balance)].into(),
+        };
+        let (first_hash, second_hash) = (B256::with_last_byte(0xa), B256::with_last_byte(0xb));
+        let diffs = [(first_hash, first), (second_hash, second)];
+
+        let writes = skipped_writes(&state, &diffs, &[coinbase]);
+        assert_eq!(
+            writes,
+            [
+                SkippedWrite { address: token, slot: Some(read_slot), writers: vec![first_hash] },
+                SkippedWrite { address: user, slot: None, writers: vec![second_hash] },
+            ]
+        );
+        assert_eq!(
+            writes[1].to_string(),
+            format!("{user} (balance, nonce or code) written by {second_hash:?}")
+        );
+    }
+}
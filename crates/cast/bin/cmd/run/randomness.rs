@@ -0,0 +1,60 @@
+//! Per-block randomness overrides.
+
+use alloy_primitives::{B256, U256};
+use cast::revm::primitives::Env;
+use eyre::{Result, WrapErr};
+use std::{collections::BTreeMap, path::Path};
+
+/// The prevrandao values of blocks, read from a JSON file mapping block numbers to values, e.g.
+/// `{"19000000": "0x…"}`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Randomness(BTreeMap<u64, B256>);
+
+impl Randomness {
+    pub fn load(path: &Path) -> Result<Self> {
+        let values: BTreeMap<String, B256> = foundry_common::fs::read_json_file(path)
+            .wrap_err_with(|| format!("failed to read randomness file {}", path.display()))?;
+        values
+            .into_iter()
+            .map(|(number, value)| {
+                let number = number.parse().wrap_err_with(|| {
+                    format!("invalid block number in randomness file: {number}")
+                })?;
+                Ok((number, value))
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    /// Sets the prevrandao, and the difficulty for blocks before the merge, of the block `number`
+    /// if the file has a value for it. Returns whether the environment was changed.
+    pub fn apply(&self, env: &mut Env, number: u64) -> bool {
+        let Some(value) = self.0.get(&number) else { return false };
+        env.block.prevrandao = Some(*value);
+        env.block.difficulty = U256::from_be_bytes(value.0);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_block_randomness() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("randomness.json");
+        let value = B256::with_last_byte(7);
+        std::fs::write(&path, format!(r#"{{"100": "{value}", "101": "{}"}}"#, B256::ZERO)).unwrap();
+        let randomness = Randomness::load(&path).unwrap();
+
+        let mut env = Env::default();
+        assert!(randomness.apply(&mut env, 100));
+        assert_eq!(env.block.prevrandao, Some(value));
+        assert_eq!(env.block.difficulty, U256::from(7));
+        assert!(!randomness.apply(&mut env, 102));
+
+        std::fs::write(&path, r#"{"latest": "0x00"}"#).unwrap();
+        assert!(Randomness::load(&path).is_err());
+    }
+}
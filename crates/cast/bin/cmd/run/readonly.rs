@@ -0,0 +1,91 @@
+//! Detection of state mutations in a transaction replayed as a static call.
+
+use alloy_primitives::Address;
+use cast::{
+    revm::interpreter::{InstructionResult, OpCode},
+    traces::CallTraceArena,
+};
+use std::fmt;
+
+/// The opcodes that are rejected in a static context.
+pub const MUTATING_OPCODES: [OpCode; 11] = [
+    OpCode::SSTORE,
+    OpCode::TSTORE,
+    OpCode::LOG0,
+    OpCode::LOG1,
+    OpCode::LOG2,
+    OpCode::LOG3,
+    OpCode::LOG4,
+    OpCode::CREATE,
+    OpCode::CREATE2,
+    OpCode::CALL,
+    OpCode::SELFDESTRUCT,
+];
+
+/// The first attempt of a transaction to mutate state in a static context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateMutation {
+    /// The address of the contract whose code attempted the mutation.
+    pub address: Address,
+    pub depth: usize,
+    /// The mutating opcode and its program counter, if the step was recorded.
+    pub step: Option<(OpCode, usize)>,
+}
+
+impl fmt::Display for StateMutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.step {
+            Some((op, pc)) => write!(f, "{} at pc {pc}", op.as_str())?,
+            None => f.write_str("state mutation")?,
+        }
+        write!(f, " in {} (depth {})", self.address, self.depth)
+    }
+}
+
+/// Returns the first call, in execution order, that was halted for mutating state in a static
+/// context.
+pub fn find_state_mutation(arena: &CallTraceArena) -> Option<StateMutation> {
+    let node = arena.nodes().iter().find(|node| {
+        matches!(
+            node.trace.status,
+            InstructionResult::StateChangeDuringStaticCall |
+                InstructionResult::CallNotAllowedInsideStatic
+        )
+    })?;
+    Some(StateMutation {
+        address: node.trace.address,
+        depth: node.trace.depth,
+        step: node.trace.steps.last().map(|step| (step.op, step.pc)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cast::revm::primitives::{AccountInfo, Bytecode, Env, EnvWithHandlerCfg, TxKind};
+    use foundry_evm::executors::TracingExecutor;
+
+    #[test]
+    fn rejects_sstore() {
+        let to = Address::with_last_byte(0xb);
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        // PUSH1 1 PUSH0 SSTORE STOP
+        let code = Bytecode::new_raw([0x60, 0x01, 0x5f, 0x55, 0x00].into());
+        let info =
+            AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() };
+        executor.backend_mut().insert_account_info(to, info);
+        executor.inspector_mut().set_read_only(true);
+        executor.inspector_mut().tracer.as_mut().unwrap().config_mut().record_steps = true;
+
+        let mut env = Env::default();
+        env.tx.transact_to = TxKind::Call(to);
+        env.tx.gas_limit = 100_000;
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+        let res = executor.transact_with_env(env).unwrap();
+        assert!(res.reverted);
+        assert_eq!(
+            find_state_mutation(res.traces.as_ref().unwrap()),
+            Some(StateMutation { address: to, depth: 0, step: Some((OpCode::SSTORE, 3)) })
+        );
+    }
+}
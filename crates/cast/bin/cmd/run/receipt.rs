@@ -0,0 +1,124 @@
+//! Comparison of the replay against the on-chain receipt of the transaction.
+
+use alloy_primitives::Address;
+use alloy_rpc_types::AnyTransactionReceipt;
+use cast::executors::RawCallResult;
+use serde::Serialize;
+use std::fmt;
+use yansi::Paint;
+
+/// The receipt fields of a transaction, either fetched from the chain or derived from the replay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptFields {
+    pub status: bool,
+    pub gas_used: u128,
+    pub effective_gas_price: u128,
+    pub logs: usize,
+    pub contract_address: Option<Address>,
+}
+
+impl ReceiptFields {
+    pub fn from_receipt(receipt: &AnyTransactionReceipt) -> Self {
+        Self {
+            status: receipt.inner.inner.status(),
+            gas_used: receipt.gas_used,
+            effective_gas_price: receipt.effective_gas_price,
+            logs: receipt.inner.inner.logs().len(),
+            contract_address: receipt.contract_address,
+        }
+    }
+
+    /// Derives the receipt fields from the replayed transaction, `created` is the address of the
+    /// contract deployed by a create transaction.
+    pub fn from_replay(raw: &RawCallResult, created: Option<Address>) -> Self {
+        Self {
+            status: !raw.reverted,
+            gas_used: raw.gas_used as u128,
+            effective_gas_price: raw.env.effective_gas_price().to(),
+            logs: raw.logs.len(),
+            contract_address: created,
+        }
+    }
+
+    fn rows(&self) -> [(&'static str, String); 5] {
+        let status = if self.status { "1 (success)" } else { "0 (failed)" };
+        let contract_address = self.contract_address.map(|a| a.to_string()).unwrap_or_default();
+        [
+            ("status", status.to_string()),
+            ("gasUsed", self.gas_used.to_string()),
+            ("effectiveGasPrice", self.effective_gas_price.to_string()),
+            ("logs", self.logs.to_string()),
+            ("contractAddress", contract_address),
+        ]
+    }
+}
+
+/// The on-chain receipt alongside the replay, field by field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ReceiptComparison {
+    pub receipt: ReceiptFields,
+    pub replay: ReceiptFields,
+}
+
+impl ReceiptComparison {
+    /// Returns the names of the fields where the replay and the receipt disagree.
+    pub fn mismatches(&self) -> Vec<&'static str> {
+        self.receipt
+            .rows()
+            .into_iter()
+            .zip(self.replay.rows())
+            .filter(|((_, receipt), (_, replay))| receipt != replay)
+            .map(|((name, _), _)| name)
+            .collect()
+    }
+
+    /// Returns the `--show-receipt` report of the JSON output, with the mismatching fields.
+    pub fn report(&self) -> serde_json::Result<serde_json::Value> {
+        let mut report = serde_json::to_value(self)?;
+        report["mismatches"] = self.mismatches().into();
+        Ok(report)
+    }
+}
+
+impl fmt::Display for ReceiptComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<20} {:<44} replay", "Receipt", "on-chain")?;
+        for ((name, receipt), (_, replay)) in
+            self.receipt.rows().into_iter().zip(self.replay.rows())
+        {
+            let row = format!("{name:<20} {receipt:<44} {replay}");
+            if receipt == replay {
+                write!(f, "\n{row}")?;
+            } else {
+                write!(f, "\n{} (mismatch)", row.red().bold())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_mismatches() {
+        let receipt = ReceiptFields {
+            status: true,
+            gas_used: 46_000,
+            effective_gas_price: 10_000_000_000,
+            logs: 2,
+            contract_address: None,
+        };
+        let comparison = ReceiptComparison { receipt, replay: receipt };
+        assert!(comparison.mismatches().is_empty());
+
+        let replay = ReceiptFields { status: false, gas_used: 30_000, logs: 0, ..receipt };
+        let comparison = ReceiptComparison { receipt, replay };
+        assert_eq!(comparison.mismatches(), vec!["status", "gasUsed", "logs"]);
+        let report = comparison.report().unwrap();
+        assert_eq!(report["replay"]["gasUsed"], 30_000);
+        assert_eq!(report["mismatches"], serde_json::json!(["status", "gasUsed", "logs"]));
+    }
+}
@@ -0,0 +1,108 @@
+//! Detection of external calls that were retried by the replayed transaction.
+
+use alloy_primitives::{Address, Bytes, U256};
+use cast::traces::{CallKind, CallTraceArena};
+use serde::Serialize;
+use std::fmt;
+
+/// The properties that make two calls identical.
+type CallKey<'a> = (CallKind, Address, U256, &'a Bytes);
+
+/// An external call that was made repeatedly with identical arguments by the same context, with
+/// differing outcomes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RetriedCall {
+    /// The address of the context that made the call.
+    pub caller: Address,
+    /// The address of the called contract.
+    pub address: Address,
+    /// The kind of the call.
+    pub kind: CallKind,
+    /// The calldata of the call.
+    pub data: Bytes,
+    /// The outcome of every attempt in execution order, `true` if it succeeded.
+    pub attempts: Vec<bool>,
+}
+
+impl fmt::Display for RetriedCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {} ", self.caller, self.address)?;
+        match self.data.get(..4) {
+            Some(selector) => write!(f, "{}", Bytes::copy_from_slice(selector))?,
+            None => write!(f, "{}", self.data)?,
+        }
+        write!(f, " [{}]: {} attempts: ", self.kind.to_str(), self.attempts.len())?;
+        for (i, success) in self.attempts.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(if *success { "succeeded" } else { "reverted" })?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the calls that were repeated identically by the same context where some attempts
+/// reverted and others succeeded, in execution order.
+///
+/// Calls are identical if they have the same kind, target, value and calldata.
+pub fn find_retries(arena: &CallTraceArena) -> Vec<RetriedCall> {
+    let nodes = arena.nodes();
+    let mut retries = vec![];
+    for node in nodes {
+        let mut calls: Vec<(CallKey<'_>, Vec<bool>)> = vec![];
+        for child in node.children.iter().map(|idx| &nodes[*idx].trace) {
+            let key = (child.kind, child.address, child.value, &child.data);
+            match calls.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, attempts)) => attempts.push(child.success),
+                None => calls.push((key, vec![child.success])),
+            }
+        }
+
+        retries.extend(
+            calls
+                .into_iter()
+                .filter(|(_, attempts)| {
+                    attempts.len() > 1 && attempts.iter().any(|s| *s) && attempts.iter().any(|s| !s)
+                })
+                .map(|((kind, address, _, data), attempts)| RetriedCall {
+                    caller: node.trace.address,
+                    address,
+                    kind,
+                    data: data.clone(),
+                    attempts,
+                }),
+        );
+    }
+    retries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+
+    #[test]
+    fn finds_retried_calls() {
+        let mut arena = arena(&[(None, 0), (Some(0), 0), (Some(0), 0), (Some(0), 0), (Some(0), 0)]);
+        let target = Address::with_last_byte(1);
+        for (idx, success) in [(1, false), (2, false), (3, true), (4, true)] {
+            let trace = &mut arena.nodes_mut()[idx].trace;
+            trace.address = target;
+            trace.data = Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef]);
+            trace.success = success;
+        }
+        // a different call that is not retried
+        arena.nodes_mut()[4].trace.data = Bytes::from_static(&[0x12, 0x34, 0x56, 0x78]);
+
+        let retries = find_retries(&arena);
+        assert_eq!(retries.len(), 1);
+        assert_eq!(retries[0].attempts, vec![false, false, true]);
+        assert_eq!(
+            retries[0].to_string(),
+            "0x0000000000000000000000000000000000000000 -> \
+             0x0000000000000000000000000000000000000001 0xdeadbeef [CALL]: 3 attempts: reverted, \
+             reverted, succeeded"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Decoding of the custom errors of reverted calls with the signatures of OpenChain.
+
+use super::filter::{self, TracePath};
+use alloy_dyn_abi::JsonAbiExt;
+use alloy_json_abi::Error;
+use alloy_primitives::{hex, Address, Selector};
+use alloy_sol_types::{Panic, Revert, SolError};
+use cast::{
+    decode::RevertDecoder,
+    traces::{identifier::SingleSignaturesIdentifier, CallKind, CallTraceArena},
+};
+use foundry_common::fmt::format_token;
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+/// A decoded custom error, as included in the JSON output.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DecodedError {
+    pub selector: Selector,
+    pub signature: String,
+    pub args: Vec<String>,
+}
+
+/// Returns the selectors of the custom errors the calls of the trace reverted with, i.e. the
+/// revert data that isn't an `Error(string)` or `Panic(uint256)`.
+fn custom_error_selectors(arena: &CallTraceArena) -> BTreeSet<Selector> {
+    arena
+        .nodes()
+        .iter()
+        .filter(|node| !node.trace.success && node.trace.output.len() >= 4)
+        .map(|node| Selector::from_slice(&node.trace.output[..4]))
+        .filter(|selector| *selector != Revert::SELECTOR && *selector != Panic::SELECTOR)
+        .collect()
+}
+
+/// Identifies the custom errors the calls of the trace reverted with by their selector.
+///
+/// The errors are looked up with OpenChain, where they're indexed as functions, unless offline.
+/// The lookups are cached by the identifier for the whole run and on disk.
+pub async fn identify_custom_errors(
+    identifier: &SingleSignaturesIdentifier,
+    arena: &CallTraceArena,
+) -> Vec<Error> {
+    let selectors = custom_error_selectors(arena);
+    if selectors.is_empty() {
+        return vec![];
+    }
+    let functions = identifier.write().await.identify_functions(&selectors).await;
+    functions
+        .into_iter()
+        .flatten()
+        .map(|function| Error { name: function.name, inputs: function.inputs })
+        .collect()
+}
+
+/// Decodes the revert data `output` with one of the identified custom `errors`.
+pub fn decode_custom_error(errors: &[Error], output: &[u8]) -> Option<DecodedError> {
+    let selector = output.get(..4).map(Selector::from_slice)?;
+    errors.iter().filter(|error| error.selector() == selector).find_map(|error| {
+        let args = error.abi_decode_input(&output[4..], false).ok()?;
+        Some(DecodedError {
+            selector,
+            signature: error.signature(),
+            args: args.iter().map(format_token).collect(),
+        })
+    })
+}
+
+/// Returns the revert data of the transaction if its call reverted.
+pub fn revert_data(arena: &CallTraceArena) -> Option<&[u8]> {
+    let root = &arena.nodes().first()?.trace;
+    (!root.success).then_some(&root.output[..])
+}
+
+/// The call a revert originated from, as printed by `--revert-only`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RevertOrigin {
+    pub path: TracePath,
+    pub kind: CallKind,
+    pub address: Address,
+    /// The selector of the called function, if the calldata has one.
+    pub selector: Option<Selector>,
+    /// The decoded reason of the revert, or its raw data.
+    pub reason: String,
+    label: Option<String>,
+}
+
+impl RevertOrigin {
+    /// Returns the first revert of the trace, see [`filter::first_revert`], with its reason
+    /// decoded as an `Error(string)`, a `Panic(uint256)` or one of the identified custom `errors`.
+    pub fn find(
+        arena: &CallTraceArena,
+        errors: &[Error],
+        labels: &HashMap<Address, String>,
+    ) -> Option<Self> {
+        let idx = filter::first_revert(arena)?;
+        let trace = &arena.nodes()[idx].trace;
+        let mut decoder = RevertDecoder::new();
+        for error in errors {
+            decoder.push_error(error.clone());
+        }
+        Some(Self {
+            path: TracePath::of(arena, idx),
+            kind: trace.kind,
+            address: trace.address,
+            selector: (!trace.kind.is_any_create())
+                .then(|| trace.data.get(..4).map(Selector::from_slice))
+                .flatten(),
+            reason: decoder.decode(&trace.output, Some(trace.status)),
+            label: labels.get(&trace.address).cloned(),
+        })
+    }
+}
+
+impl fmt::Display for RevertOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Reverted in call {} [{}] to ", self.path, self.kind.to_str())?;
+        match &self.label {
+            Some(label) => write!(f, "{label}: [{}]", self.address)?,
+            None => write!(f, "{}", self.address)?,
+        }
+        if let Some(selector) = self.selector {
+            write!(f, " {}", hex::encode_prefixed(selector))?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+impl std::fmt::Display for DecodedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.signature, hex::encode_prefixed(self.selector))?;
+        if !self.args.is_empty() {
+            write!(f, ": {}", self.args.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Address, U256};
+    use alloy_sol_types::SolValue;
+
+    #[test]
+    fn decodes_custom_errors() {
+        let error = Error::parse("InsufficientBalance(address,uint256)").unwrap();
+        let args = (Address::with_last_byte(1), U256::from(5)).abi_encode_params();
+        let output = [error.selector().as_slice(), &args].concat();
+
+        let decoded = decode_custom_error(std::slice::from_ref(&error), &output).unwrap();
+        assert_eq!(decoded.signature, "InsufficientBalance(address,uint256)");
+        assert_eq!(decoded.args, vec![Address::with_last_byte(1).to_string(), "5".to_string()]);
+        assert_eq!(
+            decoded.to_string(),
+            format!(
+                "InsufficientBalance(address,uint256) ({}): {}, 5",
+                hex::encode_prefixed(error.selector()),
+                Address::with_last_byte(1)
+            )
+        );
+
+        // other selectors and malformed data aren't decoded
+        assert_eq!(decode_custom_error(std::slice::from_ref(&error), &Revert::SELECTOR), None);
+        assert_eq!(decode_custom_error(&[error], &output[..20]), None);
+    }
+
+    #[test]
+    fn finds_revert_origin() {
+        use crate::cmd::run::filter::tests::arena;
+        use cast::revm::interpreter::InstructionResult;
+
+        let mut traces = arena(&[(None, 100_000), (Some(0), 60_000), (Some(1), 30_000)]);
+        for node in traces.nodes_mut() {
+            node.trace.success = true;
+        }
+        assert_eq!(RevertOrigin::find(&traces, &[], &HashMap::new()), None);
+
+        let token = Address::with_last_byte(1);
+        let error = Error::parse("InsufficientBalance(uint256)").unwrap();
+        for idx in [0, 1, 2] {
+            let trace = &mut traces.nodes_mut()[idx].trace;
+            trace.success = false;
+            trace.status = InstructionResult::Revert;
+        }
+        let trace = &mut traces.nodes_mut()[2].trace;
+        trace.address = token;
+        trace.data = [&[0xa9, 0x05, 0x9c, 0xbb][..], &[0; 64]].concat().into();
+        trace.output = [error.selector().as_slice(), &U256::from(5).abi_encode()].concat().into();
+
+        let labels = HashMap::from([(token, "Token".to_string())]);
+        let origin = RevertOrigin::find(&traces, &[error], &labels).unwrap();
+        assert_eq!(origin.path.to_string(), "0.0.0");
+        assert_eq!(
+            origin.to_string(),
+            format!(
+                "Reverted in call 0.0.0 [CALL] to Token: [{token}] 0xa9059cbb: \
+                 InsufficientBalance(5)"
+            )
+        );
+
+        // unknown errors are printed raw
+        traces.nodes_mut()[2].trace.output = [0xff; 8].into();
+        let origin = RevertOrigin::find(&traces, &[], &HashMap::new()).unwrap();
+        assert_eq!(origin.reason, "custom error ffffffff:ffffffff");
+    }
+}
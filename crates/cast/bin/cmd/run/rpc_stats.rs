@@ -0,0 +1,133 @@
+//! The RPC requests of a replay, broken down by phase.
+
+use comfy_table::{presets::ASCII_MARKDOWN, Table};
+use foundry_common::provider::stats::MethodStats;
+use std::{collections::BTreeMap, time::Duration};
+
+/// The phase of the replay an RPC method is used by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    TxFetch,
+    BlockFetch,
+    /// The reads of the accounts and storage slots by the fork backend, during the execution of
+    /// the transactions.
+    ForkReads,
+    Other,
+}
+
+impl Phase {
+    pub fn of(method: &str) -> Self {
+        match method {
+            "eth_getTransactionByHash" | "eth_getTransactionReceipt" => Self::TxFetch,
+            "eth_getBlockByNumber" | "eth_getBlockByHash" => Self::BlockFetch,
+            "eth_getBalance" |
+            "eth_getTransactionCount" |
+            "eth_getCode" |
+            "eth_getStorageAt" |
+            "eth_getProof" => Self::ForkReads,
+            _ => Self::Other,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::TxFetch => "Transaction fetch",
+            Self::BlockFetch => "Block fetch",
+            Self::ForkReads => "Fork backend reads",
+            Self::Other => "Other",
+        }
+    }
+}
+
+/// Returns the requests sent since `before`, by phase.
+pub fn since(
+    before: &BTreeMap<String, MethodStats>,
+    after: &BTreeMap<String, MethodStats>,
+) -> BTreeMap<Phase, MethodStats> {
+    let mut phases = BTreeMap::<Phase, MethodStats>::new();
+    for (method, stats) in after {
+        let previous = before.get(method).copied().unwrap_or_default();
+        let phase = phases.entry(Phase::of(method)).or_default();
+        phase.requests += stats.requests - previous.requests;
+        phase.time += stats.time.saturating_sub(previous.time);
+    }
+    phases
+}
+
+pub fn table(phases: &BTreeMap<Phase, MethodStats>) -> Table {
+    let mut table = Table::new();
+    table.load_preset(ASCII_MARKDOWN);
+    table.set_header(["RPC requests", "Count", "Time"]);
+    let mut total = MethodStats::default();
+    for (phase, stats) in phases {
+        table.add_row([phase.name().to_string(), stats.requests.to_string(), secs(stats.time)]);
+        total.requests += stats.requests;
+        total.time += stats.time;
+    }
+    table.add_row(["Total".to_string(), total.requests.to_string(), secs(total.time)]);
+    table
+}
+
+/// Returns the requests answered by each endpoint since `before`.
+pub fn served_since(
+    before: &BTreeMap<String, u64>,
+    after: &BTreeMap<String, u64>,
+) -> BTreeMap<String, u64> {
+    after
+        .iter()
+        .map(|(endpoint, served)| {
+            (endpoint.clone(), served - before.get(endpoint).copied().unwrap_or_default())
+        })
+        .filter(|(_, served)| *served > 0)
+        .collect()
+}
+
+pub fn endpoint_table(served: &BTreeMap<String, u64>) -> Table {
+    let mut table = Table::new();
+    table.load_preset(ASCII_MARKDOWN);
+    table.set_header(["RPC endpoint", "Answered"]);
+    for (endpoint, served) in served {
+        table.add_row([endpoint.clone(), served.to_string()]);
+    }
+    table
+}
+
+fn secs(time: Duration) -> String {
+    format!("{:.3}s", time.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaks_requests_down_by_phase() {
+        let stats =
+            |requests, millis| MethodStats { requests, time: Duration::from_millis(millis) };
+        let before = BTreeMap::from([("eth_chainId".to_string(), stats(1, 10))]);
+        let after = BTreeMap::from([
+            ("eth_chainId".to_string(), stats(2, 30)),
+            ("eth_getTransactionByHash".to_string(), stats(1, 100)),
+            ("eth_getBlockByNumber".to_string(), stats(2, 300)),
+            ("eth_getStorageAt".to_string(), stats(40, 2_000)),
+            ("eth_getCode".to_string(), stats(10, 500)),
+        ]);
+        let phases = since(&before, &after);
+        assert_eq!(phases[&Phase::ForkReads], stats(50, 2_500));
+        assert_eq!(phases[&Phase::Other], stats(1, 20));
+
+        let table = table(&phases).to_string();
+        assert!(table.contains("| Fork backend reads | 50    | 2.500s |"), "{table}");
+        assert!(table.contains("| Total              | 54    | 2.920s |"), "{table}");
+
+        let before = BTreeMap::from([("https://a.example".to_string(), 3)]);
+        let after = BTreeMap::from([
+            ("https://a.example".to_string(), 3),
+            ("https://b.example".to_string(), 7),
+        ]);
+        let served = served_since(&before, &after);
+        assert_eq!(served, BTreeMap::from([("https://b.example".to_string(), 7)]));
+        let table = endpoint_table(&served).to_string();
+        assert!(table.contains("| https://b.example | 7        |"), "{table}");
+    }
+}
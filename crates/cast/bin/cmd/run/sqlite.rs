@@ -0,0 +1,248 @@
+//! Export of replay results to a SQLite database.
+
+use alloy_primitives::{Address, TxHash, U256};
+use alloy_rpc_types::Transaction;
+use cast::{
+    executors::RawCallResult,
+    traces::{CallKind, CallTraceArena},
+};
+use eyre::Result;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::{collections::BTreeMap, path::Path};
+
+/// The schema of the database, created if absent.
+///
+/// Amounts of wei are stored as decimal strings since they don't fit into SQLite integers. Every
+/// replayed transaction has one row in `replays`, replaying a transaction again replaces its rows.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS replays (
+    tx_hash TEXT PRIMARY KEY NOT NULL,
+    block_number INTEGER,
+    sender TEXT NOT NULL,
+    recipient TEXT,
+    value TEXT NOT NULL,
+    success INTEGER NOT NULL,
+    gas_used INTEGER NOT NULL,
+    contracts_touched INTEGER NOT NULL,
+    slots_created INTEGER NOT NULL,
+    slots_cleared INTEGER NOT NULL,
+    accounts_created INTEGER NOT NULL,
+    code_bytes_added INTEGER NOT NULL,
+    replayed_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS touched_contracts (
+    tx_hash TEXT NOT NULL REFERENCES replays (tx_hash),
+    address TEXT NOT NULL,
+    calls INTEGER NOT NULL,
+    PRIMARY KEY (tx_hash, address)
+);
+CREATE TABLE IF NOT EXISTS value_transfers (
+    tx_hash TEXT NOT NULL REFERENCES replays (tx_hash),
+    position INTEGER NOT NULL,
+    sender TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (tx_hash, position)
+);
+";
+
+/// The net growth of the state caused by a transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateGrowth {
+    /// The storage slots that were set from zero to a non-zero value.
+    pub slots_created: u64,
+    /// The storage slots that were set from a non-zero value to zero.
+    pub slots_cleared: u64,
+    pub accounts_created: u64,
+    /// The size of the code of the created accounts.
+    pub code_bytes_added: u64,
+}
+
+impl StateGrowth {
+    pub fn from_raw(raw: &RawCallResult) -> Self {
+        let mut growth = Self::default();
+        for account in raw.state_changeset.values() {
+            for slot in account.storage.values() {
+                if slot.original_value.is_zero() && !slot.present_value.is_zero() {
+                    growth.slots_created += 1;
+                } else if !slot.original_value.is_zero() && slot.present_value.is_zero() {
+                    growth.slots_cleared += 1;
+                }
+            }
+            if account.is_created() {
+                growth.accounts_created += 1;
+                growth.code_bytes_added +=
+                    account.info.code.as_ref().map_or(0, |code| code.len()) as u64;
+            }
+        }
+        growth
+    }
+}
+
+/// A transfer of native value between two accounts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ValueFlow {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+}
+
+/// The structured results of a replayed transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayRecord {
+    pub tx_hash: TxHash,
+    pub block_number: Option<u64>,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub success: bool,
+    pub gas_used: u64,
+    /// The called or created contracts with the number of calls to them.
+    pub touched: BTreeMap<Address, u64>,
+    /// The value transfers in execution order.
+    pub transfers: Vec<ValueFlow>,
+    pub growth: StateGrowth,
+}
+
+impl ReplayRecord {
+    pub fn new(tx: &Transaction, block_number: Option<u64>, raw: &RawCallResult) -> Self {
+        let (touched, transfers) = raw.traces.as_ref().map(value_flow).unwrap_or_default();
+        Self {
+            tx_hash: tx.hash,
+            block_number,
+            from: tx.from,
+            to: tx.to,
+            value: tx.value,
+            success: !raw.reverted,
+            gas_used: raw.gas_used,
+            touched,
+            transfers,
+            growth: StateGrowth::from_raw(raw),
+        }
+    }
+}
+
+/// Returns the contracts touched by the calls of the arena, and the value they transferred.
+fn value_flow(arena: &CallTraceArena) -> (BTreeMap<Address, u64>, Vec<ValueFlow>) {
+    let mut touched = BTreeMap::new();
+    let mut transfers = vec![];
+    for trace in arena.nodes().iter().map(|node| &node.trace) {
+        *touched.entry(trace.address).or_default() += 1;
+        // the value of delegate calls is only apparent, and static calls can't transfer value
+        let transfers_value = !matches!(trace.kind, CallKind::DelegateCall | CallKind::StaticCall);
+        if transfers_value && trace.success && !trace.value.is_zero() {
+            transfers.push(ValueFlow { from: trace.caller, to: trace.address, value: trace.value });
+        }
+    }
+    (touched, transfers)
+}
+
+/// Inserts the record into the database at `path`, creating the database and its tables if
+/// absent.
+pub fn write_record(path: &Path, record: &ReplayRecord) -> Result<()> {
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx_hash = record.tx_hash.to_string();
+    let db = conn.transaction()?;
+    db.execute("DELETE FROM value_transfers WHERE tx_hash = ?1", [&tx_hash])?;
+    db.execute("DELETE FROM touched_contracts WHERE tx_hash = ?1", [&tx_hash])?;
+    db.execute(
+        "INSERT OR REPLACE INTO replays VALUES \
+         (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, strftime('%s', 'now'))",
+        params![
+            tx_hash,
+            record.block_number,
+            record.from.to_string(),
+            record.to.map(|to| to.to_string()),
+            record.value.to_string(),
+            record.success,
+            record.gas_used,
+            record.touched.len(),
+            record.growth.slots_created,
+            record.growth.slots_cleared,
+            record.growth.accounts_created,
+            record.growth.code_bytes_added,
+        ],
+    )?;
+    for (address, calls) in &record.touched {
+        db.execute(
+            "INSERT INTO touched_contracts VALUES (?1, ?2, ?3)",
+            params![tx_hash, address.to_string(), calls],
+        )?;
+    }
+    for (position, transfer) in record.transfers.iter().enumerate() {
+        db.execute(
+            "INSERT INTO value_transfers VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                tx_hash,
+                position,
+                transfer.from.to_string(),
+                transfer.to.to_string(),
+                transfer.value.to_string()
+            ],
+        )?;
+    }
+    db.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+
+    #[test]
+    fn writes_one_row_per_tx() {
+        let mut arena = arena(&[(None, 100_000), (Some(0), 60_000), (Some(0), 5_000)]);
+        for (idx, node) in arena.nodes_mut().iter_mut().enumerate() {
+            node.trace.success = true;
+            node.trace.address = Address::with_last_byte(idx.min(1) as u8 + 1);
+        }
+        arena.nodes_mut()[1].trace.value = U256::from(7);
+        let (touched, transfers) = value_flow(&arena);
+        assert_eq!(touched.values().copied().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            transfers,
+            vec![ValueFlow {
+                from: Address::ZERO,
+                to: Address::with_last_byte(2),
+                value: U256::from(7)
+            }]
+        );
+
+        let record = ReplayRecord {
+            tx_hash: TxHash::with_last_byte(1),
+            block_number: Some(100),
+            from: Address::ZERO,
+            to: Some(Address::with_last_byte(1)),
+            value: U256::ZERO,
+            success: true,
+            gas_used: 46_000,
+            touched,
+            transfers,
+            growth: StateGrowth { slots_created: 1, ..Default::default() },
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("replays.db");
+        write_record(&path, &record).unwrap();
+        write_record(&path, &record).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let count = |table: &str| -> u64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(count("replays"), 1);
+        assert_eq!(count("touched_contracts"), 2);
+        assert_eq!(count("value_transfers"), 1);
+        let (gas_used, slots_created): (u64, u64) = conn
+            .query_row("SELECT gas_used, slots_created FROM replays", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((gas_used, slots_created), (46_000, 1));
+    }
+}
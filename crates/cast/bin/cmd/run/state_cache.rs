@@ -0,0 +1,236 @@
+//! On-disk cache of the state after the preceding transactions of a block, for repeated replays of
+//! transactions of the same block.
+
+use alloy_genesis::GenesisAccount;
+use alloy_primitives::{hex, keccak256, Address, TxHash, B256, U256};
+use cast::{
+    backend::Backend,
+    replay::SkippedTx,
+    revm::{
+        db::{AccountState, CacheDB, DbAccount},
+        primitives::{AccountInfo, Bytecode, KECCAK_EMPTY},
+    },
+};
+use eyre::Result;
+use foundry_config::Config;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+/// What the state after the preceding transactions depends on.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotKey {
+    pub chain_id: u64,
+    /// The forked block the preceding transactions are executed on.
+    pub fork_block: u64,
+    /// The block of the preceding transactions.
+    pub block: u64,
+    /// The number of executed preceding transactions, from the start of the block.
+    pub preceding: usize,
+    /// The hash of the other settings the preceding transactions are executed with, such as the
+    /// tweaks and the state overrides, see [`settings_hash`].
+    pub settings: B256,
+}
+
+/// Hashes the settings the preceding transactions are executed with, by their debug
+/// representation.
+pub fn settings_hash(settings: impl fmt::Debug) -> B256 {
+    keccak256(format!("{settings:?}"))
+}
+
+/// The state after the preceding transactions, with the transactions that were skipped and their
+/// `console.log` messages.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub key: SnapshotKey,
+    pub skipped: Vec<SkippedTx>,
+    /// The accounts loaded by the fork, with their loaded storage slots, as dumped by
+    /// [`crate::cmd::run::dump::replayed_state`].
+    pub accounts: BTreeMap<Address, GenesisAccount>,
+    /// The accounts whose storage isn't the one of the forked block, e.g. the destroyed ones, see
+    /// [`crate::cmd::run::dump::cleared_accounts`]. The snapshots cached without them aren't read.
+    pub cleared: Vec<Address>,
+    /// The decoded `console.log` messages of the transactions that logged any, in block order.
+    pub console_logs: Vec<(TxHash, Vec<String>)>,
+}
+
+impl Snapshot {
+    /// Inserts the accounts of the snapshot into the backend, the state that isn't in the snapshot
+    /// is still fetched from the forked block, except for the cleared accounts.
+    pub fn apply(&self, backend: &mut Backend) -> Result<()> {
+        if let Some(db) = backend.active_fork_db_mut() {
+            self.clear_accounts(db);
+        }
+        for (address, account) in &self.accounts {
+            let code = account.code.clone().unwrap_or_default();
+            let code_hash = if code.is_empty() { KECCAK_EMPTY } else { keccak256(&code) };
+            let info = AccountInfo {
+                balance: account.balance,
+                nonce: account.nonce.unwrap_or_default(),
+                code_hash,
+                code: Some(Bytecode::new_raw(code)),
+            };
+            backend.insert_account_info(*address, info);
+            for (slot, value) in account.storage.iter().flatten() {
+                backend.insert_account_storage(
+                    *address,
+                    (*slot).into(),
+                    U256::from_be_bytes(value.0),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the cleared accounts as not existing, or as recreated if the snapshot holds their
+    /// state, so that neither their account nor their storage is fetched from the forked block.
+    fn clear_accounts<ExtDB>(&self, db: &mut CacheDB<ExtDB>) {
+        for address in &self.cleared {
+            let account = if self.accounts.contains_key(address) {
+                DbAccount { account_state: AccountState::StorageCleared, ..Default::default() }
+            } else {
+                DbAccount::new_not_existing()
+            };
+            db.accounts.insert(*address, account);
+        }
+    }
+}
+
+/// The states after the preceding transactions of the blocks of a chain, cached on disk.
+#[derive(Clone, Debug)]
+pub struct StateCache {
+    /// The directory of the blocks, with a directory per block number.
+    dir: PathBuf,
+}
+
+impl StateCache {
+    /// Returns the cache of the states of the chain in the Foundry cache directory, next to the
+    /// cached blocks: `~/.foundry/cache/rpc/<chain>/<block>/states/`.
+    pub fn new(chain_id: u64) -> Option<Self> {
+        Some(Self { dir: Config::foundry_chain_cache_dir(chain_id)? })
+    }
+
+    /// The snapshots of another chain, forked block, number of preceding transactions or settings
+    /// are stored in other files, so they're never read for the key.
+    fn path(&self, key: &SnapshotKey) -> PathBuf {
+        let settings = hex::encode(&key.settings[..8]);
+        self.dir
+            .join(key.block.to_string())
+            .join("states")
+            .join(format!("{}-{}-{settings}.json", key.fork_block, key.preceding))
+    }
+
+    /// Returns the cached snapshot of `key`, if it was cached and can be read.
+    pub fn get(&self, key: &SnapshotKey) -> Option<Snapshot> {
+        let path = self.path(key);
+        if !path.exists() {
+            return None;
+        }
+        match foundry_common::fs::read_json_file::<Snapshot>(&path) {
+            Ok(snapshot) if snapshot.key == *key => Some(snapshot),
+            Ok(_) => {
+                trace!(path = %path.display(), "ignoring cached state of other settings");
+                None
+            }
+            Err(err) => {
+                trace!(path = %path.display(), %err, "ignoring unreadable cached state");
+                None
+            }
+        }
+    }
+
+    /// Caches the snapshot, replacing any snapshot of the same key.
+    pub fn insert(&self, snapshot: &Snapshot) -> Result<()> {
+        let path = self.path(&snapshot.key);
+        if let Some(parent) = path.parent() {
+            foundry_common::fs::create_dir_all(parent)?;
+        }
+        foundry_common::fs::write_json_file(&path, snapshot)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Bytes, TxHash};
+    use cast::revm::{db::EmptyDB, Database, DatabaseRef};
+
+    #[test]
+    fn caches_snapshots_by_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = StateCache { dir: dir.path().to_path_buf() };
+        let key = SnapshotKey {
+            chain_id: 1,
+            fork_block: 99,
+            block: 100,
+            preceding: 3,
+            settings: settings_hash(("tweaks", 1)),
+        };
+        let account = GenesisAccount {
+            nonce: Some(2),
+            balance: U256::from(10),
+            code: Some(Bytes::from_static(&[0x00])),
+            storage: Some(BTreeMap::from([(B256::with_last_byte(1), B256::with_last_byte(7))])),
+            private_key: None,
+        };
+        let snapshot = Snapshot {
+            key: key.clone(),
+            skipped: vec![SkippedTx { hash: TxHash::with_last_byte(1), error: "reverted".into() }],
+            accounts: BTreeMap::from([(Address::with_last_byte(1), account)]),
+            cleared: vec![],
+            console_logs: vec![(TxHash::with_last_byte(2), vec!["hello".to_string()])],
+        };
+        assert_eq!(cache.get(&key), None);
+        cache.insert(&snapshot).unwrap();
+        assert_eq!(cache.get(&key), Some(snapshot.clone()));
+        for other in [
+            SnapshotKey { fork_block: 98, ..key.clone() },
+            SnapshotKey { preceding: 4, ..key.clone() },
+            SnapshotKey { settings: settings_hash(("tweaks", 2)), ..key },
+        ] {
+            assert_eq!(cache.get(&other), None);
+        }
+
+        let mut backend = Backend::spawn(None);
+        snapshot.apply(&mut backend).unwrap();
+        let info = backend.basic_ref(Address::with_last_byte(1)).unwrap().unwrap();
+        assert_eq!((info.nonce, info.balance), (2, U256::from(10)));
+        let value = backend.storage_ref(Address::with_last_byte(1), U256::from(1)).unwrap();
+        assert_eq!(value, U256::from(7));
+    }
+
+    #[test]
+    fn clears_destroyed_accounts() {
+        // the forked state, where both accounts hold a slot
+        let mut fork = CacheDB::new(EmptyDB::default());
+        let (destroyed, recreated) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        for address in [destroyed, recreated] {
+            fork.insert_account_info(address, AccountInfo { nonce: 1, ..Default::default() });
+            fork.insert_account_storage(address, U256::from(1), U256::from(7)).unwrap();
+        }
+        let account = GenesisAccount { nonce: Some(1), ..Default::default() };
+        let snapshot = Snapshot {
+            key: SnapshotKey {
+                chain_id: 1,
+                fork_block: 99,
+                block: 100,
+                preceding: 3,
+                settings: B256::ZERO,
+            },
+            skipped: vec![],
+            accounts: BTreeMap::from([(recreated, account)]),
+            cleared: vec![destroyed, recreated],
+            console_logs: vec![],
+        };
+
+        let mut db = CacheDB::new(fork);
+        snapshot.clear_accounts(&mut db);
+        db.insert_account_info(recreated, AccountInfo { nonce: 1, ..Default::default() });
+        assert_eq!(db.basic(destroyed).unwrap(), None);
+        assert_eq!(db.storage(destroyed, U256::from(1)).unwrap(), U256::ZERO);
+        assert_eq!(db.basic(recreated).unwrap().map(|info| info.nonce), Some(1));
+        assert_eq!(db.storage(recreated, U256::from(1)).unwrap(), U256::ZERO);
+    }
+}
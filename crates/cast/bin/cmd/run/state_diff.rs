@@ -0,0 +1,167 @@
+//! State changes of a replayed transaction.
+
+use alloy_primitives::{Address, U256};
+use cast::revm::{primitives::EvmState, DatabaseRef};
+use eyre::Result;
+use serde::Serialize;
+use std::{collections::HashMap, fmt};
+
+/// A value before and after the transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Change<T> {
+    pub before: T,
+    pub after: T,
+}
+
+/// A changed storage slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct SlotChange {
+    pub slot: U256,
+    #[serde(flatten)]
+    pub change: Change<U256>,
+}
+
+/// The changes of the state of an account.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    pub address: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Change<U256>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<Change<u64>>,
+    /// The changed slots, in ascending order.
+    pub storage: Vec<SlotChange>,
+}
+
+/// Returns the changes of the accounts touched by the transaction, in ascending order of address,
+/// comparing the `changeset` of the transaction with the state `before` it.
+///
+/// Accounts are named after their label in `labels`.
+pub fn state_diff<DB: DatabaseRef>(
+    before: &DB,
+    changeset: &EvmState,
+    labels: &HashMap<Address, String>,
+) -> Result<Vec<AccountDiff>>
+where
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut diffs = vec![];
+    for (address, account) in changeset {
+        let info = before.basic_ref(*address)?.unwrap_or_default();
+        let mut storage = account
+            .storage
+            .iter()
+            .filter(|(_, slot)| slot.is_changed())
+            .map(|(slot, value)| SlotChange {
+                slot: *slot,
+                change: Change { before: value.original_value, after: value.present_value },
+            })
+            .collect::<Vec<_>>();
+        storage.sort_by_key(|slot| slot.slot);
+        let diff = AccountDiff {
+            address: *address,
+            label: labels.get(address).cloned(),
+            balance: change(info.balance, account.info.balance),
+            nonce: change(info.nonce, account.info.nonce),
+            storage,
+        };
+        if diff.balance.is_some() || diff.nonce.is_some() || !diff.storage.is_empty() {
+            diffs.push(diff);
+        }
+    }
+    diffs.sort_by_key(|diff| diff.address);
+    Ok(diffs)
+}
+
+fn change<T: PartialEq>(before: T, after: T) -> Option<Change<T>> {
+    (before != after).then_some(Change { before, after })
+}
+
+impl fmt::Display for AccountDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.address)?;
+        if let Some(label) = &self.label {
+            write!(f, " ({label})")?;
+        }
+        writeln!(f, ":")?;
+        if let Some(Change { before, after }) = self.balance {
+            let delta = if after >= before {
+                format!("+{}", after - before)
+            } else {
+                format!("-{}", before - after)
+            };
+            writeln!(f, "    balance: {before} -> {after} ({delta} wei)")?;
+        }
+        if let Some(Change { before, after }) = self.nonce {
+            writeln!(f, "    nonce: {before} -> {after}")?;
+        }
+        for SlotChange { slot, change } in &self.storage {
+            writeln!(f, "    {slot:#x}: {:#x} -> {:#x}", change.before, change.after)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cast::revm::{
+        db::{CacheDB, EmptyDB},
+        primitives::{Account, AccountInfo, EvmStorageSlot},
+    };
+
+    #[test]
+    fn diffs_changed_accounts() {
+        let (sender, contract, untouched) =
+            (Address::with_last_byte(1), Address::with_last_byte(2), Address::with_last_byte(3));
+        let mut before = CacheDB::new(EmptyDB::default());
+        before.insert_account_info(
+            sender,
+            AccountInfo { balance: U256::from(10), nonce: 1, ..Default::default() },
+        );
+
+        let mut changeset = EvmState::default();
+        let account =
+            Account::from(AccountInfo { balance: U256::from(7), nonce: 2, ..Default::default() });
+        changeset.insert(sender, account);
+        let mut account = Account::from(AccountInfo::default());
+        account
+            .storage
+            .insert(U256::from(1), EvmStorageSlot::new_changed(U256::ZERO, U256::from(5)));
+        account.storage.insert(U256::from(2), EvmStorageSlot::new(U256::from(3)));
+        changeset.insert(contract, account);
+        changeset.insert(untouched, Account::from(AccountInfo::default()));
+
+        let labels = HashMap::from([(contract, "Vault".to_string())]);
+        let diffs = state_diff(&before, &changeset, &labels).unwrap();
+        assert_eq!(
+            diffs,
+            vec![
+                AccountDiff {
+                    address: sender,
+                    label: None,
+                    balance: Some(Change { before: U256::from(10), after: U256::from(7) }),
+                    nonce: Some(Change { before: 1, after: 2 }),
+                    storage: vec![],
+                },
+                AccountDiff {
+                    address: contract,
+                    label: Some("Vault".to_string()),
+                    balance: None,
+                    nonce: None,
+                    storage: vec![SlotChange {
+                        slot: U256::from(1),
+                        change: Change { before: U256::ZERO, after: U256::from(5) },
+                    }],
+                },
+            ]
+        );
+        assert_eq!(
+            diffs[0].to_string(),
+            format!("{sender}:\n    balance: 10 -> 7 (-3 wei)\n    nonce: 1 -> 2\n")
+        );
+    }
+}
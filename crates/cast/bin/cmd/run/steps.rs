@@ -0,0 +1,73 @@
+//! The opcode-level execution trace of a replayed transaction, as JSON lines.
+
+use alloy_primitives::Bytes;
+use alloy_rpc_types::trace::geth::GethDefaultTracingOptions;
+use cast::traces::{CallTraceArena, GethTraceBuilder, TracingInspectorConfig};
+use eyre::Result;
+use std::io::Write;
+
+/// Writes every executed step of the arena to `out`, one JSON object per line in execution order,
+/// and returns the number of steps.
+///
+/// The steps have the format of the struct logs of geth's `debug_traceTransaction`, with the
+/// program counter, the opcode, the remaining gas, the gas cost, the call depth and the stack
+/// before the step, so that they can be diffed against the traces of other EVM implementations.
+/// Memory and storage aren't included.
+pub fn write_steps(arena: &CallTraceArena, mut out: impl Write) -> Result<usize> {
+    let opts = GethDefaultTracingOptions::default().disable_storage();
+    let builder =
+        GethTraceBuilder::new(arena.nodes().to_vec(), TracingInspectorConfig::default_geth());
+    let frame = builder.geth_traces(0, Bytes::new(), opts);
+    for step in &frame.struct_logs {
+        serde_json::to_writer(&mut out, step)?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(frame.struct_logs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cast::{
+        executors::TracingExecutor,
+        revm::primitives::{AccountInfo, Address, Bytecode, Env, EnvWithHandlerCfg, TxKind, U256},
+        traces::StackSnapshotType,
+    };
+
+    #[test]
+    fn writes_steps_in_execution_order() {
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        let tracer = executor.inspector_mut().tracer.as_mut().unwrap();
+        tracer.config_mut().record_steps = true;
+        tracer.config_mut().record_stack_snapshots = StackSnapshotType::Full;
+        // PUSH1 1, PUSH1 2, ADD, STOP
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x01, 0x60, 0x02, 0x01, 0x00]));
+        let contract = Address::repeat_byte(0x11);
+        let info =
+            AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() };
+        executor.backend_mut().insert_account_info(contract, info);
+
+        let mut env = Env::default();
+        env.block.gas_limit = U256::from(30_000_000);
+        env.tx.transact_to = TxKind::Call(contract);
+        env.tx.gas_limit = 100_000;
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+        let raw = executor.transact_with_env(env).unwrap();
+
+        let mut out = vec![];
+        let steps = write_steps(raw.traces.as_ref().unwrap(), &mut out).unwrap();
+        assert_eq!(steps, 4);
+        let lines = String::from_utf8(out).unwrap();
+        let steps = lines
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        let ops = steps.iter().map(|step| step["op"].as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(ops, ["PUSH1", "PUSH1", "ADD", "STOP"]);
+        assert_eq!(steps[2]["pc"], 4);
+        assert_eq!(steps[2]["depth"], 1);
+        assert_eq!(steps[2]["gasCost"], 3);
+        assert_eq!(steps[2]["stack"], serde_json::json!(["0x1", "0x2"]));
+    }
+}
@@ -0,0 +1,379 @@
+//! Storage accesses of a replayed transaction.
+//!
+//! Slots of contracts with a known storage layout, i.e. tweaked contracts, are named after the
+//! variables they hold. Slots of mappings and dynamic arrays are derived from the preimages of the
+//! `KECCAK256` operations of the transaction.
+
+use alloy_primitives::{keccak256, Address, I256, U256};
+use cast::{
+    revm::interpreter::OpCode,
+    traces::{CallTraceArena, CallTraceStep, TraceMemberOrder},
+};
+use foundry_compilers::artifacts::{Storage, StorageLayout, StorageType};
+use serde::Serialize;
+use std::{collections::HashMap, fmt};
+
+/// The maximum number of slots between a derived slot and the start of its variable.
+const MAX_DERIVED_OFFSET: u64 = 1 << 32;
+
+/// The maximum recursion depth of nested mappings and arrays.
+const MAX_DEPTH: usize = 16;
+
+/// The inputs of the `KECCAK256` operations of a transaction, by their hash.
+pub type Preimages = HashMap<U256, Vec<u8>>;
+
+/// A `SLOAD` or `SSTORE` executed by the transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StorageAccess {
+    /// The address of the accessed storage.
+    pub address: Address,
+    pub slot: U256,
+    /// The loaded or stored value.
+    pub value: U256,
+    /// The value of the slot before an `SSTORE`, if known.
+    pub previous: Option<U256>,
+    pub write: bool,
+    /// The name of the slot, if derivable from the storage layout of the executed contract.
+    pub name: Option<String>,
+}
+
+impl fmt::Display for StorageAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = if self.write { "SSTORE" } else { "SLOAD " };
+        write!(f, "{op} {} ", self.address)?;
+        match &self.name {
+            Some(name) => write!(f, "{name}")?,
+            None => write!(f, "{:#x}", self.slot)?,
+        }
+        match self.previous {
+            Some(previous) if self.write => write!(f, ": {previous:#x} -> {:#x}", self.value),
+            _ => write!(f, ": {:#x}", self.value),
+        }
+    }
+}
+
+/// Returns the storage accesses of the transaction in execution order.
+///
+/// `layouts` holds the storage layouts of contracts by the address of their code, so that the
+/// storage accessed by a delegate call is named after the layout of the executed code.
+pub fn storage_accesses(
+    arena: &CallTraceArena,
+    layouts: &HashMap<Address, SlotNames>,
+) -> Vec<StorageAccess> {
+    let preimages = keccak_preimages(arena);
+    let mut accesses = vec![];
+    let nodes = arena.nodes();
+    let mut stack = vec![(0, 0)];
+    while let Some((idx, member)) = stack.pop() {
+        let node = &nodes[idx];
+        let Some(item) = node.ordering.get(member) else { continue };
+        stack.push((idx, member + 1));
+        match item {
+            TraceMemberOrder::Call(i) => stack.push((node.children[*i], 0)),
+            TraceMemberOrder::Step(i) => {
+                let step = &node.trace.steps[*i];
+                let (write, value) = match step.op {
+                    OpCode::SLOAD => (false, step.push_stack.as_ref().and_then(|s| s.last())),
+                    OpCode::SSTORE => (true, stack_item(step, 1)),
+                    _ => continue,
+                };
+                let (Some(slot), Some(value)) = (stack_item(step, 0), value) else { continue };
+                let name = layouts
+                    .get(&node.trace.address)
+                    .and_then(|names| names.name(*slot, &preimages));
+                accesses.push(StorageAccess {
+                    address: step.contract,
+                    slot: *slot,
+                    value: *value,
+                    previous: step.storage_change.and_then(|change| change.had_value),
+                    write,
+                    name,
+                });
+            }
+            TraceMemberOrder::Log(_) => {}
+        }
+    }
+    accesses
+}
+
+/// Returns the `n`th item of the stack from the top before the step was executed.
+pub(super) fn stack_item(step: &CallTraceStep, n: usize) -> Option<&U256> {
+    step.stack.as_ref().and_then(|stack| stack.iter().rev().nth(n))
+}
+
+/// Collects the inputs of the recorded `KECCAK256` steps.
+pub fn keccak_preimages(arena: &CallTraceArena) -> Preimages {
+    let mut preimages = Preimages::new();
+    for step in arena.nodes().iter().flat_map(|node| &node.trace.steps) {
+        if step.op != OpCode::KECCAK256 {
+            continue;
+        }
+        let (Some(offset), Some(size), Some(memory)) =
+            (stack_item(step, 0), stack_item(step, 1), step.memory.as_ref())
+        else {
+            continue;
+        };
+        let (Ok(offset), Ok(size)) = (usize::try_from(*offset), usize::try_from(*size)) else {
+            continue;
+        };
+        let memory = memory.as_bytes();
+        let mut preimage = vec![0; size];
+        if offset < memory.len() {
+            let end = memory.len().min(offset.saturating_add(size));
+            preimage[..end - offset].copy_from_slice(&memory[offset..end]);
+        }
+        preimages.insert(keccak256(&preimage).into(), preimage);
+    }
+    preimages
+}
+
+/// Names storage slots after the variables of a storage layout.
+#[derive(Clone, Debug)]
+pub struct SlotNames {
+    layout: StorageLayout,
+}
+
+impl SlotNames {
+    pub fn new(layout: StorageLayout) -> Self {
+        Self { layout }
+    }
+
+    /// Returns the name of the variable held by `slot`, e.g. `balances[0x…]` or `config.owner`.
+    pub fn name(&self, slot: U256, preimages: &Preimages) -> Option<String> {
+        let (name, ty, offset) = self.locate(slot, preimages, 0)?;
+        Some(self.describe(name, ty, offset))
+    }
+
+    /// Returns the name and type of the variable that `slot` belongs to, and the number of slots
+    /// between its first slot and `slot`.
+    fn locate(
+        &self,
+        slot: U256,
+        preimages: &Preimages,
+        depth: usize,
+    ) -> Option<(String, &str, U256)> {
+        if let Some(located) = self.locate_static(slot) {
+            return Some(located);
+        }
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        // the derived slot closest to the start of its variable
+        let (hash, preimage) = preimages
+            .iter()
+            .filter(|(hash, _)| slot >= **hash && slot - **hash < U256::from(MAX_DERIVED_OFFSET))
+            .max_by_key(|(hash, _)| **hash)?;
+        let offset = slot - *hash;
+        if preimage.len() > 32 {
+            // mapping values are stored at `keccak256(key . slot)`
+            let (key, base) = preimage.split_at(preimage.len() - 32);
+            let (name, ty) = self.locate_start(U256::from_be_slice(base), preimages, depth)?;
+            let ty = self.layout.types.get(ty).filter(|ty| ty.encoding == "mapping")?;
+            let key_label = self.layout.types.get(ty.key.as_deref()?)?.label.as_str();
+            Some((format!("{name}[{}]", format_key(key, key_label)), ty.value.as_deref()?, offset))
+        } else if preimage.len() == 32 {
+            // the data of dynamic arrays and long bytes is stored from `keccak256(slot)`
+            let (name, ty) = self.locate_start(U256::from_be_slice(preimage), preimages, depth)?;
+            match self.layout.types.get(ty)? {
+                ty if ty.encoding == "dynamic_array" => {
+                    let base = ty.other.get("base")?.as_str()?;
+                    let (index, offset) = self.element(base, offset);
+                    Some((format!("{name}[{index}]"), base, offset))
+                }
+                ty if ty.encoding == "bytes" => Some((format!("{name}.data"), "", offset)),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Locates `slot`, which must be the first slot of its variable.
+    fn locate_start(
+        &self,
+        slot: U256,
+        preimages: &Preimages,
+        depth: usize,
+    ) -> Option<(String, &str)> {
+        match self.locate(slot, preimages, depth + 1)? {
+            (name, ty, offset) if offset.is_zero() => Some((name, ty)),
+            _ => None,
+        }
+    }
+
+    /// Locates `slot` among the statically allocated variables.
+    fn locate_static(&self, slot: U256) -> Option<(String, &str, U256)> {
+        let vars = self
+            .layout
+            .storage
+            .iter()
+            .filter(|var| {
+                let Ok(start) = var.slot.parse::<U256>() else { return false };
+                slot >= start && slot - start < self.slots(&var.storage_type)
+            })
+            .collect::<Vec<_>>();
+        let var = vars.first()?;
+        let start = var.slot.parse::<U256>().ok()?;
+        // variables packed into the same slot are named together
+        let name = vars.iter().map(|var| var.label.as_str()).collect::<Vec<_>>().join("|");
+        Some((name, var.storage_type.as_str(), slot - start))
+    }
+
+    /// Names the slot at `offset` within a variable of the given type.
+    fn describe(&self, name: String, ty: &str, offset: U256) -> String {
+        let Some(storage_type) = self.layout.types.get(ty) else {
+            return offset_name(name, offset);
+        };
+        if storage_type.encoding != "inplace" {
+            return offset_name(name, offset);
+        }
+        if let Some(members) = members(storage_type) {
+            let member = members.iter().find(|member| {
+                member.slot.parse::<U256>().is_ok_and(|start| {
+                    offset >= start && offset - start < self.slots(&member.storage_type)
+                })
+            });
+            if let Some(member) = member {
+                let start = member.slot.parse::<U256>().unwrap_or_default();
+                return self.describe(
+                    format!("{name}.{}", member.label),
+                    &member.storage_type,
+                    offset - start,
+                );
+            }
+        }
+        if let Some(base) = storage_type.other.get("base").and_then(|base| base.as_str()) {
+            let (index, offset) = self.element(base, offset);
+            return self.describe(format!("{name}[{index}]"), base, offset);
+        }
+        offset_name(name, offset)
+    }
+
+    /// Returns the index of the first array element of type `ty` stored at `offset` from the start
+    /// of the array's data, and the offset of the slot within that element.
+    fn element(&self, ty: &str, offset: U256) -> (U256, U256) {
+        let bytes = self.bytes(ty);
+        if bytes <= U256::from(32) && !bytes.is_zero() {
+            (offset * (U256::from(32) / bytes), U256::ZERO)
+        } else {
+            let slots = self.slots(ty);
+            (offset / slots, offset % slots)
+        }
+    }
+
+    /// Returns the size of a value of the given type in bytes.
+    fn bytes(&self, ty: &str) -> U256 {
+        self.layout
+            .types
+            .get(ty)
+            .and_then(|ty| ty.number_of_bytes.parse().ok())
+            .unwrap_or(U256::from(32))
+    }
+
+    /// Returns the number of slots occupied by a value of the given type.
+    fn slots(&self, ty: &str) -> U256 {
+        self.bytes(ty).div_ceil(U256::from(32)).max(U256::from(1))
+    }
+}
+
+/// Returns the members of a struct type.
+fn members(ty: &StorageType) -> Option<Vec<Storage>> {
+    serde_json::from_value(ty.other.get("members")?.clone()).ok()
+}
+
+fn offset_name(name: String, offset: U256) -> String {
+    if offset.is_zero() {
+        name
+    } else {
+        format!("{name}+{offset}")
+    }
+}
+
+/// Formats a mapping key according to the label of its type.
+fn format_key(key: &[u8], label: &str) -> String {
+    if key.len() == 32 {
+        let word = U256::from_be_slice(key);
+        if label == "address" || label.starts_with("contract ") {
+            return Address::from_word(word.into()).to_string();
+        }
+        if label.starts_with("uint") || label.starts_with("enum ") {
+            return word.to_string();
+        }
+        if label.starts_with("int") {
+            return I256::from_raw(word).to_string();
+        }
+        if label == "bool" {
+            return (!word.is_zero()).to_string();
+        }
+    }
+    if label == "string" {
+        if let Ok(key) = std::str::from_utf8(key) {
+            return format!("{key:?}");
+        }
+    }
+    alloy_primitives::hex::encode_prefixed(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> SlotNames {
+        SlotNames::new(
+            serde_json::from_str(
+                r#"{
+                "storage": [
+                    {"astId": 1, "contract": "A", "label": "owner", "offset": 0, "slot": "0", "type": "t_address"},
+                    {"astId": 2, "contract": "A", "label": "paused", "offset": 20, "slot": "0", "type": "t_bool"},
+                    {"astId": 3, "contract": "A", "label": "config", "offset": 0, "slot": "1", "type": "t_struct(Config)"},
+                    {"astId": 4, "contract": "A", "label": "balances", "offset": 0, "slot": "3", "type": "t_mapping(t_address,t_uint256)"},
+                    {"astId": 5, "contract": "A", "label": "holders", "offset": 0, "slot": "4", "type": "t_array(t_address)dyn_storage"}
+                ],
+                "types": {
+                    "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+                    "t_bool": {"encoding": "inplace", "label": "bool", "numberOfBytes": "1"},
+                    "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"},
+                    "t_struct(Config)": {"encoding": "inplace", "label": "struct A.Config", "numberOfBytes": "64", "members": [
+                        {"astId": 6, "contract": "A", "label": "fee", "offset": 0, "slot": "0", "type": "t_uint256"},
+                        {"astId": 7, "contract": "A", "label": "treasury", "offset": 0, "slot": "1", "type": "t_address"}
+                    ]},
+                    "t_mapping(t_address,t_uint256)": {"encoding": "mapping", "key": "t_address", "label": "mapping(address => uint256)", "numberOfBytes": "32", "value": "t_uint256"},
+                    "t_array(t_address)dyn_storage": {"encoding": "dynamic_array", "label": "address[]", "numberOfBytes": "32", "base": "t_address"}
+                }
+            }"#,
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn names_static_slots() {
+        let names = layout();
+        let preimages = Preimages::new();
+        assert_eq!(names.name(U256::from(0), &preimages).unwrap(), "owner|paused");
+        assert_eq!(names.name(U256::from(1), &preimages).unwrap(), "config.fee");
+        assert_eq!(names.name(U256::from(2), &preimages).unwrap(), "config.treasury");
+        assert_eq!(names.name(U256::from(5), &preimages), None);
+    }
+
+    #[test]
+    fn names_derived_slots() {
+        let names = layout();
+        let holder = Address::with_last_byte(0xaa);
+        let mut preimages = Preimages::new();
+
+        let mut preimage = holder.into_word().to_vec();
+        preimage.extend_from_slice(&U256::from(3).to_be_bytes::<32>());
+        let balance_slot = U256::from_be_bytes(keccak256(&preimage).0);
+        preimages.insert(balance_slot, preimage);
+
+        let preimage = U256::from(4).to_be_bytes::<32>().to_vec();
+        let holders_slot = U256::from_be_bytes(keccak256(&preimage).0);
+        preimages.insert(holders_slot, preimage);
+
+        assert_eq!(names.name(balance_slot, &preimages).unwrap(), format!("balances[{holder}]"));
+        assert_eq!(names.name(holders_slot, &preimages).unwrap(), "holders[0]");
+        assert_eq!(names.name(holders_slot + U256::from(2), &preimages).unwrap(), "holders[2]");
+    }
+}
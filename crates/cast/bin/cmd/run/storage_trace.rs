@@ -0,0 +1,122 @@
+//! The storage and memory accesses of `--trace-storage`, rendered within their call frame.
+
+use super::storage::stack_item;
+use alloy_primitives::{Address, U256};
+use cast::{
+    revm::interpreter::OpCode,
+    traces::{CallTraceArena, CallTraceStep, DecodedTraceStep},
+};
+use std::collections::HashMap;
+
+/// Annotates the `SLOAD`, `SSTORE`, `MLOAD`, `MSTORE` and `MSTORE8` steps of every call frame
+/// with the accessed slot or memory range and the value.
+///
+/// The loaded values are read from the stack of the following step, so every step of the frames
+/// must be recorded with its stack. Accesses to the storage of another account than the executed
+/// one, i.e. in delegate calls, are named after `labels`.
+pub fn annotate(arena: &mut CallTraceArena, labels: &HashMap<Address, String>) {
+    for node in arena.nodes_mut() {
+        let address = node.trace.address;
+        let steps = &mut node.trace.steps;
+        for i in 0..steps.len() {
+            let loaded = steps.get(i + 1).and_then(|next| stack_item(next, 0)).copied();
+            let Some(mut line) = describe(&steps[i], loaded) else { continue };
+            let contract = steps[i].contract;
+            if contract != address && matches!(steps[i].op, OpCode::SLOAD | OpCode::SSTORE) {
+                let owner = labels.get(&contract).cloned().unwrap_or_else(|| contract.to_string());
+                line.push_str(&format!(" (storage of {owner})"));
+            }
+            steps[i].decoded = Some(DecodedTraceStep::Line(line));
+        }
+    }
+}
+
+/// Describes an access step, `loaded` being the value it pushed on the stack, if known.
+fn describe(step: &CallTraceStep, loaded: Option<U256>) -> Option<String> {
+    let first = *stack_item(step, 0)?;
+    let loaded = loaded.map_or_else(|| "?".to_string(), |value| format!("{value:#x}"));
+    Some(match step.op {
+        OpCode::SLOAD => format!("SLOAD {first:#x} -> {loaded}"),
+        OpCode::SSTORE => {
+            let value = stack_item(step, 1)?;
+            match step.storage_change.and_then(|change| change.had_value) {
+                Some(previous) => format!("SSTORE {first:#x}: {previous:#x} -> {value:#x}"),
+                None => format!("SSTORE {first:#x} = {value:#x}"),
+            }
+        }
+        OpCode::MLOAD => format!("MLOAD {} -> {loaded}", range(first, 32)),
+        OpCode::MSTORE => format!("MSTORE {} = {:#x}", range(first, 32), stack_item(step, 1)?),
+        OpCode::MSTORE8 => {
+            let value = stack_item(step, 1)? & U256::from(0xff);
+            format!("MSTORE8 {} = {value:#x}", range(first, 1))
+        }
+        _ => return None,
+    })
+}
+
+/// Formats the memory range of `size` bytes from `offset`.
+fn range(offset: U256, size: u64) -> String {
+    format!("[{offset:#x}, {:#x})", offset.saturating_add(U256::from(size)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+    use cast::revm::interpreter::InstructionResult;
+
+    fn step(op: OpCode, contract: Address, stack: &[u64]) -> CallTraceStep {
+        CallTraceStep {
+            depth: 0,
+            pc: 0,
+            op,
+            contract,
+            stack: Some(stack.iter().map(|item| U256::from(*item)).collect()),
+            push_stack: None,
+            memory: None,
+            returndata: Default::default(),
+            gas_remaining: 0,
+            gas_refund_counter: 0,
+            gas_used: 0,
+            gas_cost: 0,
+            storage_change: None,
+            status: InstructionResult::Continue,
+            decoded: None,
+        }
+    }
+
+    fn line(step: &CallTraceStep) -> Option<&str> {
+        match &step.decoded {
+            Some(DecodedTraceStep::Line(line)) => Some(line),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn annotates_accesses() {
+        let (code, proxy) = (Address::ZERO, Address::with_last_byte(1));
+        let mut arena = arena(&[(None, 100_000)]);
+        arena.nodes_mut()[0].trace.steps = vec![
+            step(OpCode::SLOAD, proxy, &[1]),
+            step(OpCode::MSTORE8, code, &[5, 0x1ff, 0x40]),
+            step(OpCode::MLOAD, code, &[0x40]),
+            step(OpCode::POP, code, &[9]),
+            step(OpCode::SSTORE, code, &[7, 1]),
+            step(OpCode::SLOAD, code, &[2]),
+        ];
+        annotate(&mut arena, &HashMap::from([(proxy, "Proxy".to_string())]));
+
+        let lines = arena.nodes()[0].trace.steps.iter().map(line).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                Some("SLOAD 0x1 -> 0x40 (storage of Proxy)"),
+                Some("MSTORE8 [0x40, 0x41) = 0xff"),
+                Some("MLOAD [0x40, 0x60) -> 0x9"),
+                None,
+                Some("SSTORE 0x1 = 0x7"),
+                Some("SLOAD 0x2 -> ?"),
+            ]
+        );
+    }
+}
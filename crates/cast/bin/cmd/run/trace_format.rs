@@ -0,0 +1,89 @@
+//! The formats of the printed call trace, to compare the replay with the tracers of the nodes.
+
+use alloy_rpc_types::{trace::geth::CallConfig, TransactionInfo};
+use cast::traces::{CallTraceArena, GethTraceBuilder, ParityTraceBuilder, TracingInspectorConfig};
+use clap::ValueEnum;
+use eyre::Result;
+use std::io::Write;
+
+/// The format of the call trace of `--trace-format`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum TraceFormat {
+    /// The decoded trace of Foundry.
+    #[default]
+    Foundry,
+    /// The JSON call frame of geth's `callTracer`, as returned by `debug_traceTransaction`.
+    Geth,
+    /// The JSON array of traces returned by `trace_transaction`.
+    Parity,
+}
+
+/// Writes the call trace of the arena to `out` as a single line of JSON in the format of the
+/// tracer of the nodes, `gas_used` being the gas used by the transaction.
+///
+/// Nothing is written for [`TraceFormat::Foundry`], which is rendered by the decoder.
+pub fn write_trace(
+    format: TraceFormat,
+    arena: &CallTraceArena,
+    gas_used: u64,
+    info: TransactionInfo,
+    mut out: impl Write,
+) -> Result<()> {
+    let nodes = arena.nodes().to_vec();
+    match format {
+        TraceFormat::Foundry => return Ok(()),
+        TraceFormat::Geth => {
+            let builder = GethTraceBuilder::new(nodes, TracingInspectorConfig::default_geth());
+            let frame = builder.geth_call_traces(CallConfig::default().with_log(), gas_used);
+            serde_json::to_writer(&mut out, &frame)?;
+        }
+        TraceFormat::Parity => {
+            let builder =
+                ParityTraceBuilder::new(nodes, None, TracingInspectorConfig::default_parity());
+            serde_json::to_writer(&mut out, &builder.into_localized_transaction_traces(info))?;
+        }
+    }
+    writeln!(out)?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+    use alloy_primitives::{Address, B256};
+
+    #[test]
+    fn writes_node_formats() {
+        let mut traces = arena(&[(None, 100_000), (Some(0), 10_000)]);
+        let nodes = traces.nodes_mut();
+        nodes[0].trace.address = Address::with_last_byte(1);
+        nodes[1].trace.address = Address::with_last_byte(2);
+        nodes[1].trace.success = true;
+        let info = TransactionInfo {
+            hash: Some(B256::repeat_byte(0x11)),
+            block_number: Some(19_000_000),
+            ..Default::default()
+        };
+
+        let write = |format| {
+            let mut out = vec![];
+            write_trace(format, &traces, 21_000, info, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert!(write(TraceFormat::Foundry).is_empty());
+
+        let geth: serde_json::Value = serde_json::from_str(&write(TraceFormat::Geth)).unwrap();
+        assert_eq!(geth["to"], Address::with_last_byte(1).to_string().to_lowercase());
+        assert_eq!(geth["gasUsed"], "0x5208");
+        assert_eq!(geth["calls"][0]["to"], Address::with_last_byte(2).to_string().to_lowercase());
+
+        let parity: serde_json::Value = serde_json::from_str(&write(TraceFormat::Parity)).unwrap();
+        let parity = parity.as_array().unwrap();
+        assert_eq!(parity.len(), 2);
+        assert_eq!(parity[0]["transactionHash"], B256::repeat_byte(0x11).to_string());
+        assert_eq!(parity[0]["blockNumber"], 19_000_000);
+        assert_eq!(parity[1]["traceAddress"], serde_json::json!([0]));
+    }
+}
@@ -0,0 +1,140 @@
+//! Reporting of plain value transfers.
+
+use alloy_primitives::{utils::format_ether, Address, U256};
+use eyre::Result;
+use foundry_evm::executors::Executor;
+use serde::Serialize;
+use std::fmt;
+
+/// A transaction without input that transfers value to an account.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueTransfer {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    /// Whether the recipient has code, i.e. its `receive` or fallback function was executed.
+    pub to_contract: bool,
+    pub balances: Vec<BalanceChange>,
+}
+
+/// The balance of an account before and after the transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct BalanceChange {
+    pub address: Address,
+    pub before: U256,
+    pub after: U256,
+}
+
+impl ValueTransfer {
+    /// Records the state of the sender and the recipient before the transfer is executed.
+    pub fn before(executor: &Executor, from: Address, to: Address, value: U256) -> Result<Self> {
+        let mut balances = vec![];
+        for address in [from, to] {
+            if balances.iter().all(|change: &BalanceChange| change.address != address) {
+                let before = executor.get_balance(address)?;
+                balances.push(BalanceChange { address, before, after: before });
+            }
+        }
+        Ok(Self { from, to, value, to_contract: !executor.is_empty_code(to)?, balances })
+    }
+
+    /// Records the balances of the sender and the recipient after the transfer was executed.
+    pub fn after(&mut self, executor: &Executor) -> Result<()> {
+        for change in &mut self.balances {
+            change.after = executor.get_balance(change.address)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ValueTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let recipient =
+            if self.to_contract { "contract, receive/fallback executed" } else { "EOA" };
+        writeln!(
+            f,
+            "Value transfer of {} ETH ({} wei) from {} to {} ({recipient})",
+            format_ether(self.value),
+            self.value,
+            self.from,
+            self.to
+        )?;
+        write!(f, "Balance changes:")?;
+        for change in &self.balances {
+            let (sign, delta) = if change.after >= change.before {
+                ('+', change.after - change.before)
+            } else {
+                ('-', change.before - change.after)
+            };
+            write!(
+                f,
+                "\n  {}: {} -> {} ({sign}{delta})",
+                change.address, change.before, change.after
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cast::revm::primitives::{AccountInfo, Bytecode, Env, EnvWithHandlerCfg, TxKind};
+    use foundry_evm::executors::TracingExecutor;
+
+    const ETHER: u64 = 1_000_000_000_000_000_000;
+
+    fn transfer(to_code: Option<&'static [u8]>) -> (ValueTransfer, bool) {
+        let (from, to) = (Address::with_last_byte(0xa), Address::with_last_byte(0xb));
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        executor.set_balance(from, U256::from(2 * ETHER)).unwrap();
+        if let Some(code) = to_code {
+            let code = Bytecode::new_raw(code.into());
+            let info =
+                AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() };
+            executor.backend_mut().insert_account_info(to, info);
+        }
+
+        let mut env = Env::default();
+        env.tx.caller = from;
+        env.tx.transact_to = TxKind::Call(to);
+        env.tx.value = U256::from(ETHER);
+        env.tx.gas_limit = 100_000;
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+
+        let mut transfer = ValueTransfer::before(&executor, from, to, U256::from(ETHER)).unwrap();
+        let res = executor.transact_with_env(env).unwrap();
+        transfer.after(&executor).unwrap();
+        (transfer, res.reverted)
+    }
+
+    #[test]
+    fn reports_eoa_transfer() {
+        let (transfer, reverted) = transfer(None);
+        assert!(!reverted);
+        assert!(!transfer.to_contract);
+        assert_eq!(
+            transfer.to_string(),
+            format!(
+                "Value transfer of 1.000000000000000000 ETH ({ETHER} wei) from {} to {} (EOA)\n\
+                 Balance changes:\n  {}: {} -> {ETHER} (-{ETHER})\n  {}: 0 -> {ETHER} (+{ETHER})",
+                transfer.from,
+                transfer.to,
+                transfer.from,
+                2 * ETHER,
+                transfer.to
+            )
+        );
+    }
+
+    #[test]
+    fn reports_transfer_to_fallback() {
+        // CALLVALUE PUSH0 SSTORE STOP: stores the received value in a fallback function
+        let (transfer, reverted) = transfer(Some(&[0x34, 0x5f, 0x55, 0x00]));
+        assert!(!reverted);
+        assert!(transfer.to_contract);
+        assert_eq!(transfer.balances[1].after, U256::from(ETHER));
+        assert!(transfer.to_string().contains("(contract, receive/fallback executed)"));
+    }
+}
@@ -0,0 +1,168 @@
+//! Comparison of the tweaked replay of a transaction against its replay without the tweaks.
+
+use alloy_primitives::{Address, Bytes, B256};
+use cast::executors::RawCallResult;
+use serde::Serialize;
+use std::fmt;
+
+/// A log emitted by a replay.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EmittedLog {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+}
+
+impl fmt::Display for EmittedLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [", self.address)?;
+        for (i, topic) in self.topics.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{topic}")?;
+        }
+        write!(f, "] {}", self.data)
+    }
+}
+
+/// The outcome of a replay.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outcome {
+    pub success: bool,
+    pub gas_used: u64,
+    /// The return or revert data, or the deployed code of a creation.
+    pub output: Bytes,
+    pub logs: Vec<EmittedLog>,
+}
+
+impl Outcome {
+    pub fn from_raw(raw: &RawCallResult) -> Self {
+        Self {
+            success: !raw.reverted,
+            gas_used: raw.gas_used,
+            output: raw.result.clone(),
+            logs: raw
+                .logs
+                .iter()
+                .map(|log| EmittedLog {
+                    address: log.address,
+                    topics: log.topics().to_vec(),
+                    data: log.data.data.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn status(&self) -> &'static str {
+        if self.success {
+            "succeeded"
+        } else {
+            "reverted"
+        }
+    }
+}
+
+/// The outcomes of the replays of a transaction without and with the tweaks, both on the same
+/// state.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TweakDiff {
+    pub original: Outcome,
+    pub tweaked: Outcome,
+}
+
+impl TweakDiff {
+    pub fn new(original: &RawCallResult, tweaked: &RawCallResult) -> Self {
+        Self { original: Outcome::from_raw(original), tweaked: Outcome::from_raw(tweaked) }
+    }
+}
+
+impl fmt::Display for TweakDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { original, tweaked } = self;
+        write!(f, "Tweak diff (original -> tweaked):")?;
+        if original == tweaked {
+            return write!(f, "\n  no differences");
+        }
+
+        if original.success == tweaked.success {
+            write!(f, "\n  status: {}", original.status())?;
+        } else {
+            write!(f, "\n  status: {} -> {}", original.status(), tweaked.status())?;
+        }
+
+        let delta = tweaked.gas_used as i128 - original.gas_used as i128;
+        if delta == 0 {
+            write!(f, "\n  gas used: {}", original.gas_used)?;
+        } else {
+            write!(f, "\n  gas used: {} -> {} ({delta:+})", original.gas_used, tweaked.gas_used)?;
+        }
+
+        if original.output == tweaked.output {
+            write!(f, "\n  output: {}", original.output)?;
+        } else {
+            write!(f, "\n  output: {} -> {}", original.output, tweaked.output)?;
+        }
+
+        if original.logs == tweaked.logs {
+            return write!(f, "\n  logs: {} identical", original.logs.len());
+        }
+        write!(f, "\n  logs: {} -> {}", original.logs.len(), tweaked.logs.len())?;
+        for i in 0..original.logs.len().max(tweaked.logs.len()) {
+            let (before, after) = (original.logs.get(i), tweaked.logs.get(i));
+            if before == after {
+                continue;
+            }
+            if let Some(log) = before {
+                write!(f, "\n  - #{i} {log}")?;
+            }
+            if let Some(log) = after {
+                write!(f, "\n  + #{i} {log}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(last_byte: u8) -> EmittedLog {
+        EmittedLog {
+            address: Address::with_last_byte(last_byte),
+            topics: vec![B256::with_last_byte(last_byte)],
+            data: Bytes::from_static(&[0x01]),
+        }
+    }
+
+    #[test]
+    fn diffs_outcomes() {
+        let original =
+            Outcome { success: true, gas_used: 50_000, output: Bytes::new(), logs: vec![log(1)] };
+        let diff = TweakDiff { original: original.clone(), tweaked: original.clone() };
+        assert_eq!(diff.to_string(), "Tweak diff (original -> tweaked):\n  no differences");
+
+        let tweaked = Outcome {
+            success: false,
+            gas_used: 42_000,
+            output: Bytes::from_static(&[0xaa]),
+            logs: vec![log(1), log(2)],
+        };
+        let diff = TweakDiff { original, tweaked };
+        assert_eq!(
+            diff.to_string(),
+            format!(
+                "Tweak diff (original -> tweaked):\n  status: succeeded -> reverted\n  gas used: \
+                 50000 -> 42000 (-8000)\n  output: 0x -> 0xaa\n  logs: 1 -> 2\n  + #1 {} [{}] \
+                 0x01",
+                Address::with_last_byte(2),
+                B256::with_last_byte(2)
+            )
+        );
+        let json = serde_json::to_value(&diff).unwrap();
+        assert_eq!(json["tweaked"]["gasUsed"], 42_000);
+        assert_eq!(json["original"]["logs"][0]["data"], "0x01");
+    }
+}
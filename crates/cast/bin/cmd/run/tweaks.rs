@@ -0,0 +1,128 @@
+//! Summary of the code tweaks applied to the backend.
+
+use alloy_primitives::Address;
+use comfy_table::{presets::ASCII_MARKDOWN, Table};
+use eyre::Result;
+use foundry_tweak::{AppliedTweak, ClonedProject, TweakPrecedence, TweakTarget};
+use serde::Serialize;
+
+/// A code replacement of a `--tweak` project or a `--tweak-code`, as included in the JSON output.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TweakSummary {
+    pub address: Address,
+    /// The root of the project the code was compiled from, `None` for a `--tweak-code`.
+    pub project: Option<String>,
+    /// The size of the replaced code, zero if the address had no code.
+    pub old_code_size: usize,
+    pub new_code_size: usize,
+}
+
+/// Retargets the `--tweak` projects to the `--tweak-at` addresses, then keeps the ones that take
+/// effect, at most one per address as resolved by `precedence`.
+///
+/// The projects overridden with `--tweak-precedence last-wins` are dropped, so that they're
+/// neither reported nor used to decode the trace or record the coverage of another project's code.
+pub fn resolve_projects(
+    mut projects: Vec<ClonedProject>,
+    targets: &[TweakTarget],
+    precedence: TweakPrecedence,
+) -> Result<Vec<ClonedProject>> {
+    foundry_tweak::retarget_tweaks(&mut projects, targets)?;
+    let effective = foundry_tweak::effective_tweaks(&projects, precedence)?;
+    Ok(effective.into_iter().cloned().collect())
+}
+
+/// Returns the summaries of the applied tweaks, attributed to the resolved project tweaking their
+/// address, if any.
+pub fn summarize(applied: &[AppliedTweak], projects: &[ClonedProject]) -> Vec<TweakSummary> {
+    applied
+        .iter()
+        .map(|tweak| TweakSummary {
+            address: tweak.address,
+            project: projects
+                .iter()
+                .find(|project| project.tweaked_address() == tweak.address)
+                .map(|project| project.root.display().to_string()),
+            old_code_size: tweak.old_code_size,
+            new_code_size: tweak.new_code_size,
+        })
+        .collect()
+}
+
+/// Formats the applied tweaks as a table.
+pub fn table(tweaks: &[TweakSummary]) -> Table {
+    let mut table = Table::new();
+    table.load_preset(ASCII_MARKDOWN);
+    table.set_header(["Address", "Source", "Old code size", "New code size"]);
+    for tweak in tweaks {
+        table.add_row([
+            tweak.address.to_string(),
+            tweak.project.clone().unwrap_or_else(|| "--tweak-code".to_string()),
+            tweak.old_code_size.to_string(),
+            tweak.new_code_size.to_string(),
+        ]);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::coverage;
+    use alloy_primitives::Bytes;
+    use foundry_tweak::TweakData;
+
+    #[test]
+    fn summarizes_applied_tweaks() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let applied = AppliedTweak { address: b, old_code_size: 0, new_code_size: 2 };
+        let mut tweaks = summarize(&[applied], &[]);
+        assert_eq!(
+            tweaks,
+            [TweakSummary { address: b, project: None, old_code_size: 0, new_code_size: 2 }]
+        );
+        tweaks.insert(
+            0,
+            TweakSummary {
+                address: a,
+                project: Some("/token".to_string()),
+                old_code_size: 100,
+                new_code_size: 120,
+            },
+        );
+
+        let table = table(&tweaks).to_string();
+        assert!(table.contains("/token"), "{table}");
+        assert!(table.contains("--tweak-code"), "{table}");
+        let json = serde_json::to_value(&tweaks[0]).unwrap();
+        assert_eq!(json["oldCodeSize"], 100);
+    }
+
+    #[test]
+    fn resolves_overridden_projects() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let project = |root: &str, address| {
+            let mut project = ClonedProject::default();
+            project.root = root.into();
+            project.metadata.address = address;
+            project
+        };
+        let projects = vec![project("/base", a), project("/hotfix", b)];
+        let targets = [TweakTarget { project: "/hotfix".into(), address: a }];
+        assert!(resolve_projects(projects.clone(), &targets, TweakPrecedence::Error).is_err());
+
+        let resolved = resolve_projects(projects, &targets, TweakPrecedence::LastWins).unwrap();
+        let roots = resolved.iter().map(|project| project.root.to_str().unwrap());
+        assert_eq!(roots.collect::<Vec<_>>(), ["/hotfix"]);
+        let code = Bytes::from_static(&[0x60, 0x00]);
+        let tweak_map = TweakData::from([(a, code.clone())]);
+        let covered = coverage::covered_projects(&resolved, &tweak_map);
+        assert_eq!(covered.len(), 1);
+        assert_eq!((covered[0].0.root.to_str(), covered[0].1), (Some("/hotfix"), &code));
+
+        let applied = AppliedTweak { address: a, old_code_size: 1, new_code_size: 2 };
+        let tweaks = summarize(&[applied], &resolved);
+        assert_eq!(tweaks[0].project.as_deref(), Some("/hotfix"));
+    }
+}
@@ -0,0 +1,115 @@
+//! Display of the typed fields of the replayed transaction.
+
+use alloy_rpc_types::Transaction;
+use std::fmt::{self, Write};
+
+/// Returns the name of the transaction type.
+fn type_name(tx_type: u8) -> &'static str {
+    match tx_type {
+        0 => "legacy",
+        1 => "EIP-2930",
+        2 => "EIP-1559",
+        3 => "EIP-4844 (blob)",
+        4 => "EIP-7702",
+        _ => "unknown",
+    }
+}
+
+fn field(s: &mut String, name: &str, value: &dyn fmt::Display) {
+    write!(s, "\n  {name:<26}{value}").unwrap();
+}
+
+/// Formats the type of the transaction and all of its fields that are set for this type.
+pub fn format_tx_fields(tx: &Transaction) -> String {
+    let tx_type = tx.transaction_type.unwrap_or_default();
+    let mut s = String::from("Transaction fields:");
+    field(&mut s, "type", &format_args!("{tx_type} ({})", type_name(tx_type)));
+    field(&mut s, "nonce", &tx.nonce);
+    field(&mut s, "gas limit", &tx.gas);
+    if let Some(gas_price) = tx.gas_price {
+        field(&mut s, "gas price", &gas_price);
+    }
+    if let Some(max_fee) = tx.max_fee_per_gas {
+        field(&mut s, "max fee per gas", &max_fee);
+    }
+    if let Some(max_priority_fee) = tx.max_priority_fee_per_gas {
+        field(&mut s, "max priority fee per gas", &max_priority_fee);
+    }
+    if let Some(max_blob_fee) = tx.max_fee_per_blob_gas {
+        field(&mut s, "max fee per blob gas", &max_blob_fee);
+    }
+    if let Some(chain_id) = tx.chain_id {
+        field(&mut s, "chain id", &chain_id);
+    }
+    if let Some(access_list) = &tx.access_list {
+        field(&mut s, "access list", &format_args!("{} item(s)", access_list.0.len()));
+        for item in &access_list.0 {
+            write!(s, "\n    {}", item.address).unwrap();
+            for key in &item.storage_keys {
+                write!(s, "\n      {key}").unwrap();
+            }
+        }
+    }
+    if let Some(hashes) = &tx.blob_versioned_hashes {
+        field(&mut s, "blob versioned hashes", &format_args!("{} hash(es)", hashes.len()));
+        for hash in hashes {
+            write!(s, "\n    {hash}").unwrap();
+        }
+    }
+    // not a typed field of the RPC transaction yet
+    if let Some(authorizations) = tx.other.get("authorizationList").and_then(|list| list.as_array())
+    {
+        field(
+            &mut s,
+            "authorization list",
+            &format_args!("{} authorization(s)", authorizations.len()),
+        );
+        for authorization in authorizations {
+            write!(s, "\n    {authorization}").unwrap();
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Address, B256};
+    use alloy_rpc_types::{AccessList, AccessListItem};
+
+    #[test]
+    fn formats_typed_fields() {
+        let tx = Transaction {
+            transaction_type: Some(2),
+            nonce: 5,
+            gas: 21_000,
+            gas_price: Some(12),
+            max_fee_per_gas: Some(20),
+            max_priority_fee_per_gas: Some(2),
+            chain_id: Some(1),
+            access_list: Some(AccessList(vec![AccessListItem {
+                address: Address::ZERO,
+                storage_keys: vec![B256::ZERO],
+            }])),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_tx_fields(&tx),
+            format!(
+                "Transaction fields:\n  \
+                 type                      2 (EIP-1559)\n  \
+                 nonce                     5\n  \
+                 gas limit                 21000\n  \
+                 gas price                 12\n  \
+                 max fee per gas           20\n  \
+                 max priority fee per gas  2\n  \
+                 chain id                  1\n  \
+                 access list               1 item(s)\n    \
+                 {}\n      \
+                 {}",
+                Address::ZERO,
+                B256::ZERO
+            )
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Verification of the replay against the call trace of the node.
+
+use super::filter::TracePath;
+use alloy_primitives::{Address, TxHash, U256};
+use alloy_provider::ext::DebugApi;
+use alloy_rpc_types::trace::geth::{
+    CallFrame, GethDebugBuiltInTracerType, GethDebugTracingOptions, GethTrace,
+};
+use cast::traces::{CallKind, CallTraceArena};
+use foundry_common::{cli_warn, provider::RetryProvider};
+use serde::Serialize;
+use std::fmt;
+
+/// A difference between the call structure of the replay and the one traced by the node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Divergence {
+    /// The position of the call in the trace.
+    pub path: TracePath,
+    pub kind: DivergenceKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DivergenceKind {
+    Target { replay: Address, node: Option<Address> },
+    Value { replay: U256, node: U256 },
+    Success { replay: bool, node: bool },
+    Subcalls { replay: usize, node: usize },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "call {}: ", self.path)?;
+        match &self.kind {
+            DivergenceKind::Target { replay, node } => match node {
+                Some(node) => write!(f, "replay called {replay}, node called {node}"),
+                None => write!(f, "replay called {replay}, node has no target"),
+            },
+            DivergenceKind::Value { replay, node } => {
+                write!(f, "replay transferred {replay} wei, node transferred {node} wei")
+            }
+            DivergenceKind::Success { replay, node } => {
+                let outcome = |success: bool| if success { "succeeded" } else { "failed" };
+                write!(f, "replay {}, node {}", outcome(*replay), outcome(*node))
+            }
+            DivergenceKind::Subcalls { replay, node } => {
+                write!(f, "replay made {replay} subcall(s), node made {node}")
+            }
+        }
+    }
+}
+
+/// Fetches the `callTracer` trace of the transaction from the node.
+///
+/// Returns `None` with a warning if the node doesn't support `debug_traceTransaction`.
+pub async fn node_call_frame(provider: &RetryProvider, tx_hash: TxHash) -> Option<CallFrame> {
+    let options = GethDebugTracingOptions::default()
+        .with_tracer(GethDebugBuiltInTracerType::CallTracer.into());
+    match provider.debug_trace_transaction(tx_hash, options).await {
+        Ok(GethTrace::CallTracer(frame)) => Some(frame),
+        Ok(_) => {
+            cli_warn!("the node did not return a call trace, skipping verification");
+            None
+        }
+        Err(err) => {
+            cli_warn!("could not fetch the trace of the node, skipping verification: {err}");
+            None
+        }
+    }
+}
+
+/// Compares the targets, values and outcomes of the calls of the replay with the ones of the
+/// node's `callTracer` frames.
+///
+/// The subcalls of a call are compared pairwise in order, up to the shorter of both.
+pub fn compare(arena: &CallTraceArena, frame: &CallFrame) -> Vec<Divergence> {
+    let mut divergences = vec![];
+    compare_call(arena, 0, frame, &mut vec![0], &mut divergences);
+    divergences
+}
+
+fn compare_call(
+    arena: &CallTraceArena,
+    idx: usize,
+    frame: &CallFrame,
+    path: &mut Vec<usize>,
+    divergences: &mut Vec<Divergence>,
+) {
+    let node = &arena.nodes()[idx];
+    let trace = &node.trace;
+    let mut diverge = |kind| divergences.push(Divergence { path: TracePath(path.clone()), kind });
+
+    if frame.to != Some(trace.address) {
+        diverge(DivergenceKind::Target { replay: trace.address, node: frame.to });
+    }
+    // the value of delegate calls is inherited and reported inconsistently by nodes
+    if !matches!(trace.kind, CallKind::DelegateCall | CallKind::StaticCall) {
+        let node_value = frame.value.unwrap_or_default();
+        if trace.value != node_value {
+            diverge(DivergenceKind::Value { replay: trace.value, node: node_value });
+        }
+    }
+    let node_success = frame.error.is_none();
+    if trace.success != node_success {
+        diverge(DivergenceKind::Success { replay: trace.success, node: node_success });
+    }
+    if node.children.len() != frame.calls.len() {
+        diverge(DivergenceKind::Subcalls { replay: node.children.len(), node: frame.calls.len() });
+    }
+
+    for (position, (&child, frame)) in node.children.iter().zip(&frame.calls).enumerate() {
+        path.push(position);
+        compare_call(arena, child, frame, path, divergences);
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::filter::tests::arena;
+
+    fn frame(to: Address, calls: Vec<CallFrame>) -> CallFrame {
+        CallFrame { to: Some(to), calls, ..Default::default() }
+    }
+
+    #[test]
+    fn reports_divergences() {
+        let mut arena = arena(&[(None, 100_000), (Some(0), 60_000), (Some(1), 5_000)]);
+        for (idx, node) in arena.nodes_mut().iter_mut().enumerate() {
+            node.trace.success = true;
+            node.trace.kind = CallKind::Call;
+            node.trace.address = Address::with_last_byte(idx as u8);
+        }
+        let mut node_frame = frame(
+            Address::with_last_byte(0),
+            vec![frame(
+                Address::with_last_byte(1),
+                vec![frame(Address::with_last_byte(2), vec![])],
+            )],
+        );
+        assert_eq!(compare(&arena, &node_frame), vec![]);
+
+        node_frame.calls[0].value = Some(U256::from(1));
+        node_frame.calls[0].calls[0].error = Some("execution reverted".to_string());
+        node_frame.calls.push(frame(Address::with_last_byte(3), vec![]));
+        let divergences = compare(&arena, &node_frame);
+        assert_eq!(
+            divergences.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "call 0: replay made 1 subcall(s), node made 2",
+                "call 0.0: replay transferred 0 wei, node transferred 1 wei",
+                "call 0.0.0: replay succeeded, node failed",
+            ]
+        );
+    }
+}
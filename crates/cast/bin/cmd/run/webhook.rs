@@ -0,0 +1,120 @@
+//! Posting of replay results to a webhook.
+
+use super::sqlite::ReplayRecord;
+use eyre::{Result, WrapErr};
+use reqwest::Url;
+use std::time::Duration;
+
+/// The timeout of a single request to the webhook.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The number of attempts to deliver the result before giving up.
+const ATTEMPTS: u32 = 3;
+
+/// Posts the record as JSON to `url`, retrying failed requests and server errors with an
+/// exponential backoff.
+pub async fn post_record(url: &Url, record: &ReplayRecord) -> Result<()> {
+    let client = reqwest::Client::builder().timeout(TIMEOUT).build()?;
+    let body = serde_json::to_vec(record)?;
+    let mut backoff = Duration::from_millis(500);
+    for attempt in 1..=ATTEMPTS {
+        let response = client
+            .post(url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match response {
+            Ok(_) => return Ok(()),
+            // the webhook rejected the request, retrying won't help
+            Err(err) if err.status().is_some_and(|status| status.is_client_error()) => {
+                return Err(err).wrap_err_with(|| format!("webhook {url} rejected the result"));
+            }
+            Err(err) if attempt == ATTEMPTS => {
+                return Err(err).wrap_err_with(|| {
+                    format!("failed to post the result to webhook {url} after {ATTEMPTS} attempts")
+                });
+            }
+            Err(_) => {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run::sqlite::StateGrowth;
+    use alloy_primitives::{Address, TxHash, U256};
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    /// Serves one connection per status, returning the received request bodies.
+    fn serve(statuses: &'static [u16]) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut bodies = vec![];
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 4096];
+                // read until the end of the body, whose length is given by the header
+                loop {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let len = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("content-length: "))
+                            .map_or(0, |len| len.parse().unwrap());
+                        if body.len() >= len {
+                            bodies.push(body.to_string());
+                            break;
+                        }
+                    }
+                }
+                write!(stream, "HTTP/1.1 {status} X\r\ncontent-length: 0\r\n\r\n").unwrap();
+            }
+            bodies
+        });
+        (url, handle)
+    }
+
+    fn record() -> ReplayRecord {
+        ReplayRecord {
+            tx_hash: TxHash::with_last_byte(1),
+            block_number: Some(100),
+            from: Address::ZERO,
+            to: None,
+            value: U256::ZERO,
+            success: true,
+            gas_used: 21_000,
+            touched: Default::default(),
+            transfers: vec![],
+            growth: StateGrowth::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let (url, server) = serve(&[503, 200]);
+        post_record(&url.parse().unwrap(), &record()).await.unwrap();
+        let bodies = server.join().unwrap();
+        assert_eq!(bodies.len(), 2);
+        let json: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        assert_eq!(json["gasUsed"], 21_000);
+        assert_eq!(json["txHash"], TxHash::with_last_byte(1).to_string());
+
+        let (url, server) = serve(&[400]);
+        assert!(post_record(&url.parse().unwrap(), &record()).await.is_err());
+        server.join().unwrap();
+    }
+}
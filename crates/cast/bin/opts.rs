@@ -868,7 +868,7 @@ pub enum CastSubcommand {
 
     /// Runs a published transaction in a local environment and prints the trace.
     #[command(visible_alias = "r")]
-    Run(RunArgs),
+    Run(Box<RunArgs>),
 
     /// Perform a raw JSON-RPC request.
     #[command(visible_alias = "rp")]
@@ -1,5 +1,6 @@
 //! Errors for this crate
 
+use alloy_primitives::TxHash;
 use foundry_config::Chain;
 use std::fmt;
 
@@ -32,3 +33,59 @@ impl fmt::Display for FunctionSignatureError {
 }
 
 impl std::error::Error for FunctionSignatureError {}
+
+/// An error of the replay of a transaction, see [`crate::replay::replay_transaction`].
+///
+/// The failures that callers may want to handle have their own variant, the other ones are
+/// [`ReplayError::Other`].
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The transaction isn't known by the node.
+    TxNotFound(TxHash),
+    /// The transaction is a system transaction, which are only replayed if allowed.
+    SystemTransaction(TxHash),
+    /// The transaction isn't mined yet.
+    PendingTransaction(TxHash),
+    /// The block isn't known by the node.
+    BlockNotFound(u64),
+    /// The fork of the block couldn't be created, e.g. because the node doesn't serve its state.
+    ForkSetupFailed(eyre::Report),
+    /// The transaction reverted, with its decoded revert reason.
+    ExecutionReverted {
+        reason: String,
+    },
+    /// The replay was cancelled with its [`CancelFlag`](crate::replay::progress::CancelFlag).
+    Cancelled,
+    Other(eyre::Report),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TxNotFound(hash) => write!(f, "tx not found: {hash:?}"),
+            Self::SystemTransaction(hash) => write!(f, "{hash:?} is a system transaction"),
+            Self::PendingTransaction(hash) => write!(f, "tx may still be pending: {hash:?}"),
+            Self::BlockNotFound(number) => write!(f, "block not found: {number}"),
+            Self::ForkSetupFailed(err) | Self::Other(err) => write!(f, "{err}"),
+            Self::ExecutionReverted { reason } => write!(f, "execution reverted: {reason}"),
+            Self::Cancelled => f.write_str("the replay was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // the message of the report is the one of the error itself
+            Self::ForkSetupFailed(err) | Self::Other(err) => err.chain().nth(1),
+            _ => None,
+        }
+    }
+}
+
+impl From<eyre::Report> for ReplayError {
+    /// Unwraps the replay errors returned as reports, e.g. by [`crate::replay::execute_preceding`].
+    fn from(err: eyre::Report) -> Self {
+        err.downcast().unwrap_or_else(Self::Other)
+    }
+}
@@ -49,6 +49,7 @@ pub use foundry_evm::*;
 
 pub mod base;
 pub mod errors;
+pub mod replay;
 mod rlp_converter;
 
 use rlp_converter::Item;
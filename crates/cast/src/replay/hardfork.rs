@@ -0,0 +1,112 @@
+//! Mapping of the blocks of commonly used chains to the EVM version they were executed with.
+
+use foundry_compilers::artifacts::EvmVersion;
+use Activation::{Block, Timestamp};
+
+/// The activation of a hardfork, at a block number or timestamp.
+#[derive(Clone, Copy, Debug)]
+enum Activation {
+    Block(u64),
+    Timestamp(u64),
+}
+
+/// The hardforks of Ethereum mainnet.
+const MAINNET: &[(Activation, EvmVersion)] = &[
+    (Block(0), EvmVersion::Homestead),
+    (Block(2_463_000), EvmVersion::TangerineWhistle),
+    (Block(2_675_000), EvmVersion::SpuriousDragon),
+    (Block(4_370_000), EvmVersion::Byzantium),
+    (Block(7_280_000), EvmVersion::Petersburg),
+    (Block(9_069_000), EvmVersion::Istanbul),
+    (Block(12_244_000), EvmVersion::Berlin),
+    (Block(12_965_000), EvmVersion::London),
+    (Block(15_537_394), EvmVersion::Paris),
+    (Block(17_034_870), EvmVersion::Shanghai),
+    (Block(19_426_587), EvmVersion::Cancun),
+];
+
+/// The hardforks of Sepolia, which started at London.
+const SEPOLIA: &[(Activation, EvmVersion)] = &[
+    (Block(0), EvmVersion::London),
+    (Block(1_735_371), EvmVersion::Paris),
+    (Timestamp(1_677_557_088), EvmVersion::Shanghai),
+    (Timestamp(1_706_655_072), EvmVersion::Cancun),
+];
+
+/// The hardforks of Optimism since Bedrock, the legacy blocks before it can't be replayed.
+const OPTIMISM: &[(Activation, EvmVersion)] = &[
+    (Block(105_235_063), EvmVersion::Paris),
+    // Canyon
+    (Timestamp(1_704_992_401), EvmVersion::Shanghai),
+    // Ecotone
+    (Timestamp(1_710_374_401), EvmVersion::Cancun),
+];
+
+/// The hardforks of Base, which started at Bedrock.
+const BASE: &[(Activation, EvmVersion)] = &[
+    (Block(0), EvmVersion::Paris),
+    // Canyon
+    (Timestamp(1_704_992_401), EvmVersion::Shanghai),
+    // Ecotone
+    (Timestamp(1_710_374_401), EvmVersion::Cancun),
+];
+
+/// The ArbOS upgrades of Arbitrum One since Nitro, the classic blocks before it can't be
+/// replayed.
+const ARBITRUM: &[(Activation, EvmVersion)] = &[
+    (Block(22_207_817), EvmVersion::London),
+    // ArbOS 11
+    (Block(184_097_479), EvmVersion::Shanghai),
+    // ArbOS 20
+    (Block(190_301_729), EvmVersion::Cancun),
+];
+
+/// The hardforks of Polygon PoS.
+const POLYGON: &[(Activation, EvmVersion)] = &[
+    (Block(0), EvmVersion::Petersburg),
+    (Block(3_395_000), EvmVersion::Istanbul),
+    (Block(14_750_000), EvmVersion::Berlin),
+    (Block(23_850_000), EvmVersion::London),
+    (Block(50_523_000), EvmVersion::Shanghai),
+    // Napoli
+    (Block(54_876_000), EvmVersion::Cancun),
+];
+
+/// Returns the EVM version the block `number` with `timestamp` of the chain was executed with, or
+/// `None` if the chain or block is unknown.
+pub fn evm_version(chain_id: u64, number: u64, timestamp: u64) -> Option<EvmVersion> {
+    let hardforks = match chain_id {
+        1 => MAINNET,
+        11155111 => SEPOLIA,
+        10 => OPTIMISM,
+        8453 => BASE,
+        42161 => ARBITRUM,
+        137 => POLYGON,
+        _ => return None,
+    };
+    hardforks
+        .iter()
+        .rev()
+        .find(|(activation, _)| match *activation {
+            Block(block) => number >= block,
+            Timestamp(activated) => timestamp >= activated,
+        })
+        .map(|&(_, evm_version)| evm_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_blocks_to_evm_versions() {
+        assert_eq!(evm_version(1, 12_964_999, 0), Some(EvmVersion::Berlin));
+        assert_eq!(evm_version(1, 17_034_870, 0), Some(EvmVersion::Shanghai));
+        assert_eq!(evm_version(1, 20_000_000, 0), Some(EvmVersion::Cancun));
+        assert_eq!(evm_version(11155111, 2_000_000, 1_677_557_087), Some(EvmVersion::Paris));
+        assert_eq!(evm_version(8453, 1, 1_704_992_401), Some(EvmVersion::Shanghai));
+        assert_eq!(evm_version(10, 105_235_062, 0), None);
+        assert_eq!(evm_version(137, 40_000_000, 0), Some(EvmVersion::London));
+        assert_eq!(evm_version(31337, 1, 0), None);
+    }
+}
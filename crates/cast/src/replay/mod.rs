@@ -0,0 +1,1115 @@
+//! Replaying of mined transactions on a fork of the block they were mined in, as done by
+//! `cast run`.
+
+use crate::errors::ReplayError;
+use alloy_primitives::{Address, Bytes, Log, TxHash, U256};
+use alloy_provider::Provider;
+use alloy_rpc_types::{Block, BlockTransactions, Transaction};
+use alloy_serde::WithOtherFields;
+use eyre::{Result, WrapErr};
+use foundry_cli::utils::TraceResult;
+use foundry_common::provider::RetryProvider;
+use foundry_compilers::artifacts::EvmVersion;
+use foundry_config::Config;
+use foundry_evm::{
+    decode::{decode_console_logs, RevertDecoder},
+    executors::{EvmError, RawCallResult, TracingExecutor},
+    fork::CreateFork,
+    inspectors::SharedCallObserver,
+    opts::EvmOpts,
+    revm::primitives::{Env, EnvWithHandlerCfg},
+    traces::TraceKind,
+    utils::configure_tx_env,
+};
+use foundry_tweak::{tweak_backend, AppliedTweak, TweakData};
+use progress::{CancelFlag, ProgressCallback};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub mod hardfork;
+pub mod prefetch;
+pub mod progress;
+pub mod set_code;
+pub mod system_tx;
+
+/// The options of [`replay_transaction`].
+#[derive(Clone, Debug, Default)]
+pub struct ReplayOptions {
+    /// Executes the transaction on the state of the parent block, without the preceding
+    /// transactions of its block.
+    pub quick: bool,
+    /// Records the steps of the execution, e.g. for the debugger.
+    pub debug: bool,
+    /// The EVM version, derived from the chain and block if not set.
+    pub evm_version: Option<EvmVersion>,
+    /// Replays system transactions: the replayed one instead of rejecting it, and the preceding
+    /// ones instead of skipping them.
+    pub allow_system_tx: bool,
+    /// The recipient of the base fee of the transactions, which is burned if not set.
+    pub basefee_recipient: Option<Address>,
+    /// The maximum number of preceding transactions of the block to execute.
+    pub stop_after: Option<usize>,
+    /// The sender to impersonate instead of the original one.
+    pub from: Option<Address>,
+    /// The input to replay the transaction with instead of the original one.
+    pub calldata: Option<Bytes>,
+    /// Skips the preceding transactions that fail to execute instead of failing the replay.
+    pub skip_errors: bool,
+    /// Replays the transaction without the nonce, balance and base fee checks, see
+    /// [`disable_checks`]. The preceding transactions are still checked.
+    pub disable_checks: bool,
+    /// The code tweaks applied to the forked state before the preceding transactions are
+    /// executed, see [`tweak_backend`].
+    pub tweaks: TweakData,
+    /// The block to fork the state from instead of the parent block, see [`fork_block_number`].
+    pub fork_block: Option<u64>,
+    /// Returns [`ReplayError::ExecutionReverted`] if the transaction reverts, instead of its
+    /// result.
+    pub fail_on_revert: bool,
+    /// Observes the call frames of the replayed transaction, but not the ones of the preceding
+    /// transactions.
+    pub call_observer: Option<SharedCallObserver>,
+    /// Reports the progress of the execution of the preceding transactions.
+    pub progress: Option<ProgressCallback>,
+    /// Cancels the replay, see [`CancelFlag`].
+    pub cancel: Option<CancelFlag>,
+}
+
+/// Hooks into the steps of a replay, to change the environment or the state in ways the
+/// [`ReplayOptions`] don't cover, e.g. to override the state of accounts. Every hook does nothing
+/// by default.
+pub trait ReplayHooks {
+    /// Called once the environment of the block is configured, before the executor is created.
+    fn configure_block(&mut self, _env: &mut Env) {}
+
+    /// Called once the state is forked and the [`ReplayOptions::tweaks`] are applied, before the
+    /// preceding transactions are executed.
+    fn prepare_state(&mut self, _executor: &mut TracingExecutor) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once the environment of the replayed transaction is configured, right before it is
+    /// executed.
+    fn before_target(
+        &mut self,
+        _executor: &mut TracingExecutor,
+        _env: &EnvWithHandlerCfg,
+        _tx: &WithOtherFields<Transaction>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with the warnings about the replay that don't fail it, e.g. that the delegations of
+    /// set-code transactions are emulated.
+    fn warn(&mut self, _warning: &str) {}
+}
+
+impl ReplayHooks for () {}
+
+/// The options of [`execute_preceding`].
+#[derive(Clone, Debug, Default)]
+pub struct PrecedingOptions {
+    /// The recipient of the base fee of the transactions, which is burned if not set.
+    pub basefee_recipient: Option<Address>,
+    /// Executes the system transactions instead of skipping them.
+    pub allow_system_tx: bool,
+    /// Skips the transactions that fail to execute instead of returning the error. Failed system
+    /// transactions are always skipped.
+    pub skip_errors: bool,
+    /// Reports the progress of the execution, e.g. to a [`ProgressCallback::progress_bar`].
+    pub progress: Option<ProgressCallback>,
+    /// Decodes the `console.log` messages of the transactions.
+    pub console_logs: bool,
+    /// Stops the execution before the next transaction once cancelled.
+    pub cancel: Option<CancelFlag>,
+    /// Executes the transactions without the nonce, balance and base fee checks, see
+    /// [`disable_checks`].
+    pub disable_checks: bool,
+}
+
+/// The outcome of [`execute_preceding`].
+#[derive(Clone, Debug, Default)]
+pub struct PrecedingResult {
+    /// The transactions that were skipped because they failed.
+    pub skipped: Vec<SkippedTx>,
+    /// The number of system transactions that were skipped without being executed, unless
+    /// [`PrecedingOptions::allow_system_tx`] is set.
+    pub skipped_system: usize,
+    /// The decoded `console.log` messages of the transactions that logged any, in block order,
+    /// if [`PrecedingOptions::console_logs`] is set.
+    pub console_logs: Vec<(TxHash, Vec<String>)>,
+}
+
+/// A preceding transaction that was skipped because it failed to execute.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedTx {
+    pub hash: TxHash,
+    pub error: String,
+}
+
+impl fmt::Display for SkippedTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.hash, self.error)
+    }
+}
+
+/// Returns the block to fork the state from to replay a transaction of block `tx_block_number`:
+/// `fork_block` if set, and the parent block otherwise.
+///
+/// Errors if `fork_block` is after the block of the transaction. Only the preceding transactions of
+/// the block of the transaction are executed on top of the forked state, whatever its block.
+pub fn fork_block_number(tx_block_number: u64, fork_block: Option<u64>) -> Result<u64> {
+    match fork_block {
+        Some(fork_block) if fork_block > tx_block_number => eyre::bail!(
+            "the fork block {fork_block} is after the block {tx_block_number} of the transaction"
+        ),
+        Some(fork_block) => Ok(fork_block),
+        None => Ok(tx_block_number.saturating_sub(1)),
+    }
+}
+
+/// Replays the mined transaction `tx_hash` on a fork of its parent block, or of
+/// [`ReplayOptions::fork_block`], after the preceding transactions of its block unless
+/// [`ReplayOptions::quick`] is set.
+///
+/// The fork is created from `config` and `evm_opts`, whose fork block is overridden. The decoded
+/// `console.log` messages of the transaction are in [`TraceResult::logs`], and the preceding
+/// transactions skipped with [`ReplayOptions::skip_errors`] in its `skippedTransactions` report.
+///
+/// The failures callers may want to handle, e.g. a pending transaction, have their own variant of
+/// [`ReplayError`].
+///
+/// # Example
+///
+/// ```no_run
+/// use cast::replay::{replay_transaction, ReplayOptions};
+/// use foundry_common::provider::ProviderBuilder;
+/// use foundry_config::Config;
+/// use foundry_evm::opts::EvmOpts;
+///
+/// # async fn foo() -> eyre::Result<()> {
+/// let config = Config { eth_rpc_url: Some("http://localhost:8545".into()), ..Default::default() };
+/// let evm_opts = EvmOpts { fork_url: config.eth_rpc_url.clone(), ..Default::default() };
+/// let provider = ProviderBuilder::new("http://localhost:8545").build()?;
+/// let tx_hash = "0xe5a5df2bfd4c5e6f2c7ab0e1e3e2ad0d0ff3a3fe5bb9f0e1bd9cfef3e1a4c8d2".parse()?;
+/// let result =
+///     replay_transaction(&config, evm_opts, &provider, tx_hash, &ReplayOptions::default())
+///         .await?;
+/// println!("success: {}, gas used: {}", result.success, result.gas_used);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn replay_transaction(
+    config: &Config,
+    evm_opts: EvmOpts,
+    provider: &RetryProvider,
+    tx_hash: TxHash,
+    options: &ReplayOptions,
+) -> Result<TraceResult, ReplayError> {
+    replay_transaction_with(config, evm_opts, provider, tx_hash, options, &mut ()).await
+}
+
+/// Same as [`replay_transaction`], but calls the `hooks` at every step of the replay.
+pub async fn replay_transaction_with(
+    config: &Config,
+    evm_opts: EvmOpts,
+    provider: &RetryProvider,
+    tx_hash: TxHash,
+    options: &ReplayOptions,
+    hooks: &mut dyn ReplayHooks,
+) -> Result<TraceResult, ReplayError> {
+    let tx = provider
+        .get_transaction_by_hash(tx_hash)
+        .await
+        .wrap_err_with(|| format!("tx not found: {tx_hash:?}"))?
+        .ok_or(ReplayError::TxNotFound(tx_hash))?;
+    if !options.allow_system_tx && system_tx::is_system_tx(&tx) {
+        return Err(ReplayError::SystemTransaction(tx_hash));
+    }
+    let tx_block_number = tx.block_number.ok_or(ReplayError::PendingTransaction(tx_hash))?;
+    let block = provider
+        .get_block(tx_block_number.into(), true.into())
+        .await
+        .map_err(eyre::Report::from)?;
+
+    // we need to fork off the parent block
+    let mut config = config.clone();
+    config.fork_block_number = Some(fork_block_number(tx_block_number, options.fork_block)?);
+    let (env, fork, _) = TracingExecutor::get_fork_material(&config, evm_opts)
+        .await
+        .map_err(ReplayError::ForkSetupFailed)?;
+
+    let block_txs = match block.as_ref().map(|block| &block.transactions) {
+        Some(BlockTransactions::Full(txs)) => txs.as_slice(),
+        _ => &[],
+    };
+    let all_txs = block_txs.iter().chain([&tx.inner]);
+    let mut replayer =
+        BlockReplayer::new(env, fork, tx_block_number, block.as_ref(), all_txs, options, hooks);
+    replayer.prepare_state(options, hooks)?;
+
+    let mut skipped = vec![];
+    if !options.quick {
+        let block = block.as_ref().ok_or(ReplayError::BlockNotFound(tx_block_number))?;
+        let BlockTransactions::Full(txs) = &block.transactions else {
+            return Err(eyre::eyre!("Could not get block txs").into());
+        };
+        let preceding = txs
+            .iter()
+            .position(|block_tx| block_tx.hash == tx_hash)
+            .ok_or_else(|| eyre::eyre!("{tx_hash:?} not found in block {tx_block_number}"))?;
+        let end = options.stop_after.map_or(preceding, |stop_after| preceding.min(stop_after));
+        let preceding_options = PrecedingOptions {
+            basefee_recipient: options.basefee_recipient,
+            allow_system_tx: options.allow_system_tx,
+            skip_errors: options.skip_errors,
+            progress: options.progress.clone(),
+            cancel: options.cancel.clone(),
+            ..Default::default()
+        };
+        skipped = replayer.execute_preceding(&txs[..end], &preceding_options)?.skipped;
+    }
+
+    if options.cancel.as_ref().is_some_and(CancelFlag::is_cancelled) {
+        return Err(ReplayError::Cancelled);
+    }
+
+    if let Some(from) = options.from {
+        fund_impersonated(&mut replayer.executor, &tx, from)?;
+    }
+    let (raw, trace_kind, _) = replayer.replay_target(&tx, false, options, hooks)?;
+    if options.fail_on_revert && raw.reverted {
+        let reason = RevertDecoder::new().decode(&raw.result, Some(raw.exit_reason));
+        return Err(ReplayError::ExecutionReverted { reason });
+    }
+
+    let mut result = TraceResult::from_raw(raw, trace_kind);
+    if !skipped.is_empty() {
+        let skipped = serde_json::to_value(&skipped).map_err(eyre::Report::from)?;
+        result.reports.insert("skippedTransactions".to_string(), skipped);
+    }
+    Ok(result)
+}
+
+/// The replay of transactions of a block on a fork of the state before it: the steps of
+/// [`replay_transaction`], for callers that replay several transactions of the block or inspect
+/// the state in between, e.g. `cast run`.
+pub struct BlockReplayer {
+    pub executor: TracingExecutor,
+    /// The environment of the block, in which the transactions are configured.
+    pub env: EnvWithHandlerCfg,
+    /// The EVM version of the replay, derived from the chain and block unless
+    /// [`ReplayOptions::evm_version`] is set.
+    pub evm_version: Option<EvmVersion>,
+}
+
+impl BlockReplayer {
+    /// Creates the executor of the replay of block `number` on the `fork` of the state before it,
+    /// or on a local state without `fork`.
+    ///
+    /// `txs` are the transactions executed in the block. If any is a set-code transaction, the
+    /// spec that supports them is selected, see [`set_code::enable_set_code`], and
+    /// [`ReplayHooks::warn`] is called since their delegations are emulated.
+    pub fn new<'a>(
+        mut env: Env,
+        fork: Option<CreateFork>,
+        number: u64,
+        block: Option<&Block>,
+        txs: impl Iterator<Item = &'a Transaction> + Clone,
+        options: &ReplayOptions,
+        hooks: &mut dyn ReplayHooks,
+    ) -> Self {
+        let mut evm_version = options.evm_version;
+        configure_block_env(&mut env, number, block, &mut evm_version);
+        hooks.configure_block(&mut env);
+
+        let mut executor = TracingExecutor::new(env.clone(), fork, evm_version, options.debug);
+        if txs.clone().any(set_code::is_set_code_tx) {
+            set_code::enable_set_code(&mut executor, txs);
+            hooks.warn(
+                "Emulating the delegations of EIP-7702 set-code transactions, whose authorization \
+                 gas isn't charged. Results may differ from the live execution!",
+            );
+        }
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+        Self { executor, env, evm_version }
+    }
+
+    /// Applies the [`ReplayOptions::tweaks`] to the state, then calls
+    /// [`ReplayHooks::prepare_state`]. Returns the applied tweaks.
+    pub fn prepare_state(
+        &mut self,
+        options: &ReplayOptions,
+        hooks: &mut dyn ReplayHooks,
+    ) -> Result<Vec<AppliedTweak>> {
+        let applied = tweak_backend(self.executor.backend_mut(), &options.tweaks)?;
+        hooks.prepare_state(&mut self.executor)?;
+        Ok(applied)
+    }
+
+    /// Executes the preceding transactions `txs`, see [`execute_preceding`].
+    pub fn execute_preceding(
+        &mut self,
+        txs: &[Transaction],
+        options: &PrecedingOptions,
+    ) -> Result<PrecedingResult> {
+        execute_preceding(&mut self.executor, &mut self.env, txs, options)
+    }
+
+    /// Executes the replayed transaction `tx` with the [`ReplayOptions`] of the target, e.g. its
+    /// sender and input, and returns the same as [`execute_target`].
+    ///
+    /// A `pending` transaction is replayed without nonce and base fee checks, see
+    /// [`configure_target_env`]. The checks are restored for the following transactions, and the
+    /// base fee is credited to [`ReplayOptions::basefee_recipient`] if set.
+    pub fn replay_target(
+        &mut self,
+        tx: &WithOtherFields<Transaction>,
+        pending: bool,
+        options: &ReplayOptions,
+        hooks: &mut dyn ReplayHooks,
+    ) -> Result<(RawCallResult, TraceKind, Option<Address>)> {
+        let Self { executor, env, .. } = self;
+        let input = options.calldata.as_ref().unwrap_or(&tx.input);
+        let disable_base_fee =
+            configure_target_env(executor, env, tx, options.from, input, pending)?;
+        let disable_balance_check = env.cfg.disable_balance_check;
+        let result = hooks.before_target(executor, env, tx).and_then(|()| {
+            if options.disable_checks {
+                disable_checks(env);
+            }
+            executor.set_call_observer(options.call_observer.clone());
+            let result = execute_target(executor, env, tx);
+            executor.set_call_observer(None);
+            result
+        });
+        env.cfg.disable_base_fee = disable_base_fee;
+        env.cfg.disable_balance_check = disable_balance_check;
+        let (raw, trace_kind, created) = result?;
+        if let Some(recipient) = options.basefee_recipient {
+            credit_base_fee(executor, recipient, &raw.env, raw.gas_used)?;
+        }
+        Ok((raw, trace_kind, created))
+    }
+}
+
+/// Configures the environment of the block the transaction is replayed in, and derives the EVM
+/// version from the chain and block if it is not set.
+pub fn configure_block_env(
+    env: &mut Env,
+    number: u64,
+    block: Option<&Block>,
+    evm_version: &mut Option<EvmVersion>,
+) {
+    env.block.number = U256::from(number);
+
+    if let Some(block) = block {
+        env.block.timestamp = U256::from(block.header.timestamp);
+        env.block.coinbase = block.header.miner;
+        env.block.difficulty = block.header.difficulty;
+        env.block.prevrandao = Some(block.header.mix_hash.unwrap_or_default());
+        env.block.basefee = U256::from(block.header.base_fee_per_gas.unwrap_or_default());
+        env.block.gas_limit = U256::from(block.header.gas_limit);
+        // the blob base fee of EIP-4844, derived from the excess blob gas
+        if let Some(excess_blob_gas) = block.header.excess_blob_gas {
+            env.block.set_blob_excess_gas_and_price(excess_blob_gas as u64);
+        }
+
+        if evm_version.is_none() {
+            *evm_version = hardfork::evm_version(env.cfg.chain_id, number, block.header.timestamp)
+                .or_else(|| {
+                    // for unknown chains, a block with excess_blob_gas is assumed to be Cancun
+                    block.header.excess_blob_gas.is_some().then_some(EvmVersion::Cancun)
+                });
+        }
+    }
+}
+
+/// Executes the preceding transactions `txs`, skipping system transactions unless
+/// [`PrecedingOptions::allow_system_tx`] is set, and returns the transactions that were skipped
+/// because they failed along with the decoded console logs.
+///
+/// The base fee of every transaction is credited to [`PrecedingOptions::basefee_recipient`] if set,
+/// and burned otherwise. Returns [`ReplayError::Cancelled`] once [`PrecedingOptions::cancel`] is
+/// cancelled.
+pub fn execute_preceding(
+    executor: &mut TracingExecutor,
+    env: &mut EnvWithHandlerCfg,
+    txs: &[Transaction],
+    options: &PrecedingOptions,
+) -> Result<PrecedingResult> {
+    let report = |done| {
+        if let Some(progress) = &options.progress {
+            progress.report(done, txs.len());
+        }
+    };
+
+    let mut result = PrecedingResult::default();
+    for (index, tx) in txs.iter().enumerate() {
+        if options.cancel.as_ref().is_some_and(CancelFlag::is_cancelled) {
+            return Err(ReplayError::Cancelled.into());
+        }
+        // System transactions such as on L2s don't contain any pricing info so
+        // we skip them otherwise this would cause
+        // reverts
+        let system = system_tx::is_system_tx(tx);
+        if system && !options.allow_system_tx {
+            result.skipped_system += 1;
+            report(index + 1);
+            continue;
+        }
+
+        configure_tx_env(env, tx);
+        let (disable_base_fee, disable_balance_check) =
+            (env.cfg.disable_base_fee, env.cfg.disable_balance_check);
+        if system {
+            system_tx::configure_system_tx_env(env);
+        } else {
+            set_code::prepare_tx(executor, env, tx)?;
+        }
+        if options.disable_checks {
+            disable_checks(env);
+        }
+        let executed = execute_block_tx(executor, env, tx);
+        env.cfg.disable_base_fee = disable_base_fee;
+        env.cfg.disable_balance_check = disable_balance_check;
+        let (gas_used, logs) = match executed {
+            Ok(executed) => executed,
+            // e.g. a deposit transferring the ETH it mints, which isn't part of the block txs
+            Err(err) if system || options.skip_errors => {
+                result.skipped.push(SkippedTx { hash: tx.hash, error: format!("{err:#}") });
+                report(index + 1);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        if let Some(recipient) = options.basefee_recipient {
+            credit_base_fee(executor, recipient, env, gas_used)?;
+        }
+        if options.console_logs {
+            let console_logs = decode_console_logs(&logs);
+            if !console_logs.is_empty() {
+                result.console_logs.push((tx.hash, console_logs));
+            }
+        }
+
+        report(index + 1);
+    }
+
+    Ok(result)
+}
+
+/// Executes a preceding transaction of the block configured in `env`, returning its gas used and
+/// logs.
+fn execute_block_tx(
+    executor: &mut TracingExecutor,
+    env: &EnvWithHandlerCfg,
+    tx: &Transaction,
+) -> Result<(u64, Vec<Log>)> {
+    if let Some(to) = tx.to {
+        tracing::trace!(tx=?tx.hash,?to, "executing previous call transaction");
+        let raw = executor.transact_with_env(env.clone()).wrap_err_with(|| {
+            format!("Failed to execute transaction: {:?} in block {}", tx.hash, env.block.number)
+        })?;
+        Ok((raw.gas_used, raw.logs))
+    } else {
+        tracing::trace!(tx=?tx.hash, "executing previous create transaction");
+        match executor.deploy_with_env(env.clone(), None) {
+            Ok(res) => Ok((res.raw.gas_used, res.raw.logs)),
+            // Reverted transactions should be skipped
+            Err(EvmError::Execution(err)) => Ok((err.raw.gas_used, err.raw.logs)),
+            Err(error) => Err(error).wrap_err_with(|| {
+                format!("Failed to deploy transaction: {:?} in block {}", tx.hash, env.block.number)
+            }),
+        }
+    }
+}
+
+/// Configures `env` for the replayed transaction `tx`, sent by `from` instead of its sender and
+/// with `input` as its input, and returns the base fee check setting to restore after its
+/// execution.
+///
+/// The ETH minted by a deposit transaction is credited to its sender, and the delegations of a
+/// set-code transaction are applied, see [`set_code`]. A `pending` transaction is
+/// replayed without nonce and base fee checks, since it may not be includable in the latest block.
+pub fn configure_target_env(
+    executor: &mut TracingExecutor,
+    env: &mut EnvWithHandlerCfg,
+    tx: &WithOtherFields<Transaction>,
+    from: Option<Address>,
+    input: &Bytes,
+    pending: bool,
+) -> Result<bool> {
+    configure_tx_env(env, tx);
+    let disable_base_fee = env.cfg.disable_base_fee;
+    if system_tx::is_system_tx(tx) {
+        system_tx::configure_system_tx_env(env);
+        system_tx::mint_deposit(executor, tx)?;
+    } else {
+        set_code::prepare_tx(executor, env, tx)?;
+    }
+    if pending {
+        env.tx.nonce = None;
+        env.cfg.disable_base_fee = true;
+    }
+    if let Some(from) = from {
+        env.tx.caller = from;
+        env.tx.nonce = None;
+    }
+    env.tx.data = input.clone();
+    Ok(disable_base_fee)
+}
+
+/// Disables the validity checks of the transaction configured in `env`: its nonce isn't checked
+/// against the sender's, and it may pay less than the base fee or more than the sender's balance.
+///
+/// This is meant for forensic replays of transactions that fail these checks on the replayed
+/// state, the execution may then diverge from the one of the chain. The base fee and balance
+/// checks need to be restored for the following transactions.
+pub fn disable_checks(env: &mut Env) {
+    env.tx.nonce = None;
+    env.cfg.disable_base_fee = true;
+    env.cfg.disable_balance_check = true;
+}
+
+/// Executes the replayed transaction `tx` configured in `env`, returning its result, the kind of
+/// its trace and the address of the contract it created, if any.
+///
+/// A reverted creation is not an error, its result is returned as for a reverted call.
+pub fn execute_target(
+    executor: &mut TracingExecutor,
+    env: &EnvWithHandlerCfg,
+    tx: &Transaction,
+) -> Result<(RawCallResult, TraceKind, Option<Address>)> {
+    if let Some(to) = tx.to {
+        tracing::trace!(tx=?tx.hash, to=?to, "executing call transaction");
+        Ok((executor.transact_with_env(env.clone())?, TraceKind::Execution, None))
+    } else {
+        tracing::trace!(tx=?tx.hash, "executing create transaction");
+        match executor.deploy_with_env(env.clone(), None) {
+            Ok(res) => Ok((res.raw, TraceKind::Deployment, Some(res.address))),
+            Err(EvmError::Execution(err)) => Ok((err.raw, TraceKind::Deployment, None)),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Funds the impersonated `sender` of the transaction with the balance it lacks to pay for the gas
+/// and value of the transaction.
+pub fn fund_impersonated(
+    executor: &mut TracingExecutor,
+    tx: &Transaction,
+    sender: Address,
+) -> Result<()> {
+    let gas_cost = U256::from(tx.gas).saturating_mul(U256::from(tx.gas_price.unwrap_or_default()));
+    let cost = gas_cost.saturating_add(tx.value);
+    if executor.get_balance(sender)? < cost {
+        executor.set_balance(sender, cost)?;
+    }
+    Ok(())
+}
+
+/// Credits the base fee of a transaction that used `gas_used` to `recipient`.
+pub fn credit_base_fee(
+    executor: &mut TracingExecutor,
+    recipient: Address,
+    env: &EnvWithHandlerCfg,
+    gas_used: u64,
+) -> Result<()> {
+    let fee = env.block.basefee * U256::from(gas_used);
+    if !fee.is_zero() {
+        let balance = executor.get_balance(recipient)?;
+        executor.set_balance(recipient, balance + fee)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_errors_keep_their_messages() {
+        let hash = TxHash::with_last_byte(1);
+        let err = eyre::Report::from(ReplayError::PendingTransaction(hash));
+        assert_eq!(err.to_string(), format!("tx may still be pending: {hash:?}"));
+        let downcast = err.downcast_ref::<ReplayError>();
+        assert!(matches!(downcast, Some(ReplayError::PendingTransaction(h)) if *h == hash));
+
+        let fork = eyre::eyre!("connection refused").wrap_err("failed to fork");
+        let err = eyre::Report::from(ReplayError::ForkSetupFailed(fork));
+        let chain = err.chain().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(chain, ["failed to fork", "connection refused"]);
+    }
+
+    #[test]
+    fn credits_base_fee_to_recipient() {
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        let mut env = Env::default();
+        env.block.basefee = U256::from(10);
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+
+        let recipient = Address::with_last_byte(1);
+        executor.set_balance(recipient, U256::from(5)).unwrap();
+        credit_base_fee(&mut executor, recipient, &env, 21_000).unwrap();
+        assert_eq!(executor.get_balance(recipient).unwrap(), U256::from(210_005));
+    }
+
+    #[test]
+    fn funds_impersonated_sender() {
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        let tx = Transaction {
+            gas: 21_000,
+            gas_price: Some(10),
+            value: U256::from(5),
+            ..Default::default()
+        };
+        let sender = Address::with_last_byte(1);
+        fund_impersonated(&mut executor, &tx, sender).unwrap();
+        assert_eq!(executor.get_balance(sender).unwrap(), U256::from(210_005));
+
+        // a sufficient balance is left untouched
+        executor.set_balance(sender, U256::from(1_000_000)).unwrap();
+        fund_impersonated(&mut executor, &tx, sender).unwrap();
+        assert_eq!(executor.get_balance(sender).unwrap(), U256::from(1_000_000));
+    }
+
+    #[test]
+    fn forks_parent_block_by_default() {
+        assert_eq!(fork_block_number(100, None).unwrap(), 99);
+        assert_eq!(fork_block_number(100, Some(90)).unwrap(), 90);
+        assert_eq!(fork_block_number(100, Some(100)).unwrap(), 100);
+        assert!(fork_block_number(100, Some(101)).is_err());
+    }
+
+    #[test]
+    fn skips_failed_preceding_txs() {
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        let mut env = Env::default();
+        env.block.gas_limit = U256::from(30_000_000);
+        let mut env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+        // the sender can't pay for the gas
+        let tx = Transaction {
+            hash: TxHash::repeat_byte(1),
+            from: Address::repeat_byte(0x22),
+            to: Some(Address::repeat_byte(0x11)),
+            gas: 21_000,
+            gas_price: Some(10),
+            ..Default::default()
+        };
+        let txs = [tx];
+
+        let options = PrecedingOptions::default();
+        assert!(execute_preceding(&mut executor, &mut env, &txs, &options).is_err());
+
+        let options = PrecedingOptions { skip_errors: true, ..options };
+        let result = execute_preceding(&mut executor, &mut env, &txs, &options).unwrap();
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].hash, TxHash::repeat_byte(1));
+        assert_eq!(result.skipped_system, 0);
+    }
+
+    #[test]
+    fn reports_progress_until_cancelled() {
+        use std::sync::{Arc, Mutex};
+
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        let mut env = EnvWithHandlerCfg::new_with_spec_id(Box::default(), executor.spec_id());
+        // deposits, skipped as system transactions
+        let deposit = Transaction {
+            transaction_type: Some(foundry_common::SYSTEM_TRANSACTION_TYPE),
+            ..Default::default()
+        };
+        let txs = [deposit.clone(), deposit];
+
+        let cancel = CancelFlag::new();
+        let reported = Arc::new(Mutex::new(vec![]));
+        let progress = ProgressCallback::new({
+            let (reported, cancel) = (reported.clone(), cancel.clone());
+            move |done, total| {
+                reported.lock().unwrap().push((done, total));
+                cancel.cancel();
+            }
+        });
+        let options = PrecedingOptions {
+            progress: Some(progress),
+            cancel: Some(cancel),
+            ..Default::default()
+        };
+        let err = execute_preceding(&mut executor, &mut env, &txs, &options).unwrap_err();
+        assert!(matches!(ReplayError::from(err), ReplayError::Cancelled));
+        assert_eq!(*reported.lock().unwrap(), [(1, 2)]);
+
+        let options = PrecedingOptions::default();
+        let result = execute_preceding(&mut executor, &mut env, &txs, &options).unwrap();
+        assert_eq!(result.skipped_system, 2);
+    }
+
+    #[test]
+    fn decodes_preceding_console_logs() {
+        use crate::revm::primitives::{keccak256, AccountInfo, Bytecode};
+
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        let mut env = Env::default();
+        env.block.gas_limit = U256::from(30_000_000);
+        let mut env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+        // emits the DSTest event `log("hi")`
+        let mut code = vec![0x60, 0x20, 0x60, 0x00, 0x52, 0x60, 0x02, 0x60, 0x20, 0x52];
+        code.extend([0x61, b'h', b'i', 0x60, 0xf0, 0x1b, 0x60, 0x40, 0x52, 0x7f]);
+        code.extend(keccak256("log(string)"));
+        code.extend([0x60, 0x60, 0x60, 0x00, 0xa1, 0x00]);
+        let code = Bytecode::new_raw(code.into());
+        let contract = Address::repeat_byte(0x11);
+        let info =
+            AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() };
+        executor.backend_mut().insert_account_info(contract, info);
+        let tx = Transaction {
+            hash: TxHash::repeat_byte(1),
+            from: Address::repeat_byte(0x22),
+            to: Some(contract),
+            gas: 100_000,
+            gas_price: Some(0),
+            ..Default::default()
+        };
+        let mut txs = [tx];
+
+        let options = PrecedingOptions::default();
+        let result = execute_preceding(&mut executor, &mut env, &txs, &options).unwrap();
+        assert!(result.console_logs.is_empty());
+
+        txs[0].nonce = 1;
+        let options = PrecedingOptions { console_logs: true, ..options };
+        let result = execute_preceding(&mut executor, &mut env, &txs, &options).unwrap();
+        assert_eq!(result.console_logs, vec![(TxHash::repeat_byte(1), vec!["hi".to_string()])]);
+    }
+
+    #[test]
+    fn replays_blob_txs() {
+        use crate::revm::primitives::{calc_blob_gasprice, AccountInfo, Bytecode};
+
+        let mut block = Block::default();
+        block.header.gas_limit = 30_000_000;
+        block.header.excess_blob_gas = Some(10_000_000);
+        let mut env = Env::default();
+        let mut evm_version = Some(EvmVersion::Cancun);
+        configure_block_env(&mut env, 1, Some(&block), &mut evm_version);
+        let blob_gas_price = calc_blob_gasprice(10_000_000);
+        assert_eq!(env.block.get_blob_gasprice(), Some(blob_gas_price));
+
+        let mut executor = TracingExecutor::new(env.clone(), None, evm_version, false);
+        // returns BLOBHASH(0) and BLOBBASEFEE
+        let code = Bytecode::new_raw(Bytes::from_static(&[
+            0x60, 0x00, 0x49, 0x60, 0x00, 0x52, 0x4a, 0x60, 0x20, 0x52, 0x60, 0x40, 0x60, 0x00,
+            0xf3,
+        ]));
+        // not the address of a precompile
+        let contract = Address::repeat_byte(0x11);
+        let info =
+            AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() };
+        executor.backend_mut().insert_account_info(contract, info);
+        let sender = Address::repeat_byte(0x22);
+        executor.set_balance(sender, U256::MAX).unwrap();
+
+        let mut blob_hash = [0x11; 32];
+        blob_hash[0] = 0x01;
+        let tx = WithOtherFields::new(Transaction {
+            from: sender,
+            to: Some(contract),
+            gas: 100_000,
+            max_fee_per_blob_gas: Some(blob_gas_price),
+            blob_versioned_hashes: Some(vec![blob_hash.into()]),
+            ..Default::default()
+        });
+        let mut env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+        configure_target_env(&mut executor, &mut env, &tx, None, &tx.input, false).unwrap();
+        let (raw, _, _) = execute_target(&mut executor, &env, &tx).unwrap();
+        assert!(!raw.reverted);
+        assert_eq!(&raw.result[..32], &blob_hash);
+        assert_eq!(U256::from_be_slice(&raw.result[32..]), U256::from(blob_gas_price));
+    }
+
+    #[test]
+    fn configures_impersonated_target() {
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        let mut env = EnvWithHandlerCfg::new_with_spec_id(Box::default(), executor.spec_id());
+        let tx = WithOtherFields::new(Transaction {
+            from: Address::with_last_byte(1),
+            nonce: 3,
+            input: Bytes::from_static(&[1]),
+            ..Default::default()
+        });
+        let from = Address::with_last_byte(2);
+        let input = Bytes::from_static(&[2]);
+        let disable_base_fee =
+            configure_target_env(&mut executor, &mut env, &tx, Some(from), &input, true).unwrap();
+        assert!(!disable_base_fee);
+        assert!(env.cfg.disable_base_fee);
+        assert_eq!((env.tx.caller, env.tx.nonce, &env.tx.data), (from, None, &input));
+    }
+
+    #[test]
+    fn disables_checks() {
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        let mut env = EnvWithHandlerCfg::new_with_spec_id(Box::default(), executor.spec_id());
+        env.block.basefee = U256::from(10);
+        // a sender without balance, whose nonce doesn't match
+        let tx = WithOtherFields::new(Transaction {
+            from: Address::with_last_byte(1),
+            to: Some(Address::with_last_byte(2)),
+            nonce: 3,
+            gas: 100_000,
+            gas_price: Some(1),
+            ..Default::default()
+        });
+        configure_target_env(&mut executor, &mut env, &tx, None, &tx.input, false).unwrap();
+        assert!(execute_target(&mut executor, &env, &tx).is_err());
+
+        disable_checks(&mut env);
+        let (raw, _, _) = execute_target(&mut executor, &env, &tx).unwrap();
+        assert!(!raw.reverted);
+    }
+
+    #[test]
+    fn observes_call_frames() {
+        use crate::revm::primitives::{AccountInfo, Bytecode};
+        use foundry_evm::inspectors::{CallFrame, CallFrameOutcome, CallObserver};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Frames(Vec<(usize, Option<Address>, Option<u64>)>);
+        impl CallObserver for Frames {
+            fn call_start(&mut self, frame: &CallFrame) {
+                self.0.push((frame.depth, frame.callee, None));
+            }
+            fn call_end(&mut self, frame: &CallFrame, outcome: &CallFrameOutcome) {
+                assert!(outcome.success);
+                self.0.push((frame.depth, frame.callee, Some(outcome.gas_used)));
+            }
+        }
+
+        let mut executor = TracingExecutor::new(Env::default(), None, None, false);
+        let (contract, callee) = (Address::repeat_byte(0x11), Address::repeat_byte(0x33));
+        // CALL(gas(), callee, 0, 0, 0, 0, 0)
+        let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+        code.extend_from_slice(callee.as_slice());
+        code.extend_from_slice(&[0x5a, 0xf1, 0x00]);
+        let code = Bytecode::new_raw(Bytes::from(code));
+        let info =
+            AccountInfo { code_hash: code.hash_slow(), code: Some(code), ..Default::default() };
+        executor.backend_mut().insert_account_info(contract, info);
+
+        let frames = Arc::new(Mutex::new(Frames::default()));
+        executor.set_call_observer(Some(frames.clone().into()));
+        let tx = WithOtherFields::new(Transaction {
+            to: Some(contract),
+            gas: 100_000,
+            ..Default::default()
+        });
+        let mut env = EnvWithHandlerCfg::new_with_spec_id(Box::default(), executor.spec_id());
+        configure_target_env(&mut executor, &mut env, &tx, None, &tx.input, false).unwrap();
+        execute_target(&mut executor, &env, &tx).unwrap();
+
+        let frames = std::mem::take(&mut frames.lock().unwrap().0);
+        let depths = frames.iter().map(|(depth, callee, _)| (*depth, *callee)).collect::<Vec<_>>();
+        assert_eq!(
+            depths,
+            [(1, Some(contract)), (2, Some(callee)), (2, Some(callee)), (1, Some(contract))]
+        );
+        assert_eq!(frames[2].2, Some(0));
+        assert!(frames[3].2.unwrap() > 0);
+    }
+
+    /// Increments the value of slot 0 and returns it.
+    const COUNTER: [u8; 18] = [
+        0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x80, 0x60, 0x00, 0x55, 0x60, 0x00, 0x52, 0x60, 0x20,
+        0x60, 0x00, 0xf3,
+    ];
+
+    /// A call to the counter at `0x11..11` by `sender`, at `index` of block 10.
+    fn counter_tx(index: u8, sender: u8) -> Transaction {
+        Transaction {
+            hash: TxHash::with_last_byte(index + 1),
+            from: Address::repeat_byte(sender),
+            to: Some(Address::repeat_byte(0x11)),
+            gas: 100_000,
+            gas_price: Some(0),
+            block_number: Some(10),
+            transaction_index: Some(index.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn replays_block_with_hooks() {
+        #[derive(Default)]
+        struct Hooks {
+            before_target: usize,
+        }
+        impl ReplayHooks for Hooks {
+            fn prepare_state(&mut self, executor: &mut TracingExecutor) -> Result<()> {
+                let counter = Address::repeat_byte(0x11);
+                executor.backend_mut().insert_account_storage(
+                    counter,
+                    U256::ZERO,
+                    U256::from(40),
+                )?;
+                Ok(())
+            }
+            fn before_target(
+                &mut self,
+                _executor: &mut TracingExecutor,
+                env: &EnvWithHandlerCfg,
+                _tx: &WithOtherFields<Transaction>,
+            ) -> Result<()> {
+                assert_eq!(env.tx.caller, Address::repeat_byte(0x33));
+                self.before_target += 1;
+                Ok(())
+            }
+        }
+
+        let mut block = Block::default();
+        block.header.gas_limit = 30_000_000;
+        let preceding = counter_tx(0, 0x22);
+        let target = WithOtherFields::new(counter_tx(1, 0x33));
+        // the counter is deployed by the tweak
+        let tweaks = [(Address::repeat_byte(0x11), Bytes::from_static(&COUNTER))].into();
+        let options = ReplayOptions { tweaks, ..Default::default() };
+        let mut hooks = Hooks::default();
+        let txs = [&preceding, &target.inner];
+        let mut replayer = BlockReplayer::new(
+            Env::default(),
+            None,
+            10,
+            Some(&block),
+            txs.into_iter(),
+            &options,
+            &mut hooks,
+        );
+        assert_eq!(replayer.env.block.number, U256::from(10));
+        assert_eq!(replayer.prepare_state(&options, &mut hooks).unwrap().len(), 1);
+        replayer.execute_preceding(&[preceding], &PrecedingOptions::default()).unwrap();
+        let (raw, kind, _) = replayer.replay_target(&target, false, &options, &mut hooks).unwrap();
+        assert_eq!(
+            (U256::from_be_slice(&raw.result), kind),
+            (U256::from(42), TraceKind::Execution)
+        );
+        assert_eq!(hooks.before_target, 1);
+    }
+
+    /// Serves the JSON-RPC requests with `respond`, called with the method and the parameters of
+    /// every request, and returns the URL of the server.
+    fn serve_rpc(
+        respond: impl Fn(&str, &serde_json::Value) -> serde_json::Value + Send + Sync + 'static,
+    ) -> String {
+        use std::{
+            io::{BufRead, BufReader, Read, Write},
+            net::TcpListener,
+            sync::Arc,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let respond = Arc::new(respond);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let (stream, respond) = (stream.unwrap(), respond.clone());
+                // the connections are kept alive across requests
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut stream = stream;
+                    loop {
+                        let mut len = 0;
+                        loop {
+                            let mut line = String::new();
+                            if reader.read_line(&mut line).unwrap_or_default() == 0 {
+                                return;
+                            }
+                            let line = line.trim_end().to_ascii_lowercase();
+                            if line.is_empty() {
+                                break;
+                            }
+                            if let Some(value) = line.strip_prefix("content-length: ") {
+                                len = value.parse().unwrap();
+                            }
+                        }
+                        let mut body = vec![0; len];
+                        reader.read_exact(&mut body).unwrap();
+                        let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                        let response = |request: &serde_json::Value| {
+                            let method = request["method"].as_str().unwrap();
+                            let result = respond(method, &request["params"]);
+                            serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+                        };
+                        let response = match &request {
+                            serde_json::Value::Array(batch) => batch.iter().map(response).collect(),
+                            request => response(request),
+                        }
+                        .to_string();
+                        let head = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n",
+                            response.len()
+                        );
+                        if stream.write_all((head + &response).as_bytes()).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn replays_transaction_from_provider() {
+        use alloy_primitives::B256;
+        use foundry_common::provider::ProviderBuilder;
+
+        let (preceding, target) = (counter_tx(0, 0x22), counter_tx(1, 0x33));
+        let mut block = Block::default();
+        block.header.number = Some(10);
+        block.header.gas_limit = 30_000_000;
+        block.transactions = BlockTransactions::Full(vec![preceding, target.clone()]);
+        let block = serde_json::to_value(&block).unwrap();
+        let tx = serde_json::to_value(&target).unwrap();
+        let counter = serde_json::to_value(Address::repeat_byte(0x11)).unwrap();
+        let url = serve_rpc(move |method, params| match method {
+            "eth_chainId" => "0x1".into(),
+            "eth_gasPrice" | "eth_getBalance" | "eth_getTransactionCount" => "0x0".into(),
+            "eth_getStorageAt" => B256::ZERO.to_string().into(),
+            "eth_getBlockByNumber" => block.clone(),
+            "eth_getTransactionByHash" => tx.clone(),
+            "eth_getCode" if params[0] == counter => {
+                Bytes::from_static(&COUNTER).to_string().into()
+            }
+            "eth_getCode" => "0x".into(),
+            _ => panic!("unexpected request {method}"),
+        });
+
+        let config = Config {
+            eth_rpc_url: Some(url.clone()),
+            no_storage_caching: true,
+            ..Default::default()
+        };
+        let evm_opts = EvmOpts { memory_limit: config.memory_limit, ..Default::default() };
+        let provider = ProviderBuilder::new(&url).build().unwrap();
+        let output = |result: TraceResult| {
+            let traces = result.traces.unwrap();
+            U256::from_be_slice(&traces[0].1.nodes()[0].trace.output)
+        };
+        let options = ReplayOptions::default();
+        let result =
+            replay_transaction(&config, evm_opts.clone(), &provider, target.hash, &options)
+                .await
+                .unwrap();
+        assert!(result.success);
+        // the preceding transaction of the block incremented the counter first
+        assert_eq!(output(result), U256::from(2));
+
+        let options = ReplayOptions { quick: true, ..Default::default() };
+        let result =
+            replay_transaction(&config, evm_opts.clone(), &provider, target.hash, &options)
+                .await
+                .unwrap();
+        assert_eq!(output(result), U256::from(1));
+    }
+}
@@ -0,0 +1,146 @@
+//! Concurrent warming of a fork with the state read by the preceding transactions of a block.
+//!
+//! The preceding transactions must be executed one after the other, and every state read a
+//! transaction misses in the fork cache is a round trip to the RPC. The fork backend serves
+//! requests of several threads concurrently, so the state the transactions are likely to read is
+//! fetched upfront from a pool of threads instead.
+
+use alloy_primitives::{Address, U256};
+use alloy_rpc_types::Transaction;
+use foundry_evm::{
+    backend::Backend,
+    revm::{inspectors::NoOpInspector, primitives::EnvWithHandlerCfg, DatabaseRef},
+    utils::configure_tx_env,
+};
+use std::collections::BTreeSet;
+
+use super::system_tx;
+
+/// The number of threads fetching state concurrently.
+pub const CONCURRENCY: usize = 16;
+
+/// What was prefetched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrefetchStats {
+    /// The accounts of the senders, recipients and access lists.
+    pub accounts: usize,
+    /// The storage slots of the access lists.
+    pub slots: usize,
+    /// The transactions that were executed on the state of the parent block to warm the state
+    /// that isn't declared in access lists.
+    pub prepassed: usize,
+}
+
+/// Warms the fork of `backend` with the state the transactions `txs` are likely to read.
+///
+/// First the senders, the recipients and the access lists of the transactions are fetched, then
+/// every transaction is executed on its own copy of `backend`, as if it was the first of the
+/// block and without nonce check. The results of these executions are discarded, only the state
+/// they fetched is kept in the shared fork cache. System transactions aren't executed, and
+/// failures of the prefetching are ignored: the state is then fetched during the actual replay.
+pub fn prefetch(backend: &Backend, env: &EnvWithHandlerCfg, txs: &[Transaction]) -> PrefetchStats {
+    let mut accounts = BTreeSet::from([env.block.coinbase]);
+    let mut slots = BTreeSet::new();
+    for tx in txs {
+        accounts.insert(tx.from);
+        accounts.extend(tx.to);
+        for item in tx.access_list.iter().flat_map(|list| list.0.iter()) {
+            accounts.insert(item.address);
+            slots.extend(
+                item.storage_keys.iter().map(|slot| (item.address, U256::from_be_bytes(slot.0))),
+            );
+        }
+    }
+
+    let reads = accounts
+        .iter()
+        .map(|&address| Read::Account(address))
+        .chain(slots.iter().map(|&(address, slot)| Read::Storage(address, slot)))
+        .collect::<Vec<_>>();
+    for_each_concurrently(&reads, |read| {
+        let result = match *read {
+            Read::Account(address) => backend.basic_ref(address).map(drop),
+            Read::Storage(address, slot) => backend.storage_ref(address, slot).map(drop),
+        };
+        if let Err(err) = result {
+            tracing::trace!(?read, %err, "failed to prefetch");
+        }
+    });
+
+    let prepassed = txs.iter().filter(|tx| !system_tx::is_system_tx(tx)).collect::<Vec<_>>();
+    for_each_concurrently(&prepassed, |tx| {
+        let mut env = env.clone();
+        configure_tx_env(&mut env, tx);
+        // the preceding transactions of the sender aren't executed
+        env.tx.nonce = None;
+        if let Err(err) = backend.clone().inspect(&mut env, NoOpInspector) {
+            tracing::trace!(tx=?tx.hash, %err, "failed to prepass");
+        }
+    });
+
+    PrefetchStats { accounts: accounts.len(), slots: slots.len(), prepassed: prepassed.len() }
+}
+
+/// A state read of the prefetching.
+#[derive(Clone, Copy, Debug)]
+enum Read {
+    Account(Address),
+    Storage(Address, U256),
+}
+
+/// Calls `f` for every item, from up to [`CONCURRENCY`] threads.
+fn for_each_concurrently<T: Sync>(items: &[T], f: impl Fn(&T) + Sync) {
+    if items.is_empty() {
+        return;
+    }
+    let chunk_size = items.len().div_ceil(CONCURRENCY);
+    std::thread::scope(|scope| {
+        for chunk in items.chunks(chunk_size) {
+            let f = &f;
+            scope.spawn(move || chunk.iter().for_each(f));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn visits_every_item_once() {
+        let items = (0..100).collect::<Vec<usize>>();
+        let sum = AtomicUsize::new(0);
+        let calls = AtomicUsize::new(0);
+        for_each_concurrently(&items, |item| {
+            sum.fetch_add(*item, Ordering::Relaxed);
+            calls.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(calls.into_inner(), 100);
+        assert_eq!(sum.into_inner(), 4950);
+        for_each_concurrently(&[] as &[usize], |_| unreachable!());
+    }
+
+    #[test]
+    fn prefetches_declared_state() {
+        use alloy_primitives::B256;
+        use alloy_rpc_types::{AccessList, AccessListItem};
+
+        let backend = Backend::spawn(None);
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::default(), Default::default());
+        let mut tx = Transaction {
+            from: Address::repeat_byte(0x11),
+            to: Some(Address::repeat_byte(0x22)),
+            gas: 100_000,
+            ..Default::default()
+        };
+        tx.access_list = Some(AccessList(vec![AccessListItem {
+            address: Address::repeat_byte(0x22),
+            storage_keys: vec![B256::with_last_byte(1), B256::with_last_byte(2)],
+        }]));
+
+        let stats = prefetch(&backend, &env, &[tx]);
+        // the sender, the recipient and the coinbase
+        assert_eq!(stats, PrefetchStats { accounts: 3, slots: 2, prepassed: 1 });
+    }
+}
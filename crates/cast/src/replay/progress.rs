@@ -0,0 +1,84 @@
+//! Observation and cancellation of the execution of the preceding transactions, for the callers
+//! embedding the replay, e.g. to show their own progress or abort on shutdown.
+
+use foundry_cli::utils::init_progress;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// A callback invoked after each preceding transaction, with the number of processed transactions
+/// and their total.
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(usize, usize) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new(callback: impl Fn(usize, usize) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    /// Returns a callback updating a progress bar of `len` transactions, drawn to stderr.
+    pub fn progress_bar(len: usize) -> Self {
+        let pb = init_progress(len as u64, "tx");
+        pb.set_position(0);
+        Self::new(move |done, _| pb.set_position(done as u64))
+    }
+
+    pub(crate) fn report(&self, done: usize, total: usize) {
+        (self.0)(done, total)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// A flag shared with the replay, checked before each transaction: once cancelled, the replay
+/// returns [`ReplayError::Cancelled`](crate::errors::ReplayError::Cancelled).
+///
+/// The state of a cancelled replay is dropped with its fork, nothing outlives the call.
+#[derive(Clone, Debug, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the replays the flag was passed to.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn reports_progress_and_cancels() {
+        let reported = Arc::new(Mutex::new(vec![]));
+        let callback = ProgressCallback::new({
+            let reported = reported.clone();
+            move |done, total| reported.lock().unwrap().push((done, total))
+        });
+        callback.report(1, 2);
+        assert_eq!(*reported.lock().unwrap(), [(1, 2)]);
+
+        let flag = CancelFlag::new();
+        let shared = flag.clone();
+        assert!(!flag.is_cancelled());
+        shared.cancel();
+        assert!(flag.is_cancelled());
+    }
+}
@@ -0,0 +1,352 @@
+//! EIP-7702 set-code transactions, whose authorization lists delegate the code of their signers
+//! to contracts.
+//!
+//! The EVM doesn't support delegations yet, so they are emulated: the code of the delegate is set
+//! as the code of the delegating account, instead of the `0xef0100 || address` delegation
+//! designator. The execution of calls to the account is the same, but `EXTCODESIZE`,
+//! `EXTCODEHASH` and `EXTCODECOPY` return the code of the delegate, and the gas of the
+//! authorizations isn't charged.
+
+use alloy_primitives::{keccak256, Address, Bytes, Signature, B256, U256};
+use alloy_rlp::{Encodable, Header};
+use alloy_rpc_types::Transaction;
+use eyre::{Result, WrapErr};
+use foundry_evm::{
+    executors::TracingExecutor,
+    revm::{
+        primitives::{Bytecode, EnvWithHandlerCfg, SpecId, KECCAK_EMPTY},
+        Database, DatabaseRef,
+    },
+};
+use serde::Deserialize;
+
+/// The type of set-code transactions.
+pub const SET_CODE_TX_TYPE: u8 = 4;
+
+/// The prefix of the delegation designators, followed by the address of the delegate.
+pub const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// The prefix of the signed payload of authorizations.
+const AUTHORIZATION_MAGIC: u8 = 0x05;
+
+/// Returns whether the transaction is an EIP-7702 set-code transaction.
+pub fn is_set_code_tx(tx: &Transaction) -> bool {
+    tx.transaction_type == Some(SET_CODE_TX_TYPE)
+}
+
+/// A signed authorization of the authorization list of a set-code transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Authorization {
+    /// The chain the authorization is valid on, any chain if zero.
+    pub chain_id: U256,
+    /// The delegate, or zero to clear the delegation.
+    pub address: Address,
+    #[serde(with = "alloy_serde::quantity")]
+    pub nonce: u64,
+    #[serde(with = "alloy_serde::quantity")]
+    pub y_parity: u64,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl Authorization {
+    /// Returns the hash signed by the authority: `keccak256(0x05 || rlp([chain_id, address,
+    /// nonce]))`.
+    pub fn signature_hash(&self) -> B256 {
+        let payload_length = self.chain_id.length() + self.address.length() + self.nonce.length();
+        let mut buf = vec![AUTHORIZATION_MAGIC];
+        Header { list: true, payload_length }.encode(&mut buf);
+        self.chain_id.encode(&mut buf);
+        self.address.encode(&mut buf);
+        self.nonce.encode(&mut buf);
+        keccak256(buf)
+    }
+
+    /// Recovers the account that signed the authorization, `None` if the signature is invalid or
+    /// has a high `s`, which EIP-7702 rejects like EIP-2 for transactions.
+    pub fn authority(&self) -> Option<Address> {
+        let signature = Signature::from_rs_and_parity(self.r, self.s, self.y_parity).ok()?;
+        if signature.normalize_s().is_some() {
+            return None;
+        }
+        signature.recover_address_from_prehash(&self.signature_hash()).ok()
+    }
+}
+
+/// Returns the authorization list of the transaction, empty if it has none.
+pub fn authorization_list(tx: &Transaction) -> Result<Vec<Authorization>> {
+    match tx.other.get_deserialized::<Vec<Authorization>>("authorizationList") {
+        Some(list) => list.wrap_err_with(|| format!("invalid authorization list of {:?}", tx.hash)),
+        None => Ok(vec![]),
+    }
+}
+
+/// Selects the Prague spec if any of the transactions is a set-code transaction and the executor
+/// has an earlier spec. Returns whether the spec was changed.
+pub fn enable_set_code<'a>(
+    executor: &mut TracingExecutor,
+    mut txs: impl Iterator<Item = &'a Transaction>,
+) -> bool {
+    if executor.spec_id() >= SpecId::PRAGUE || !txs.any(is_set_code_tx) {
+        return false;
+    }
+    executor.set_spec_id(SpecId::PRAGUE);
+    true
+}
+
+/// Prepares the execution of the transaction configured in `env`: the code of its recipient is
+/// resolved if it's a delegated account, and the delegations of its authorization list are
+/// applied. Returns the number of applied delegations.
+pub fn prepare_tx(
+    executor: &mut TracingExecutor,
+    env: &mut EnvWithHandlerCfg,
+    tx: &Transaction,
+) -> Result<usize> {
+    if let Some(to) = tx.to {
+        resolve_delegation(executor, to)?;
+    }
+    if !is_set_code_tx(tx) {
+        return Ok(0);
+    }
+    apply_authorizations(executor, env, tx)
+}
+
+/// Applies the valid authorizations of the set-code transaction `tx`, in order, and bumps the
+/// nonces of their authorities. Authorizations of another chain, with an invalid signature, of an
+/// authority with code that isn't a delegation, see [`can_delegate`], or with another nonce than
+/// the one of their authority are skipped, as by the EVM.
+///
+/// The authorizations are applied before the execution, so the nonce check of the sender is
+/// disabled if it is an authority: its nonce is expected to be bumped by the transaction first.
+fn apply_authorizations(
+    executor: &mut TracingExecutor,
+    env: &mut EnvWithHandlerCfg,
+    tx: &Transaction,
+) -> Result<usize> {
+    let mut applied = 0;
+    for authorization in authorization_list(tx)? {
+        let chain_id = authorization.chain_id;
+        if !chain_id.is_zero() && chain_id != U256::from(env.cfg.chain_id) {
+            continue;
+        }
+        let Some(authority) = authorization.authority() else { continue };
+        let mut info = executor.backend_mut().basic(authority)?.unwrap_or_default();
+        if !can_delegate(executor, authority)? {
+            continue;
+        }
+        let expected_nonce = info.nonce + u64::from(authority == tx.from);
+        if authorization.nonce != expected_nonce {
+            continue;
+        }
+
+        let code = if authorization.address.is_zero() {
+            Bytes::new()
+        } else {
+            delegate_code(executor, authorization.address)?
+        };
+        info.code_hash = if code.is_empty() { KECCAK_EMPTY } else { keccak256(&code) };
+        info.code = Some(Bytecode::new_raw(code));
+        info.nonce += 1;
+        executor.backend_mut().insert_account_info(authority, info);
+        if authority == tx.from {
+            env.tx.nonce = None;
+        }
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+/// Returns whether `authority` can delegate its code, i.e. it has no code or is delegated already.
+///
+/// Since the emulated delegations replace the designator with the code of the delegate, the code
+/// is the one of the forked state, where the accounts delegated before the replay still hold their
+/// designator, or the one of the current state without a fork.
+fn can_delegate(executor: &mut TracingExecutor, authority: Address) -> Result<bool> {
+    let info = match executor.backend().active_fork_db() {
+        Some(fork) => fork.db.basic_ref(authority)?,
+        None => executor.backend_mut().basic(authority)?,
+    };
+    let code = info.and_then(|info| info.code).map(|code| code.original_bytes());
+    Ok(code.map_or(true, |code| code.is_empty() || delegate_of(&code).is_some()))
+}
+
+/// Replaces the delegation designator of `address`, if it was delegated by an earlier
+/// transaction, with the code of its delegate. Returns whether it was delegated.
+fn resolve_delegation(executor: &mut TracingExecutor, address: Address) -> Result<bool> {
+    let Some(mut info) = executor.backend_mut().basic(address)? else { return Ok(false) };
+    let code = info.code.as_ref().map(|code| code.original_bytes()).unwrap_or_default();
+    let Some(delegate) = delegate_of(&code) else { return Ok(false) };
+    let code = delegate_code(executor, delegate)?;
+    info.code_hash = if code.is_empty() { KECCAK_EMPTY } else { keccak256(&code) };
+    info.code = Some(Bytecode::new_raw(code));
+    executor.backend_mut().insert_account_info(address, info);
+    Ok(true)
+}
+
+/// Returns the delegate of a delegation designator.
+fn delegate_of(code: &[u8]) -> Option<Address> {
+    let delegate = code.strip_prefix(&DELEGATION_PREFIX)?;
+    (delegate.len() == Address::len_bytes()).then(|| Address::from_slice(delegate))
+}
+
+/// Returns the code executed for a delegation to `delegate`: its code, or none if it's itself a
+/// delegated account, since delegations aren't followed.
+fn delegate_code(executor: &mut TracingExecutor, delegate: Address) -> Result<Bytes> {
+    let info = executor.backend_mut().basic(delegate)?.unwrap_or_default();
+    let code = info.code.map(|code| code.original_bytes()).unwrap_or_default();
+    Ok(if delegate_of(&code).is_some() { Bytes::new() } else { code })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+    use foundry_evm::revm::primitives::{AccountInfo, Env};
+
+    /// The order of the secp256k1 curve.
+    const SECP256K1N: U256 = U256::from_be_bytes(alloy_primitives::hex!(
+        "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141"
+    ));
+
+    fn authorization(signer: &PrivateKeySigner, address: Address, nonce: u64) -> Authorization {
+        let mut authorization = Authorization {
+            chain_id: U256::from(1),
+            address,
+            nonce,
+            y_parity: 0,
+            r: U256::ZERO,
+            s: U256::ZERO,
+        };
+        let signature = signer.sign_hash_sync(&authorization.signature_hash()).unwrap();
+        authorization.r = signature.r();
+        authorization.s = signature.s();
+        authorization.y_parity = signature.v().y_parity() as u64;
+        authorization
+    }
+
+    fn set_code_tx(from: Address, list: &[Authorization]) -> Transaction {
+        let mut tx = Transaction {
+            from,
+            to: Some(from),
+            transaction_type: Some(SET_CODE_TX_TYPE),
+            ..Default::default()
+        };
+        tx.other.insert(
+            "authorizationList".to_string(),
+            serde_json::to_value(
+                list.iter()
+                    .map(|a| {
+                        serde_json::json!({
+                            "chainId": format!("{:#x}", a.chain_id),
+                            "address": a.address,
+                            "nonce": format!("{:#x}", a.nonce),
+                            "yParity": format!("{:#x}", a.y_parity),
+                            "r": format!("{:#x}", a.r),
+                            "s": format!("{:#x}", a.s),
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap(),
+        );
+        tx
+    }
+
+    fn executor() -> (TracingExecutor, EnvWithHandlerCfg) {
+        let mut env = Env::default();
+        env.cfg.chain_id = 1;
+        let executor = TracingExecutor::new(env.clone(), None, None, false);
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), SpecId::PRAGUE);
+        (executor, env)
+    }
+
+    #[test]
+    fn applies_authorizations() {
+        let mut env = Env::default();
+        env.cfg.chain_id = 1;
+        let mut executor = TracingExecutor::new(env.clone(), None, None, false);
+        let delegate = Address::repeat_byte(0xde);
+        let code = Bytes::from_static(&[0x60, 0x00]);
+        let info = AccountInfo {
+            code_hash: keccak256(&code),
+            code: Some(Bytecode::new_raw(code.clone())),
+            ..Default::default()
+        };
+        executor.backend_mut().insert_account_info(delegate, info);
+
+        let alice = PrivateKeySigner::random();
+        let bob = PrivateKeySigner::random();
+        let list = [
+            authorization(&alice, delegate, 0),
+            // a stale nonce
+            authorization(&bob, delegate, 5),
+        ];
+        let mut tx = set_code_tx(alice.address(), &list);
+        assert_eq!(authorization_list(&tx).unwrap(), list);
+        assert_eq!(list[0].authority(), Some(alice.address()));
+
+        assert!(enable_set_code(&mut executor, std::iter::once(&tx)));
+        assert_eq!(executor.spec_id(), SpecId::PRAGUE);
+        let mut env = EnvWithHandlerCfg::new_with_spec_id(Box::new(env), executor.spec_id());
+        env.tx.nonce = Some(0);
+        // the sender is the authority, so the authorization nonce follows the transaction's
+        assert_eq!(prepare_tx(&mut executor, &mut env, &tx).unwrap(), 0);
+
+        tx.from = Address::repeat_byte(0x11);
+        assert_eq!(prepare_tx(&mut executor, &mut env, &tx).unwrap(), 1);
+        let info = executor.backend_mut().basic(alice.address()).unwrap().unwrap();
+        assert_eq!((info.nonce, info.code.unwrap().original_bytes()), (1, code.clone()));
+        assert_eq!(env.tx.nonce, Some(0));
+
+        // a designator left by an earlier transaction is resolved
+        let carol = Address::repeat_byte(0xca);
+        let designator = Bytes::from([&DELEGATION_PREFIX[..], delegate.as_slice()].concat());
+        let info = AccountInfo {
+            code_hash: keccak256(&designator),
+            code: Some(Bytecode::new_raw(designator)),
+            ..Default::default()
+        };
+        executor.backend_mut().insert_account_info(carol, info);
+        let call = Transaction { to: Some(carol), ..Default::default() };
+        assert_eq!(prepare_tx(&mut executor, &mut env, &call).unwrap(), 0);
+        let info = executor.backend_mut().basic(carol).unwrap().unwrap();
+        assert_eq!(info.code.unwrap().original_bytes(), code);
+    }
+
+    #[test]
+    fn skips_authorities_with_code() {
+        let (mut executor, mut env) = executor();
+        let alice = PrivateKeySigner::random();
+        let code = Bytes::from_static(&[0x60, 0x00]);
+        let info = AccountInfo {
+            code_hash: keccak256(&code),
+            code: Some(Bytecode::new_raw(code.clone())),
+            ..Default::default()
+        };
+        executor.backend_mut().insert_account_info(alice.address(), info);
+
+        let tx =
+            set_code_tx(Address::repeat_byte(0x11), &[authorization(&alice, Address::ZERO, 0)]);
+        assert_eq!(prepare_tx(&mut executor, &mut env, &tx).unwrap(), 0);
+        let info = executor.backend_mut().basic(alice.address()).unwrap().unwrap();
+        assert_eq!((info.nonce, info.code.unwrap().original_bytes()), (0, code));
+    }
+
+    #[test]
+    fn skips_high_s_authorizations() {
+        let (mut executor, mut env) = executor();
+        let alice = PrivateKeySigner::random();
+        let mut high_s = authorization(&alice, Address::ZERO, 0);
+        // the other signature of the same hash by the same key
+        high_s.s = SECP256K1N - high_s.s;
+        high_s.y_parity ^= 1;
+        assert!(high_s.authority().is_none());
+
+        let tx = set_code_tx(Address::repeat_byte(0x11), &[high_s]);
+        assert_eq!(prepare_tx(&mut executor, &mut env, &tx).unwrap(), 0);
+        let info = executor.backend_mut().basic(alice.address()).unwrap().unwrap_or_default();
+        assert_eq!(info.nonce, 0);
+    }
+}
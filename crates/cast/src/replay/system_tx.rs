@@ -0,0 +1,67 @@
+//! Replaying of the system and deposit transactions of L2s, e.g. Optimism.
+
+use alloy_primitives::U256;
+use alloy_rpc_types::Transaction;
+use alloy_serde::WithOtherFields;
+use eyre::Result;
+use foundry_common::{is_known_system_sender, SYSTEM_TRANSACTION_TYPE};
+use foundry_evm::{executors::TracingExecutor, revm::primitives::Env};
+
+/// Returns whether the transaction is sent by a known system sender or is a deposit transaction.
+pub fn is_system_tx(tx: &Transaction) -> bool {
+    is_known_system_sender(tx.from) || tx.transaction_type == Some(SYSTEM_TRANSACTION_TYPE)
+}
+
+/// Adjusts the env of a system transaction after `configure_tx_env`.
+///
+/// System transactions don't pay for gas and have no nonce that needs to match the sender's. The
+/// base fee check is disabled and needs to be restored for the following transactions.
+pub fn configure_system_tx_env(env: &mut Env) {
+    env.tx.gas_price = U256::ZERO;
+    env.tx.gas_priority_fee = None;
+    env.tx.nonce = None;
+    env.cfg.disable_base_fee = true;
+}
+
+/// Credits the ETH minted by a deposit transaction to its sender, as the L2 does before executing
+/// it.
+pub fn mint_deposit(
+    executor: &mut TracingExecutor,
+    tx: &WithOtherFields<Transaction>,
+) -> Result<()> {
+    if let Some(mint) = deposit_mint(tx) {
+        let balance = executor.get_balance(tx.from)?;
+        executor.set_balance(tx.from, balance.saturating_add(mint))?;
+    }
+    Ok(())
+}
+
+/// Returns the ETH minted by a deposit transaction, from its `mint` field.
+fn deposit_mint(tx: &WithOtherFields<Transaction>) -> Option<U256> {
+    let mint = tx.other.get("mint")?;
+    serde_json::from_value::<U256>(mint.clone()).ok().filter(|mint| !mint.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configures_deposit_txs() {
+        let mut tx = WithOtherFields::new(Transaction {
+            transaction_type: Some(SYSTEM_TRANSACTION_TYPE),
+            ..Default::default()
+        });
+        assert!(is_system_tx(&tx));
+        assert_eq!(deposit_mint(&tx), None);
+        tx.other.insert("mint".to_string(), "0x64".into());
+        assert_eq!(deposit_mint(&tx), Some(U256::from(100)));
+
+        let mut env = Env::default();
+        env.tx.gas_price = U256::from(7);
+        env.tx.nonce = Some(1);
+        configure_system_tx_env(&mut env);
+        assert_eq!((env.tx.gas_price, env.tx.nonce), (U256::ZERO, None));
+        assert!(env.cfg.disable_base_fee);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::opts::ChainValueParser;
 use clap::Parser;
 use eyre::Result;
+use foundry_common::provider::runtime_transport::{parse_header, RuntimeTransportError};
 use foundry_config::{
     figment::{
         self,
@@ -18,6 +19,9 @@ const FLASHBOTS_URL: &str = "https://rpc.flashbots.net/fast";
 #[derive(Clone, Debug, Default, Parser)]
 pub struct RpcOpts {
     /// The RPC endpoint.
+    ///
+    /// Several comma-separated endpoints can be given, the requests then fail over from one to
+    /// the next when it fails or rate-limits them.
     #[arg(short = 'r', long = "rpc-url", env = "ETH_RPC_URL")]
     pub url: Option<String>,
 
@@ -40,6 +44,19 @@ pub struct RpcOpts {
     /// "0x6bb38c26db65749ab6e472080a3d20a2f35776494e72016d1e339593f21c59bc"]'
     #[arg(long, env = "ETH_RPC_JWT_SECRET")]
     pub jwt_secret: Option<String>,
+
+    /// Custom HTTP header sent with every request to the RPC endpoint, e.g. for an authenticating
+    /// gateway.
+    ///
+    /// Can be specified multiple times.
+    #[arg(long = "rpc-header", value_name = "KEY:VALUE", value_parser = parse_rpc_header)]
+    pub rpc_headers: Vec<String>,
+}
+
+/// Checks that the header is of the form `KEY:VALUE`, with a valid key and value.
+fn parse_rpc_header(header: &str) -> Result<String, RuntimeTransportError> {
+    parse_header(header)?;
+    Ok(header.to_string())
 }
 
 impl_figment_convert_cast!(RpcOpts);
@@ -84,6 +101,9 @@ impl RpcOpts {
         if let Ok(Some(jwt)) = self.jwt(None) {
             dict.insert("eth_rpc_jwt".into(), jwt.into_owned().into());
         }
+        if !self.rpc_headers.is_empty() {
+            dict.insert("eth_rpc_headers".into(), self.rpc_headers.clone().into());
+        }
         dict
     }
 }
@@ -179,6 +199,25 @@ impl figment::Provider for EthereumOpts {
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_rpc_headers() {
+        let args = RpcOpts::parse_from([
+            "foundry-cli",
+            "--rpc-header",
+            "Authorization: Bearer token",
+            "--rpc-header=X-Api-Key:key",
+        ]);
+        assert_eq!(args.rpc_headers, ["Authorization: Bearer token", "X-Api-Key:key"]);
+        assert_eq!(
+            args.dict()["eth_rpc_headers"],
+            vec!["Authorization: Bearer token", "X-Api-Key:key"].into()
+        );
+
+        for header in ["Authorization", "Bad Key:value", ":value", "Key:bad\nvalue"] {
+            assert!(RpcOpts::try_parse_from(["foundry-cli", "--rpc-header", header]).is_err());
+        }
+    }
+
     #[test]
     fn parse_etherscan_opts() {
         let args: EtherscanOpts =
@@ -1,4 +1,4 @@
-use alloy_json_abi::JsonAbi;
+use alloy_json_abi::{Error, JsonAbi};
 use alloy_primitives::Address;
 use eyre::{Result, WrapErr};
 use foundry_common::{cli_warn, fs, TestFunctionExt};
@@ -11,16 +11,21 @@ use foundry_compilers::{
 use foundry_config::{error::ExtractConfigError, figment::Figment, Chain, Config, NamedChain};
 use foundry_debugger::Debugger;
 use foundry_evm::{
+    decode::decode_console_logs,
     executors::{DeployResult, EvmError, RawCallResult},
     opts::EvmOpts,
     traces::{
         decode_trace_arena,
         identifier::{EtherscanIdentifier, SignaturesIdentifier},
-        render_trace_arena, CallTraceDecoder, CallTraceDecoderBuilder, TraceKind, Traces,
+        render_trace_arena_with_colors, CallTraceArena, CallTraceDecoder, CallTraceDecoderBuilder,
+        TraceKind, Traces,
     },
 };
+use serde::Serialize;
 use std::{
+    collections::{BTreeMap, HashMap},
     fmt::Write,
+    io,
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -311,18 +316,31 @@ pub fn read_constructor_args_file(constructor_args_path: PathBuf) -> Result<Vec<
 }
 
 /// A slimmed down return from the executor used for returning minimal trace + gas metering info
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TraceResult {
     pub success: bool,
     pub traces: Option<Traces>,
     pub gas_used: u64,
+    /// The decoded `console.log` messages.
+    pub logs: Vec<String>,
+    /// Additional reports of the command, included in the JSON output by name.
+    #[serde(flatten)]
+    pub reports: BTreeMap<String, serde_json::Value>,
 }
 
 impl TraceResult {
     /// Create a new [`TraceResult`] from a [`RawCallResult`].
     pub fn from_raw(raw: RawCallResult, trace_kind: TraceKind) -> Self {
+        let logs = decode_console_logs(&raw.logs);
         let RawCallResult { gas_used, traces, reverted, .. } = raw;
-        Self { success: !reverted, traces: traces.map(|arena| vec![(trace_kind, arena)]), gas_used }
+        Self {
+            success: !reverted,
+            traces: traces.map(|arena| vec![(trace_kind, arena)]),
+            gas_used,
+            logs,
+            reports: BTreeMap::new(),
+        }
     }
 }
 
@@ -346,30 +364,94 @@ impl TryFrom<Result<DeployResult, EvmError>> for TraceResult {
 
 /// labels the traces, conditionally prints them or opens the debugger
 pub async fn handle_traces(
-    mut result: TraceResult,
+    result: TraceResult,
     config: &Config,
     chain: Option<Chain>,
     labels: Vec<String>,
     debug: bool,
 ) -> Result<()> {
-    let labels = labels.iter().filter_map(|label_str| {
-        let mut iter = label_str.split(':');
+    // the name is everything after the first colon, and may contain colons
+    let labels = labels
+        .iter()
+        .filter_map(|label_str| {
+            let (addr, label) = label_str.split_once(':')?;
+            Some((Address::from_str(addr).ok()?, label.to_string()))
+        })
+        .collect();
+    let options = TraceOptions { labels: Some(labels), debug, ..Default::default() };
+    handle_traces_with(result, config, chain, options, &mut io::stdout(), |_| {}).await
+}
 
-        if let Some(addr) = iter.next() {
-            if let (Ok(address), Some(label)) = (Address::from_str(addr), iter.next()) {
-                return Some((address, label.to_string()));
-            }
+/// How [`handle_traces_with`] decodes and prints the traces. The default decodes the traces with
+/// the labels of the config only and prints them at the lowest verbosity.
+#[derive(Clone, Debug, Default)]
+pub struct TraceOptions<'a> {
+    /// The labels, which take precedence over the labels of the config. Without labels, the
+    /// addresses are rendered raw, without any label, including the ones of the config and of the
+    /// identified contracts.
+    pub labels: Option<HashMap<Address, String>>,
+    /// The custom errors used to decode the reverts of the traces.
+    pub errors: Vec<Error>,
+    /// The ABIs of known contracts, e.g. of local projects, which decode the calls, events and
+    /// errors of the traces before the ABIs identified on Etherscan and the signatures of
+    /// OpenChain. Address arguments are rendered with their labels.
+    pub abis: &'a [JsonAbi],
+    /// Opens the traces in the debugger.
+    pub debug: bool,
+    /// Prints the decoded result as a single JSON object.
+    pub json: bool,
+    /// Decodes the `bytes` arguments that look like calldata as nested calls.
+    pub decode_nested_bytes: bool,
+    /// The number of `-v` flags, as for `forge test`: from [`RAW_LOGS_VERBOSITY`] the raw logs are
+    /// printed after every trace, and from [`SETUP_TRACES_VERBOSITY`] the traces of the setup
+    /// calls are included.
+    pub verbosity: u8,
+}
+
+/// Same as [`handle_traces`], but decodes and prints the traces with the given `options`, writes
+/// them to `out` and calls `annotate` with every trace arena once it has been decoded, right before
+/// it is printed.
+///
+/// The config has no color setting: as for every other command, the traces are colored unless
+/// the [`yansi`] colors are disabled, e.g. when stdout isn't a terminal or with `NO_COLOR`.
+pub async fn handle_traces_with(
+    mut result: TraceResult,
+    config: &Config,
+    chain: Option<Chain>,
+    options: TraceOptions<'_>,
+    out: &mut (dyn io::Write + Send),
+    annotate: impl FnMut(&mut CallTraceArena),
+) -> Result<()> {
+    let TraceOptions { labels, errors, abis, debug, json, decode_nested_bytes, verbosity } =
+        options;
+    if debug && json {
+        eyre::bail!("the debugger is interactive and can't print JSON");
+    }
+
+    if verbosity < SETUP_TRACES_VERBOSITY {
+        if let Some(traces) = result.traces.as_mut() {
+            traces.retain(|(kind, _)| *kind != TraceKind::Setup);
         }
-        None
-    });
+    }
+
+    let no_labels = labels.is_none();
+    let labels = labels.unwrap_or_default();
     let config_labels = config.labels.clone().into_iter();
-    let mut decoder = CallTraceDecoderBuilder::new()
-        .with_labels(labels.chain(config_labels))
+    let mut decoder = CallTraceDecoderBuilder::new();
+    for abi in abis {
+        decoder = decoder.with_abi(abi);
+    }
+    let mut decoder = decoder
+        .with_labels(config_labels.chain(labels))
+        .with_nested_bytes_decoding(decode_nested_bytes)
         .with_signature_identifier(SignaturesIdentifier::new(
             Config::foundry_cache_dir(),
             config.offline,
         )?)
         .build();
+    for error in errors {
+        decoder.push_error(error);
+    }
 
     let mut etherscan_identifier = EtherscanIdentifier::new(config, chain)?;
     if let Some(etherscan_identifier) = &mut etherscan_identifier {
@@ -377,6 +459,9 @@ pub async fn handle_traces(
             decoder.identify(trace, etherscan_identifier);
         }
     }
+    if no_labels {
+        decoder.labels = Default::default();
+    }
 
     if debug {
         let sources = if let Some(etherscan_identifier) = etherscan_identifier {
@@ -390,29 +475,91 @@ pub async fn handle_traces(
             .sources(sources)
             .build();
         debugger.try_run()?;
+    } else if json {
+        print_json_traces_with(&mut result, &decoder, out, annotate).await?;
     } else {
-        print_traces(&mut result, &decoder).await?;
+        print_traces_with(&mut result, &decoder, verbosity, out, annotate).await?;
     }
 
     Ok(())
 }
 
+/// The verbosity from which [`handle_traces_with`] prints the raw logs after every trace.
+pub const RAW_LOGS_VERBOSITY: u8 = 3;
+
+/// The verbosity from which [`handle_traces_with`] includes the traces of the setup calls.
+pub const SETUP_TRACES_VERBOSITY: u8 = 4;
+
 pub async fn print_traces(result: &mut TraceResult, decoder: &CallTraceDecoder) -> Result<()> {
+    print_traces_with(result, decoder, 0, &mut io::stdout(), |_| {}).await
+}
+
+/// Same as [`print_traces`], but writes the traces to `out` and calls `annotate` with every trace
+/// arena once it has been decoded. From [`RAW_LOGS_VERBOSITY`], the raw logs of every trace are
+/// written after it.
+///
+/// The traces are only colored if the [`yansi`] colors are enabled, e.g. not when stdout isn't a
+/// terminal or with `NO_COLOR`.
+pub async fn print_traces_with(
+    result: &mut TraceResult,
+    decoder: &CallTraceDecoder,
+    verbosity: u8,
+    out: &mut (dyn io::Write + Send),
+    mut annotate: impl FnMut(&mut CallTraceArena),
+) -> Result<()> {
     let traces = result.traces.as_mut().expect("No traces found");
 
-    println!("Traces:");
+    writeln!(out, "Traces:")?;
     for (_, arena) in traces {
         decode_trace_arena(arena, decoder).await?;
-        println!("{}", render_trace_arena(arena));
+        annotate(arena);
+        writeln!(out, "{}", render_trace_arena_with_colors(arena, yansi::is_enabled()))?;
+        if verbosity >= RAW_LOGS_VERBOSITY {
+            write_raw_logs(arena, out)?;
+        }
     }
-    println!();
+    writeln!(out)?;
 
     if result.success {
-        println!("{}", "Transaction successfully executed.".green());
+        writeln!(out, "{}", "Transaction successfully executed.".green())?;
     } else {
-        println!("{}", "Transaction failed.".red());
+        writeln!(out, "{}", "Transaction failed.".red())?;
+    }
+
+    writeln!(out, "Gas used: {}", result.gas_used)?;
+    Ok(())
+}
+
+/// Writes the logs of the trace as emitted, call by call.
+fn write_raw_logs(arena: &CallTraceArena, out: &mut (dyn io::Write + Send)) -> Result<()> {
+    let logs = arena
+        .nodes()
+        .iter()
+        .flat_map(|node| node.logs.iter().map(|log| (node.trace.address, &log.raw_log)))
+        .collect::<Vec<_>>();
+    if logs.is_empty() {
+        return Ok(());
     }
+    writeln!(out, "Raw logs:")?;
+    for (address, log) in logs {
+        let topics = log.topics().iter().map(|topic| topic.to_string()).collect::<Vec<_>>();
+        writeln!(out, "  {address} [{}] {}", topics.join(", "), log.data)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
 
-    println!("Gas used: {}", result.gas_used);
+/// Same as [`print_traces_with`], but prints the decoded result as a single JSON object.
+pub async fn print_json_traces_with(
+    result: &mut TraceResult,
+    decoder: &CallTraceDecoder,
+    out: &mut (dyn io::Write + Send),
+    mut annotate: impl FnMut(&mut CallTraceArena),
+) -> Result<()> {
+    for (_, arena) in result.traces.as_mut().expect("No traces found") {
+        decode_trace_arena(arena, decoder).await?;
+        annotate(arena);
+    }
+    writeln!(out, "{}", serde_json::to_string(result)?)?;
     Ok(())
 }
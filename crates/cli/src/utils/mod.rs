@@ -102,6 +102,10 @@ pub fn get_provider_builder(config: &Config) -> Result<ProviderBuilder> {
         builder = builder.jwt(jwt.as_ref());
     }
 
+    if let Some(headers) = &config.eth_rpc_headers {
+        builder = builder.headers(headers.clone());
+    }
+
     Ok(builder)
 }
 
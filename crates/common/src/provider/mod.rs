@@ -2,6 +2,7 @@
 
 pub mod retry;
 pub mod runtime_transport;
+pub mod stats;
 pub mod tower;
 
 use crate::{
@@ -75,6 +76,8 @@ pub fn try_get_http_provider(builder: impl AsRef<str>) -> Result<RetryProvider>
 pub struct ProviderBuilder {
     // Note: this is a result, so we can easily chain builder calls
     url: Result<Url>,
+    /// The URLs of the endpoints to fail over to.
+    fallbacks: Result<Vec<Url>>,
     chain: NamedChain,
     max_retry: u32,
     timeout_retry: u32,
@@ -90,42 +93,20 @@ pub struct ProviderBuilder {
 
 impl ProviderBuilder {
     /// Creates a new builder instance
+    ///
+    /// Several comma-separated URLs can be given, the requests then fail over from one endpoint to
+    /// the next when it fails or rate-limits them, see [`RuntimeTransport`].
     pub fn new(url_str: &str) -> Self {
-        // a copy is needed for the next lines to work
-        let mut url_str = url_str;
-
-        // invalid url: non-prefixed URL scheme is not allowed, so we prepend the default http
-        // prefix
-        let storage;
-        if url_str.starts_with("localhost:") {
-            storage = format!("http://{url_str}");
-            url_str = storage.as_str();
-        }
-
-        let url = Url::parse(url_str)
-            .or_else(|err| match err {
-                ParseError::RelativeUrlWithoutBase => {
-                    if SocketAddr::from_str(url_str).is_ok() {
-                        Url::parse(&format!("http://{url_str}"))
-                    } else {
-                        let path = Path::new(url_str);
-
-                        if let Ok(path) = resolve_path(path) {
-                            Url::parse(&format!("file://{}", path.display()))
-                        } else {
-                            Err(err)
-                        }
-                    }
-                }
-                _ => Err(err),
-            })
-            .wrap_err_with(|| format!("invalid provider URL: {url_str:?}"));
+        let mut urls = url_str.split(',').map(str::trim);
+        let url = parse_url(urls.next().unwrap_or_default());
+        let fallbacks = urls.filter(|url| !url.is_empty()).map(parse_url).collect();
 
         // Use the final URL string to guess if it's a local URL.
         let is_local = url.as_ref().map_or(false, |url| guess_local_url(url.as_str()));
 
         Self {
             url,
+            fallbacks,
             chain: NamedChain::Mainnet,
             max_retry: 8,
             timeout_retry: 8,
@@ -150,6 +131,15 @@ impl ProviderBuilder {
         self
     }
 
+    /// Sets the request timeout, if `Some`, after which requests fail instead of being retried.
+    pub fn maybe_timeout(mut self, timeout: Option<Duration>) -> Self {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+            self.timeout_retry = 0;
+        }
+        self
+    }
+
     /// Sets the chain of the node the provider will connect to
     pub fn chain(mut self, chain: NamedChain) -> Self {
         self.chain = chain;
@@ -237,6 +227,7 @@ impl ProviderBuilder {
     pub fn build(self) -> Result<RetryProvider> {
         let Self {
             url,
+            fallbacks,
             chain: _,
             max_retry,
             timeout_retry,
@@ -247,7 +238,7 @@ impl ProviderBuilder {
             headers,
             is_local,
         } = self;
-        let url = url?;
+        let (url, fallbacks) = (url?, fallbacks?);
 
         let retry_layer = RetryBackoffLayer::new(
             max_retry,
@@ -256,6 +247,7 @@ impl ProviderBuilder {
             compute_units_per_second,
         );
         let transport = RuntimeTransportBuilder::new(url)
+            .with_fallbacks(fallbacks)
             .with_timeout(timeout)
             .with_headers(headers)
             .with_jwt(jwt)
@@ -272,6 +264,7 @@ impl ProviderBuilder {
     pub fn build_with_wallet(self, wallet: EthereumWallet) -> Result<RetryProviderWithSigner> {
         let Self {
             url,
+            fallbacks,
             chain: _,
             max_retry,
             timeout_retry,
@@ -282,7 +275,7 @@ impl ProviderBuilder {
             headers,
             is_local,
         } = self;
-        let url = url?;
+        let (url, fallbacks) = (url?, fallbacks?);
 
         let retry_layer = RetryBackoffLayer::new(
             max_retry,
@@ -292,6 +285,7 @@ impl ProviderBuilder {
         );
 
         let transport = RuntimeTransportBuilder::new(url)
+            .with_fallbacks(fallbacks)
             .with_timeout(timeout)
             .with_headers(headers)
             .with_jwt(jwt)
@@ -308,6 +302,39 @@ impl ProviderBuilder {
     }
 }
 
+/// Parses a provider URL, defaulting to HTTP for the URLs without scheme and to IPC for paths.
+fn parse_url(url_str: &str) -> Result<Url> {
+    // a copy is needed for the next lines to work
+    let mut url_str = url_str;
+
+    // invalid url: non-prefixed URL scheme is not allowed, so we prepend the default http
+    // prefix
+    let storage;
+    if url_str.starts_with("localhost:") {
+        storage = format!("http://{url_str}");
+        url_str = storage.as_str();
+    }
+
+    Url::parse(url_str)
+        .or_else(|err| match err {
+            ParseError::RelativeUrlWithoutBase => {
+                if SocketAddr::from_str(url_str).is_ok() {
+                    Url::parse(&format!("http://{url_str}"))
+                } else {
+                    let path = Path::new(url_str);
+
+                    if let Ok(path) = resolve_path(path) {
+                        Url::parse(&format!("file://{}", path.display()))
+                    } else {
+                        Err(err)
+                    }
+                }
+            }
+            _ => Err(err),
+        })
+        .wrap_err_with(|| format!("invalid provider URL: {url_str:?}"))
+}
+
 #[cfg(not(windows))]
 fn resolve_path(path: &Path) -> Result<PathBuf, ()> {
     if path.is_absolute() {
@@ -330,6 +357,7 @@ fn resolve_path(path: &Path) -> Result<PathBuf, ()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloy_provider::Provider;
 
     #[test]
     fn can_auto_correct_missing_prefix() {
@@ -339,4 +367,61 @@ mod tests {
         let url = builder.url.unwrap();
         assert_eq!(url, Url::parse("http://localhost:8545").unwrap());
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn times_out_hanging_requests() {
+        // accepts connections but never responds
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let provider = ProviderBuilder::new(&url)
+            .maybe_timeout(Some(Duration::from_millis(200)))
+            .build()
+            .unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(10), provider.get_chain_id()).await;
+        assert!(result.expect("request did not time out").is_err());
+        drop(listener);
+    }
+
+    #[test]
+    fn parses_fallback_urls() {
+        let builder = ProviderBuilder::new("http://localhost:8545, localhost:8546");
+        assert_eq!(builder.url.unwrap(), Url::parse("http://localhost:8545").unwrap());
+        assert_eq!(builder.fallbacks.unwrap(), [Url::parse("http://localhost:8546").unwrap()]);
+        assert!(ProviderBuilder::new("http://localhost:8545,").fallbacks.unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fails_over_to_fallback_endpoints() {
+        use std::io::{Read, Write};
+
+        // refuses connections
+        let down = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let down_url = format!("http://{}", down.local_addr().unwrap());
+        drop(down);
+        // answers the chain id requests
+        let up = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let up_url = format!("http://{}", up.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in up.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = vec![0; 4096];
+                let len = stream.read(&mut request).unwrap();
+                let request = String::from_utf8_lossy(&request[..len]);
+                let id = request.split("\"id\":").nth(1).unwrap().split([',', '}']).next().unwrap();
+                let body = format!(r#"{{"jsonrpc":"2.0","id":{id},"result":"0x5"}}"#);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let provider = ProviderBuilder::new(&format!("{down_url},{up_url}")).build().unwrap();
+        assert_eq!(provider.get_chain_id().await.unwrap(), 5);
+        assert_eq!(provider.get_chain_id().await.unwrap(), 5);
+        assert_eq!(stats::endpoints().get(&up_url), Some(&2));
+        assert_eq!(stats::endpoints().get(&down_url), None);
+    }
 }
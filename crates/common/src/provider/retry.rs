@@ -14,7 +14,7 @@ pub trait RetryPolicy: Send + Sync + std::fmt::Debug {
 }
 
 /// Implements [RetryPolicy] that will retry requests that errored with
-/// status code 429 i.e. TOO_MANY_REQUESTS
+/// status code 429 i.e. TOO_MANY_REQUESTS, or with the transient server errors 502, 503 and 504
 ///
 /// Infura often fails with a `"header not found"` rpc error which is apparently linked to load
 /// balancing, which are retried as well.
@@ -92,7 +92,8 @@ fn should_retry_transport_level_error(error: &TransportErrorKind) -> bool {
         }
 
         TransportErrorKind::HttpError(err) => {
-            if err.status == 429 {
+            // too many requests, or a bad gateway, unavailable service or gateway timeout
+            if matches!(err.status, 429 | 502..=504) {
                 return true
             }
             should_retry_body(&err.body)
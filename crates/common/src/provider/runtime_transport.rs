@@ -1,6 +1,10 @@
 //! Runtime transport that connects on first request, which can take either of an HTTP,
 //! WebSocket, or IPC transport and supports retries based on CUPS logic.
 
+use super::{
+    retry::{RateLimitRetryPolicy, RetryPolicy},
+    stats,
+};
 use crate::REQUEST_TIMEOUT;
 use alloy_json_rpc::{RequestPacket, ResponsePacket};
 use alloy_pubsub::{PubSubConnect, PubSubFrontend};
@@ -12,12 +16,31 @@ use alloy_transport_http::Http;
 use alloy_transport_ipc::IpcConnect;
 use alloy_transport_ws::WsConnect;
 use reqwest::header::{HeaderName, HeaderValue};
-use std::{fmt, path::PathBuf, str::FromStr, sync::Arc};
+use std::{
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 use thiserror::Error;
 use tokio::sync::RwLock;
 use tower::Service;
 use url::Url;
 
+/// Parses an HTTP header of the form `KEY:VALUE`, with the whitespace around the key and the value
+/// trimmed.
+pub fn parse_header(header: &str) -> Result<(HeaderName, HeaderValue), RuntimeTransportError> {
+    let make_err = || RuntimeTransportError::BadHeader(header.to_string());
+    let (key, val) = header.split_once(':').ok_or_else(make_err)?;
+    Ok((
+        HeaderName::from_str(key.trim()).map_err(|_| make_err())?,
+        HeaderValue::from_str(val.trim()).map_err(|_| make_err())?,
+    ))
+}
+
 /// An enum representing the different transports that can be used to connect to a runtime.
 /// Only meant to be used internally by [RuntimeTransport].
 #[derive(Clone, Debug)]
@@ -66,12 +89,19 @@ pub enum RuntimeTransportError {
 /// request is made. When the first request is made, it will connect to the runtime using either an
 /// HTTP WebSocket, or IPC transport depending on the URL used.
 /// It also supports retries for rate-limiting and timeout-related errors.
+///
+/// Requests fail over to the fallback endpoints, in order, when the endpoint in use fails or
+/// rate-limits them. The endpoint that served the last request keeps being used.
 #[derive(Clone, Debug, Error)]
 pub struct RuntimeTransport {
     /// The inner actual transport used.
     inner: Arc<RwLock<Option<InnerTransport>>>,
     /// The URL to connect to.
     url: Url,
+    /// The endpoints to fail over to, each without fallbacks.
+    fallbacks: Vec<Self>,
+    /// The endpoint in use: 0 for [`Self::url`], `i` for the `i`-th fallback.
+    active: Arc<AtomicUsize>,
     /// The headers to use for requests.
     headers: Vec<String>,
     /// The JWT to use for requests.
@@ -84,6 +114,7 @@ pub struct RuntimeTransport {
 #[derive(Debug)]
 pub struct RuntimeTransportBuilder {
     url: Url,
+    fallbacks: Vec<Url>,
     headers: Vec<String>,
     jwt: Option<String>,
     timeout: std::time::Duration,
@@ -92,7 +123,13 @@ pub struct RuntimeTransportBuilder {
 impl RuntimeTransportBuilder {
     /// Create a new builder with the given URL.
     pub fn new(url: Url) -> Self {
-        Self { url, headers: vec![], jwt: None, timeout: REQUEST_TIMEOUT }
+        Self { url, fallbacks: vec![], headers: vec![], jwt: None, timeout: REQUEST_TIMEOUT }
+    }
+
+    /// Set the URLs of the endpoints to fail over to, in order.
+    pub fn with_fallbacks(mut self, fallbacks: Vec<Url>) -> Self {
+        self.fallbacks = fallbacks;
+        self
     }
 
     /// Set the URL for the transport.
@@ -116,13 +153,17 @@ impl RuntimeTransportBuilder {
     /// Builds the [RuntimeTransport] and returns it in a disconnected state.
     /// The runtime transport will then connect when the first request happens.
     pub fn build(self) -> RuntimeTransport {
-        RuntimeTransport {
+        let endpoint = |url| RuntimeTransport {
             inner: Arc::new(RwLock::new(None)),
-            url: self.url,
-            headers: self.headers,
-            jwt: self.jwt,
+            url,
+            fallbacks: vec![],
+            active: Arc::new(AtomicUsize::new(0)),
+            headers: self.headers.clone(),
+            jwt: self.jwt.clone(),
             timeout: self.timeout,
-        }
+        };
+        let fallbacks = self.fallbacks.iter().cloned().map(endpoint).collect();
+        RuntimeTransport { fallbacks, ..endpoint(self.url.clone()) }
     }
 }
 
@@ -164,14 +205,8 @@ impl RuntimeTransport {
 
         // Add any custom headers.
         for header in self.headers.iter() {
-            let make_err = || RuntimeTransportError::BadHeader(header.to_string());
-
-            let (key, val) = header.split_once(':').ok_or_else(make_err)?;
-
-            headers.insert(
-                HeaderName::from_str(key.trim()).map_err(|_| make_err())?,
-                HeaderValue::from_str(val.trim()).map_err(|_| make_err())?,
-            );
+            let (key, val) = parse_header(header)?;
+            headers.insert(key, val);
         }
 
         client_builder = client_builder.default_headers(headers);
@@ -203,14 +238,49 @@ impl RuntimeTransport {
         Ok(InnerTransport::Ipc(ipc))
     }
 
-    /// Sends a request using the underlying transport.
+    /// Sends a request to the endpoint in use, failing over to the next endpoints if it fails or
+    /// is rate-limited. See [`Self::request_endpoint`].
+    pub fn request(&self, req: RequestPacket) -> TransportFut<'static> {
+        if self.fallbacks.is_empty() {
+            return self.request_endpoint(req);
+        }
+        let this = self.clone();
+        Box::pin(async move {
+            let count = this.fallbacks.len() + 1;
+            let first = this.active.load(Ordering::Relaxed);
+            let mut last: Option<(String, _)> = None;
+            for offset in 0..count {
+                let index = (first + offset) % count;
+                let endpoint = if index == 0 { &this } else { &this.fallbacks[index - 1] };
+                let name = endpoint_name(&endpoint.url);
+                if let Some((failed, _)) = &last {
+                    warn!(%failed, next = %name, "RPC endpoint failed, failing over to the next one");
+                }
+                let res = endpoint.request_endpoint(req.clone()).await;
+                let failed = match &res {
+                    Ok(res) => res.as_error().is_some_and(|err| {
+                        RateLimitRetryPolicy.should_retry(&TransportError::ErrorResp(err.clone()))
+                    }),
+                    Err(_) => true,
+                };
+                if !failed {
+                    this.active.store(index, Ordering::Relaxed);
+                    return res;
+                }
+                last = Some((name, res));
+            }
+            last.expect("there is at least one endpoint").1
+        })
+    }
+
+    /// Sends a request using the underlying transport, without failing over.
     /// If this is the first request, it will connect to the appropriate transport depending on the
     /// URL scheme. When sending the request, retries will be automatically handled depending
     /// on the parameters set on the [RuntimeTransport].
     /// For sending the actual request, this action is delegated down to the
     /// underlying transport through Tower's [tower::Service::call]. See tower's [tower::Service]
     /// trait for more information.
-    pub fn request(&self, req: RequestPacket) -> TransportFut<'static> {
+    pub fn request_endpoint(&self, req: RequestPacket) -> TransportFut<'static> {
         let this = self.clone();
         Box::pin(async move {
             let mut inner = this.inner.read().await;
@@ -227,7 +297,7 @@ impl RuntimeTransport {
             }
 
             // SAFETY: We just checked that the inner transport exists.
-            match inner.as_ref().expect("must've been initialized") {
+            let res = match inner.as_ref().expect("must've been initialized") {
                 InnerTransport::Http(http) => {
                     let mut http = http;
                     http.call(req)
@@ -241,7 +311,11 @@ impl RuntimeTransport {
                     ipc.call(req)
                 }
             }
-            .await
+            .await;
+            if res.is_ok() {
+                stats::record_endpoint(&endpoint_name(&this.url));
+            }
+            res
         })
     }
 
@@ -292,6 +366,15 @@ impl tower::Service<RequestPacket> for &RuntimeTransport {
     }
 }
 
+/// Returns the name of the endpoint of `url` for reports. The path and query of HTTP and WebSocket
+/// URLs are omitted, since they may contain API keys.
+pub fn endpoint_name(url: &Url) -> String {
+    match url.host_str() {
+        Some(_) => url.origin().ascii_serialization(),
+        None => url.to_string(),
+    }
+}
+
 fn build_auth(jwt: String) -> eyre::Result<Authorization> {
     // Decode jwt from hex, then generate claims (iat with current timestamp)
     let secret = JwtSecret::from_hex(jwt)?;
@@ -0,0 +1,99 @@
+//! Process-wide statistics of the RPC requests sent by the providers built by
+//! [`ProviderBuilder`](super::ProviderBuilder), including the ones of the fork backends.
+
+use alloy_json_rpc::RequestPacket;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+static STATS: Mutex<BTreeMap<String, MethodStats>> = Mutex::new(BTreeMap::new());
+
+static ENDPOINTS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+static RETRIES: AtomicU64 = AtomicU64::new(0);
+
+/// The requests of an RPC method.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MethodStats {
+    /// The number of sent requests, every retry included.
+    pub requests: u64,
+    /// The time spent waiting for the responses, excluding the backoffs between retries.
+    pub time: Duration,
+}
+
+/// Records that `request` was sent at `start` and answered, or failed, now.
+///
+/// The time of a batch request is split evenly between its requests.
+pub fn record(request: &RequestPacket, start: Instant) {
+    let elapsed = start.elapsed();
+    let methods = match request {
+        RequestPacket::Single(request) => vec![request.method()],
+        RequestPacket::Batch(requests) => requests.iter().map(|request| request.method()).collect(),
+    };
+    let Some(count) = u32::try_from(methods.len()).ok().filter(|count| *count > 0) else {
+        return;
+    };
+    let mut stats = STATS.lock().unwrap_or_else(|err| err.into_inner());
+    for method in methods {
+        let entry = stats.entry(method.to_string()).or_default();
+        entry.requests += 1;
+        entry.time += elapsed / count;
+    }
+}
+
+/// Records that a request was answered by `endpoint`, named by
+/// [`endpoint_name`](super::runtime_transport::endpoint_name).
+pub fn record_endpoint(endpoint: &str) {
+    let mut endpoints = ENDPOINTS.lock().unwrap_or_else(|err| err.into_inner());
+    *endpoints.entry(endpoint.to_string()).or_default() += 1;
+}
+
+/// Returns the number of requests answered so far by each endpoint.
+pub fn endpoints() -> BTreeMap<String, u64> {
+    ENDPOINTS.lock().unwrap_or_else(|err| err.into_inner()).clone()
+}
+
+/// Records that a failed request is retried.
+pub fn record_retry() {
+    RETRIES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the number of requests retried so far by the process.
+pub fn retries() -> u64 {
+    RETRIES.load(Ordering::Relaxed)
+}
+
+/// Returns the requests sent so far by the process, by method.
+pub fn snapshot() -> BTreeMap<String, MethodStats> {
+    STATS.lock().unwrap_or_else(|err| err.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_json_rpc::{Id, Request};
+
+    #[test]
+    fn records_requests_by_method() {
+        let request =
+            |method: &'static str| Request::new(method, Id::Number(1), ()).serialize().unwrap();
+        let start = Instant::now();
+        record(&RequestPacket::Single(request("test_single")), start);
+        record(&RequestPacket::Batch(vec![request("test_batch"), request("test_batch")]), start);
+        let stats = snapshot();
+        assert_eq!(stats["test_single"].requests, 1);
+        assert_eq!(stats["test_batch"].requests, 2);
+    }
+
+    #[test]
+    fn counts_retries() {
+        let before = retries();
+        record_retry();
+        assert!(retries() > before);
+    }
+}
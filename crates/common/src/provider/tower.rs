@@ -6,6 +6,7 @@ use std::{
         Arc,
     },
     task::{Context, Poll},
+    time::Instant,
 };
 
 use alloy_json_rpc::{RequestPacket, ResponsePacket};
@@ -14,6 +15,7 @@ use alloy_transport::{TransportError, TransportErrorKind, TransportFut};
 use super::{
     retry::{RateLimitRetryPolicy, RetryPolicy},
     runtime_transport::RuntimeTransport,
+    stats,
 };
 
 /// An Alloy Tower Layer that is responsible for retrying requests based on the
@@ -102,7 +104,9 @@ impl tower::Service<RequestPacket> for RetryBackoffService<RuntimeTransport> {
             let mut rate_limit_retry_number: u32 = 0;
             loop {
                 let err;
+                let start = Instant::now();
                 let fut = this.inner.call(request.clone()).await;
+                stats::record(&request, start);
 
                 match fut {
                     Ok(res) => {
@@ -123,14 +127,16 @@ impl tower::Service<RequestPacket> for RetryBackoffService<RuntimeTransport> {
                         return Err(TransportErrorKind::custom_str("Max retries exceeded"))
                     }
                     trace!("retrying request due to {:?}", err);
+                    stats::record_retry();
 
                     let current_queued_reqs = this.requests_enqueued.load(Ordering::SeqCst) as u64;
 
                     // try to extract the requested backoff from the error or compute the next
                     // backoff based on retry count
                     let backoff_hint = this.policy.backoff_hint(&err);
-                    let next_backoff = backoff_hint
-                        .unwrap_or_else(|| std::time::Duration::from_millis(this.initial_backoff));
+                    let next_backoff = backoff_hint.unwrap_or_else(|| {
+                        exponential_backoff(this.initial_backoff, rate_limit_retry_number)
+                    });
 
                     // requests are usually weighted and can vary from 10 CU to several 100 CU,
                     // cheaper requests are more common some example alchemy
@@ -165,6 +171,16 @@ impl tower::Service<RequestPacket> for RetryBackoffService<RuntimeTransport> {
     }
 }
 
+/// The number of times the backoff is doubled at most, bounding it to 16 times the initial one.
+const MAX_BACKOFF_DOUBLINGS: u32 = 4;
+
+/// Returns the backoff before the `retry_number`th retry, starting at 1: the initial backoff in
+/// milliseconds, doubled on each retry up to [`MAX_BACKOFF_DOUBLINGS`] times.
+fn exponential_backoff(initial_backoff: u64, retry_number: u32) -> std::time::Duration {
+    let doublings = retry_number.saturating_sub(1).min(MAX_BACKOFF_DOUBLINGS);
+    std::time::Duration::from_millis(initial_backoff.saturating_mul(1 << doublings))
+}
+
 /// Calculates an offset in seconds by taking into account the number of currently queued requests,
 /// number of requests that were ahead in the queue when the request was first issued, the average
 /// cost a weighted request (heuristic), and the number of available compute units per seconds.
@@ -222,6 +222,8 @@ pub struct Config {
     pub eth_rpc_url: Option<String>,
     /// JWT secret that should be used for any rpc calls
     pub eth_rpc_jwt: Option<String>,
+    /// custom HTTP headers of the form `KEY:VALUE` that should be sent with any rpc calls
+    pub eth_rpc_headers: Option<Vec<String>>,
     /// etherscan API key, or alias for an `EtherscanConfig` in `etherscan` table
     pub etherscan_api_key: Option<String>,
     /// Multiple etherscan api configs and their aliases
@@ -1674,6 +1676,17 @@ impl Config {
         Ok(())
     }
 
+    /// Clears the cache of function and event signatures: `~/.foundry/cache/signatures`.
+    pub fn clean_foundry_signatures_cache() -> eyre::Result<()> {
+        if let Some(cache_dir) = Self::foundry_cache_dir() {
+            let _ = fs::remove_file(cache_dir.join("signatures"));
+        } else {
+            eyre::bail!("failed to get foundry_cache_dir");
+        }
+
+        Ok(())
+    }
+
     /// Clears the foundry etherscan cache.
     pub fn clean_foundry_etherscan_cache() -> eyre::Result<()> {
         if let Some(cache_dir) = Self::foundry_etherscan_cache_dir() {
@@ -2113,6 +2126,7 @@ impl Default for Config {
             memory_limit: 1 << 27, // 2**27 = 128MiB = 134_217_728 bytes
             eth_rpc_url: None,
             eth_rpc_jwt: None,
+            eth_rpc_headers: None,
             etherscan_api_key: None,
             verbosity: 0,
             remappings: vec![],
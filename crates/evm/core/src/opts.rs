@@ -8,6 +8,7 @@ use foundry_common::{provider::ProviderBuilder, ALCHEMY_FREE_TIER_CUPS};
 use foundry_config::{Chain, Config};
 use revm::primitives::{BlockEnv, CfgEnv, TxEnv};
 use serde::{Deserialize, Deserializer, Serialize};
+use std::time::Duration;
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct EvmOpts {
@@ -28,6 +29,14 @@ pub struct EvmOpts {
     /// Initial retry backoff.
     pub fork_retry_backoff: Option<u64>,
 
+    /// The timeout of the requests to the fork endpoint in seconds, after which they fail instead
+    /// of being retried.
+    pub fork_timeout: Option<u64>,
+
+    /// The custom HTTP headers of the requests to the fork endpoint, of the form `KEY:VALUE`.
+    #[serde(rename = "eth_rpc_headers")]
+    pub fork_headers: Option<Vec<String>>,
+
     /// The available compute units per second.
     ///
     /// See also <https://docs.alchemy.com/reference/compute-units#what-are-cups-compute-units-per-second>
@@ -87,6 +96,10 @@ impl EvmOpts {
         let fork_url = fork_url.as_ref();
         let provider = ProviderBuilder::new(fork_url)
             .compute_units_per_second(self.get_compute_units_per_second())
+            .maybe_max_retry(self.fork_retries)
+            .maybe_initial_backoff(self.fork_retry_backoff)
+            .maybe_timeout(self.get_fork_timeout())
+            .headers(self.get_fork_headers())
             .build()?;
         environment(
             &provider,
@@ -186,6 +199,16 @@ impl EvmOpts {
         }
     }
 
+    /// Returns the timeout of the requests to the fork endpoint, if any.
+    pub fn get_fork_timeout(&self) -> Option<Duration> {
+        self.fork_timeout.map(Duration::from_secs)
+    }
+
+    /// Returns the custom HTTP headers of the requests to the fork endpoint.
+    pub fn get_fork_headers(&self) -> Vec<String> {
+        self.fork_headers.clone().unwrap_or_default()
+    }
+
     /// Returns the chain ID from the RPC, if any.
     pub async fn get_remote_chain_id(&self) -> Option<Chain> {
         if let Some(ref url) = self.fork_url {
@@ -196,6 +219,8 @@ impl EvmOpts {
             trace!(?url, "retrieving chain via eth_chainId");
             let provider = ProviderBuilder::new(url.as_str())
                 .compute_units_per_second(self.get_compute_units_per_second())
+                .maybe_timeout(self.get_fork_timeout())
+                .headers(self.get_fork_headers())
                 .build()
                 .ok()
                 .unwrap_or_else(|| panic!("Failed to establish provider to {url}"));
@@ -95,7 +95,10 @@ pub fn configure_tx_env(env: &mut revm::primitives::Env, tx: &Transaction) {
         .collect();
     env.tx.value = tx.value.to();
     env.tx.data = alloy_primitives::Bytes(tx.input.0.clone());
-    env.tx.transact_to = tx.to.map(TxKind::Call).unwrap_or(TxKind::Create)
+    env.tx.transact_to = tx.to.map(TxKind::Call).unwrap_or(TxKind::Create);
+    // the blob fields of EIP-4844 transactions, reset for the other transactions
+    env.tx.blob_hashes = tx.blob_versioned_hashes.clone().unwrap_or_default();
+    env.tx.max_fee_per_blob_gas = tx.max_fee_per_blob_gas.map(U256::from);
 }
 
 /// Get the gas used, accounting for refunds
@@ -288,6 +291,24 @@ where
 mod tests {
     use super::*;
 
+    #[test]
+    fn configures_blob_tx_env() {
+        let mut env = revm::primitives::Env::default();
+        let hash = alloy_primitives::B256::with_last_byte(1);
+        let tx = Transaction {
+            blob_versioned_hashes: Some(vec![hash]),
+            max_fee_per_blob_gas: Some(10),
+            ..Default::default()
+        };
+        configure_tx_env(&mut env, &tx);
+        assert_eq!(env.tx.blob_hashes, vec![hash]);
+        assert_eq!(env.tx.max_fee_per_blob_gas, Some(U256::from(10)));
+
+        configure_tx_env(&mut env, &Transaction::default());
+        assert!(env.tx.blob_hashes.is_empty());
+        assert_eq!(env.tx.max_fee_per_blob_gas, None);
+    }
+
     #[test]
     fn build_evm() {
         let mut db = revm::db::EmptyDB::default();
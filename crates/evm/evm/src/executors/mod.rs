@@ -236,6 +236,13 @@ impl Executor {
         self
     }
 
+    /// Sets the EVM spec ID the transactions are executed with.
+    #[inline]
+    pub fn set_spec_id(&mut self, spec_id: SpecId) -> &mut Self {
+        self.env.handler_cfg.spec_id = spec_id;
+        self
+    }
+
     /// Deploys a contract and commits the new state to the underlying database.
     ///
     /// Executes a CREATE transaction with the contract `code` and persistent database state
@@ -728,6 +735,8 @@ pub struct RawCallResult {
     pub out: Option<Output>,
     /// The chisel state
     pub chisel_state: Option<(Vec<U256>, Vec<u8>, InstructionResult)>,
+    /// The number of call frames that weren't traced because they were too deep.
+    pub elided_trace_frames: usize,
 }
 
 impl Default for RawCallResult {
@@ -750,6 +759,7 @@ impl Default for RawCallResult {
             cheatcodes: Default::default(),
             out: None,
             chisel_state: None,
+            elided_trace_frames: 0,
         }
     }
 }
@@ -856,8 +866,15 @@ fn convert_executed_result(
         _ => Bytes::new(),
     };
 
-    let InspectorData { mut logs, labels, traces, coverage, cheatcodes, chisel_state } =
-        inspector.collect();
+    let InspectorData {
+        mut logs,
+        labels,
+        traces,
+        coverage,
+        cheatcodes,
+        chisel_state,
+        elided_trace_frames,
+    } = inspector.collect();
 
     if logs.is_empty() {
         logs = exec_logs;
@@ -886,5 +903,6 @@ fn convert_executed_result(
         cheatcodes,
         out,
         chisel_state,
+        elided_trace_frames,
     })
 }
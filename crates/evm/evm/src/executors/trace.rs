@@ -1,4 +1,7 @@
-use crate::executors::{Executor, ExecutorBuilder};
+use crate::{
+    executors::{Executor, ExecutorBuilder},
+    inspectors::SharedCallObserver,
+};
 use foundry_compilers::artifacts::EvmVersion;
 use foundry_config::{utils::evm_spec_id, Chain, Config};
 use foundry_evm_core::{backend::Backend, fork::CreateFork, opts::EvmOpts};
@@ -33,6 +36,13 @@ impl TracingExecutor {
         self.executor.spec_id()
     }
 
+    /// Sets the observer of the call frames of the executed transactions, nested ones included.
+    ///
+    /// Nothing is observed by default, at no cost.
+    pub fn set_call_observer(&mut self, observer: Option<SharedCallObserver>) {
+        self.executor.inspector_mut().set_call_observer(observer);
+    }
+
     /// uses the fork block number from the config
     pub async fn get_fork_material(
         config: &Config,
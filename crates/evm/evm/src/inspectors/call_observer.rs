@@ -0,0 +1,133 @@
+use alloy_primitives::{Address, Bytes, U256};
+use foundry_evm_traces::CallKind;
+use revm::interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, InterpreterResult};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// Observes the call frames of the executed transactions, e.g. to compute custom metrics.
+///
+/// The frames are reported in execution order: [`CallObserver::call_start`] when a frame is
+/// entered, and [`CallObserver::call_end`] when it returns, nested frames included.
+pub trait CallObserver: Send {
+    /// Called when a call or create frame is entered.
+    fn call_start(&mut self, frame: &CallFrame) {
+        let _ = frame;
+    }
+
+    /// Called when a call or create frame returns, with the frame it was entered with.
+    fn call_end(&mut self, frame: &CallFrame, outcome: &CallFrameOutcome) {
+        let _ = (frame, outcome);
+    }
+}
+
+/// A call or create frame, as entered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallFrame {
+    /// The call depth of the frame, the top-level frame having a depth of 1.
+    pub depth: usize,
+    pub kind: CallKind,
+    pub caller: Address,
+    /// The called address, `None` for creates whose address is known on return.
+    pub callee: Option<Address>,
+    /// The transferred value, or the apparent one of delegate calls.
+    pub value: U256,
+    /// The calldata, or the init code of creates.
+    pub input: Bytes,
+    pub gas_limit: u64,
+}
+
+/// How a [`CallFrame`] returned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallFrameOutcome {
+    pub success: bool,
+    /// The return data, or the revert data.
+    pub output: Bytes,
+    pub gas_used: u64,
+    /// The created address, for successful creates.
+    pub created: Option<Address>,
+}
+
+impl CallFrameOutcome {
+    fn new(result: &InterpreterResult, created: Option<Address>) -> Self {
+        Self {
+            success: result.is_ok(),
+            output: result.output.clone(),
+            gas_used: result.gas.spent(),
+            created: created.filter(|_| result.is_ok()),
+        }
+    }
+}
+
+/// A [`CallObserver`] shared with the executor, so that it can be read after the execution.
+#[derive(Clone)]
+pub struct SharedCallObserver(Arc<Mutex<dyn CallObserver>>);
+
+impl<T: CallObserver + 'static> From<Arc<Mutex<T>>> for SharedCallObserver {
+    fn from(observer: Arc<Mutex<T>>) -> Self {
+        Self(observer)
+    }
+}
+
+impl fmt::Debug for SharedCallObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedCallObserver")
+    }
+}
+
+/// Reports the frames of the execution to a [`CallObserver`].
+#[derive(Clone, Debug)]
+pub struct CallObserverInspector {
+    observer: SharedCallObserver,
+    /// The entered frames that haven't returned yet.
+    frames: Vec<CallFrame>,
+}
+
+impl CallObserverInspector {
+    pub fn new(observer: SharedCallObserver) -> Self {
+        Self { observer, frames: vec![] }
+    }
+
+    pub(crate) fn call(&mut self, depth: usize, call: &CallInputs) {
+        self.start(CallFrame {
+            depth,
+            kind: call.scheme.into(),
+            caller: call.caller,
+            callee: Some(call.target_address),
+            value: call.value.get(),
+            input: call.input.clone(),
+            gas_limit: call.gas_limit,
+        });
+    }
+
+    pub(crate) fn call_end(&mut self, outcome: &CallOutcome) {
+        self.end(CallFrameOutcome::new(&outcome.result, None));
+    }
+
+    pub(crate) fn create(&mut self, depth: usize, create: &CreateInputs) {
+        self.start(CallFrame {
+            depth,
+            kind: create.scheme.into(),
+            caller: create.caller,
+            callee: None,
+            value: create.value,
+            input: create.init_code.clone(),
+            gas_limit: create.gas_limit,
+        });
+    }
+
+    pub(crate) fn create_end(&mut self, outcome: &CreateOutcome) {
+        self.end(CallFrameOutcome::new(&outcome.result, outcome.address));
+    }
+
+    fn start(&mut self, frame: CallFrame) {
+        self.observer.0.lock().unwrap_or_else(|err| err.into_inner()).call_start(&frame);
+        self.frames.push(frame);
+    }
+
+    fn end(&mut self, outcome: CallFrameOutcome) {
+        let Some(frame) = self.frames.pop() else { return };
+        self.observer.0.lock().unwrap_or_else(|err| err.into_inner()).call_end(&frame, &outcome);
+    }
+}
@@ -0,0 +1,83 @@
+use revm::{
+    interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, Gas},
+    Database, EvmContext, Inspector,
+};
+
+/// An inspector that adjusts the gas available to every call frame by a fixed delta.
+///
+/// This is an experimental tool to probe gas-dependent control flow, e.g. `gasleft()` checks, and
+/// makes the execution diverge from the real one.
+///
+/// A negative delta withholds gas from every frame, the withheld gas is consumed by the caller. A
+/// positive delta grants extra gas to every frame, which is taken back from the gas the frame
+/// returns, so that a caller never gets back more gas than it forwarded.
+#[derive(Clone, Debug, Default)]
+pub struct GasPerturbation {
+    /// The gas added to the gas limit of every frame.
+    pub delta: i64,
+    /// The extra gas granted to the open frames, along with the depth of their caller.
+    granted: Vec<(u64, u64)>,
+}
+
+impl GasPerturbation {
+    /// Creates a new inspector that adjusts the gas of every frame by `delta`.
+    pub fn new(delta: i64) -> Self {
+        Self { delta, granted: vec![] }
+    }
+
+    fn adjust_limit(&mut self, depth: u64, gas_limit: &mut u64) {
+        if self.delta < 0 {
+            *gas_limit = gas_limit.saturating_sub(self.delta.unsigned_abs());
+        } else {
+            let extra = self.delta as u64;
+            *gas_limit = gas_limit.saturating_add(extra);
+            self.granted.push((depth, extra));
+        }
+    }
+
+    fn take_back(&mut self, depth: u64, gas: &mut Gas) {
+        match self.granted.last() {
+            Some(&(granted_depth, extra)) if granted_depth == depth => {
+                self.granted.pop();
+                let _ = gas.record_cost(extra.min(gas.remaining()));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for GasPerturbation {
+    fn call(&mut self, ecx: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.adjust_limit(ecx.journaled_state.depth(), &mut inputs.gas_limit);
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        ecx: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        mut outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.take_back(ecx.journaled_state.depth(), &mut outcome.result.gas);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        ecx: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.adjust_limit(ecx.journaled_state.depth(), &mut inputs.gas_limit);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        ecx: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        mut outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.take_back(ecx.journaled_state.depth(), &mut outcome.result.gas);
+        outcome
+    }
+}
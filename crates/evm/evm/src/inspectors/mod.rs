@@ -7,9 +7,17 @@ pub use foundry_evm_traces::{StackSnapshotType, TracingInspector, TracingInspect
 
 pub use revm_inspectors::access_list::AccessListInspector;
 
+mod call_observer;
+pub use call_observer::{
+    CallFrame, CallFrameOutcome, CallObserver, CallObserverInspector, SharedCallObserver,
+};
+
 mod chisel_state;
 pub use chisel_state::ChiselState;
 
+mod gas_perturbation;
+pub use gas_perturbation::GasPerturbation;
+
 mod logs;
 pub use logs::LogCollector;
 
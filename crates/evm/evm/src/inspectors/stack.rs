@@ -1,6 +1,7 @@
 use super::{
-    Cheatcodes, CheatsConfig, ChiselState, CoverageCollector, Fuzzer, LogCollector,
-    StackSnapshotType, TracingInspector, TracingInspectorConfig,
+    CallObserverInspector, Cheatcodes, CheatsConfig, ChiselState, CoverageCollector, Fuzzer,
+    GasPerturbation, LogCollector, SharedCallObserver, StackSnapshotType, TracingInspector,
+    TracingInspectorConfig,
 };
 use alloy_primitives::{Address, Bytes, Log, TxKind, U256};
 use foundry_cheatcodes::CheatcodesExecutor;
@@ -244,6 +245,9 @@ pub struct InspectorData {
     pub coverage: Option<HitMaps>,
     pub cheatcodes: Option<Cheatcodes>,
     pub chisel_state: Option<(Vec<U256>, Vec<u8>, InstructionResult)>,
+    /// The number of call frames that weren't traced because they were deeper than
+    /// [`InspectorStackInner::max_trace_depth`].
+    pub elided_trace_frames: usize,
 }
 
 /// Contains data about the state of outer/main EVM which created and invoked the inner EVM context.
@@ -285,19 +289,69 @@ pub struct InspectorStack {
 /// See [`InspectorStack`].
 #[derive(Default, Clone, Debug)]
 pub struct InspectorStackInner {
+    pub call_observer: Option<CallObserverInspector>,
     pub chisel_state: Option<ChiselState>,
     pub coverage: Option<CoverageCollector>,
     pub fuzzer: Option<Fuzzer>,
+    pub gas_perturbation: Option<GasPerturbation>,
     pub log_collector: Option<LogCollector>,
     pub printer: Option<CustomPrintTracer>,
     pub tracer: Option<TracingInspector>,
     pub enable_isolation: bool,
+    /// Whether the top-level call is executed as a static call, rejecting any state mutation.
+    pub read_only: bool,
+    /// The maximum call depth recorded by the tracer, the top-level frame having a depth of 1.
+    ///
+    /// Deeper frames are still executed, but neither they nor their steps and logs are traced.
+    pub max_trace_depth: Option<usize>,
+    /// The number of call frames that weren't traced because of [`Self::max_trace_depth`].
+    pub elided_trace_frames: usize,
+    /// The number of elided call frames currently being executed.
+    elided_frames_entered: usize,
 
     /// Flag marking if we are in the inner EVM context.
     pub in_inner_context: bool,
     pub inner_context_data: Option<InnerContextData>,
 }
 
+impl InspectorStackInner {
+    /// Whether the tracer records the frames, steps and logs at call depth `depth`, the top-level
+    /// frame having a depth of 1.
+    #[inline]
+    fn traces_depth(&self, depth: usize) -> bool {
+        self.max_trace_depth.map_or(true, |max| depth <= max)
+    }
+
+    /// The depth of the frame being entered or exited in `ecx`, as seen by the tracer.
+    #[inline]
+    fn frame_depth<DB: DatabaseExt>(&self, ecx: &EvmContext<DB>) -> usize {
+        ecx.journaled_state.depth + self.in_inner_context as usize + 1
+    }
+
+    /// Records entering a call frame, returning whether it is traced.
+    #[inline]
+    fn enter_frame<DB: DatabaseExt>(&mut self, ecx: &EvmContext<DB>) -> bool {
+        let traced = self.traces_depth(self.frame_depth(ecx));
+        if !traced {
+            self.elided_frames_entered += 1;
+            if self.tracer.is_some() {
+                self.elided_trace_frames += 1;
+            }
+        }
+        traced
+    }
+
+    /// Records exiting a call frame, returning whether it is traced.
+    #[inline]
+    fn exit_frame<DB: DatabaseExt>(&mut self, ecx: &EvmContext<DB>) -> bool {
+        let traced = self.traces_depth(self.frame_depth(ecx));
+        if !traced {
+            self.elided_frames_entered = self.elided_frames_entered.saturating_sub(1);
+        }
+        traced
+    }
+}
+
 /// Struct keeping mutable references to both parts of [InspectorStack] and implementing
 /// [revm::Inspector]. This struct can be obtained via [InspectorStack::as_mut] or via
 /// [CheatcodesExecutor::get_inspector] method implemented for [InspectorStackInner].
@@ -339,7 +393,17 @@ impl InspectorStack {
                     )*
                 };
             }
-            push!(cheatcodes, chisel_state, coverage, fuzzer, log_collector, printer, tracer);
+            push!(
+                call_observer,
+                cheatcodes,
+                chisel_state,
+                coverage,
+                fuzzer,
+                gas_perturbation,
+                log_collector,
+                printer,
+                tracer
+            );
             if self.enable_isolation {
                 enabled.push("isolation");
             }
@@ -394,6 +458,31 @@ impl InspectorStack {
         self.coverage = yes.then(Default::default);
     }
 
+    /// Set the gas delta by which every call frame is perturbed, see [GasPerturbation].
+    #[inline]
+    pub fn set_gas_perturbation(&mut self, delta: Option<i64>) {
+        self.gas_perturbation = delta.map(GasPerturbation::new);
+    }
+
+    /// Set the observer of the call frames, see [CallObserverInspector].
+    #[inline]
+    pub fn set_call_observer(&mut self, observer: Option<SharedCallObserver>) {
+        self.call_observer = observer.map(CallObserverInspector::new);
+    }
+
+    /// Set whether to execute the top-level call as a static call.
+    #[inline]
+    pub fn set_read_only(&mut self, yes: bool) {
+        self.read_only = yes;
+    }
+
+    /// Set the maximum call depth recorded by the tracer, see
+    /// [`InspectorStackInner::max_trace_depth`].
+    #[inline]
+    pub fn set_max_trace_depth(&mut self, depth: Option<usize>) {
+        self.max_trace_depth = depth;
+    }
+
     /// Set whether to enable call isolation.
     #[inline]
     pub fn enable_isolation(&mut self, yes: bool) {
@@ -440,7 +529,15 @@ impl InspectorStack {
     pub fn collect(self) -> InspectorData {
         let Self {
             cheatcodes,
-            inner: InspectorStackInner { chisel_state, coverage, log_collector, tracer, .. },
+            inner:
+                InspectorStackInner {
+                    chisel_state,
+                    coverage,
+                    log_collector,
+                    tracer,
+                    elided_trace_frames,
+                    ..
+                },
         } = self;
 
         InspectorData {
@@ -453,6 +550,7 @@ impl InspectorStack {
             coverage: coverage.map(|coverage| coverage.maps),
             cheatcodes,
             chisel_state: chisel_state.and_then(|state| state.state),
+            elided_trace_frames,
         }
     }
 
@@ -488,9 +586,15 @@ impl<'a> InspectorStackRefMut<'a> {
         outcome: CallOutcome,
     ) -> CallOutcome {
         let result = outcome.result.result;
+        let traced = self.exit_frame(ecx);
         call_inspectors_adjust_depth!(
             #[ret]
-            [&mut self.fuzzer, &mut self.tracer, &mut self.cheatcodes, &mut self.printer],
+            [
+                &mut self.fuzzer,
+                self.tracer.as_mut().filter(|_| traced),
+                &mut self.cheatcodes,
+                &mut self.printer,
+            ],
             |inspector| {
                 let new_outcome = inspector.call_end(ecx, inputs, outcome.clone());
 
@@ -642,8 +746,14 @@ impl<'a> InspectorStackRefMut<'a> {
 
 impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
     fn initialize_interp(&mut self, interpreter: &mut Interpreter, ecx: &mut EvmContext<DB>) {
+        let traced = self.elided_frames_entered == 0;
         call_inspectors_adjust_depth!(
-            [&mut self.coverage, &mut self.tracer, &mut self.cheatcodes, &mut self.printer],
+            [
+                &mut self.coverage,
+                self.tracer.as_mut().filter(|_| traced),
+                &mut self.cheatcodes,
+                &mut self.printer,
+            ],
             |inspector| inspector.initialize_interp(interpreter, ecx),
             self,
             ecx
@@ -651,10 +761,11 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
     }
 
     fn step(&mut self, interpreter: &mut Interpreter, ecx: &mut EvmContext<DB>) {
+        let traced = self.elided_frames_entered == 0;
         call_inspectors_adjust_depth!(
             [
                 &mut self.fuzzer,
-                &mut self.tracer,
+                self.tracer.as_mut().filter(|_| traced),
                 &mut self.coverage,
                 &mut self.cheatcodes,
                 &mut self.printer,
@@ -666,8 +777,9 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
     }
 
     fn step_end(&mut self, interpreter: &mut Interpreter, ecx: &mut EvmContext<DB>) {
+        let traced = self.elided_frames_entered == 0;
         call_inspectors_adjust_depth!(
-            [&mut self.tracer, &mut self.chisel_state, &mut self.printer],
+            [self.tracer.as_mut().filter(|_| traced), &mut self.chisel_state, &mut self.printer],
             |inspector| inspector.step_end(interpreter, ecx),
             self,
             ecx
@@ -675,8 +787,14 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
     }
 
     fn log(&mut self, ecx: &mut EvmContext<DB>, log: &Log) {
+        let traced = self.elided_frames_entered == 0;
         call_inspectors_adjust_depth!(
-            [&mut self.tracer, &mut self.log_collector, &mut self.cheatcodes, &mut self.printer],
+            [
+                self.tracer.as_mut().filter(|_| traced),
+                &mut self.log_collector,
+                &mut self.cheatcodes,
+                &mut self.printer,
+            ],
             |inspector| inspector.log(ecx, log),
             self,
             ecx
@@ -689,9 +807,24 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
             return None;
         }
 
+        if self.read_only && ecx.journaled_state.depth == 0 {
+            call.is_static = true;
+        }
+
+        let depth = self.frame_depth(ecx);
+        if let Some(call_observer) = &mut self.call_observer {
+            call_observer.call(depth, call);
+        }
+
+        let traced = self.enter_frame(ecx);
         call_inspectors_adjust_depth!(
             #[ret]
-            [&mut self.fuzzer, &mut self.tracer, &mut self.log_collector, &mut self.printer],
+            [
+                &mut self.fuzzer,
+                self.tracer.as_mut().filter(|_| traced),
+                &mut self.log_collector,
+                &mut self.printer,
+            ],
             |inspector| {
                 let mut out = None;
                 if let Some(output) = inspector.call(ecx, call) {
@@ -732,6 +865,10 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
             return Some(CallOutcome { result, memory_offset: call.return_memory_offset.clone() })
         }
 
+        if let Some(gas_perturbation) = &mut self.gas_perturbation {
+            gas_perturbation.call(ecx, call);
+        }
+
         None
     }
 
@@ -747,6 +884,13 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
             return outcome
         }
 
+        let outcome = match &mut self.gas_perturbation {
+            Some(gas_perturbation) => gas_perturbation.call_end(ecx, inputs, outcome),
+            None => outcome,
+        };
+        if let Some(call_observer) = &mut self.call_observer {
+            call_observer.call_end(&outcome);
+        }
         let outcome = self.do_call_end(ecx, inputs, outcome);
         if outcome.result.is_revert() {
             // Encountered a revert, since cheatcodes may have altered the evm state in such a way
@@ -770,9 +914,15 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
             return None;
         }
 
+        let depth = self.frame_depth(ecx);
+        if let Some(call_observer) = &mut self.call_observer {
+            call_observer.create(depth, create);
+        }
+
+        let traced = self.enter_frame(ecx);
         call_inspectors_adjust_depth!(
             #[ret]
-            [&mut self.tracer, &mut self.coverage, &mut self.cheatcodes],
+            [self.tracer.as_mut().filter(|_| traced), &mut self.coverage, &mut self.cheatcodes],
             |inspector| inspector.create(ecx, create).map(Some),
             self,
             ecx
@@ -794,6 +944,10 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
             return Some(CreateOutcome { result, address })
         }
 
+        if let Some(gas_perturbation) = &mut self.gas_perturbation {
+            gas_perturbation.create(ecx, create);
+        }
+
         None
     }
 
@@ -809,11 +963,19 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
             return outcome
         }
 
+        let outcome = match &mut self.gas_perturbation {
+            Some(gas_perturbation) => gas_perturbation.create_end(ecx, call, outcome),
+            None => outcome,
+        };
+        if let Some(call_observer) = &mut self.call_observer {
+            call_observer.create_end(&outcome);
+        }
         let result = outcome.result.result;
+        let traced = self.exit_frame(ecx);
 
         call_inspectors_adjust_depth!(
             #[ret]
-            [&mut self.tracer, &mut self.cheatcodes, &mut self.printer],
+            [self.tracer.as_mut().filter(|_| traced), &mut self.cheatcodes, &mut self.printer],
             |inspector| {
                 let new_outcome = inspector.create_end(ecx, call, outcome.clone());
 
@@ -841,9 +1003,10 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
             return None;
         }
 
+        let traced = self.enter_frame(ecx);
         call_inspectors_adjust_depth!(
             #[ret]
-            [&mut self.tracer, &mut self.coverage, &mut self.cheatcodes],
+            [self.tracer.as_mut().filter(|_| traced), &mut self.coverage, &mut self.cheatcodes],
             |inspector| inspector.eofcreate(ecx, create).map(Some),
             self,
             ecx
@@ -882,10 +1045,11 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
         }
 
         let result = outcome.result.result;
+        let traced = self.exit_frame(ecx);
 
         call_inspectors_adjust_depth!(
             #[ret]
-            [&mut self.tracer, &mut self.cheatcodes, &mut self.printer],
+            [self.tracer.as_mut().filter(|_| traced), &mut self.cheatcodes, &mut self.printer],
             |inspector| {
                 let new_outcome = inspector.eofcreate_end(ecx, call, outcome.clone());
 
@@ -904,9 +1068,11 @@ impl<'a, DB: DatabaseExt> Inspector<DB> for InspectorStackRefMut<'a> {
     }
 
     fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
-        call_inspectors!([&mut self.tracer, &mut self.printer], |inspector| {
-            Inspector::<DB>::selfdestruct(inspector, contract, target, value)
-        });
+        let traced = self.elided_frames_entered == 0;
+        call_inspectors!(
+            [self.tracer.as_mut().filter(|_| traced), &mut self.printer],
+            |inspector| { Inspector::<DB>::selfdestruct(inspector, contract, target, value) }
+        );
     }
 }
 
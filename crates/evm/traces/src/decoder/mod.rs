@@ -18,6 +18,7 @@ use foundry_evm_core::{
     },
     decode::RevertDecoder,
 };
+use futures::future::{BoxFuture, FutureExt};
 use itertools::Itertools;
 use once_cell::sync::OnceCell;
 use revm_inspectors::tracing::types::{DecodedCallLog, DecodedCallTrace};
@@ -77,6 +78,13 @@ impl CallTraceDecoderBuilder {
         self
     }
 
+    /// Sets whether to decode `bytes` arguments that look like calldata as nested calls.
+    #[inline]
+    pub fn with_nested_bytes_decoding(mut self, yes: bool) -> Self {
+        self.decoder.decode_nested_bytes = yes;
+        self
+    }
+
     /// Sets the signature identifier for events and functions.
     #[inline]
     pub fn with_signature_identifier(mut self, identifier: SingleSignaturesIdentifier) -> Self {
@@ -120,6 +128,8 @@ pub struct CallTraceDecoder {
     pub signature_identifier: Option<SingleSignaturesIdentifier>,
     /// Verbosity level
     pub verbosity: u8,
+    /// Whether to decode `bytes` arguments that start with a known selector as nested calls.
+    pub decode_nested_bytes: bool,
 }
 
 impl CallTraceDecoder {
@@ -182,6 +192,7 @@ impl CallTraceDecoder {
 
             signature_identifier: None,
             verbosity: 0,
+            decode_nested_bytes: false,
         }
     }
 
@@ -342,9 +353,14 @@ impl CallTraceDecoder {
                 return DecodedCallTrace { label, call_data: None, return_data: None };
             };
 
+            let mut call_data = self.decode_function_input(trace, func);
+            if self.decode_nested_bytes && trace.address != CHEATCODE_ADDRESS {
+                self.decode_nested_inputs(func, &cdata[SELECTOR_LEN..], &mut call_data).await;
+            }
+
             DecodedCallTrace {
                 label,
-                call_data: Some(self.decode_function_input(trace, func)),
+                call_data: Some(call_data),
                 return_data: self.decode_function_output(trace, functions),
             }
         } else {
@@ -385,6 +401,76 @@ impl CallTraceDecoder {
         DecodedCallData { signature: func.signature(), args: args.unwrap_or_default() }
     }
 
+    /// Replaces the decoded `bytes` arguments of a call that look like calldata with their decoding
+    /// as nested calls.
+    async fn decode_nested_inputs(
+        &self,
+        func: &Function,
+        data: &[u8],
+        call_data: &mut DecodedCallData,
+    ) {
+        let Ok(values) = func.abi_decode_input(data, false) else { return };
+        if values.len() != call_data.args.len() {
+            return;
+        }
+        for (arg, value) in call_data.args.iter_mut().zip(&values) {
+            if contains_bytes(value) {
+                *arg = self.format_nested(value, 0).await;
+            }
+        }
+    }
+
+    /// Formats a value, decoding `bytes` that start with the selector of a known function and
+    /// are valid inputs to it as nested calls.
+    fn format_nested<'a>(&'a self, value: &'a DynSolValue, depth: usize) -> BoxFuture<'a, String> {
+        async move {
+            match value {
+                DynSolValue::Bytes(data) if depth < MAX_NESTED_CALL_DEPTH => {
+                    let Some((selector, inputs)) = data.split_first_chunk::<SELECTOR_LEN>() else {
+                        return self.apply_label(value)
+                    };
+                    let Some(func) = self.identify_function(selector).await else {
+                        return self.apply_label(value)
+                    };
+                    let Ok(values) = func.abi_decode_input(inputs, true) else {
+                        return self.apply_label(value)
+                    };
+                    let mut args = Vec::with_capacity(values.len());
+                    for value in &values {
+                        args.push(self.format_nested(value, depth + 1).await);
+                    }
+                    format!("{} => {}({})", self.apply_label(value), func.name, args.join(", "))
+                }
+                DynSolValue::Array(values) | DynSolValue::FixedArray(values)
+                    if values.iter().any(contains_bytes) =>
+                {
+                    let mut items = Vec::with_capacity(values.len());
+                    for value in values {
+                        items.push(self.format_nested(value, depth).await);
+                    }
+                    format!("[{}]", items.join(", "))
+                }
+                DynSolValue::Tuple(values) if values.iter().any(contains_bytes) => {
+                    let mut items = Vec::with_capacity(values.len());
+                    for value in values {
+                        items.push(self.format_nested(value, depth).await);
+                    }
+                    format!("({})", items.join(", "))
+                }
+                _ => self.apply_label(value),
+            }
+        }
+        .boxed()
+    }
+
+    /// Returns the first known function with the given selector.
+    async fn identify_function(&self, selector: &[u8]) -> Option<Function> {
+        if let Some(function) = self.functions.get(selector).and_then(|fs| fs.first()) {
+            return Some(function.clone());
+        }
+        self.signature_identifier.as_ref()?.write().await.identify_function(selector).await
+    }
+
     /// Custom decoding for cheatcode inputs.
     fn decode_cheatcode_inputs(&self, func: &Function, data: &[u8]) -> Option<Vec<String>> {
         match func.name.as_str() {
@@ -605,6 +691,20 @@ impl CallTraceDecoder {
     }
 }
 
+/// The maximum depth of nested calls decoded from `bytes` arguments.
+const MAX_NESTED_CALL_DEPTH: usize = 4;
+
+/// Returns whether the value is or contains `bytes`.
+fn contains_bytes(value: &DynSolValue) -> bool {
+    match value {
+        DynSolValue::Bytes(_) => true,
+        DynSolValue::Array(values) |
+        DynSolValue::FixedArray(values) |
+        DynSolValue::Tuple(values) => values.iter().any(contains_bytes),
+        _ => false,
+    }
+}
+
 /// Restore the order of the params of a decoded event,
 /// as Alloy returns the indexed and unindexed params separately.
 fn reconstruct_params(event: &Event, decoded: &DecodedEvent) -> Vec<DynSolValue> {
@@ -631,7 +731,48 @@ fn indexed_inputs(event: &Event) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy_primitives::hex;
+    use alloy_primitives::{hex, U256};
+
+    #[tokio::test]
+    async fn decodes_nested_calls() {
+        let execute = Function::parse("execute(address,bytes)").unwrap();
+        let transfer = Function::parse("transfer(address,uint256)").unwrap();
+        let mut decoder = CallTraceDecoderBuilder::new().with_nested_bytes_decoding(true).build();
+        decoder.push_function(execute.clone());
+        decoder.push_function(transfer.clone());
+
+        let (target, recipient) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let inner = transfer
+            .abi_encode_input(&[
+                DynSolValue::Address(recipient),
+                DynSolValue::from(U256::from(100)),
+            ])
+            .unwrap();
+        let data = execute
+            .abi_encode_input(&[DynSolValue::Address(target), DynSolValue::Bytes(inner.clone())])
+            .unwrap();
+        let trace = CallTrace { data: data.into(), ..Default::default() };
+
+        let args = decoder.decode_function(&trace).await.call_data.unwrap().args;
+        assert_eq!(
+            args,
+            vec![
+                target.to_string(),
+                format!("{} => transfer({recipient}, 100)", hex::encode_prefixed(&inner))
+            ]
+        );
+
+        // not valid calldata of the function with the selector
+        let data = execute
+            .abi_encode_input(&[
+                DynSolValue::Address(target),
+                DynSolValue::Bytes(inner[..8].to_vec()),
+            ])
+            .unwrap();
+        let trace = CallTrace { data: data.into(), ..Default::default() };
+        let args = decoder.decode_function(&trace).await.call_data.unwrap().args;
+        assert_eq!(args[1], hex::encode_prefixed(&inner[..8]));
+    }
 
     #[test]
     fn test_should_redact_pk() {
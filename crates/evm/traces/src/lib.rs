@@ -8,16 +8,17 @@
 #[macro_use]
 extern crate tracing;
 
+use colorchoice::ColorChoice;
 use foundry_common::contracts::{ContractsByAddress, ContractsByArtifact};
 use serde::{Deserialize, Serialize};
 
 pub use revm_inspectors::tracing::{
     types::{
-        CallKind, CallLog, CallTrace, CallTraceNode, DecodedCallData, DecodedCallLog,
-        DecodedCallTrace,
+        CallKind, CallLog, CallTrace, CallTraceNode, CallTraceStep, DecodedCallData,
+        DecodedCallLog, DecodedCallTrace, DecodedTraceStep, TraceMemberOrder,
     },
-    CallTraceArena, FourByteInspector, GethTraceBuilder, ParityTraceBuilder, StackSnapshotType,
-    TraceWriter, TracingInspector, TracingInspectorConfig,
+    CallTraceArena, FourByteInspector, GethTraceBuilder, OpcodeFilter, ParityTraceBuilder,
+    StackSnapshotType, TraceWriter, TracingInspector, TracingInspectorConfig,
 };
 
 /// Call trace address identifiers.
@@ -51,6 +52,15 @@ pub fn render_trace_arena(arena: &CallTraceArena) -> String {
     String::from_utf8(w.into_writer()).expect("trace writer wrote invalid UTF-8")
 }
 
+/// Same as [`render_trace_arena`], but only colors the output if `use_colors` instead of when
+/// stdout is a terminal.
+pub fn render_trace_arena_with_colors(arena: &CallTraceArena, use_colors: bool) -> String {
+    let colors = if use_colors { ColorChoice::Always } else { ColorChoice::Never };
+    let mut w = TraceWriter::new(Vec::<u8>::new()).use_colors(colors);
+    w.write_arena(arena).expect("Failed to write traces");
+    String::from_utf8(w.into_writer()).expect("trace writer wrote invalid UTF-8")
+}
+
 /// Specifies the kind of trace.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TraceKind {
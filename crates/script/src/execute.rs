@@ -97,7 +97,7 @@ impl LinkedState {
                 ..Default::default()
             };
 
-            build_tweak_data(&cloned_projects, &rpc, args.tweak_quick).await?
+            build_tweak_data(&cloned_projects, &rpc, args.tweak_quick, Default::default()).await?
         } else {
             TweakData::new()
         };
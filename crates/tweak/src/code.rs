@@ -280,6 +280,7 @@ async fn prepare_backend(
             NamedChain::try_from(project.metadata.chain_id)
                 .map_err(|_| eyre!("invalid chain id"))?,
         )
+        .headers(rpc.rpc_headers.clone())
         .build()?;
 
     // get block number
@@ -2,13 +2,15 @@ pub mod code;
 pub mod compatibility;
 mod constants;
 mod metadata;
+pub mod verify;
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr};
 
 use alloy_primitives::{keccak256, Address, Bytes, B256};
 use eyre::Result;
 
 use foundry_cli::opts::RpcOpts;
+use foundry_common::cli_warn;
 use foundry_evm::{backend::Backend, fork::CreateFork};
 pub use metadata::ClonedProject;
 use revm::{
@@ -18,21 +20,189 @@ use revm::{
 
 pub type TweakData = BTreeMap<Address, Bytes>;
 
+/// How multiple tweaks of the same address are resolved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TweakPrecedence {
+    /// Tweaking the same address multiple times is an error.
+    #[default]
+    Error,
+    /// The tweak specified last takes effect.
+    LastWins,
+}
+
+/// A raw runtime code tweak of an address, e.g. `0xd8dA…6045:0x6080…`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeTweak {
+    pub address: Address,
+    pub code: Bytes,
+}
+
+impl FromStr for CodeTweak {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, code) =
+            s.split_once(':').ok_or_else(|| format!("expected `<address>:<code>`, got `{s}`"))?;
+        Ok(Self {
+            address: address.parse().map_err(|e| format!("invalid address `{address}`: {e}"))?,
+            code: code.parse().map_err(|e| format!("invalid code `{code}`: {e}"))?,
+        })
+    }
+}
+
+/// The address to apply the code of a cloned project to instead of the address it was cloned from,
+/// e.g. `path/to/project:0xd8dA…6045`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TweakTarget {
+    pub project: PathBuf,
+    pub address: Address,
+}
+
+impl FromStr for TweakTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the path may contain colons, the address can't
+        let (project, address) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("expected `<project>:<address>`, got `{s}`"))?;
+        if project.is_empty() {
+            return Err(format!("missing project in `{s}`"));
+        }
+        Ok(Self {
+            project: project.into(),
+            address: address.parse().map_err(|e| format!("invalid address `{address}`: {e}"))?,
+        })
+    }
+}
+
+/// Sets the addresses the projects are applied to, failing if the project of a target isn't
+/// among `projects` or is retargeted to the same address multiple times.
+///
+/// A project retargeted to several addresses, e.g. multiple deployments of the same contract, is
+/// applied to all of them: every other address is tweaked by a copy of the project, inserted after
+/// it, which shares its compilation output.
+///
+/// The projects of the targets are matched by root, which must be the same absolute path.
+pub fn retarget_tweaks(projects: &mut Vec<ClonedProject>, targets: &[TweakTarget]) -> Result<()> {
+    for target in targets {
+        let positions = projects
+            .iter()
+            .enumerate()
+            .filter(|(_, project)| project.root == target.project)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let (Some(&first), Some(&last)) = (positions.first(), positions.last()) else {
+            eyre::bail!("{} is not one of the tweak projects", target.project.display());
+        };
+        if positions.iter().any(|&i| projects[i].tweak_address == Some(target.address)) {
+            eyre::bail!(
+                "{} is retargeted to {} multiple times",
+                target.project.display(),
+                target.address
+            );
+        }
+        if projects[first].tweak_address.is_none() {
+            projects[first].tweak_address = Some(target.address);
+        } else {
+            let mut copy = projects[first].clone();
+            copy.tweak_address = Some(target.address);
+            projects.insert(last + 1, copy);
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every address the projects are retargeted to holds code in `backend`, i.e. that it
+/// is a deployed contract whose code can be tweaked.
+pub fn check_tweak_targets(backend: &mut Backend, projects: &[ClonedProject]) -> Result<()> {
+    for project in projects {
+        let Some(address) = project.tweak_address else { continue };
+        let has_code = backend
+            .basic(address)?
+            .is_some_and(|info| info.code_hash != KECCAK_EMPTY && info.code_hash != B256::ZERO);
+        if !has_code {
+            eyre::bail!(
+                "{address} has no code on-chain, it can't be tweaked by {}",
+                project.root.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Adds the raw code tweaks to the tweak data, after the tweaks already in it.
+///
+/// An address that is already tweaked is resolved by `precedence`, as by [`effective_tweaks`].
+pub fn add_code_tweaks(
+    tweak_data: &mut TweakData,
+    code_tweaks: &[CodeTweak],
+    precedence: TweakPrecedence,
+) -> Result<()> {
+    for tweak in code_tweaks {
+        let address = tweak.address;
+        if tweak_data.contains_key(&address) {
+            match precedence {
+                TweakPrecedence::Error => eyre::bail!(
+                    "{address} is tweaked multiple times, use `--tweak-precedence last-wins` to \
+                     let the code tweak specified last take effect"
+                ),
+                TweakPrecedence::LastWins => {
+                    cli_warn!("the previous tweak of {address} is overridden by a code tweak")
+                }
+            }
+        }
+        tweak_data.insert(address, tweak.code.clone());
+    }
+    Ok(())
+}
+
 pub async fn build_tweak_data(
-    projects: &Vec<ClonedProject>,
+    projects: &[ClonedProject],
     rpc: &RpcOpts,
     quick: bool,
+    precedence: TweakPrecedence,
 ) -> Result<TweakData> {
     let mut tweak_data = BTreeMap::new();
-    for project in projects {
-        let metadata = &project.metadata;
-        let address = metadata.address;
+    for project in effective_tweaks(projects, precedence)? {
+        let address = project.tweaked_address();
         let code = project.tweaked_code(rpc, quick).await?;
         tweak_data.insert(address, code);
     }
     Ok(tweak_data)
 }
 
+/// Returns the projects that take effect, i.e. at most one project per tweaked address, in
+/// ascending address order.
+///
+/// Callers should use these instead of all the projects once the tweaks are resolved, e.g. to
+/// decode or report the tweaked contracts, since an overridden project isn't applied.
+pub fn effective_tweaks(
+    projects: &[ClonedProject],
+    precedence: TweakPrecedence,
+) -> Result<Vec<&ClonedProject>> {
+    let mut effective = BTreeMap::<Address, &ClonedProject>::new();
+    for project in projects {
+        let address = project.tweaked_address();
+        if let Some(previous) = effective.insert(address, project) {
+            match precedence {
+                TweakPrecedence::Error => eyre::bail!(
+                    "both {} and {} tweak {address}, use `--tweak-precedence last-wins` to let \
+                     the latter take effect",
+                    previous.root.display(),
+                    project.root.display()
+                ),
+                TweakPrecedence::LastWins => cli_warn!(
+                    "the tweak of {address} by {} is overridden by {}",
+                    previous.root.display(),
+                    project.root.display()
+                ),
+            }
+        }
+    }
+    Ok(effective.into_values().collect())
+}
+
 pub fn build_tweaked_backend(fork: Option<CreateFork>, tweak_data: &TweakData) -> Result<Backend> {
     let mut backend = Backend::spawn(fork);
     for (address, code) in tweak_data {
@@ -59,9 +229,30 @@ pub fn tweak_backend_once(
     Ok(())
 }
 
-pub fn tweak_backend(backend: &mut Backend, tweak_data: &TweakData) -> Result<()> {
+/// A code replacement applied by [`tweak_backend`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AppliedTweak {
+    pub address: Address,
+    /// The size of the replaced code, zero if the address had no code.
+    pub old_code_size: usize,
+    pub new_code_size: usize,
+}
+
+/// Replaces the code of the tweaked addresses in the backend and returns the replacements, in
+/// ascending address order.
+pub fn tweak_backend(backend: &mut Backend, tweak_data: &TweakData) -> Result<Vec<AppliedTweak>> {
+    let mut applied = Vec::with_capacity(tweak_data.len());
     for (tweak_address, tweaked_code) in tweak_data {
         let mut info = backend.basic(*tweak_address)?.unwrap_or_default();
+        let old_code = match &info.code {
+            Some(code) => code.clone(),
+            None => backend.code_by_hash(info.code_hash)?,
+        };
+        applied.push(AppliedTweak {
+            address: *tweak_address,
+            old_code_size: old_code.original_bytes().len(),
+            new_code_size: tweaked_code.len(),
+        });
         let code_hash = if tweaked_code.as_ref().is_empty() {
             revm::primitives::KECCAK_EMPTY
         } else {
@@ -72,5 +263,115 @@ pub fn tweak_backend(backend: &mut Backend, tweak_data: &TweakData) -> Result<()
         backend.insert_account_info(*tweak_address, info);
     }
 
-    Ok(())
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(root: &str, address: Address) -> ClonedProject {
+        let mut project = ClonedProject { root: root.into(), ..Default::default() };
+        project.metadata.address = address;
+        project
+    }
+
+    #[test]
+    fn test_conflicting_tweaks() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let projects = [project("/base", a), project("/other", b), project("/hotfix", a)];
+
+        assert!(effective_tweaks(&projects, TweakPrecedence::Error).is_err());
+        assert!(effective_tweaks(&projects[..2], TweakPrecedence::Error).is_ok());
+
+        let effective = effective_tweaks(&projects, TweakPrecedence::LastWins).unwrap();
+        let roots = effective.iter().map(|p| p.root.to_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(roots, vec!["/hotfix", "/other"]);
+    }
+
+    #[test]
+    fn test_retargeted_tweaks() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let projects = [project("/base", a), project("/hotfix", a)];
+        assert!(effective_tweaks(&projects, TweakPrecedence::Error).is_err());
+
+        let target: TweakTarget = format!("/hotfix:{b}").parse().unwrap();
+        assert_eq!(target, TweakTarget { project: "/hotfix".into(), address: b });
+        let mut projects = projects.to_vec();
+        retarget_tweaks(&mut projects, std::slice::from_ref(&target)).unwrap();
+        assert_eq!(projects[1].tweaked_address(), b);
+        assert_eq!(effective_tweaks(&projects, TweakPrecedence::Error).unwrap().len(), 2);
+
+        assert!(retarget_tweaks(&mut projects, &[target]).is_err());
+        let unknown = TweakTarget { project: "/other".into(), address: b };
+        assert!(retarget_tweaks(&mut projects, &[unknown]).is_err());
+        assert!(format!(":{b}").parse::<TweakTarget>().is_err());
+        assert!("/hotfix:0x12".parse::<TweakTarget>().is_err());
+    }
+
+    #[test]
+    fn test_multiple_tweak_targets() {
+        let (a, b, c) =
+            (Address::with_last_byte(1), Address::with_last_byte(2), Address::with_last_byte(3));
+        let mut projects = vec![project("/token", a), project("/other", c)];
+        let targets = [
+            TweakTarget { project: "/token".into(), address: a },
+            TweakTarget { project: "/token".into(), address: b },
+        ];
+        retarget_tweaks(&mut projects, &targets).unwrap();
+        let tweaks = projects
+            .iter()
+            .map(|p| (p.root.to_str().unwrap(), p.tweaked_address()))
+            .collect::<Vec<_>>();
+        assert_eq!(tweaks, [("/token", a), ("/token", b), ("/other", c)]);
+        assert_eq!(effective_tweaks(&projects, TweakPrecedence::Error).unwrap().len(), 3);
+        assert!(retarget_tweaks(&mut projects, &targets[1..]).is_err());
+
+        let mut backend = Backend::spawn(None);
+        let code = Bytes::from_static(&[0x00]);
+        tweak_backend_once(&mut backend, a, code).unwrap();
+        assert!(check_tweak_targets(&mut backend, &projects[..1]).is_ok());
+        assert!(check_tweak_targets(&mut backend, &projects).is_err());
+        // the projects applied to the addresses they were cloned from aren't checked
+        assert!(check_tweak_targets(&mut backend, &projects[2..]).is_ok());
+    }
+
+    #[test]
+    fn test_code_tweaks() {
+        let address = Address::with_last_byte(1);
+        let tweak: CodeTweak = format!("{address}:0x6000").parse().unwrap();
+        assert_eq!(tweak, CodeTweak { address, code: Bytes::from_static(&[0x60, 0x00]) });
+        assert!(format!("{address}").parse::<CodeTweak>().is_err());
+        assert!(format!("{address}:0xzz").parse::<CodeTweak>().is_err());
+        assert!("0x12:0x6000".parse::<CodeTweak>().is_err());
+
+        let mut tweak_data = TweakData::new();
+        add_code_tweaks(&mut tweak_data, std::slice::from_ref(&tweak), TweakPrecedence::Error)
+            .unwrap();
+        assert_eq!(tweak_data[&address], tweak.code);
+        assert!(add_code_tweaks(
+            &mut tweak_data,
+            std::slice::from_ref(&tweak),
+            TweakPrecedence::Error
+        )
+        .is_err());
+
+        // with last-wins, the code tweak specified last takes effect
+        let other = CodeTweak { address, code: Bytes::from_static(&[0x60, 0x01]) };
+        let mut overridden = TweakData::new();
+        add_code_tweaks(
+            &mut overridden,
+            &[tweak.clone(), other.clone()],
+            TweakPrecedence::LastWins,
+        )
+        .unwrap();
+        assert_eq!(overridden, TweakData::from([(address, other.code)]));
+
+        let mut backend = Backend::spawn(None);
+        let applied = tweak_backend(&mut backend, &tweak_data).unwrap();
+        assert_eq!(applied, [AppliedTweak { address, old_code_size: 0, new_code_size: 2 }]);
+        tweak_data.insert(address, Bytes::from_static(&[0x00]));
+        let applied = tweak_backend(&mut backend, &tweak_data).unwrap();
+        assert_eq!(applied, [AppliedTweak { address, old_code_size: 2, new_code_size: 1 }]);
+    }
 }
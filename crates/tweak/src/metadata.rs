@@ -24,6 +24,9 @@ pub struct ClonedProject {
     pub root: PathBuf,
     pub config: Config,
     pub metadata: CloneMetadata,
+    /// The address to apply the tweaked code to instead of the address of the on-chain contract
+    /// the project was cloned from.
+    pub tweak_address: Option<Address>,
 
     // cache
     pub(crate) _compile_output: Arc<Mutex<Option<ProjectCompileOutput>>>,
@@ -78,6 +81,7 @@ impl ClonedProject {
             root,
             config,
             metadata,
+            tweak_address: None,
             _compile_output: Default::default(),
             _main_artifact: Default::default(),
         })
@@ -131,6 +135,11 @@ impl ClonedProject {
         Ok(Self::get_cache(self._main_artifact.clone()))
     }
 
+    /// Returns the address the tweaked code of the project is applied to.
+    pub fn tweaked_address(&self) -> Address {
+        self.tweak_address.unwrap_or(self.metadata.address)
+    }
+
     /// Get the tweaked code of the main contract of the project.
     pub async fn tweaked_code(&self, rpc: &RpcOpts, quick: bool) -> Result<Bytes> {
         // check chain id
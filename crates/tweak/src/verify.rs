@@ -0,0 +1,79 @@
+//! Verification of the tweaked code against the on-chain code.
+
+use std::fmt;
+
+use alloy_primitives::Address;
+use eyre::Result;
+use foundry_evm::backend::Backend;
+use revm::Database;
+
+use crate::TweakData;
+
+/// Whether the tweaked code of an address matches its on-chain code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TweakVerification {
+    pub address: Address,
+    pub matches: bool,
+}
+
+impl fmt::Display for TweakVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.matches { "matches" } else { "differs from" };
+        write!(f, "{}: the tweaked code {status} the on-chain code", self.address)
+    }
+}
+
+/// Compares the tweaked code of every address with its on-chain code in `backend`, which must not
+/// be tweaked yet.
+///
+/// The CBOR metadata trailers are ignored, since they routinely differ between compilations. The
+/// immutables are the same as on-chain because the tweaked code is obtained by replaying the
+/// creation transaction.
+pub fn verify_tweak_data(
+    backend: &mut Backend,
+    tweak_data: &TweakData,
+) -> Result<Vec<TweakVerification>> {
+    let mut verifications = vec![];
+    for (address, tweaked_code) in tweak_data {
+        let info = backend.basic(*address)?.unwrap_or_default();
+        let code = match info.code {
+            Some(code) => code,
+            None => backend.code_by_hash(info.code_hash)?,
+        };
+        let matches =
+            strip_metadata(code.original_byte_slice()) == strip_metadata(tweaked_code.as_ref());
+        verifications.push(TweakVerification { address: *address, matches });
+    }
+    Ok(verifications)
+}
+
+/// Strips the CBOR metadata trailer, whose length is encoded in the last two bytes, from the
+/// runtime code.
+fn strip_metadata(code: &[u8]) -> &[u8] {
+    let Some(offset) = code.len().checked_sub(2) else { return code };
+    let len = u16::from_be_bytes([code[offset], code[offset + 1]]) as usize + 2;
+    // the CBOR metadata is a map, starting with 0xa1 to 0xa5 for the usual number of entries
+    match code.len().checked_sub(len) {
+        Some(start) if (0xa1..=0xa5).contains(&code[start]) => &code[..start],
+        _ => code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_metadata() {
+        // PUSH1 0 followed by a CBOR map `{"a": 1}` of 4 bytes and its length
+        let code = [0x60, 0x00, 0xa1, 0x61, 0x61, 0x01, 0x00, 0x04];
+        assert_eq!(strip_metadata(&code), &[0x60, 0x00]);
+
+        let other = [0x60, 0x00, 0xa1, 0x61, 0x61, 0x02, 0x00, 0x04];
+        assert_eq!(strip_metadata(&code), strip_metadata(&other));
+
+        // no metadata
+        assert_eq!(strip_metadata(&[0x60, 0x00, 0x00]), &[0x60, 0x00, 0x00]);
+        assert_eq!(strip_metadata(&[0x00]), &[0x00]);
+    }
+}